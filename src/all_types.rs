@@ -0,0 +1,105 @@
+//! Zone exploration when AXFR is refused (--all-types): the domain is queried for every type
+//! dqy knows, the queries being sent at once as with --parallel, and a table tells which
+//! types returned data.
+use crate::args::CliOptions;
+use crate::dns::message::Message;
+use crate::dns::rfc::qtype::QType;
+use crate::error::Result;
+use crate::get_messages;
+use crate::show::header_section;
+
+// types not worth asking: OPT is a pseudo-RR (RFC6891 section 6.1.1)
+const SKIPPED: &[QType] = &[QType::OPT];
+
+pub fn types() -> Vec<QType> {
+    QType::supported()
+        .into_iter()
+        .filter(|qt| !SKIPPED.contains(qt))
+        .collect()
+}
+
+// answers of the type queried, CNAMEs followed by the resolver not counted
+fn answers(msg: &Message) -> usize {
+    msg.response
+        .answer
+        .iter()
+        .flat_map(|list| list.iter())
+        .filter(|rr| rr.r#type == msg.query.question.qtype)
+        .count()
+}
+
+// rcode and number of answers of a row
+fn row(msg: &Message) -> (String, usize) {
+    (msg.response.rcode().to_string(), answers(msg))
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// query all types and print out a line per type
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn all_types(options: &mut CliOptions) -> Result<()> {
+    let types = types();
+    options.protocol.qtype = types.clone();
+    options.transport.parallel = true;
+    let messages = get_messages(None, options)?;
+
+    println!("{}", header_section("TYPES", None));
+    println!("{:<12} {:<10} {:>7}", "TYPE", "RCODE", "ANSWERS");
+
+    // types left unanswered, truncated or rejected are resent one at a time with the
+    // usual retries and fallbacks, and a failure is reported in their row
+    options.transport.parallel = false;
+    let mut with_data = Vec::new();
+    for qtype in types.iter() {
+        if options.transport.cancel.is_cancelled() {
+            break;
+        }
+
+        let result = match messages.iter().find(|msg| msg.query.question.qtype == *qtype) {
+            Some(msg) => Ok(row(msg)),
+            None => {
+                options.protocol.qtype = vec![*qtype];
+                get_messages(None, options).map(|resent| resent.iter().map(row).next().unwrap_or_default())
+            }
+        };
+
+        match result {
+            Ok((rcode, count)) => {
+                println!("{:<12} {:<10} {:>7}", qtype.to_string(), rcode, count);
+                if count > 0 {
+                    with_data.push(qtype.to_string());
+                }
+            }
+            Err(e) => println!(
+                "{:<12} {:<10} {:>7}",
+                qtype.to_string(),
+                e.class().to_string().to_uppercase(),
+                "-"
+            ),
+        }
+    }
+
+    println!(
+        "\n{} of {} types returned data: {}",
+        with_data.len(),
+        types.len(),
+        if with_data.is_empty() {
+            String::from("none")
+        } else {
+            with_data.join(",")
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all() {
+        let types = types();
+        assert!(types.contains(&QType::A) && types.contains(&QType::SVCB));
+        assert!(!types.contains(&QType::OPT));
+    }
+}
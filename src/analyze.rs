@@ -0,0 +1,167 @@
+//! Heuristics on responses to give some hints about the server behaviour (--analyze)
+use crate::dns::rfc::{
+    qtype::QType, resource_record::ResourceRecord, response::Response, response_code::ResponseCode, rrlist::RRList,
+};
+
+// count RRs in a section matching the predicate
+fn count_rr<P: Fn(&ResourceRecord) -> bool>(list: Option<&RRList>, predicate: P) -> usize {
+    list.map(|l| l.iter().filter(|rr| predicate(rr)).count()).unwrap_or(0)
+}
+
+// negative answers should carry the SOA for negative caching (RFC2308)
+fn missing_soa(resp: &Response) -> Option<String> {
+    let soa = count_rr(resp.authority(), |rr| rr.r#type == QType::SOA);
+    let ns = count_rr(resp.authority(), |rr| rr.r#type == QType::NS);
+    let negative = match resp.rcode() {
+        ResponseCode::NXDomain => Some("NXDOMAIN"),
+        ResponseCode::NoError if resp.answer.is_none() && ns == 0 && !resp.is_compact_denial() => Some("NODATA"),
+        _ => None,
+    };
+
+    negative.filter(|_| soa == 0).map(|negative| {
+        format!(
+            "{} without SOA in authority section, negative caching TTL is unknown (RFC2308)",
+            negative
+        )
+    })
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// return what doesn't follow the RFCs in the response, reported as warnings
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn lint(resp: &Response) -> Vec<String> {
+    let mut findings: Vec<String> = missing_soa(resp).into_iter().collect();
+
+    // RRs of the same RRset should share the TTL (RFC2181 5.2)
+    let sections = [resp.answer.as_ref(), resp.authority(), resp.additional()];
+    for list in sections.into_iter().flatten() {
+        for rrset in list.rrsets().iter().filter(|set| set.ttl_mismatch()) {
+            findings.push(format!(
+                "RRset {} {} has different TTLs, the lowest one ({}) applies (RFC2181)",
+                rrset.name(),
+                rrset.r#type(),
+                rrset.ttl().unwrap_or_default()
+            ));
+        }
+    }
+
+    findings
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// return a list of comments on the response
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn analyze(resp: &Response) -> Vec<String> {
+    let mut comments = Vec::new();
+
+    // OPT is not real data
+    let authority = count_rr(resp.authority(), |_| true);
+    let additional = count_rr(resp.additional(), |rr| rr.r#type != QType::OPT);
+    let soa = count_rr(resp.authority(), |rr| rr.r#type == QType::SOA);
+
+    // compact denial: NXDOMAIN is disguised as NODATA
+    if resp.is_compact_denial() {
+        comments.push(
+            "compact denial of existence (RFC9824): NOERROR with NXNAME in the NSEC type bitmap, the name doesn't exist"
+                .to_string(),
+        );
+        return comments;
+    }
+
+    if resp.rcode() == ResponseCode::NXDomain {
+        comments.extend(missing_soa(resp));
+        return comments;
+    }
+
+    if resp.rcode() != ResponseCode::NoError {
+        return comments;
+    }
+
+    // we've got an answer
+    if resp.answer.is_some() {
+        if authority == 0 && additional == 0 {
+            comments.push("minimal responses: only the answer section is returned".to_string());
+        } else {
+            comments.push(format!(
+                "full response: {} authority and {} additional record(s) included",
+                authority, additional
+            ));
+        }
+        return comments;
+    }
+
+    // no answer: either NODATA or a referral
+    let ns = count_rr(resp.authority(), |rr| rr.r#type == QType::NS);
+    let glue = count_rr(resp.additional(), |rr| {
+        rr.r#type == QType::A || rr.r#type == QType::AAAA
+    });
+
+    if soa > 0 {
+        comments.push("NODATA response with SOA in authority section".to_string());
+    } else if ns > 0 && glue == 0 {
+        comments.push(format!(
+            "referral with {} NS record(s) but without glue: minimal responses or out-of-bailiwick name servers",
+            ns
+        ));
+    } else if ns > 0 {
+        comments.push(format!("full referral: {} NS and {} glue record(s)", ns, glue));
+    } else {
+        comments.push("empty response without any authority data".to_string());
+    }
+
+    // the server of the parent zone has no authority outside it: the parent of the
+    // delegated zone is taken as its bailiwick, which is the case of most delegations
+    if let Some(bailiwick) = resp.referral_zone().and_then(|zone| zone.parent()) {
+        let outside = resp.out_of_bailiwick(&bailiwick);
+        if outside.is_empty() {
+            comments.push(format!("all additional records are in bailiwick of {}", bailiwick));
+        } else {
+            let names: Vec<_> = outside.iter().map(|rr| format!("{} {}", rr.name, rr.r#type)).collect();
+            comments.push(format!(
+                "{} additional record(s) out of bailiwick of {}, not to be trusted as glue: {}",
+                outside.len(),
+                bailiwick,
+                names.join(", ")
+            ));
+        }
+    }
+
+    comments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::tests::get_packets;
+    use type2network::FromNetworkOrder;
+
+    #[test]
+    fn minimal() {
+        let pcap = get_packets("./tests/pcap/cap1.pcap", 0, 1);
+        let mut buffer = std::io::Cursor::new(&pcap.1[0x2A..]);
+
+        let mut resp = Response::default();
+        resp.deserialize_from(&mut buffer).unwrap();
+
+        let comments = analyze(&resp);
+        assert_eq!(comments.len(), 1);
+        assert!(comments[0].starts_with("minimal responses"));
+        assert!(lint(&resp).is_empty());
+    }
+
+    #[test]
+    fn compact_denial() {
+        let pcap = get_packets("./tests/pcap/compact_denial.pcap", 0, 1);
+        let mut buffer = std::io::Cursor::new(&pcap.1[0x2A..]);
+
+        let mut resp = Response::default();
+        resp.deserialize_from(&mut buffer).unwrap();
+
+        assert_eq!(resp.rcode(), ResponseCode::NoError);
+        assert!(resp.is_compact_denial());
+
+        let comments = analyze(&resp);
+        assert_eq!(comments.len(), 1);
+        assert!(comments[0].starts_with("compact denial of existence"));
+    }
+}
@@ -0,0 +1,184 @@
+//! Comments appended to each RR in text output (--annotate): absolute expiry time
+//! computed from the TTL, reverse name of addresses, special-use address ranges and
+//! validity period of signatures relative to now.
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+use chrono::{TimeDelta, Utc};
+
+use crate::args::CliOptions;
+use crate::dns::date_time::humanize;
+use crate::dns::message::MessageList;
+use crate::dns::rfc::{domain::DomainName, qtype::QType, resource_record::ResourceRecord};
+use crate::get_messages;
+use crate::show::DisplayOptions;
+
+// IANA special-purpose address registries (RFC6890 and updates) and private ranges
+const SPECIAL_USE: &[(&str, u8, &str)] = &[
+    ("0.0.0.0", 8, "this network"),
+    ("10.0.0.0", 8, "private (RFC1918)"),
+    ("100.64.0.0", 10, "shared address space (CGN)"),
+    ("127.0.0.0", 8, "loopback"),
+    ("169.254.0.0", 16, "link local"),
+    ("172.16.0.0", 12, "private (RFC1918)"),
+    ("192.0.0.0", 24, "IETF protocol assignments"),
+    ("192.0.2.0", 24, "documentation (TEST-NET-1)"),
+    ("192.88.99.0", 24, "6to4 relay anycast"),
+    ("192.168.0.0", 16, "private (RFC1918)"),
+    ("198.18.0.0", 15, "benchmarking"),
+    ("198.51.100.0", 24, "documentation (TEST-NET-2)"),
+    ("203.0.113.0", 24, "documentation (TEST-NET-3)"),
+    ("224.0.0.0", 4, "multicast"),
+    ("240.0.0.0", 4, "reserved"),
+    ("255.255.255.255", 32, "limited broadcast"),
+    ("::", 128, "unspecified"),
+    ("::1", 128, "loopback"),
+    ("::ffff:0:0", 96, "IPv4-mapped"),
+    ("64:ff9b::", 96, "IPv4/IPv6 translation"),
+    ("100::", 64, "discard only"),
+    ("2001::", 32, "TEREDO"),
+    ("2001:db8::", 32, "documentation"),
+    ("2002::", 16, "6to4"),
+    ("fc00::", 7, "unique local"),
+    ("fe80::", 10, "link local"),
+    ("ff00::", 8, "multicast"),
+];
+
+// true if ip is in the network/prefix
+fn in_network(ip: &IpAddr, network: &IpAddr, prefix: u8) -> bool {
+    let (ip, network, bits) = match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(net)) => (u32::from(*ip) as u128, u32::from(*net) as u128, 32),
+        (IpAddr::V6(ip), IpAddr::V6(net)) => (u128::from(*ip), u128::from(*net), 128),
+        _ => return false,
+    };
+    let shift = bits - prefix as u32;
+    shift >= bits || (ip >> shift) == (network >> shift)
+}
+
+// the special-use range the address belongs to, if any
+pub fn special_use(ip: &IpAddr) -> Option<&'static str> {
+    SPECIAL_USE
+        .iter()
+        .find(|(net, prefix, _)| in_network(ip, &net.parse().unwrap(), *prefix))
+        .map(|(_, _, comment)| *comment)
+}
+
+// e.g.: 4.3.2.1.in-addr.arpa for 1.2.3.4
+pub fn reverse_name(ip: &IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let octets: Vec<_> = ip.octets().iter().rev().map(|o| o.to_string()).collect();
+            format!("{}.in-addr.arpa", octets.join("."))
+        }
+        IpAddr::V6(ip) => {
+            let nibbles: Vec<_> = ip
+                .octets()
+                .iter()
+                .rev()
+                .flat_map(|o| [o & 0x0F, o >> 4])
+                .map(|n| format!("{:x}", n))
+                .collect();
+            format!("{}.ip6.arpa", nibbles.join("."))
+        }
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// resolve the PTR of all addresses found in the answers (--annotate-ptr)
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn resolve_ptr(messages: &MessageList, options: &CliOptions) -> BTreeMap<IpAddr, String> {
+    let mut options = options.clone();
+    options.protocol.qtype = vec![QType::PTR];
+
+    let addresses = messages.answers().filter_map(|rr| rr.ip_address());
+
+    let mut names = BTreeMap::new();
+    for ip in addresses {
+        if names.contains_key(&ip) || options.transport.cancel.is_cancelled() {
+            continue;
+        }
+        let Ok(domain) = DomainName::try_from(reverse_name(&ip).as_str()) else {
+            continue;
+        };
+        options.protocol.domain_name = domain;
+
+        // failures are not worth reporting: the reverse name is kept
+        let ptr = get_messages(None, &options)
+            .ok()
+            .and_then(|list| list.answers_of(QType::PTR).next().map(|rr| rr.r_data.to_string()));
+        if let Some(ptr) = ptr {
+            names.insert(ip, ptr);
+        }
+    }
+
+    names
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// return the comments on the RR. Those relative to now are left out with --stable
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn annotations(rr: &ResourceRecord, display_options: &DisplayOptions) -> Vec<String> {
+    let mut comments = Vec::new();
+    let stable = display_options.stable;
+
+    if let Some(regular) = rr.opt_or_class_ttl.regular().filter(|_| !stable) {
+        let expiry = Utc::now() + TimeDelta::seconds(regular.ttl() as i64);
+        comments.push(format!("expires {}", expiry.format("%Y-%m-%dT%H:%M:%SZ")));
+    }
+
+    if let Some(ip) = rr.ip_address() {
+        match display_options.ptr_names.get(&ip) {
+            Some(name) => comments.push(format!("PTR {}", name)),
+            None => comments.push(reverse_name(&ip)),
+        }
+        if let Some(range) = special_use(&ip) {
+            comments.push(range.to_string());
+        }
+    }
+
+    if let Some(rrsig) = rr.rrsig() {
+        if stable {
+            comments.push(format!("inception {}", rrsig.sign_inception));
+            comments.push(format!("expiration {}", rrsig.sign_expiration));
+        } else {
+            let (inception, expiration) = rrsig.validity(Utc::now().timestamp());
+            comments.push(format!("inception {} ({})", rrsig.sign_inception, humanize(inception)));
+            comments.push(format!(
+                "expiration {} ({})",
+                rrsig.sign_expiration,
+                humanize(expiration)
+            ));
+        }
+    }
+
+    comments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses() {
+        let ip: IpAddr = "1.2.3.4".parse().unwrap();
+        assert_eq!(reverse_name(&ip), "4.3.2.1.in-addr.arpa");
+        assert!(special_use(&ip).is_none());
+
+        let ip: IpAddr = "2001:db8::567:89ab".parse().unwrap();
+        assert_eq!(
+            reverse_name(&ip),
+            "b.a.9.8.7.6.5.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+        assert_eq!(special_use(&ip), Some("documentation"));
+
+        assert_eq!(special_use(&"172.31.255.1".parse().unwrap()), Some("private (RFC1918)"));
+        assert!(special_use(&"172.32.0.1".parse().unwrap()).is_none());
+        assert_eq!(
+            special_use(&"255.255.255.255".parse().unwrap()),
+            Some("limited broadcast")
+        );
+        assert_eq!(special_use(&"::".parse().unwrap()), Some("unspecified"));
+        assert_eq!(special_use(&"fd00::1".parse().unwrap()), Some("unique local"));
+        assert!(special_use(&"2606:4700:4700::1111".parse().unwrap()).is_none());
+    }
+}
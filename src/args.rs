@@ -8,19 +8,41 @@ use std::str::FromStr;
 use std::time::Duration;
 
 use clap::builder::styling;
-use clap::{crate_version, Arg, ArgAction, Command};
+use clap::{crate_version, parser::ValueSource, Arg, ArgAction, Command};
+#[cfg(feature = "doh")]
 use http::*;
 use log::trace;
 use rustc_version_runtime::version;
 use simplelog::*;
 
+use crate::annotate::reverse_name;
+use crate::cache::cache_dir;
 use crate::cli_options::{DnsProtocolOptions, EdnsOptions};
+use crate::cookies;
 use crate::dns::rfc::domain::DomainName;
-use crate::dns::rfc::{flags::BitFlags, qclass::QClass, qtype::QType};
+use crate::dns::rfc::opt::client_subnet::ClientSubnet;
+use crate::dns::rfc::{
+    flags::BitFlags,
+    header::{Header, HeaderCraft},
+    qclass::QClass,
+    qtype::QType,
+    tsig::{TsigAlgorithm, TsigKey},
+};
 use crate::error::Error;
-use crate::show::{DisplayOptions, DumpOptions};
+use crate::i18n::{set_lang, Lang};
+use crate::json::query_from_json;
+use crate::keyring::{fetch_secret, read_key_file};
+use crate::show::{set_numeric, set_offsets, ColumnWidths, DisplayOptions, DumpOptions};
+use crate::template::{is_template, parse_fmt, Template};
 use crate::transport::network::{IPVersion, Protocol};
-use crate::transport::{endpoint::EndPoint, TransportOptions};
+use crate::transport::{
+    cancel::CancellationToken,
+    endpoint::{EndPoint, FALLBACK_RESOLVERS},
+    pool::ConnectionPool,
+    TransportOptions,
+};
+#[cfg(any(feature = "dot", feature = "doh"))]
+use crate::transport::{Roots, TlsVersion};
 
 // value of the environment variable for flags if any
 const ENV_FLAGS: &str = "DQY_FLAGS";
@@ -99,13 +121,11 @@ impl CliOptions {
         // println!("options without dash:{:?}", without_dash);
         // println!("options with dash:{:?}", with_dash);
 
-        let mut server = "";
+        let mut servers: Vec<&str> = Vec::new();
 
         // build list of supported QTypes from txt file
         let supported_types = {
-            let tmp: Vec<_> = include_str!("../doc/supported_types.txt")
-                .split_ascii_whitespace()
-                .collect();
+            let tmp: Vec<_> = QType::supported().iter().map(QType::to_string).collect();
             tmp.join(",")
         };
 
@@ -114,7 +134,7 @@ impl CliOptions {
         //───────────────────────────────────────────────────────────────────────────────────
         for arg in without_dash.iter() {
             if let Some(s) = arg.strip_prefix('@') {
-                server = s;
+                servers.push(s);
                 continue;
             }
 
@@ -192,7 +212,7 @@ Supported query types: {}
                     .short('c')
                     .long("class")
                     .long_help(
-                        "Query class as specified in RFC1035. Possible values: IN, CS, CH, HS.",
+                        "Query class as specified in RFC1035. Possible values: IN, CS, CH, HS, NONE, ANY or CLASSn.",
                     )
                     .action(ArgAction::Set)
                     .value_name("CLASS")
@@ -219,7 +239,40 @@ Supported query types: {}
             .arg(
                 Arg::new("trace")
                     .long("trace")
-                    .long_help("Iterative lookup from a random root server.")
+                    .long_help("Iterative lookup from a random root server. With several types, each one is traced concurrently from the same root server, and the delegation paths are compared: the hops shared by all types are shown once, followed by the divergent ones of each type.")
+                    .action(ArgAction::SetTrue)
+            )
+            .arg(
+                Arg::new("browser-view")
+                    .long("browser-view")
+                    .long_help("Send the A, AAAA and HTTPS queries a browser sends for the domain and print out the connection parameters it would choose: addresses, port, ALPN and whether ECH is available.")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("follow-svcb")
+            )
+            .arg(
+                Arg::new("service")
+                    .long("service")
+                    .long_help("Query the SRV and SVCB records of the SERVICE of the domain (e.g.: _sip._tcp or sip/tcp, TCP being the default protocol), resolve their targets and print out the list of endpoints in the order a client would try them. Ex: dqy example.com --service _xmpp-client._tcp")
+                    .action(ArgAction::Set)
+                    .value_name("SERVICE")
+            )
+            .arg(
+                Arg::new("browse")
+                    .long("browse")
+                    .long_help("Browse the DNS-SD services of DOMAIN (RFC6763): the service types listed at _services._dns-sd._udp.DOMAIN, their instances and the SRV and TXT records of each instance are printed out as a tree.")
+                    .action(ArgAction::Set)
+                    .value_name("DOMAIN")
+            )
+            .arg(
+                Arg::new("all-types")
+                    .long("all-types")
+                    .long_help("Query the domain for every type dqy supports, all queries being sent at once as with --parallel, and print out a table of the rcode and number of answers of each type. Handy to explore a zone when AXFR is refused. Ex: dqy example.com @ns1.example.com --all-types")
+                    .action(ArgAction::SetTrue)
+            )
+            .arg(
+                Arg::new("follow-svcb")
+                    .long("follow-svcb")
+                    .long_help("Query the HTTPS (or SVCB if given) record of the domain, follow AliasMode records (priority 0) and print out the service parameters (ALPN, port, ECH) and addresses a client would use.")
                     .action(ArgAction::SetTrue)
             )
             //───────────────────────────────────────────────────────────────────────────────────
@@ -243,12 +296,23 @@ Supported query types: {}
                     .value_name("IPV6")
                     .help_heading("Transport options")
             )
+            .arg(
+                Arg::new("prefer")
+                    .long("prefer")
+                    .long_help("When the resolver has both IPv4 and IPv6 addresses, try addresses of this version first. Unlike -4 and -6, addresses of the other version are still used if needed.")
+                    .action(ArgAction::Set)
+                    .value_parser(["ipv4", "ipv6"])
+                    .value_name("VERSION")
+                    .conflicts_with_all(["4", "6"])
+                    .help_heading("Transport options")
+            )
             .arg(
                 Arg::new("alpn")
                     .long("alpn")
                     .long_help("Forces ALPN protocol to 'DoT' for DNS over TLS queries.")
                     .action(ArgAction::SetTrue)
                     .value_name("ALPN")
+                    .hide(cfg!(not(feature = "dot")))
                     .help_heading("Transport options")
             )
             .arg(
@@ -258,6 +322,33 @@ Supported query types: {}
                     .action(ArgAction::Set)
                     .value_name("CERT")
                     .value_parser(clap::value_parser!(PathBuf))
+                    .hide(cfg!(not(any(feature = "dot", feature = "doh"))))
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("system-roots")
+                    .long("system-roots")
+                    .long_help("Authenticate DoT, DoH and DoQ resolvers with the root certificates of the operating system store, which has the ones added by the administrator (e.g. of a TLS inspecting proxy). This is the default. The built-in webpki roots are used if the store can't be found.")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("webpki-roots")
+                    .hide(cfg!(not(any(feature = "dot", feature = "doh"))))
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("webpki-roots")
+                    .long("webpki-roots")
+                    .long_help("Authenticate DoT, DoH and DoQ resolvers with the Mozilla root certificates built in dqy instead of the operating system store.")
+                    .action(ArgAction::SetTrue)
+                    .hide(cfg!(not(any(feature = "dot", feature = "doh"))))
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("deadline")
+                    .long("deadline")
+                    .long_help("Sets an overall time limit for all operations (in ms). When reached, remaining queries are not sent and results already received are displayed.")
+                    .action(ArgAction::Set)
+                    .value_parser(clap::value_parser!(u64))
+                    .value_name("DEADLINE")
                     .help_heading("Transport options")
             )
             .arg(
@@ -266,6 +357,15 @@ Supported query types: {}
                     .long_help("Sets transport to DNS over QUIC (DoQ).")
                     .action(ArgAction::SetTrue)
                     .value_name("doq")
+                    .hide(cfg!(not(feature = "doq")))
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("doq-migrate")
+                    .long("doq-migrate")
+                    .long_help("Experimental: with DoQ, once the queries are answered, rebind the local UDP socket to another port and send them again to check the resolver follows the connection migration (RFC9000), as when a mobile device changes networks. Whether the session survived is reported with --stats and in JSON.")
+                    .action(ArgAction::SetTrue)
+                    .hide(cfg!(not(feature = "doq")))
                     .help_heading("Transport options")
             )
             .arg(
@@ -276,16 +376,67 @@ Supported query types: {}
                     .visible_aliases(["doh", "DoH"])
                     .action(ArgAction::SetTrue)
                     .value_name("https")
+                    .hide(cfg!(not(feature = "doh")))
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("doh-json")
+                    .long("doh-json")
+                    .long_help("Use the JSON API of the DoH resolver (e.g. @https://dns.google/resolve) instead of wire format. Implies --https.")
+                    .action(ArgAction::SetTrue)
+                    .hide(cfg!(not(feature = "doh")))
                     .help_heading("Transport options")
             )
             .arg(
                 Arg::new("https-version")
                     .long("https-version")
-                    .long_help("Sets the HTTPS version when using DNS over https (DoH).")
+                    .long_help(
+                        "Sets the HTTPS version when using DNS over https (DoH). With v3, the query is sent \
+over QUIC (HTTP/3) and proxies are not used.",
+                    )
                     .action(ArgAction::Set)
                     .value_name("https-version")
                     .value_parser(["v1", "v2", "v3"])
                     .default_value("v2")
+                    .hide(cfg!(not(feature = "doh")))
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("retry-after")
+                    .long("retry-after")
+                    .long_help("With DoH, when the server answers 429 (Too Many Requests) or 503 (Service Unavailable) with a Retry-After header, wait for the delay advised and resend the query if it's at most SECONDS, 3 times at most. Without this option, or if the delay is longer, dqy stops and reports the delay advised by the server.")
+                    .action(ArgAction::Set)
+                    .value_parser(clap::value_parser!(u64))
+                    .value_name("SECONDS")
+                    .hide(cfg!(not(feature = "doh")))
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("no-proxy")
+                    .long("no-proxy")
+                    .long_help("With DoH, connect directly to the resolver instead of going through the proxy given by the HTTPS_PROXY or HTTP_PROXY environment variables. Resolvers listed in NO_PROXY are always reached directly. The proxy used is printed out with --stats.")
+                    .action(ArgAction::SetTrue)
+                    .hide(cfg!(not(feature = "doh")))
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("odoh-target")
+                    .long("odoh-target")
+                    .long_help("Use Oblivious DoH (RFC9230): the queries are encrypted with the public key of this DoH resolver (e.g. https://odoh.cloudflare-dns.com/dns-query), fetched from its /.well-known/odohconfigs URL. Without --odoh-proxy, they're sent directly to the resolver. Implies --https.")
+                    .action(ArgAction::Set)
+                    .value_name("URL")
+                    .conflicts_with_all(["doh-json", "https-version"])
+                    .hide(cfg!(not(feature = "odoh")))
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("odoh-proxy")
+                    .long("odoh-proxy")
+                    .long_help("With --odoh-target, relay the encrypted queries through this ODoH proxy (e.g. https://odoh1.surfdomeinen.nl/proxy), which sees the client address but not the queries. The target is given to the proxy with the targethost and targetpath parameters.")
+                    .action(ArgAction::Set)
+                    .value_name("URL")
+                    .requires("odoh-target")
+                    .hide(cfg!(not(feature = "odoh")))
                     .help_heading("Transport options")
             )
             .arg(
@@ -316,6 +467,56 @@ Supported query types: {}
                     .value_parser(clap::value_parser!(PathBuf))
                     .help_heading("Transport options")
             )
+            .arg(
+                Arg::new("tls-min")
+                    .long("tls-min")
+                    .long_help("Lowest TLS version accepted for DoT and DoH. DoQ always uses TLS 1.3. The negotiated version is reported with --stats for DoT.")
+                    .action(ArgAction::Set)
+                    .value_parser(["1.2", "1.3"])
+                    .default_value("1.2")
+                    .value_name("VERSION")
+                    .hide(cfg!(not(any(feature = "dot", feature = "doh"))))
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("tls-max")
+                    .long("tls-max")
+                    .long_help("Highest TLS version offered for DoT and DoH. E.g.: '--tls-max 1.2' checks that a resolver still supports TLS 1.2, '--tls-min 1.3' that it supports TLS 1.3.")
+                    .action(ArgAction::Set)
+                    .value_parser(["1.2", "1.3"])
+                    .default_value("1.3")
+                    .value_name("VERSION")
+                    .hide(cfg!(not(any(feature = "dot", feature = "doh"))))
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("resolvers")
+                    .long("resolvers")
+                    .long_help("Read resolvers from FILE, or from the standard input if FILE is '-', one per line with the same syntax as @server (e.g.: 1.1.1.1, 9.9.9.9:5353, https://dns.google/dns-query). Empty lines and lines starting with '#' are skipped. They're added after the @server ones and used the same way: failover, --all-servers or --race.")
+                    .action(ArgAction::Set)
+                    .value_name("FILE")
+                    .conflicts_with("resolve-file")
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("fallback-resolvers")
+                    .long("fallback-resolvers")
+                    .long_help("Resolvers used when no @server is given and no host resolver is found (e.g.: empty /etc/resolv.conf in a container). Defaults to 1.1.1.1, 9.9.9.9, 2606:4700:4700::1111 and 2620:fe::fe. A warning is printed out when they're used.")
+                    .action(ArgAction::Set)
+                    .num_args(1..255)
+                    .value_delimiter(',')
+                    .value_name("IP,...")
+                    .value_parser(clap::value_parser!(IpAddr))
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("no-fallback-resolvers")
+                    .long("no-fallback-resolvers")
+                    .long_help("Fail when no host resolver is found instead of using the fallback resolvers.")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("fallback-resolvers")
+                    .help_heading("Transport options")
+            )
             .arg(
                 Arg::new("sni")
                     .long("sni")
@@ -323,8 +524,85 @@ Supported query types: {}
                     .action(ArgAction::Set)
                     .required(false)
                     .value_name("SNI")
+                    .hide(cfg!(not(feature = "dot")))
                     .help_heading("Transport options")                    
             )
+            .arg(
+                Arg::new("source-ip")
+                    .long("source-ip")
+                    .long_help("Source IP address to send queries from, whatever the transport. When given several times, answers received from each source are compared to detect split-horizon configurations.")
+                    .action(ArgAction::Append)
+                    .value_parser(clap::value_parser!(IpAddr))
+                    .value_name("IP")
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("unconnected-udp")
+                    .long("unconnected-udp")
+                    .long_help("Don't connect the UDP socket to the resolver. Answers coming from any address are accepted (e.g.: a multi-homed server answering from another address), but an ICMP port unreachable is not reported and ends as a timeout instead of a 'connection refused (ICMP)' error.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("pool")
+                    .long("pool")
+                    .long_help("Keep TCP, DoT, DoH and DoQ connections open and reuse them for the next queries to the same resolver (e.g.: with --replay, --shootout or --trace), instead of connecting and handshaking for each query. Pool statistics are printed at exit, and queries sent on a reused connection are reported with --stats.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("pool-max")
+                    .long("pool-max")
+                    .long_help("Maximum number of idle connections kept by --pool. The least recently used one is closed when the pool is full.")
+                    .action(ArgAction::Set)
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("8")
+                    .value_name("MAX")
+                    .requires("pool")
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("pool-idle-timeout")
+                    .long("pool-idle-timeout")
+                    .long_help("Idle connections of --pool not used for this long (in ms) are closed.")
+                    .action(ArgAction::Set)
+                    .value_parser(clap::value_parser!(u64))
+                    .default_value("30000")
+                    .value_name("TIMEOUT")
+                    .requires("pool")
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("keepalive")
+                    .long("keepalive")
+                    .long_help("Keep the connection of watch modes open between queries, and NAT bindings with it, by pinging the resolver every PERIOD ms: a query with the edns-tcp-keepalive option over TCP and DoT, a HTTP/2 PING over DoH, a QUIC PING over DoQ. Implies --pool. A connection silently dropped by the resolver is reported and reopened.")
+                    .action(ArgAction::Set)
+                    .value_parser(clap::value_parser!(u64).range(1..))
+                    .value_name("PERIOD")
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("parallel")
+                    .long("parallel")
+                    .long_help("When several types are queried, send all queries at once instead of waiting for each response before sending the next query. Over UDP, TCP and DoT the queries are pipelined on the same socket or connection and responses are matched by ID in whatever order they arrive. Over DoQ each query has its own stream on the same connection. Over DoH queries are still sent one after the other. Unanswered, truncated or EDNS-rejected queries are then resent one by one.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("all-servers")
+                    .long("all-servers")
+                    .long_help("When several @server are given, send the query to all of them and print out the results of each one. Without this option, the servers are tried one after the other until one of them answers.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("race")
+                    .long("race")
+                    .long_help("Send the query simultaneously over UDP to all addresses of the resolvers (e.g.: all host resolvers, all addresses of a resolver name or all the @server given), display the first valid answer and list the RTT of each server.")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with_all(["tcp", "tls", "https", "doq"])
+                    .help_heading("Transport options")
+            )
             .arg(
                 Arg::new("tcp")
                     .short('T')
@@ -343,6 +621,26 @@ Supported query types: {}
                     .value_name("TIMEOUT")
                     .help_heading("Transport options")
             )
+            .arg(
+                Arg::new("max-udp-retries")
+                    .long("max-udp-retries")
+                    .long_help("Over UDP, resend the query up to RETRIES times when no response is received before the timeout, instead of failing at the first timeout. Each retry waits twice as long as the previous one (see --udp-backoff), with a random jitter. Retries are logged with -v.")
+                    .action(ArgAction::Set)
+                    .value_parser(clap::value_parser!(u8))
+                    .default_value("0")
+                    .value_name("RETRIES")
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("udp-backoff")
+                    .long("udp-backoff")
+                    .long_help("Delay (in ms) before the first UDP retry of --max-udp-retries, doubled at each retry. Half of the delay is random to avoid synchronized retries.")
+                    .action(ArgAction::Set)
+                    .value_parser(clap::value_parser!(u64))
+                    .default_value("250")
+                    .value_name("BACKOFF")
+                    .help_heading("Transport options")
+            )
             .arg(
                 Arg::new("tls")
                     .short('S')
@@ -351,6 +649,14 @@ Supported query types: {}
                     .visible_aliases(["dot", "DoT"])
                     .action(ArgAction::SetTrue)
                     .value_name("TLS")
+                    .hide(cfg!(not(feature = "dot")))
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("dns0x20")
+                    .long("dns0x20")
+                    .long_help("Randomly mixes upper and lower case letters of the query name (DNS 0x20). The QNAME echoed by the server is shown when its case differs from what was sent.")
+                    .action(ArgAction::SetTrue)
                     .help_heading("Transport options")
             )
             .arg(
@@ -373,6 +679,63 @@ Supported query types: {}
                     .value_parser(["aa", "ad", "cd", "ra", "rd", "tc", "z"])
                     .help_heading("Transport options")
             )
+            .arg(
+                Arg::new("nta")
+                    .long("nta")
+                    .long_help("Negative trust anchor: names in ZONE are treated as insecure. The CD flag is set for queries below ZONE, so that the resolver returns data even if the zone signatures are broken. Can be given several times.")
+                    .action(ArgAction::Append)
+                    .value_name("ZONE")
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("craft")
+                    .long("craft")
+                    .long_help("Force header fields of the query, whatever its content, as a list of comma-separated field=value. Possible fields: id, qr, opcode, aa, tc, rd, ra, z, ad, cd, rcode, qdcount, ancount, nscount, arcount. Values are decimal or hexadecimal with a 0x prefix. Counts are not checked against the actual sections: this builds intentionally malformed messages for lab exercises. Ex: --craft qr=1,ancount=2,id=0xBEEF")
+                    .action(ArgAction::Set)
+                    .value_name("FIELDS")
+                    .value_parser(clap::value_parser!(HeaderCraft))
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("tsig-name")
+                    .long("tsig-name")
+                    .long_help("Sign the query with the TSIG key NAME. The base64 secret is read from the OS keyring (Secret Service with secret-tool on Linux, Keychain on macOS) under the 'dqy' service and the NAME account, or from the agent given with --tsig-agent. Secrets are thus never given on the command line.")
+                    .action(ArgAction::Set)
+                    .value_name("NAME")
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("tsig-algorithm")
+                    .long("tsig-algorithm")
+                    .long_help("HMAC algorithm of the TSIG key.")
+                    .action(ArgAction::Set)
+                    .value_name("ALGORITHM")
+                    .value_parser(clap::value_parser!(TsigAlgorithm))
+                    .default_value("hmac-sha256")
+                    .requires("tsig-name")
+                    .conflicts_with("tsig-file")
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("tsig-agent")
+                    .long("tsig-agent")
+                    .long_help("Unix socket of an agent holding TSIG secrets, queried instead of the OS keyring. The agent receives the key name followed by a newline and answers the base64 secret on one line.")
+                    .action(ArgAction::Set)
+                    .value_name("SOCKET")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .requires("tsig-name")
+                    .help_heading("Transport options")
+            )
+            .arg(
+                Arg::new("tsig-file")
+                    .long("tsig-file")
+                    .long_help("Read the TSIG key from a BIND key file or a named.conf snippet, as generated by tsig-keygen: key \"name\" { algorithm hmac-sha256; secret \"...\"; };. If the file holds several keys, the one to use is given with --tsig-name.")
+                    .action(ArgAction::Set)
+                    .value_name("FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .conflicts_with("tsig-agent")
+                    .help_heading("Transport options")
+            )
             //───────────────────────────────────────────────────────────────────────────────────
             // EDNS options
             //───────────────────────────────────────────────────────────────────────────────────   
@@ -386,10 +749,35 @@ Supported query types: {}
                     .value_name("BUFSIZE")
                     .help_heading("EDNS options")
             )
+            .arg(
+                Arg::new("chain")
+                    .long("chain")
+                    .long_help("Sets the EDNS CHAIN option in the OPT record with the closest trust point NAME (e.g.: '.'), to request the full chain of trust in one response (RFC7901). DNSSEC OK bit is set.")
+                    .action(ArgAction::Set)
+                    .value_name("NAME")
+                    .help_heading("EDNS options")
+            )
+            .arg(
+                Arg::new("ecs")
+                    .long("ecs")
+                    .long_help("Sets the EDNS Client Subnet option in the OPT record with SUBNET (e.g.: 192.0.2.0/24 or 2001:db8::/48), to get answers targeted to that network (RFC7871).")
+                    .action(ArgAction::Set)
+                    .value_name("SUBNET")
+                    .help_heading("EDNS options")
+            )
+            .arg(
+                Arg::new("ecs-sweep")
+                    .long("ecs-sweep")
+                    .long_help("Repeat the query with each EDNS Client Subnet listed in FILE (one per line) and group subnets by the answers received, to map CDN geo-targeting.")
+                    .action(ArgAction::Set)
+                    .value_name("FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .help_heading("EDNS options")
+            )
             .arg(
                 Arg::new("cookie")
                     .long("cookie")
-                    .long_help("Sets EDNS COOKIE option in OPT record.")
+                    .long_help("Sets EDNS COOKIE option in OPT record. Without value, the client cookie is derived from the client and server addresses, and the server cookie returned by each resolver is sent back in the next queries. With --cookie=HEX, the client cookie is the first 16 hex digits, possibly followed by a server cookie.")
                     .action(ArgAction::Set)
                     .value_name("COOKIE")
                     .num_args(0..=1)
@@ -397,6 +785,24 @@ Supported query types: {}
                     .require_equals(true)
                     .help_heading("EDNS options")
             )
+            .arg(
+                Arg::new("badcookie")
+                    .long("badcookie")
+                    .long_help("When the server answers BADCOOKIE, resend the query once with the server cookie it returned.")
+                    .action(ArgAction::SetTrue)
+                    .requires("cookie")
+                    .help_heading("EDNS options")
+            )
+            .arg(
+                Arg::new("cookie-jar")
+                    .long("cookie-jar")
+                    .long_help("File where the client secret and the server cookies are kept from one run to the other. Defaults to $XDG_CACHE_HOME/dqy/cookies.json or ~/.cache/dqy/cookies.json.")
+                    .action(ArgAction::Set)
+                    .value_name("FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .requires("cookie")
+                    .help_heading("EDNS options")
+            )
             // .arg(
             //     Arg::new("dau")
             //         .long("dau")
@@ -440,6 +846,24 @@ Supported query types: {}
             //         .value_parser(clap::value_parser!(u8))
             //         .help_heading("EDNS options")
             // )
+            .arg(
+                Arg::new("expire")
+                    .long("expire")
+                    .long_help("Sets the EDNS EXPIRE option in the OPT record for SOA, AXFR or IXFR queries. Primaries return the remaining expire timer of the zone (RFC7314).")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("EDNS options")
+            )
+            .arg(
+                Arg::new("key-tag")
+                    .long("key-tag")
+                    .long_help("Sets the EDNS edns-key-tag option in the OPT record with the list of trusted key tags (RFC8145). Ex: --key-tag 20326,38696")
+                    .value_delimiter(',')
+                    .action(ArgAction::Set)
+                    .value_parser(clap::value_parser!(u16))
+                    .num_args(1..)
+                    .value_name("KEYTAG")
+                    .help_heading("EDNS options")
+            )
             .arg(
                 Arg::new("no-opt")
                     .long("no-opt")
@@ -447,6 +871,13 @@ Supported query types: {}
                     .action(ArgAction::SetTrue)
                     .help_heading("EDNS options")
             )
+            .arg(
+                Arg::new("no-edns-fallback")
+                    .long("no-edns-fallback")
+                    .long_help("If set, the query is not resent without OPT record when the server replies FORMERR or NOTIMP without OPT, as servers not implementing EDNS do. The fallback is not done over DoQ.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("EDNS options")
+            )
             .arg(
                 Arg::new("nsid")
                     .long("nsid")
@@ -463,6 +894,35 @@ Supported query types: {}
                     .value_parser(clap::value_parser!(u16))
                     .help_heading("EDNS options")
             )
+            .arg(
+                Arg::new("probe-bufsize")
+                    .long("probe-bufsize")
+                    .long_help("Discover the maximum UDP response size deliverable to the client by sending the query with varying EDNS bufsize values. Use a type giving large responses (e.g.: DNSKEY).")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("EDNS options")
+            )
+            .arg(
+                Arg::new("frag-test")
+                    .long("frag-test")
+                    .long_help("Send the query over UDP with larger and larger EDNS bufsize values and report which responses are received, to find out whether fragmented responses are dropped on the path (e.g.: by a firewall or a NAT). DNSKEY is queried unless a type is given.")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("probe-bufsize")
+                    .help_heading("EDNS options")
+            )
+            .arg(
+                Arg::new("report-channel")
+                    .long("report-channel")
+                    .long_help("Sets an empty EDNS Report-Channel option in the OPT record. Agent domains returned by authoritative servers are always decoded (RFC9567).")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("EDNS options")
+            )
+            .arg(
+                Arg::new("reporting")
+                    .long("reporting")
+                    .long_help("For each response carrying a Report-Channel option and Extended DNS Errors, build the error report query _er.<qtype>.<qname>.<error>._er.<agent domain> and send it for type TXT, like a reporting resolver would do (RFC9567). Implies --report-channel.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("EDNS options")
+            )
             .arg(
                 Arg::new("zoneversion")
                     .long("zoneversion")
@@ -473,6 +933,28 @@ Supported query types: {}
             //───────────────────────────────────────────────────────────────────────────────────
             // Display options
             //───────────────────────────────────────────────────────────────────────────────────   
+            .arg(
+                Arg::new("analyze")
+                    .long("analyze")
+                    .long_help("Augment output with some heuristics on responses, e.g.: minimal responses, referrals without glue.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Display options")
+            )
+            .arg(
+                Arg::new("annotate")
+                    .long("annotate")
+                    .long_help("Append comments to each RR: absolute expiry time computed from the TTL, and for A and AAAA records the reverse name of the address and its special-use range (private, documentation, etc) if any.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Display options")
+            )
+            .arg(
+                Arg::new("annotate-ptr")
+                    .long("annotate-ptr")
+                    .long_help("With --annotate, resolve the PTR of the addresses found in the answers instead of only printing their reverse names.")
+                    .action(ArgAction::SetTrue)
+                    .requires("annotate")
+                    .help_heading("Display options")
+            )
             .arg(
                 Arg::new("align")
                     .long("align")
@@ -481,11 +963,35 @@ Supported query types: {}
                     .value_name("ALIGN")
                     .help_heading("Display options")
             )
+            .arg(
+                Arg::new("explain-flags")
+                    .long("explain-flags")
+                    .long_help("After the response header, explain each flag set and the response code.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Display options")
+            )
+            .arg(
+                Arg::new("group")
+                    .long("group")
+                    .long_help("Group RRs by RRset: one header line per RRset (owner, type, class, TTL) followed by indented RDATA lines. Useful for large TXT or MX sets.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Display options")
+            )
+            .arg(
+                Arg::new("width")
+                    .long("width")
+                    .long_help("Column widths for RR output, as a list of comma-separated column=width. Possible columns: name, type, class, ttl, length. With 'auto', widths are computed from the result set. Ex: --width name=40,type=8 or --width auto")
+                    .action(ArgAction::Set)
+                    .value_name("WIDTHS")
+                    .value_parser(clap::value_parser!(ColumnWidths))
+                    .help_heading("Display options")
+            )
             .arg(
                 Arg::new("fmt")
                     .long("fmt")
-                    .long_help("User-defined format for RR output. Specify a list of comma-separated fields. Possible values: name, type, length, class, ttl, rdata. For OPT record: payload, extcode, version, flags. Ex: -fmt 'type,name,ttl,rdata'")
+                    .long_help("User-defined format for RR output. Specify a list of comma-separated fields. Possible values: name, type, length, class, ttl, rdata. For OPT record: payload, extcode, version, flags. Ex: -fmt 'type,name,ttl,rdata'. Or a template with fields between braces, each followed by an optional list of types it applies to and an optional alignment (<, > or ^) and width, other text being printed as is. Ex: --fmt '{name:<40}{ttl:>8} {rdata@MX|SRV}'")
                     .action(ArgAction::Set)
+                    .value_parser(parse_fmt)
                     .value_name("FORMAT")
                     .help_heading("Display options")
             )
@@ -518,6 +1024,30 @@ Supported query types: {}
                     .action(ArgAction::SetTrue)
                     .help_heading("Display options")
             )
+            .arg(
+                Arg::new("json-version")
+                    .long("json-version")
+                    .long_help("Use the stable JSON schema with this version instead of the native JSON output, which might change between releases. Implies --json if neither --json nor --json-pretty is set.")
+                    .action(ArgAction::Set)
+                    .value_name("VERSION")
+                    .value_parser(clap::value_parser!(u8).range(1..=1))
+                    .help_heading("Display options")
+            )
+            .arg(
+                Arg::new("offsets")
+                    .long("offsets")
+                    .long_help("Add the offset and length in bytes of each RR in the message to the JSON output.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Display options")
+            )
+            .arg(
+                Arg::new("json-rfc8427")
+                    .long("json-rfc8427")
+                    .long_help("Messages are rendered using the DNS-in-JSON representation of RFC8427. Implies --json if neither --json nor --json-pretty is set.")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("json-version")
+                    .help_heading("Display options")
+            )
             // .arg(
             //     Arg::new("no-add")
             //         .long("no-add")
@@ -532,6 +1062,29 @@ Supported query types: {}
             //         .action(ArgAction::SetTrue)
             //         .help_heading("Display options")
             // )
+            .arg(
+                Arg::new("no-bogon-check")
+                    .long("no-bogon-check")
+                    .long_help("Don't warn about A or AAAA answers pointing at special-use address space (private, loopback, link local, documentation, etc), which might be a DNS rebinding attempt or a misconfiguration.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Display options")
+            )
+            .arg(
+                Arg::new("numeric")
+                    .long("numeric")
+                    .long_help("Print types, classes, opcodes, rcodes and algorithms as raw numbers instead of mnemonics. Useful to compare with packet captures. JSON output is not affected.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Display options")
+            )
+            .arg(
+                Arg::new("lang")
+                    .long("lang")
+                    .long_help("Language of section headers, errors and warnings: en or fr. Taken from the LC_ALL, LC_MESSAGES or LANG environment variables if not given, English being used for other languages.")
+                    .action(ArgAction::Set)
+                    .value_name("LANG")
+                    .value_parser(["en", "fr"])
+                    .help_heading("Display options")
+            )
             .arg(
                 Arg::new("no-colors")
                     .long("no-colors")
@@ -539,6 +1092,13 @@ Supported query types: {}
                     .action(ArgAction::SetTrue)
                     .help_heading("Display options")
             )
+            .arg(
+                Arg::new("screen-reader")
+                    .long("screen-reader")
+                    .long_help("Output suited to screen readers: each RR is printed on one line with labeled fields separated by commas (e.g.: name: example.com., type: A, class: IN, ttl: 60, data: 1.2.3.4) instead of aligned columns, and colors are disabled. --group is ignored.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Display options")
+            )
             .arg(
                 Arg::new("question")
                     .long("question")
@@ -574,6 +1134,44 @@ Supported query types: {}
             //         .action(ArgAction::SetTrue)
             //         .help_heading("Display options")
             // )
+            .arg(
+                Arg::new("privacy-report")
+                    .long("privacy-report")
+                    .long_help("Prints out a report of the metadata exposed by the query (plaintext or encrypted transport, ECS, padding, cookie, QNAME minimization) with suggestions.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Display options")
+            )
+            .arg(
+                Arg::new("time")
+                    .long("time")
+                    .visible_alias("date")
+                    .long_help("Prints out the RFC3339 timestamp of when each query was sent, in the stats or as the 'timestamp' field of each message in JSON.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Display options")
+            )
+            .arg(
+                Arg::new("stable")
+                    .long("stable")
+                    .long_help("Remove from the output what changes from one run to the other: message IDs and elapsed times are set to 0, TTLs counted down by caches are set to 0, timestamps, HTTP cache ages and annotations relative to now are removed, and network counters (retransmissions, reconnects, reused connections, lost packets) are reset. Text or JSON output can then be diffed against golden files.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Display options")
+            )
+            .arg(
+                Arg::new("wire-stats")
+                    .long("wire-stats")
+                    .long_help("Prints out the size of each section of the response, and how names are compressed: labels written, compression pointers and bytes saved.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Display options")
+            )
+            .arg(
+                Arg::new("simulate-bufsize")
+                    .long("simulate-bufsize")
+                    .long_help("Shows which RRs of the response would have been dropped, or would have made the response truncated, had it needed to fit in N bytes. Useful to tune minimal-responses and bufsize settings.")
+                    .action(ArgAction::Set)
+                    .value_parser(clap::value_parser!(u16).range(12..))
+                    .value_name("N")
+                    .help_heading("Display options")
+            )
             .arg(
                 Arg::new("stats")
                     .long("stats")
@@ -619,6 +1217,196 @@ Supported query types: {}
                     .action(ArgAction::SetTrue)
                     .help_heading("Display options")
             )
+            .arg(
+                Arg::new("check-resolvers")
+                    .long("check-resolvers")
+                    .long_help("Send a . NS query to each address of the resolvers (host resolvers if no @server is given) and print out whether it answers over UDP with its RTT, whether EDNS is supported and whether TCP and DoT (port 853) are available.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Display options")
+            )
+            .arg(
+                Arg::new("from-json")
+                    .long("from-json")
+                    .long_help("Build the query from its RFC8427 JSON description found in FILE (header flags, question, OPT record), instead of the command line.")
+                    .action(ArgAction::Set)
+                    .value_name("FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("replay")
+                    .long("replay")
+                    .long_help("Re-issue queries recorded in a session log FILE to the resolver and compare responses with the recorded ones.")
+                    .action(ArgAction::Set)
+                    .value_name("FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("replay-timing")
+                    .long("replay-timing")
+                    .long_help("When replaying a session log, wait between queries as in the original session.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("shootout")
+                    .long("shootout")
+                    .long_help("Compare the performance of the resolvers listed in the first FILE (one per line, same syntax as @server), by querying each domain listed in the second FILE. Latency percentiles and failure rate are reported for each resolver.")
+                    .action(ArgAction::Set)
+                    .num_args(2)
+                    .value_names(["RESOLVERS", "DOMAINS"])
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("warmup")
+                    .long("warmup")
+                    .long_help("Number of domains queried first to warm up each resolver cache before measuring, with --shootout.")
+                    .action(ArgAction::Set)
+                    .value_name("N")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("1")
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("fuzz-server")
+                    .long("fuzz-server")
+                    .long_help("Send N random but valid queries to the server: random names under the domain given, random types, header flags and EDNS options. Rcodes and failures are summarized, and anomalies are listed with the query which caused them: malformed responses, ID or question not echoed, FORMERR to a valid query, or the server not answering anymore.")
+                    .action(ArgAction::Set)
+                    .value_name("N")
+                    .value_parser(clap::value_parser!(usize))
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("csv")
+                    .long("csv")
+                    .long_help("Print out the answers as CSV, with the fields of --fmt as columns (name, type, class, ttl and rdata by default), or the --shootout comparison.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("rate")
+                    .long("rate")
+                    .long_help("Send at most QPS queries per second with --shootout, --replay, --ecs-sweep, --expect-zone or --fuzz-server.")
+                    .action(ArgAction::Set)
+                    .value_name("QPS")
+                    .value_parser(clap::value_parser!(u32).range(1..))
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("jitter")
+                    .long("jitter")
+                    .long_help("Delay each query of --shootout, --replay, --ecs-sweep, --expect-zone or --fuzz-server by a random time between 0 and MS milliseconds, on top of --rate. Scripts started at the same time then don't send synchronized bursts of queries.")
+                    .action(ArgAction::Set)
+                    .value_name("MS")
+                    .value_parser(clap::value_parser!(u64))
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("shuffle")
+                    .long("shuffle")
+                    .long_help("Query the lists of --shootout (resolvers and domains), --ecs-sweep (subnets) or --expect-zone (records) in a random order.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("stats-db")
+                    .long("stats-db")
+                    .long_help("Append the latency or failure of each query to the statistics database FILE (JSON lines), to follow resolvers across invocations.")
+                    .action(ArgAction::Set)
+                    .value_name("FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("stats-report")
+                    .long("stats-report")
+                    .long_help("Do not query but summarize the --stats-db database: number of queries, failure rate and average latency of each resolver, and how the latency of the last 24 hours compares to the previous ones.")
+                    .action(ArgAction::SetTrue)
+                    .requires("stats-db")
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("serve-stale")
+                    .long("serve-stale")
+                    .long_help("Keep the responses received in the cache FILE, and when the resolver doesn't answer or answers SERVFAIL, display the last response received for the same resolver, name and type, with its original TTLs and a warning giving its age (RFC8767). Responses with a 0 TTL are not cached, and are served up to 3 days after their TTL expired. FILE defaults to $XDG_CACHE_HOME/dqy/responses.json or ~/.cache/dqy/responses.json.")
+                    .action(ArgAction::Set)
+                    .value_name("FILE")
+                    .num_args(0..=1)
+                    .default_missing_value("")
+                    .require_equals(true)
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("expect-zone")
+                    .long("expect-zone")
+                    .long_help("Parse the zone FILE (RFC1035 format) and query each of its names and types, reporting records missing from the answers, extra records and differing RRsets. Names are relative to the domain given on the command line unless $ORIGIN is set. Ex: dqy @ns1.example.com example.com --expect-zone db.example.com")
+                    .action(ArgAction::Set)
+                    .value_name("FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("rebind-watch")
+                    .long("rebind-watch")
+                    .long_help("Send the query again and again every --interval ms until Ctrl-C or --deadline, and alert when the answers flip between public and private (special-use) address space, which is what a DNS rebinding attack does.")
+                    .action(ArgAction::SetTrue)
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("interval")
+                    .long("interval")
+                    .long_help("Time between 2 queries (in ms) for watch modes: 1000 ms by default for --rebind-watch, the SOA refresh value for --serial-watch.")
+                    .action(ArgAction::Set)
+                    .value_name("INTERVAL")
+                    .value_parser(clap::value_parser!(u64))
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("auto-interval")
+                    .long("auto-interval")
+                    .long_help("For watch modes, poll at the SOA refresh interval of the zone and at the SOA retry interval after a failed query, as secondary servers do.")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("interval")
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("serial-watch")
+                    .long("serial-watch")
+                    .long_help("Query the SOA of the zone at regular intervals (the SOA refresh value unless --interval is given) and log each serial change, until Ctrl-C, --deadline or the --target-serial is reached.")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("rebind-watch")
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("target-serial")
+                    .long("target-serial")
+                    .long_help("Stop --serial-watch once the SOA serial reaches this value (using serial number arithmetic), e.g. when waiting for a change to be published.")
+                    .action(ArgAction::Set)
+                    .value_name("SERIAL")
+                    .value_parser(clap::value_parser!(u32))
+                    .requires("serial-watch")
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("packet-log")
+                    .long("packet-log")
+                    .long_help("Append one JSON line per DNS message sent or received to FILE, whichever the mode: timestamp, direction, transport, peer and the message as base64, without the length prefix of streams.")
+                    .action(ArgAction::Set)
+                    .value_name("FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .help_heading("Miscellaneous options")
+            )
+            .arg(
+                Arg::new("session-log")
+                    .long("session-log")
+                    .long_help("Append one JSON line per query/response exchange to FILE (time the query was sent, endpoint, transport, query and response as base64, summary), whichever mode sends the queries.")
+                    .action(ArgAction::Set)
+                    .value_name("FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .help_heading("Miscellaneous options")
+            )
             .arg(
                 Arg::new("write-response")
                     .long("wr")
@@ -654,30 +1442,88 @@ Supported query types: {}
 
         let matches = cmd.get_matches_from(with_dash.iter());
 
+        // resolvers listed with --resolvers come after the @server ones
+        let listed = match matches.get_one::<String>("resolvers") {
+            Some(source) => read_resolvers(source)?,
+            None => Vec::new(),
+        };
+        servers.extend(listed.iter().map(String::as_str));
+
+        // the transport mode is guessed from the first resolver, the ODoH target being the
+        // resolver queried
+        let odoh_target = matches.get_one::<String>("odoh-target");
+        let server = match odoh_target {
+            Some(target) => target.as_str(),
+            None => servers.first().copied().unwrap_or_default(),
+        };
+
         //───────────────────────────────────────────────────────────────────────────────────
         // transport mode
         //───────────────────────────────────────────────────────────────────────────────────
         if matches.get_flag("tcp") {
             options.transport.transport_mode = Protocol::Tcp;
         }
+        // transports which are not compiled in are rejected, not replaced by UDP
         if matches.get_flag("tls") {
-            options.transport.transport_mode = Protocol::DoT;
-        }
-        if matches.get_flag("https") || server.starts_with("https://") {
-            options.transport.transport_mode = Protocol::DoH;
-
-            // set HTTP version
-            let v = matches.get_one::<String>("https-version").unwrap().to_string();
+            #[cfg(not(feature = "dot"))]
+            return Err(Error::Dns(crate::error::Dns::NotBuiltWith("dot")));
 
-            match v.as_str() {
-                "v1" => options.transport.https_version = Some(version::Version::HTTP_11),
-                "v2" => options.transport.https_version = Some(version::Version::HTTP_2),
-                "v3" => options.transport.https_version = Some(version::Version::HTTP_3),
-                _ => unimplemented!("this version of HTTP is not implemented"),
+            #[cfg(feature = "dot")]
+            {
+                options.transport.transport_mode = Protocol::DoT;
+            }
+        }
+        if matches.get_flag("https")
+            || matches.get_flag("doh-json")
+            || odoh_target.is_some()
+            || server.starts_with("https://")
+        {
+            #[cfg(not(feature = "doh"))]
+            return Err(Error::Dns(crate::error::Dns::NotBuiltWith("doh")));
+
+            #[cfg(feature = "doh")]
+            {
+                options.transport.transport_mode = Protocol::DoH;
+                options.transport.doh_json = matches.get_flag("doh-json");
+                options.transport.retry_after = matches.get_one::<u64>("retry-after").map(|s| Duration::from_secs(*s));
+                options.transport.no_proxy = matches.get_flag("no-proxy");
+
+                #[cfg(not(feature = "odoh"))]
+                if odoh_target.is_some() {
+                    return Err(Error::Dns(crate::error::Dns::NotBuiltWith("odoh")));
+                }
+                #[cfg(feature = "odoh")]
+                {
+                    options.transport.odoh = odoh_target.is_some();
+                    options.transport.odoh_proxy = matches.get_one::<String>("odoh-proxy").cloned();
+                }
+
+                // set HTTP version
+                let v = matches.get_one::<String>("https-version").unwrap().to_string();
+
+                match v.as_str() {
+                    "v1" => options.transport.https_version = Some(version::Version::HTTP_11),
+                    "v2" => options.transport.https_version = Some(version::Version::HTTP_2),
+                    #[cfg(not(feature = "doh3"))]
+                    "v3" => return Err(Error::Dns(crate::error::Dns::NotBuiltWith("doh3"))),
+                    #[cfg(feature = "doh3")]
+                    "v3" => options.transport.https_version = Some(version::Version::HTTP_3),
+                    _ => unimplemented!("this version of HTTP is not implemented"),
+                }
             }
         }
         if matches.get_flag("doq") || server.starts_with("quic://") {
-            options.transport.transport_mode = Protocol::DoQ;
+            #[cfg(not(feature = "doq"))]
+            return Err(Error::Dns(crate::error::Dns::NotBuiltWith("doq")));
+
+            #[cfg(feature = "doq")]
+            {
+                options.transport.transport_mode = Protocol::DoQ;
+            }
+        }
+        #[cfg(feature = "doq")]
+        {
+            options.transport.doq_migrate = matches.get_flag("doq-migrate");
         }
 
         //───────────────────────────────────────────────────────────────────────────────────
@@ -695,9 +1541,24 @@ Supported query types: {}
             // end point is build from these
             options.transport.endpoint = EndPoint::try_from((path, options.transport.port))?;
         }
-        // no server provided: we use the host resolver
+        // no server provided: we use the host resolver, or the fallback ones if there's none
         else if server.is_empty() {
-            options.transport.endpoint = EndPoint::try_from(options.transport.port)?;
+            let fallback: Vec<IpAddr> = if matches.get_flag("no-fallback-resolvers") {
+                Vec::new()
+            } else if let Some(ips) = matches.get_many::<IpAddr>("fallback-resolvers") {
+                ips.copied().collect()
+            } else {
+                FALLBACK_RESOLVERS.to_vec()
+            };
+
+            options.transport.endpoint = match EndPoint::try_from(options.transport.port) {
+                Ok(ep) if !ep.addrs.is_empty() => ep,
+                _ if !fallback.is_empty() => {
+                    options.transport.fallback_resolvers = true;
+                    EndPoint::from((fallback.as_slice(), options.transport.port))
+                }
+                host => host?,
+            };
         }
         // server was provided (e.g.: 1.1.1.1 or one.one.one.one)
         //
@@ -724,8 +1585,14 @@ Supported query types: {}
         if options.protocol.qtype.is_empty() {
             let vals: Vec<QType> = matches.get_many("type").unwrap().copied().collect();
             options.protocol.qtype = vals;
+
+            // fragmentation needs large responses
+            if matches.get_flag("frag-test") && matches.value_source("type") == Some(ValueSource::DefaultValue) {
+                options.protocol.qtype = vec![QType::DNSKEY];
+            }
         }
         options.protocol.qclass = *matches.get_one::<QClass>("class").unwrap();
+        options.protocol.dns0x20 = matches.get_flag("dns0x20");
 
         //───────────────────────────────────────────────────────────────────────────────────
         // ip versions (Any is by default)
@@ -765,11 +1632,54 @@ Supported query types: {}
         // only keep ipv4 or ipv6 addresses if -4 or -6 is provided
         options.transport.endpoint.retain(&options.transport.ip_version);
 
+        // or try first those of the preferred version
+        options.transport.prefer = match matches.get_one::<String>("prefer").map(String::as_str) {
+            Some("ipv4") => IPVersion::V4,
+            Some("ipv6") => IPVersion::V6,
+            _ => IPVersion::Any,
+        };
+        options.transport.endpoint.prefer(&options.transport.prefer);
+
         //───────────────────────────────────────────────────────────────────────────────────
         // timeout
         //───────────────────────────────────────────────────────────────────────────────────
         options.transport.timeout = Duration::from_millis(*matches.get_one::<u64>("timeout").unwrap());
 
+        // UDP retransmissions
+        options.transport.udp_retries = *matches.get_one::<u8>("max-udp-retries").unwrap();
+        options.transport.udp_backoff = Duration::from_millis(*matches.get_one::<u64>("udp-backoff").unwrap());
+
+        // all endpoint addresses at once
+        options.transport.race = matches.get_flag("race");
+        options.transport.udp_unconnected = matches.get_flag("unconnected-udp");
+        options.transport.parallel = matches.get_flag("parallel");
+
+        // reuse of TCP, DoT and DoH connections. Kept alive connections mustn't expire
+        // between 2 pings
+        options.transport.keepalive = matches.get_one::<u64>("keepalive").map(|p| Duration::from_millis(*p));
+        if matches.get_flag("pool") || options.transport.keepalive.is_some() {
+            let idle_timeout = Duration::from_millis(*matches.get_one::<u64>("pool-idle-timeout").unwrap());
+            options.transport.pool = Some(ConnectionPool::new(
+                *matches.get_one::<usize>("pool-max").unwrap(),
+                idle_timeout.max(options.transport.keepalive.unwrap_or_default() * 2),
+            ));
+        }
+
+        //───────────────────────────────────────────────────────────────────────────────────
+        // source addresses
+        //───────────────────────────────────────────────────────────────────────────────────
+        if let Some(ips) = matches.get_many::<IpAddr>("source-ip") {
+            options.transport.source_ips = ips.copied().collect();
+            options.transport.source = options.transport.source_ips.first().copied();
+        }
+
+        //───────────────────────────────────────────────────────────────────────────────────
+        // overall deadline
+        //───────────────────────────────────────────────────────────────────────────────────
+        if let Some(deadline) = matches.get_one::<u64>("deadline") {
+            options.transport.cancel = CancellationToken::default().with_deadline(Duration::from_millis(*deadline));
+        }
+
         //───────────────────────────────────────────────────────────────────────────────────
         // if reverse query, ignore all other options
         //───────────────────────────────────────────────────────────────────────────────────
@@ -781,27 +1691,20 @@ Supported query types: {}
             // try to convert to a valid IP address
             let addr = IpAddr::from_str(ip).map_err(|e| Error::IPParse(e, ip.to_string()))?;
 
-            if addr.is_ipv4() {
-                let mut limbs: Vec<_> = ip.split('.').collect();
-                limbs.reverse();
-                options.protocol.domain_string = format!("{}.in-addr.arpa", limbs.join("."));
-            } else {
-                // get individual u8 values because an ipv6 address might omit a heading 0
-                // ex: 2001:470:30:84:e276:63ff:fe72:3900 => 2001:0470:0030:84:e276:63ff:fe72:3900
-
-                // this will convert to ["2001", "0470", "0030", "0084", "e276", "63ff", "fe72", "3900"]
-                let split = ip
-                    .split(':') // split accordsing to ":"
-                    .map(|x| format!("{:0>4}", x)) // convert to string with heading 0
-                    .collect::<Vec<String>>()
-                    .join(""); // and finally join to get a whole string
-
-                // now reverse and join each digit with .
-                let mut domain: Vec<_> = split.split("").filter(|x| !x.is_empty()).collect();
-                domain.reverse();
+            options.protocol.domain_string = reverse_name(&addr);
+        }
 
-                options.protocol.domain_string = format!("{}.ip6.arpa", domain.join("."));
-            }
+        //───────────────────────────────────────────────────────────────────────────────────
+        // query described in a JSON file: question is taken from it
+        //───────────────────────────────────────────────────────────────────────────────────
+        if let Some(path) = matches.get_one::<PathBuf>("from-json") {
+            let json = std::fs::read_to_string(path).map_err(|e| Error::OpenFile(e, path.to_path_buf()))?;
+            let query = query_from_json(&json)?;
+
+            options.protocol.qtype = vec![query.question.qtype];
+            options.protocol.qclass = query.question.qclass;
+            options.protocol.domain_string = query.question.qname.to_string();
+            options.protocol.from_json = Some(json);
         }
 
         //───────────────────────────────────────────────────────────────────────────────────
@@ -833,15 +1736,54 @@ Supported query types: {}
         }
         trace!("options flags: {:?}", options.flags);
 
+        // forced header fields: opcode and rcode must be representable
+        if let Some(craft) = matches.get_one::<HeaderCraft>("craft") {
+            craft.apply(&mut Header::default())?;
+            options.protocol.craft = Some(craft.clone());
+        }
+
+        // TSIG key is read from a key file, or its secret is fetched from the keyring or an agent
+        if let Some(path) = matches.get_one::<PathBuf>("tsig-file") {
+            let name = matches.get_one::<String>("tsig-name");
+            options.protocol.tsig = Some(read_key_file(path, name.map(|n| n.as_str()))?);
+        } else if let Some(name) = matches.get_one::<String>("tsig-name") {
+            let agent = matches.get_one::<PathBuf>("tsig-agent");
+            let secret = fetch_secret(name, agent.map(|p| p.as_path()))?;
+            let algorithm = *matches.get_one::<TsigAlgorithm>("tsig-algorithm").unwrap();
+            options.protocol.tsig = Some(TsigKey::new(name, algorithm, &secret)?);
+        }
+
+        // negative trust anchors
+        if let Some(zones) = matches.get_many::<String>("nta") {
+            options.protocol.nta = zones
+                .map(|z| DomainName::try_from(z.as_str()))
+                .collect::<Result<_, _>>()?;
+        }
+
         //───────────────────────────────────────────────────────────────────────────────────
         // EDNS or OPT record and options
         //───────────────────────────────────────────────────────────────────────────────────
         options.edns.no_opt = matches.get_flag("no-opt");
+        options.edns.no_fallback = matches.get_flag("no-edns-fallback");
         options.edns.dnssec = matches.get_flag("dnssec");
         options.edns.nsid = matches.get_flag("nsid");
         options.edns.zoneversion = matches.get_flag("zoneversion");
+        options.edns.expire = matches.get_flag("expire");
+        options.edns.reporting = matches.get_flag("reporting");
+        options.edns.report_channel = matches.get_flag("report-channel") || options.edns.reporting;
+        options.edns.probe_bufsize = matches.get_flag("probe-bufsize");
+        options.edns.frag_test = matches.get_flag("frag-test");
         options.edns.padding = matches.get_one::<u16>("padding").copied();
 
+        // CHAIN needs DNSSEC records
+        if let Some(closest) = matches.get_one::<String>("chain") {
+            options.edns.chain = Some(DomainName::try_from(closest.as_str())?);
+            options.edns.dnssec = true;
+        }
+
+        options.edns.keytag = matches
+            .get_many::<u16>("key-tag")
+            .map(|v| v.copied().collect::<Vec<u16>>());
         // options.edns.dau = matches.get_many::<u8>("dau").map(|v| v.copied().collect::<Vec<u8>>());
         // options.edns.dhu = matches.get_many::<u8>("dhu").map(|v| v.copied().collect::<Vec<u8>>());
         // options.edns.n3u = matches.get_many::<u8>("n3u").map(|v| v.copied().collect::<Vec<u8>>());
@@ -853,15 +1795,44 @@ Supported query types: {}
             if let Some(cookie) = matches.get_one::<String>("cookie") {
                 options.edns.cookie = Some(cookie.clone());
             }
+            options.edns.badcookie = matches.get_flag("badcookie");
+            options.edns.cookie_jar = matches
+                .get_one::<PathBuf>("cookie-jar")
+                .cloned()
+                .or_else(cookies::default_path);
+        }
+
+        // ECS
+        if let Some(subnet) = matches.get_one::<String>("ecs") {
+            ClientSubnet::from_str(subnet)?;
+            options.edns.client_subnet = Some(subnet.clone());
+        }
+        if let Some(path) = matches.get_one::<PathBuf>("ecs-sweep") {
+            options.edns.ecs_sweep = Some(path.to_path_buf());
         }
 
         //───────────────────────────────────────────────────────────────────────────────────
         // manage display options
         //───────────────────────────────────────────────────────────────────────────────────
         options.display.align_names = matches.get_flag("align");
+        options.display.group = matches.get_flag("group");
+        options.display.explain_flags = matches.get_flag("explain-flags");
+        if let Some(widths) = matches.get_one::<ColumnWidths>("width") {
+            options.display.widths = widths.clone();
+        }
+        options.display.analyze = matches.get_flag("analyze");
+        options.display.bogon_check = !matches.get_flag("no-bogon-check");
+        options.display.annotate = matches.get_flag("annotate");
+        options.display.annotate_ptr = matches.get_flag("annotate-ptr");
+        options.display.privacy_report = matches.get_flag("privacy-report");
         options.display.show_headers = matches.get_flag("headers");
         options.display.json = matches.get_flag("json");
         options.display.json_pretty = matches.get_flag("json-pretty");
+        options.display.json_version = matches.get_one::<u8>("json-version").copied();
+        options.display.json_rfc8427 = matches.get_flag("json-rfc8427");
+        if (options.display.json_version.is_some() || options.display.json_rfc8427) && !options.display.json_pretty {
+            options.display.json = true;
+        }
         // options.display.no_additional = matches.get_flag("no-add");
         // options.display.no_authorative = matches.get_flag("no-auth");
         options.display.show_question = matches.get_flag("question");
@@ -870,6 +1841,10 @@ Supported query types: {}
         options.display.show_all = matches.get_flag("show-all");
         //options.display.show_opt = matches.get_flag("show-opt");
         options.display.stats = matches.get_flag("stats");
+        options.display.timestamp = matches.get_flag("time");
+        options.display.stable = matches.get_flag("stable");
+        options.display.wire_stats = matches.get_flag("wire-stats");
+        options.display.simulate_bufsize = matches.get_one::<u16>("simulate-bufsize").copied();
         options.display.puny = matches.get_flag("puny");
 
         // handlebars template
@@ -903,19 +1878,44 @@ Supported query types: {}
             options.display.align_names = true;
         }
 
+        // labeled fields instead of columns, without colors
+        options.display.screen_reader = matches.get_flag("screen-reader");
+
         // if no-colors, sets the NO_COLOR variable
-        if matches.get_flag("no-colors") {
+        if matches.get_flag("no-colors") || options.display.screen_reader {
             std::env::set_var("NO_COLOR", "1");
         }
 
+        // language of messages
+        let lang = matches.get_one::<String>("lang").map(|l| Lang::from_str(l).unwrap());
+        set_lang(lang.unwrap_or_else(Lang::from_env));
+
+        // RR offsets in JSON output
+        set_offsets(matches.get_flag("offsets"));
+
+        // raw numbers instead of mnemonics, except for JSON
+        if matches.get_flag("numeric") && !options.display.json && !options.display.json_pretty {
+            set_numeric(true);
+        }
+
         if let Some(fmt) = matches.get_one::<String>("fmt") {
-            options.display.fmt = fmt.to_string();
+            // already checked by parse_fmt()
+            if is_template(fmt) {
+                options.display.template = Template::from_str(fmt).ok();
+            } else {
+                options.display.fmt = fmt.to_string();
+            }
         }
 
         //───────────────────────────────────────────────────────────────────────────────────
         // manage other misc. options
         //───────────────────────────────────────────────────────────────────────────────────
         options.display.trace = matches.get_flag("trace");
+        options.dump.follow_svcb = matches.get_flag("follow-svcb");
+        options.dump.browser_view = matches.get_flag("browser-view");
+        options.dump.service = matches.get_one::<String>("service").cloned();
+        options.dump.browse = matches.get_one::<String>("browse").cloned();
+        options.dump.all_types = matches.get_flag("all-types");
 
         //───────────────────────────────────────────────────────────────────────────────────
         // finally convert domain as a string to a domain name
@@ -953,11 +1953,47 @@ Supported query types: {}
         if let Some(d) = matches.get_one::<String>("sni") {
             options.transport.endpoint.sni = Some(d.to_string());
         }
-        options.transport.alpn = matches.get_flag("alpn");
+        #[cfg(feature = "dot")]
+        {
+            options.transport.alpn = matches.get_flag("alpn");
+        }
+
+        //───────────────────────────────────────────────────────────────────────────────────
+        // several @server: the others are used for failover or with --all-servers
+        //───────────────────────────────────────────────────────────────────────────────────
+        if servers.len() > 1 && !matches.contains_id("resolve-file") {
+            let mut endpoints = vec![options.transport.endpoint.clone()];
+            for server in &servers[1..] {
+                let mut endpoint = EndPoint::new(server, options.transport.port)?;
+                endpoint.retain(&options.transport.ip_version);
+                endpoint.prefer(&options.transport.prefer);
+                endpoint.sni = options.transport.endpoint.sni.clone();
+                endpoints.push(endpoint);
+            }
+            options.transport.endpoints = endpoints;
+        }
+        options.transport.all_servers = matches.get_flag("all-servers");
+
+        //───────────────────────────────────────────────────────────────────────────────────
+        // TLS versions
+        //───────────────────────────────────────────────────────────────────────────────────
+        #[cfg(any(feature = "dot", feature = "doh"))]
+        {
+            let version = |id: &str| match matches.get_one::<String>(id).unwrap().as_str() {
+                "1.2" => TlsVersion::Tls12,
+                _ => TlsVersion::Tls13,
+            };
+            options.transport.tls_min = version("tls-min");
+            options.transport.tls_max = version("tls-max");
+            if options.transport.tls_min > options.transport.tls_max {
+                return Err(Error::Dns(crate::error::Dns::InvalidTlsVersions));
+            }
+        }
 
         //───────────────────────────────────────────────────────────────────────────────────
         // Cert file
         //───────────────────────────────────────────────────────────────────────────────────
+        #[cfg(any(feature = "dot", feature = "doh"))]
         if let Some(path) = matches.get_one::<PathBuf>("cert") {
             // read PEM file
             let mut buf = Vec::new();
@@ -968,6 +2004,10 @@ Supported query types: {}
 
             options.transport.cert = Some(buf);
         }
+        #[cfg(any(feature = "dot", feature = "doh"))]
+        if matches.get_flag("webpki-roots") {
+            options.transport.roots = Roots::WebPki;
+        }
 
         //───────────────────────────────────────────────────────────────────────────────────
         // Dump options
@@ -978,6 +2018,48 @@ Supported query types: {}
             }
         }
 
+        if let Some(path) = matches.get_one::<PathBuf>("replay") {
+            options.dump.replay = Some(path.to_path_buf());
+        }
+        options.dump.replay_timing = matches.get_flag("replay-timing");
+
+        if let Some(mut paths) = matches.get_many::<PathBuf>("shootout") {
+            let resolvers = paths.next().unwrap().to_path_buf();
+            let domains = paths.next().unwrap().to_path_buf();
+            options.dump.shootout = Some((resolvers, domains));
+        }
+        options.dump.warmup = *matches.get_one::<usize>("warmup").unwrap();
+        options.dump.expect_zone = matches.get_one::<PathBuf>("expect-zone").cloned();
+        options.dump.csv = matches.get_flag("csv");
+        options.display.csv = options.dump.csv;
+        options.dump.check_resolvers = matches.get_flag("check-resolvers");
+        options.dump.fuzz_server = matches.get_one::<usize>("fuzz-server").copied();
+        options.dump.stats_db = matches.get_one::<PathBuf>("stats-db").cloned();
+        options.dump.serve_stale = matches
+            .get_one::<String>("serve-stale")
+            .and_then(|path| match path.as_str() {
+                "" => cache_dir().map(|dir| dir.join("responses.json")),
+                _ => Some(PathBuf::from(path)),
+            });
+        options.dump.stats_report = matches.get_flag("stats-report");
+
+        // pacing of bulk runs
+        options.dump.rate = matches.get_one::<u32>("rate").copied();
+        options.dump.jitter = matches.get_one::<u64>("jitter").map(|ms| Duration::from_millis(*ms));
+        options.dump.shuffle = matches.get_flag("shuffle");
+
+        // watch modes
+        options.dump.rebind_watch = matches.get_flag("rebind-watch");
+        options.dump.serial_watch = matches.get_flag("serial-watch");
+        options.dump.target_serial = matches.get_one::<u32>("target-serial").copied();
+        options.dump.interval = matches.get_one::<u64>("interval").map(|i| Duration::from_millis(*i));
+        options.dump.auto_interval = matches.get_flag("auto-interval");
+
+        if let Some(path) = matches.get_one::<PathBuf>("session-log") {
+            options.dump.session_log = Some(path.to_path_buf());
+        }
+        options.dump.packet_log = matches.get_one::<PathBuf>("packet-log").cloned();
+
         if let Some(path) = matches.get_one::<PathBuf>("write-response") {
             if options.protocol.qtype.len() == 1 {
                 options.dump.write_response = Some(path.to_path_buf());
@@ -996,6 +2078,24 @@ Supported query types: {}
     }
 }
 
+// resolvers given with --resolvers: one per line, from stdin if source is '-'
+fn read_resolvers(source: &str) -> crate::error::Result<Vec<String>> {
+    let mut content = String::new();
+    let read = if source == "-" {
+        std::io::stdin().read_to_string(&mut content)
+    } else {
+        File::open(source).and_then(|mut f| f.read_to_string(&mut content))
+    };
+    read.map_err(|e| Error::OpenFile(e, PathBuf::from(source)))?;
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| l.trim_start_matches('@').to_string())
+        .collect())
+}
+
 // display list of found host resolvers and try to bind
 fn list_resolvers(trp_options: &TransportOptions) {
     for addr in &trp_options.endpoint.addrs {
@@ -1150,6 +2250,37 @@ mod tests {
         assert_eq!(&opts.transport.endpoint.server_name, &"2606:4700:4700::1111");
     }
 
+    #[test]
+    fn with_servers() {
+        let opts = CliOptions::from_str("A www.google.com @1.1.1.1 @9.9.9.9:5353 --all-servers").unwrap();
+
+        assert_eq!(&opts.transport.endpoint.server_name, "1.1.1.1");
+        let servers: Vec<_> = opts
+            .transport
+            .endpoints
+            .iter()
+            .map(|e| e.server_name.as_str())
+            .collect();
+        assert_eq!(servers, vec!["1.1.1.1", "9.9.9.9"]);
+        assert_eq!(opts.transport.endpoints[1].port, 5353);
+        assert!(opts.transport.all_servers);
+
+        // a single server needs no list
+        let opts = CliOptions::from_str("A www.google.com @1.1.1.1").unwrap();
+        assert!(opts.transport.endpoints.is_empty());
+
+        // resolvers from a file come after the @server ones
+        let opts = CliOptions::from_str("A www.google.com @8.8.8.8 --resolvers ./tests/resolvers.txt").unwrap();
+        let servers: Vec<_> = opts
+            .transport
+            .endpoints
+            .iter()
+            .map(|e| e.server_name.as_str())
+            .collect();
+        assert_eq!(servers, vec!["8.8.8.8", "1.1.1.1", "9.9.9.9"]);
+        assert_eq!(opts.transport.endpoints[2].port, 5353);
+    }
+
     #[test]
     fn with_tcp() {
         let opts = CliOptions::from_str("@2606:4700:4700::1111 A AAAA MX www.google.com --tcp -6");
@@ -1189,6 +2320,38 @@ mod tests {
         assert!(!opts.flags.authorative_answer);
     }
 
+    #[test]
+    fn nta() {
+        use crate::cli_options::FromOptions;
+        use crate::dns::rfc::query::Query;
+
+        let opts = CliOptions::from_str("@1.1.1.1 A www.example.com --nta example.com --nta example.org").unwrap();
+        assert_eq!(opts.protocol.nta.len(), 2);
+
+        // CD is set below the negative trust anchor only
+        let query = Query::from_options(&opts, &QType::A).unwrap();
+        assert!(query.header.flags.bitflags().checking_disabled);
+
+        let opts = CliOptions::from_str("@1.1.1.1 A www.example.net --nta example.com").unwrap();
+        let query = Query::from_options(&opts, &QType::A).unwrap();
+        assert!(!query.header.flags.bitflags().checking_disabled);
+    }
+
+    #[test]
+    fn craft() {
+        use crate::cli_options::FromOptions;
+        use crate::dns::rfc::query::Query;
+
+        // counts are kept even if the OPT record is added
+        let opts = CliOptions::from_str("@1.1.1.1 A www.example.com --craft qr=1,arcount=0,id=0xBEEF").unwrap();
+        let query = Query::from_options(&opts, &QType::A).unwrap();
+        assert!(query.header.flags.is_response());
+        assert_eq!(query.header.ar_count, 0);
+        assert_eq!(query.header.id, 0xBEEF);
+
+        assert!(CliOptions::from_str("@1.1.1.1 A www.example.com --craft opcode=12").is_err());
+    }
+
     //#[test]
     fn with_env() {
         std::env::set_var("DQY_FLAGS", "@1.1.1.1 --dnssec");
@@ -0,0 +1,78 @@
+//! Answers pointing at special-use address space (private, loopback, link local,
+//! documentation, etc): a public name resolving there is a possible DNS rebinding
+//! attempt or a misconfiguration.
+use std::fmt;
+use std::net::IpAddr;
+
+use serde::Serialize;
+
+use crate::annotate::special_use;
+use crate::dns::rfc::response::Response;
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Bogon {
+    pub name: String,
+    pub address: IpAddr,
+    pub range: &'static str,
+}
+
+impl fmt::Display for Bogon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} points to {} ({}): possible DNS rebinding or misconfiguration",
+            self.name, self.address, self.range
+        )
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// return the A and AAAA answers in special-use ranges
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn bogons(resp: &Response) -> Vec<Bogon> {
+    resp.answer
+        .iter()
+        .flat_map(|list| list.iter())
+        .filter_map(|rr| {
+            let address = rr.ip_address()?;
+            let range = special_use(&address)?;
+            Some(Bogon {
+                name: rr.name.to_string(),
+                address,
+                range,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use type2network::FromNetworkOrder;
+
+    #[test]
+    fn private_answer() {
+        // example.com A 10.0.0.1 and 93.184.215.14
+        let wire: &[u8] = &[
+            0x00, 0x01, 0x81, 0x80, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, // header
+            0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o', b'm', 0x00, 0x00, 0x01, 0x00,
+            0x01, // question
+            0xC0, 0x0C, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x0E, 0x10, 0x00, 0x04, 10, 0, 0, 1, // private
+            0xC0, 0x0C, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x0E, 0x10, 0x00, 0x04, 93, 184, 215, 14,
+        ];
+
+        let mut resp = Response::default();
+        resp.deserialize_from(&mut std::io::Cursor::new(wire)).unwrap();
+
+        let found = bogons(&resp);
+        assert_eq!(
+            found,
+            vec![Bogon {
+                name: String::from("example.com."),
+                address: "10.0.0.1".parse().unwrap(),
+                range: "private (RFC1918)"
+            }]
+        );
+        assert!(found[0].to_string().starts_with("example.com. points to 10.0.0.1"));
+    }
+}
@@ -0,0 +1,251 @@
+//! Response cache for --serve-stale (RFC8767): responses are kept in a JSON file, and when
+//! the resolver fails (no response, or SERVFAIL) the last response received for the same
+//! resolver, name and type is displayed instead, with its original TTLs and a warning
+//! giving its age. Responses with a 0 TTL are never cached (RFC1035 section 3.2.1).
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use type2network::FromNetworkOrder;
+
+use crate::args::CliOptions;
+use crate::cli_options::FromOptions;
+use crate::dns::message::{Message, MessageList};
+use crate::dns::rfc::{qtype::QType, query::Query, response::Response, response_code::ResponseCode};
+use crate::error::{Error, ErrorClass, Result};
+use crate::i18n::fill;
+use crate::transport::NetworkInfo;
+use crate::warnings::{Kind, Warnings};
+
+// stale responses are served up to 3 days after their TTL expired (RFC8767 section 5)
+const MAX_STALE: i64 = 3 * 24 * 3600;
+
+// $XDG_CACHE_HOME/dqy or ~/.cache/dqy
+pub fn cache_dir() -> Option<PathBuf> {
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(cache.join("dqy"))
+}
+
+// lowest TTL of the answer and authority sections, None if there's no record
+fn min_ttl(response: &Response) -> Option<u32> {
+    [response.answer.as_ref(), response.authority()]
+        .into_iter()
+        .flatten()
+        .flat_map(|list| list.iter())
+        .filter_map(|rr| rr.opt_or_class_ttl.regular().map(|r| r.ttl()))
+        .min()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    // when the response was received, as a UNIX timestamp
+    received: i64,
+
+    // wire data as base64
+    response: String,
+}
+
+impl Entry {
+    fn response(&self) -> Option<(Response, Vec<u8>)> {
+        let raw = general_purpose::STANDARD.decode(&self.response).ok()?;
+        let mut response = Response::default();
+        response.deserialize_from(&mut Cursor::new(raw.as_slice())).ok()?;
+        Some((response, raw))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+struct Cache(BTreeMap<String, Entry>);
+
+impl Cache {
+    fn load(path: &Path) -> Self {
+        File::open(path)
+            .ok()
+            .and_then(|f| serde_json::from_reader(BufReader::new(f)).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| Error::OpenFile(e, dir.to_path_buf()))?;
+        }
+        let f = File::create(path).map_err(|e| Error::OpenFile(e, path.to_path_buf()))?;
+        serde_json::to_writer(f, self).map_err(|e| Error::OpenFile(e.into(), path.to_path_buf()))
+    }
+
+    // the same name and type asked to another resolver is another entry
+    fn key(options: &CliOptions, qtype: &QType) -> String {
+        format!(
+            "{} {} {} {}",
+            options.transport.endpoint.server_name,
+            options.transport.transport_mode,
+            options.protocol.domain_name,
+            qtype
+        )
+    }
+
+    // keep a response unless it must not be cached
+    fn store(&mut self, options: &CliOptions, msg: &Message, now: i64) {
+        let key = Self::key(options, &msg.query.question.qtype);
+        let cacheable = matches!(msg.response.rcode(), ResponseCode::NoError | ResponseCode::NXDomain)
+            && min_ttl(&msg.response).is_some_and(|ttl| ttl > 0);
+        if !cacheable {
+            debug!("response for {} not cached", key);
+            return;
+        }
+
+        let entry = Entry {
+            received: now,
+            response: general_purpose::STANDARD.encode(&msg.raw_response),
+        };
+        self.0.insert(key, entry);
+    }
+
+    // the cached message with its age, if not too old to be served
+    fn stale(&self, options: &CliOptions, qtype: &QType, now: i64) -> Option<(Message, i64, u32)> {
+        let entry = self.0.get(&Self::key(options, qtype))?;
+        let (response, raw_response) = entry.response()?;
+        let age = now - entry.received;
+        let ttl = min_ttl(&response)?;
+        if age > ttl as i64 + MAX_STALE {
+            debug!("cached response for {} expired {}s ago", qtype, age - ttl as i64);
+            return None;
+        }
+
+        let msg = Message {
+            query: Query::from_options(options, qtype)?,
+            response,
+            netinfo: NetworkInfo::default(),
+            raw_response,
+            elapsed: Default::default(),
+            timestamp: None,
+        };
+        Some((msg, age, ttl))
+    }
+}
+
+fn warn_stale(warnings: &mut Warnings, qtype: QType, reason: &str, age: i64, ttl: u32) {
+    warnings.push(
+        Kind::Stale,
+        qtype,
+        fill(
+            "STALE answer served from cache ({}), received {}s ago with a {}s TTL",
+            &[&reason, &age, &ttl],
+        ),
+    );
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// --serve-stale: cache the responses received, and replace the failed ones by their
+// cached counterpart
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn serve_stale(
+    path: &Path,
+    options: &CliOptions,
+    result: Result<MessageList>,
+    warnings: &mut Warnings,
+) -> Result<MessageList> {
+    let mut cache = Cache::load(path);
+    let now = Utc::now().timestamp();
+
+    match result {
+        Ok(mut messages) => {
+            for msg in messages.iter_mut() {
+                let qtype = msg.query.question.qtype;
+                if msg.response.rcode() != ResponseCode::ServFail {
+                    cache.store(options, msg, now);
+                } else if let Some((stale, age, ttl)) = cache.stale(options, &qtype, now) {
+                    warn_stale(warnings, qtype, "SERVFAIL", age, ttl);
+                    *msg = stale;
+                }
+            }
+            cache.save(path)?;
+            Ok(messages)
+        }
+
+        // all types must be in the cache, otherwise the error is reported
+        Err(e) if e.class() != ErrorClass::Cancelled => {
+            let stale: Option<Vec<_>> = options
+                .protocol
+                .qtype
+                .iter()
+                .map(|qtype| cache.stale(options, qtype, now))
+                .collect();
+            let Some(stale) = stale else {
+                return Err(e);
+            };
+
+            let reason = e.localized();
+            let mut messages = Vec::with_capacity(stale.len());
+            for (msg, age, ttl) in stale {
+                warn_stale(warnings, msg.query.question.qtype, &reason, age, ttl);
+                messages.push(msg);
+            }
+            Ok(MessageList::new(messages))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::rfc::domain::DomainName;
+
+    fn message(options: &CliOptions, raw_response: Vec<u8>) -> Message {
+        let mut response = Response::default();
+        response
+            .deserialize_from(&mut Cursor::new(raw_response.as_slice()))
+            .unwrap();
+        Message {
+            query: Query::from_options(options, &QType::A).unwrap(),
+            response,
+            netinfo: NetworkInfo::default(),
+            raw_response,
+            elapsed: Default::default(),
+            timestamp: None,
+        }
+    }
+
+    // www.example.com. A 192.0.2.1 with the TTL given
+    fn raw_response(ttl: u32) -> Vec<u8> {
+        let mut raw = vec![
+            0x12, 0x34, 0x81, 0x80, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, // header
+            0x03, b'w', b'w', b'w', 0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o', b'm', 0x00, 0x00,
+            0x01, 0x00, 0x01, // question
+            0xC0, 0x0C, 0x00, 0x01, 0x00, 0x01, // answer
+        ];
+        raw.extend_from_slice(&ttl.to_be_bytes());
+        raw.extend_from_slice(&[0x00, 0x04, 192, 0, 2, 1]);
+        raw
+    }
+
+    #[test]
+    fn stale() {
+        let mut options = CliOptions::default();
+        options.protocol.domain_name = DomainName::try_from("www.example.com.").unwrap();
+        let mut cache = Cache::default();
+
+        // 0 TTL: not cached
+        cache.store(&options, &message(&options, raw_response(0)), 1000);
+        assert!(cache.stale(&options, &QType::A, 1000).is_none());
+
+        cache.store(&options, &message(&options, raw_response(300)), 1000);
+        let (msg, age, ttl) = cache.stale(&options, &QType::A, 1600).unwrap();
+        assert_eq!((age, ttl), (600, 300));
+        assert_eq!(msg.answers(), message(&options, raw_response(300)).answers());
+
+        // too old or another type
+        assert!(cache.stale(&options, &QType::A, 1300 + MAX_STALE + 1).is_none());
+        assert!(cache.stale(&options, &QType::AAAA, 1600).is_none());
+    }
+}
@@ -1,15 +1,24 @@
 //! Manage command line arguments here.
 use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
 
 use log::trace;
 
 use crate::args::CliOptions;
 use crate::dns::rfc::domain::ROOT;
+use crate::dns::rfc::opt::chain::CHAIN;
+use crate::dns::rfc::opt::client_subnet::ClientSubnet;
 use crate::dns::rfc::opt::cookie::COOKIE;
+use crate::dns::rfc::opt::report_chanel::ReportChannel;
 use crate::dns::rfc::opt::zoneversion::ZONEVERSION;
 use crate::dns::rfc::{
     domain::{DomainName, ROOT_DOMAIN},
+    header::HeaderCraft,
     opt::{
+        dau_dhu_n3u::EdnsKeyTag,
+        expire::EXPIRE,
+        keepalive::TcpKeepalive,
         //dau_dhu_n3u::{EdnsKeyTag, DAU, DHU, N3U},
         nsid::NSID,
         //opt_rr::OPT,
@@ -19,7 +28,9 @@ use crate::dns::rfc::{
     qtype::QType,
     query::{MetaRR, Query},
     resource_record::OPT,
+    tsig::{TsigKey, TSIG},
 };
+use crate::json::query_from_json;
 
 // DNSSEC OK
 const DNSSEC_FLAG: u16 = 0x8000;
@@ -39,9 +50,33 @@ pub struct EdnsOptions {
     // add COOKIE option
     pub cookie: Option<String>,
 
+    // resend once with the new server cookie when the server answers BADCOOKIE
+    pub badcookie: bool,
+
+    // server cookies kept from one run to the other
+    pub cookie_jar: Option<PathBuf>,
+
+    // add EDNS Client Subnet option (e.g.: 192.0.2.0/24)
+    pub client_subnet: Option<String>,
+
+    // file of client subnets to sweep, comparing answers per subnet
+    pub ecs_sweep: Option<PathBuf>,
+
     // add ZONEVERSION option if true
     pub zoneversion: bool,
 
+    // add EXPIRE option to SOA, AXFR or IXFR queries
+    pub expire: bool,
+
+    // add CHAIN option with the closest trust point
+    pub chain: Option<DomainName>,
+
+    // add an empty Report-Channel option
+    pub report_channel: bool,
+
+    // send error reports to the agent domain found in responses
+    pub reporting: bool,
+
     // padding if the form of +padding=20
     pub padding: Option<u16>,
 
@@ -55,6 +90,15 @@ pub struct EdnsOptions {
 
     // if true, OPT is included
     pub no_opt: bool,
+
+    // if true, don't resend without OPT when the server rejects EDNS
+    pub no_fallback: bool,
+
+    // discover the maximum UDP response size deliverable to the client
+    pub probe_bufsize: bool,
+
+    // find out whether fragmented UDP responses are dropped on the path
+    pub frag_test: bool,
 }
 
 //───────────────────────────────────────────────────────────────────────────────────
@@ -75,6 +119,21 @@ pub struct DnsProtocolOptions {
 
     // domain name but converted to a DomainName struct
     pub domain_name: DomainName,
+
+    // randomize the QNAME case
+    pub dns0x20: bool,
+
+    // query described in a JSON file (--from-json)
+    pub from_json: Option<String>,
+
+    // negative trust anchors: zones treated as insecure
+    pub nta: Vec<DomainName>,
+
+    // header fields forced in the query (--craft)
+    pub craft: Option<HeaderCraft>,
+
+    // key used to sign queries
+    pub tsig: Option<TsigKey>,
 }
 
 impl Default for DnsProtocolOptions {
@@ -85,6 +144,11 @@ impl Default for DnsProtocolOptions {
             resolvers: Vec::new(),
             domain_string: String::from(ROOT), // by default, query is NS and sent to root
             domain_name: ROOT_DOMAIN,
+            dns0x20: false,
+            from_json: None,
+            nta: Vec::new(),
+            craft: None,
+            tsig: None,
         }
     }
 }
@@ -124,6 +188,13 @@ impl FromOptions<u16> for OPT {
             opt.add_option(COOKIE::from(cookie.as_str()));
         }
 
+        // ECS, it's been checked when reading cli options
+        if let Some(subnet) = &edns.client_subnet {
+            if let Ok(subnet) = ClientSubnet::from_str(subnet) {
+                opt.add_option(subnet);
+            }
+        }
+
         // padding
         if let Some(len) = edns.padding {
             opt.add_option(Padding::new(len));
@@ -134,6 +205,16 @@ impl FromOptions<u16> for OPT {
             opt.add_option(ZONEVERSION::default());
         }
 
+        // Report-Channel
+        if edns.report_channel {
+            opt.add_option(ReportChannel::default());
+        }
+
+        // CHAIN
+        if let Some(closest) = &edns.chain {
+            opt.add_option(CHAIN::from(closest.clone()));
+        }
+
         // DAU, DHU & N3U
         // if let Some(list) = &edns.dau {
         //     opt.add_option(DAU::from(list.as_slice()));
@@ -146,9 +227,14 @@ impl FromOptions<u16> for OPT {
         // }
 
         // edns-key-tag
-        // if let Some(list) = &edns.keytag {
-        //     opt.add_option(EdnsKeyTag::from(list.as_slice()));
-        // }
+        if let Some(list) = &edns.keytag {
+            opt.add_option(EdnsKeyTag::from(list.as_slice()));
+        }
+
+        // edns-tcp-keepalive is not allowed over UDP (RFC7828 3.2.1) nor DoQ (RFC9250 5.5.2)
+        if options.transport.keepalive.is_some() && options.transport.transport_mode.is_tcp() {
+            opt.add_option(TcpKeepalive::default());
+        }
 
         Some(opt)
     }
@@ -159,20 +245,44 @@ impl FromOptions<&QType> for Query {
     // build query from the cli options
     //───────────────────────────────────────────────────────────────────────────────────
     fn from_options(options: &CliOptions, qt: &QType) -> Option<Query> {
+        // query is fully described in a JSON file, it's been checked when reading cli options
+        if let Some(json) = &options.protocol.from_json {
+            return query_from_json(json).ok();
+        }
+
         //───────────────────────────────────────────────────────────────────────────────────
         // build the OPT record to be added in the additional section
         //───────────────────────────────────────────────────────────────────────────────────
-        let opt = OPT::from_options(options, options.transport.bufsize);
+        let mut opt = OPT::from_options(options, options.transport.bufsize);
+
+        // EXPIRE is only meaningful for zone transfers or SOA
+        if let Some(opt) = opt.as_mut() {
+            if options.edns.expire && matches!(qt, QType::SOA | QType::AXFR | QType::IXFR) {
+                opt.add_option(EXPIRE::default());
+            }
+        }
         trace!("OPT record: {:#?}", &opt);
 
         //───────────────────────────────────────────────────────────────────────────────────
         // build Query
         //───────────────────────────────────────────────────────────────────────────────────
+        let domain = if options.protocol.dns0x20 {
+            options.protocol.domain_name.randomize_case()
+        } else {
+            options.protocol.domain_name.clone()
+        };
+
+        // below a negative trust anchor, ask the resolver not to validate
+        let mut flags = options.flags.clone();
+        if options.protocol.nta.iter().any(|zone| domain.is_subdomain_of(zone)) {
+            flags.checking_disabled = true;
+        }
+
         let mut query = Query::build()
             .with_type(qt)
             .with_class(&options.protocol.qclass)
-            .with_domain(&options.protocol.domain_name)
-            .with_flags(&options.flags);
+            .with_domain(&domain)
+            .with_flags(&flags);
 
         //───────────────────────────────────────────────────────────────────────────────────
         // Reserve length if TCP or TLS
@@ -187,6 +297,21 @@ impl FromOptions<&QType> for Query {
         if let Some(opt) = opt {
             query = query.with_additional(MetaRR::OPT(opt));
         }
+
+        //───────────────────────────────────────────────────────────────────────────────────
+        // TSIG must be the last RR
+        //───────────────────────────────────────────────────────────────────────────────────
+        if let Some(key) = &options.protocol.tsig {
+            query = query.with_additional(MetaRR::TSIG(TSIG::new(key.clone())));
+        }
+
+        //───────────────────────────────────────────────────────────────────────────────────
+        // crafted header fields are set last to keep inconsistent counts. It's been
+        // checked when reading cli options
+        //───────────────────────────────────────────────────────────────────────────────────
+        if let Some(craft) = &options.protocol.craft {
+            craft.apply(&mut query.header).ok()?;
+        }
         trace!("Query record: {:#?}", &query);
 
         Some(query)
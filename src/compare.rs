@@ -0,0 +1,281 @@
+//! Compare answers got from different sources or servers
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::read_to_string;
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
+
+use colored::Colorize;
+
+use crate::args::CliOptions;
+use crate::dns::message::MessageList;
+use crate::dns::rfc::{domain::DomainName, opt::client_subnet::ClientSubnet, qtype::QType};
+use crate::dns::zonefile::parse_zone;
+use crate::error::Error;
+use crate::get_messages;
+use crate::i18n::fill;
+use crate::pacing::{shuffle, Pacer};
+use crate::show::{header_section, Show};
+
+//───────────────────────────────────────────────────────────────────────────────────
+// gather all answer RRsets as a set of strings, RDATA being in canonical order so the
+// order the server chose doesn't matter. TTL is excluded because it varies with caching
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn answer_set(messages: &MessageList) -> BTreeSet<String> {
+    messages
+        .rrsets()
+        .iter()
+        .map(|rrset| {
+            let rdata: Vec<_> = rrset.canonical().iter().map(|rr| rr.rdata_string()).collect();
+            format!(
+                "{} {} {}",
+                rrset.name().to_string().to_lowercase(),
+                rrset.r#type(),
+                rdata.join(" | ")
+            )
+        })
+        .collect()
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// send the same queries from each source address and compare answers: differences
+// are the sign of views (split-horizon)
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn split_horizon(options: &mut CliOptions) -> crate::error::Result<()> {
+    let sources = options.transport.source_ips.clone();
+    let mut results: Vec<(IpAddr, BTreeSet<String>)> = Vec::with_capacity(sources.len());
+
+    for ip in sources {
+        options.transport.source = Some(ip);
+
+        let messages = match get_messages(None, options) {
+            Ok(messages) => messages,
+            Err(e) => {
+                eprintln!("{}", fill("source {}: {}", &[&ip, &e.localized()]));
+                continue;
+            }
+        };
+
+        println!("{}", header_section(&format!("source {}", ip), None));
+        let max_length = messages.max_length();
+        for msg in messages.iter() {
+            msg.show(&options.display, max_length);
+        }
+        println!();
+
+        results.push((ip, answer_set(&messages)));
+    }
+
+    // compare to answers received from the first source
+    let Some((first_ip, first)) = results.first() else {
+        return Ok(());
+    };
+    let mut differ = false;
+
+    for (ip, answers) in results.iter().skip(1) {
+        if answers != first {
+            differ = true;
+            println!("answers from {} differ from answers from {}:", ip, first_ip);
+            for a in first.difference(answers) {
+                println!("{} {}", "-".bright_red(), a);
+            }
+            for a in answers.difference(first) {
+                println!("{} {}", "+".bright_green(), a);
+            }
+        }
+    }
+
+    if differ {
+        println!("split-horizon: answers depend on the source address, views are likely configured");
+    } else {
+        println!("same answers from all sources: no split-horizon detected");
+    }
+
+    Ok(())
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// send the same queries with each client subnet and group subnets by answers
+// received: each group is a geo-targeted answer of a CDN (--ecs-sweep)
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn ecs_sweep(options: &mut CliOptions, path: &Path) -> crate::error::Result<()> {
+    let content = read_to_string(path).map_err(|e| Error::OpenFile(e, path.to_path_buf()))?;
+
+    // answers => list of subnets with the scope returned by the server
+    let mut groups: BTreeMap<BTreeSet<String>, Vec<String>> = BTreeMap::new();
+
+    // invalid subnets are reported and skipped, as they would be silently sent without ECS
+    let mut subnets: Vec<_> = content
+        .lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, l)| !l.is_empty() && !l.starts_with('#'))
+        .filter(|(i, l)| match ClientSubnet::from_str(l) {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    fill("line {}: invalid subnet {} ({})", &[&(i + 1), l, &e.localized()])
+                );
+                false
+            }
+        })
+        .map(|(_, l)| l)
+        .collect();
+    shuffle(&mut subnets, &options.dump);
+    let mut pacer = Pacer::new(&options.dump);
+
+    for subnet in subnets {
+        options.transport.cancel.check()?;
+        pacer.wait();
+        options.edns.client_subnet = Some(subnet.to_string());
+
+        let messages = match get_messages(None, options) {
+            Ok(messages) => messages,
+            Err(e) => {
+                eprintln!("{}", fill("subnet {}: {}", &[&subnet, &e.localized()]));
+                continue;
+            }
+        };
+
+        // scope prefix length tells for which network the answer is valid
+        let scope = messages
+            .iter()
+            .find_map(|msg| msg.response().client_subnet())
+            .map(|ecs| format!("{} (scope /{})", subnet, ecs.scope()))
+            .unwrap_or_else(|| format!("{} (no ECS in response)", subnet));
+
+        groups.entry(answer_set(&messages)).or_default().push(scope);
+    }
+
+    for (i, (answers, subnets)) in groups.iter().enumerate() {
+        println!(
+            "{}",
+            header_section(&format!("answer set #{} ({} subnets)", i + 1, subnets.len()), None)
+        );
+        for a in answers {
+            println!("{}", a);
+        }
+        println!("subnets:");
+        for s in subnets {
+            println!("  {}", s);
+        }
+        println!();
+    }
+
+    if groups.len() > 1 {
+        println!(
+            "{} different answer sets: answers depend on the client subnet",
+            groups.len()
+        );
+    } else {
+        println!("same answers for all subnets: no geo-targeting detected");
+    }
+
+    Ok(())
+}
+
+// RDATA of the answers for this name and type, in the same form as in the zone file
+fn live_rdata(messages: &MessageList, name: &str, qtype: QType) -> BTreeSet<String> {
+    messages
+        .rrsets()
+        .iter()
+        .filter(|rrset| rrset.r#type() == qtype && rrset.name().to_string().to_lowercase() == name)
+        .flat_map(|rrset| rrset.iter().map(move |rr| normalize(qtype, &rr.rdata_string())))
+        .collect()
+}
+
+// names are case insensitive, but not character strings
+fn normalize(qtype: QType, rdata: &str) -> String {
+    match qtype {
+        QType::TXT | QType::SPF => rdata.to_string(),
+        _ => rdata.to_lowercase(),
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// query each name and type of the zone file and report records missing from the
+// answers, extra ones and differing RRsets (--expect-zone)
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn expect_zone(options: &mut CliOptions, path: &Path) -> crate::error::Result<()> {
+    let content = read_to_string(path).map_err(|e| Error::OpenFile(e, path.to_path_buf()))?;
+
+    // names are relative to the domain given on the command line without $ORIGIN
+    let entries = parse_zone(&content, &options.protocol.domain_string)?;
+
+    // expected RRsets
+    let mut expected: BTreeMap<(String, String), (QType, BTreeSet<String>)> = BTreeMap::new();
+    for entry in &entries {
+        expected
+            .entry((entry.name.clone(), entry.qtype.to_string()))
+            .or_insert_with(|| (entry.qtype, BTreeSet::new()))
+            .1
+            .insert(normalize(entry.qtype, &entry.rdata_string()));
+    }
+
+    let (mut same, mut missing, mut extra) = (0, 0, 0);
+
+    // the report follows the query order
+    let mut expected: Vec<_> = expected.into_iter().collect();
+    shuffle(&mut expected, &options.dump);
+    let mut pacer = Pacer::new(&options.dump);
+
+    for ((name, _), (qtype, zone)) in &expected {
+        options.transport.cancel.check()?;
+        pacer.wait();
+        options.protocol.domain_name = DomainName::try_from(name.as_str())?;
+        options.protocol.qtype = vec![*qtype];
+
+        let live = match get_messages(None, options) {
+            Ok(messages) => live_rdata(&messages, name, *qtype),
+            Err(e) => {
+                eprintln!("{} {}: {}", name, qtype, e.localized());
+                BTreeSet::new()
+            }
+        };
+
+        if &live == zone {
+            same += 1;
+            continue;
+        }
+
+        let status = if live.is_empty() {
+            "missing"
+        } else if zone.is_subset(&live) {
+            "extra"
+        } else {
+            "differs"
+        };
+        println!("{}", header_section(&format!("{} {} ({})", name, qtype, status), None));
+        for rdata in zone.difference(&live) {
+            missing += 1;
+            println!("{} {} {} {}", "-".bright_red(), name, qtype, rdata);
+        }
+        for rdata in live.difference(zone) {
+            extra += 1;
+            println!("{} {} {} {}", "+".bright_green(), name, qtype, rdata);
+        }
+        println!();
+    }
+
+    println!(
+        "{} RRsets checked: {} identical, {} missing record(s), {} extra record(s)",
+        expected.len(),
+        same,
+        missing,
+        extra
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalized() {
+        assert_eq!(normalize(QType::MX, "10 Mail.Example.com."), "10 mail.example.com.");
+        assert_eq!(normalize(QType::TXT, "Hello World"), "Hello World");
+    }
+}
@@ -0,0 +1,244 @@
+//! DNS cookies (RFC7873, RFC9018): the client cookie is derived from the client and server
+//! addresses and a secret, so it changes when the client moves to another network, and the
+//! server cookies returned by resolvers are kept in a small JSON file (--cookie-jar) to be
+//! sent back in the next queries, from one run to the other.
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use log::debug;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+
+use crate::args::CliOptions;
+use crate::cache::cache_dir;
+use crate::dns::rfc::{opt::cookie::COOKIE, query::Query, response::Response};
+use crate::error::{Error, Result};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CookieJar {
+    // client secret, hex encoded
+    secret: String,
+
+    // last server cookie, hex encoded, of each server address
+    servers: BTreeMap<IpAddr, String>,
+
+    // file the jar is saved to, if any
+    #[serde(skip)]
+    path: Option<PathBuf>,
+
+    // a server cookie changed since the jar was loaded
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl CookieJar {
+    fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            secret: base16::encode_lower(&rand::random::<[u8; 16]>()),
+            path,
+            changed: true,
+            ..Default::default()
+        }
+    }
+
+    // HMAC-SHA256-64 of the client and server addresses (RFC9018 section 4.1 leaves the
+    // function to the client)
+    fn client_cookie(&self, client: IpAddr, server: IpAddr) -> [u8; 8] {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, self.secret.as_bytes());
+        let mut ctx = hmac::Context::with_key(&key);
+        for ip in [client, server] {
+            match ip {
+                IpAddr::V4(ip) => ctx.update(&ip.octets()),
+                IpAddr::V6(ip) => ctx.update(&ip.octets()),
+            }
+        }
+
+        let mut cookie = [0u8; 8];
+        cookie.copy_from_slice(&ctx.sign().as_ref()[..8]);
+        cookie
+    }
+
+    fn server_cookie(&self, server: IpAddr) -> Option<Vec<u8>> {
+        self.servers.get(&server).and_then(|hex| base16::decode(hex).ok())
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// the jar is global because queries are built and responses received deep inside the
+// protocol functions, whichever mode triggered the exchange
+//───────────────────────────────────────────────────────────────────────────────────
+static JAR: Mutex<Option<CookieJar>> = Mutex::new(None);
+
+// $XDG_CACHE_HOME/dqy/cookies.json or ~/.cache/dqy/cookies.json
+pub fn default_path() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("cookies.json"))
+}
+
+// load the jar, or start a new one with a new secret if the file doesn't exist or can't be
+// read. Without a file, server cookies are only kept during the run. The jar is saved when
+// the guard returned is dropped, whichever mode ran
+pub fn open(path: Option<&Path>) -> SaveOnExit {
+    let jar = path
+        .and_then(|path| File::open(path).ok())
+        .and_then(|f| serde_json::from_reader::<_, CookieJar>(BufReader::new(f)).ok())
+        .filter(|jar| base16::decode(&jar.secret).is_ok_and(|secret| secret.len() >= 16));
+
+    let jar = match jar {
+        Some(jar) => CookieJar {
+            path: path.map(Path::to_path_buf),
+            changed: false,
+            ..jar
+        },
+        None => CookieJar::new(path.map(Path::to_path_buf)),
+    };
+    debug!("cookie jar {:?} with {} server cookies", jar.path, jar.servers.len());
+    *JAR.lock().unwrap() = Some(jar);
+    SaveOnExit
+}
+
+pub struct SaveOnExit;
+
+impl Drop for SaveOnExit {
+    fn drop(&mut self) {
+        if let Err(e) = save() {
+            eprintln!("{}", e.localized());
+        }
+    }
+}
+
+// write the jar back if a server cookie changed
+fn save() -> Result<()> {
+    let jar = JAR.lock().unwrap();
+    let Some(jar) = jar.as_ref().filter(|jar| jar.changed) else {
+        return Ok(());
+    };
+    let Some(path) = &jar.path else {
+        return Ok(());
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| Error::OpenFile(e, dir.to_path_buf()))?;
+    }
+    let f = File::create(path).map_err(|e| Error::OpenFile(e, path.to_path_buf()))?;
+    serde_json::to_writer_pretty(f, jar).map_err(|e| Error::OpenFile(e.into(), path.to_path_buf()))
+}
+
+// address the client sends from to reach the server: the one given with --source-ip,
+// otherwise the one of the default route (connecting a UDP socket sends nothing)
+fn local_ip(server: IpAddr, source: Option<IpAddr>) -> IpAddr {
+    if let Some(ip) = source {
+        return ip;
+    }
+
+    let unspecified = match server {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+
+    UdpSocket::bind(SocketAddr::new(unspecified, 0))
+        .and_then(|sock| sock.connect(SocketAddr::new(server, 53)).map(|_| sock))
+        .and_then(|sock| sock.local_addr())
+        .map(|addr| addr.ip())
+        .unwrap_or(unspecified)
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// set the cookie of the query sent to peer: client cookie derived from the addresses
+// unless given with --cookie=HEX, and the server cookie it last returned
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn prepare(query: &mut Query, options: &CliOptions, peer: Option<SocketAddr>) {
+    let jar = JAR.lock().unwrap();
+    let (Some(jar), Some(peer)) = (jar.as_ref(), peer) else {
+        return;
+    };
+    let Some(sent) = query.cookie() else {
+        return;
+    };
+
+    let fixed = options
+        .edns
+        .cookie
+        .as_ref()
+        .and_then(|hex| hex.get(..16))
+        .is_some_and(|hex| base16::decode(hex).is_ok());
+    let client_cookie = if fixed {
+        sent.client_cookie
+    } else {
+        jar.client_cookie(local_ip(peer.ip(), options.transport.source), peer.ip())
+    };
+
+    // the last server cookie returned wins over the one given with --cookie=HEX
+    let server_cookie = jar.server_cookie(peer.ip()).or_else(|| sent.server_cookie.clone());
+
+    if let Some(opt) = query.opt_mut() {
+        opt.set_option(COOKIE::new(client_cookie, server_cookie));
+    }
+}
+
+// keep the server cookie of the response if it comes with our client cookie (RFC7873
+// section 5.3)
+pub fn learn(query: &Query, response: &Response, peer: Option<SocketAddr>) {
+    let mut jar = JAR.lock().unwrap();
+    let (Some(jar), Some(peer)) = (jar.as_mut(), peer) else {
+        return;
+    };
+    let (Some(sent), Some(received)) = (query.cookie(), response.cookie()) else {
+        return;
+    };
+    let Some(server) = received.server_cookie.as_ref() else {
+        return;
+    };
+
+    if sent.client_cookie != received.client_cookie {
+        debug!("client cookie not echoed by {}, server cookie ignored", peer);
+        return;
+    }
+    let hex = base16::encode_lower(server);
+    if jar.servers.get(&peer.ip()) != Some(&hex) {
+        jar.servers.insert(peer.ip(), hex);
+        jar.changed = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_cookie() {
+        let jar = CookieJar::new(None);
+        let client: IpAddr = "192.0.2.1".parse().unwrap();
+        let server: IpAddr = "1.1.1.1".parse().unwrap();
+
+        // same addresses, same cookie
+        let cookie = jar.client_cookie(client, server);
+        assert_eq!(cookie, jar.client_cookie(client, server));
+
+        // another network or another server, another cookie
+        assert_ne!(cookie, jar.client_cookie("198.51.100.1".parse().unwrap(), server));
+        assert_ne!(cookie, jar.client_cookie(client, "8.8.8.8".parse().unwrap()));
+
+        // another secret
+        assert_ne!(cookie, CookieJar::new(None).client_cookie(client, server));
+    }
+
+    #[test]
+    fn json() {
+        let mut jar = CookieJar::new(None);
+        jar.servers
+            .insert("1.1.1.1".parse().unwrap(), String::from("0102030405060708"));
+
+        let json = serde_json::to_string(&jar).unwrap();
+        let back: CookieJar = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.secret, jar.secret);
+        assert_eq!(
+            back.server_cookie("1.1.1.1".parse().unwrap()),
+            Some(vec![1, 2, 3, 4, 5, 6, 7, 8])
+        );
+        assert_eq!(back.server_cookie("8.8.8.8".parse().unwrap()), None);
+    }
+}
@@ -42,6 +42,12 @@ impl Buffer {
     }
 }
 
+impl From<&[u8]> for Buffer {
+    fn from(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+}
+
 impl Deref for Buffer {
     type Target = [u8];
 
@@ -2,7 +2,7 @@
 
 use std::fmt;
 
-use chrono::DateTime;
+use chrono::{DateTime, SecondsFormat, Utc};
 
 use type2network::FromNetworkOrder;
 use type2network_derive::FromNetwork;
@@ -10,6 +10,35 @@ use type2network_derive::FromNetwork;
 #[derive(Debug, Default, PartialEq, FromNetwork)]
 pub struct DnsDateTime(u32);
 
+impl DnsDateTime {
+    // seconds from now to this time, negative if in the past. Times wrap around every
+    // 136 years and are compared using serial number arithmetic (RFC4034 3.1.5)
+    pub fn offset(&self, now: i64) -> i64 {
+        self.0.wrapping_sub(now as u32) as i32 as i64
+    }
+}
+
+// e.g.: "in 2d 3h" or "5m 10s ago" for an offset in seconds from now
+pub fn humanize(offset: i64) -> String {
+    let secs = offset.unsigned_abs();
+    let (d, h, m, s) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60, secs % 60);
+    let span = if d > 0 {
+        format!("{}d {}h", d, h)
+    } else if h > 0 {
+        format!("{}h {}m", h, m)
+    } else if m > 0 {
+        format!("{}m {}s", m, s)
+    } else {
+        format!("{}s", s)
+    };
+
+    if offset >= 0 {
+        format!("in {}", span)
+    } else {
+        format!("{} ago", span)
+    }
+}
+
 impl fmt::Display for DnsDateTime {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let date_time = DateTime::from_timestamp(self.0 as i64, 0)
@@ -21,6 +50,11 @@ impl fmt::Display for DnsDateTime {
     }
 }
 
+// RFC3339 timestamp of the current time, as given for each query with --time
+pub fn timestamp() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
 // Custom serialization
 use serde::{Serialize, Serializer};
 impl Serialize for DnsDateTime {
@@ -41,4 +75,24 @@ mod tests {
         let dt = DnsDateTime(0);
         assert_eq!(dt.to_string(), "19700101000000");
     }
+
+    #[test]
+    fn relative() {
+        let dt = DnsDateTime(1_000_000);
+        assert_eq!(dt.offset(1_000_000 - 90_000), 90_000);
+        assert_eq!(humanize(dt.offset(1_000_000 - 90_000)), "in 1d 1h");
+        assert_eq!(humanize(dt.offset(1_000_000 + 310)), "5m 10s ago");
+        assert_eq!(humanize(0), "in 0s");
+
+        // after the 2106 wrap-around
+        let dt = DnsDateTime(10);
+        assert_eq!(dt.offset(u32::MAX as i64), 11);
+    }
+
+    #[test]
+    fn now() {
+        let ts = timestamp();
+        assert!(ts.ends_with('Z'));
+        assert!(DateTime::parse_from_rfc3339(&ts).is_ok());
+    }
 }
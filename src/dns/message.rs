@@ -1,18 +1,50 @@
 //! A comination of a query and a response
 //!
-use std::{fmt, ops::Deref};
+use std::{fmt, ops::Deref, time::Duration};
 
-use super::rfc::{query::Query, response::Response, response_code::ResponseCode};
+use colored::Colorize;
+
+use super::rfc::{
+    qtype::QType, query::Query, resource_record::ResourceRecord, response::Response, response_code::ResponseCode,
+    rrset::RRSet,
+};
 
 use log::{error, trace};
 use serde::Serialize;
+use type2network::ToNetworkOrder;
+
+use crate::analyze::analyze;
+use crate::bogon::bogons;
+use crate::json::{rfc8427, JsonOutputV1};
+use crate::show::{header_section, ColumnWidths, DisplayOptions, QueryInfo, Show, ShowAll};
+use crate::stats::ExchangeStats;
+use crate::template::{csv_field, DEFAULT_CSV_FIELDS};
+use crate::transport::NetworkInfo;
+use crate::wire::{Fate, WireStats};
 
-use crate::show::{header_section, DisplayOptions, QueryInfo, Show, ShowAll};
+// printed when the NOERROR response is actually a NXDOMAIN
+const COMPACT_DENIAL: &str = "compact denial of existence: NXNAME found in NSEC type bitmap, the name doesn't exist";
 
 #[derive(Debug, Serialize)]
 pub struct Message {
     pub query: Query,
     pub response: Response,
+
+    // when the query was sent (RFC3339), only with --time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+
+    // bytes sent and received for this query/response exchange
+    #[serde(skip)]
+    pub netinfo: NetworkInfo,
+
+    // response as received from the wire
+    #[serde(skip)]
+    pub raw_response: Vec<u8>,
+
+    // time between sending the query and receiving the response
+    #[serde(skip)]
+    pub elapsed: Duration,
 }
 
 impl Message {
@@ -30,6 +62,55 @@ impl Message {
         &self.response
     }
 
+    //───────────────────────────────────────────────────────────────────────────────────
+    // --stable: remove what changes from one run to the other, so the output can be
+    // compared with golden files
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn stabilize(&mut self) {
+        self.query.header.id = 0;
+        self.response.stabilize();
+        self.timestamp = None;
+        self.elapsed = Duration::ZERO;
+        self.netinfo.stabilize();
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // when the server doesn't echo the exact QNAME case (e.g. with --dns0x20), show
+    // the received QNAME with mismatched characters highlighted
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn qname_echo(&self) -> Option<String> {
+        let sent = &self.query.question.qname;
+        let received = &self.response.question.qname;
+
+        if sent != received || sent.eq_case(received) {
+            return None;
+        }
+
+        let sent = sent.to_string();
+        let received = received.to_string();
+
+        let highlighted: String = if sent.len() == received.len() {
+            received
+                .chars()
+                .zip(sent.chars())
+                .map(|(r, s)| {
+                    if r == s {
+                        r.to_string()
+                    } else {
+                        r.to_string().bright_red().bold().to_string()
+                    }
+                })
+                .collect()
+        } else {
+            received.bright_red().to_string()
+        };
+
+        Some(format!(
+            "QNAME case not preserved: sent {}, received {}",
+            sent, highlighted
+        ))
+    }
+
     //───────────────────────────────────────────────────────────────────────────────────
     // check if response corresponds to what the client sent
     //───────────────────────────────────────────────────────────────────────────────────
@@ -64,6 +145,149 @@ impl Message {
     pub fn max_length(&self) -> usize {
         self.response.max_length()
     }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // query as sent on the wire, without the leading length
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn raw_query(&self) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::new();
+        let _ = self.query.serialize_compressed(&mut buffer);
+
+        if self.query.length.is_some() {
+            buffer.drain(..2);
+        }
+        buffer
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // answers as strings "name type rdata" without TTL (which varies with caching)
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn answers(&self) -> Vec<String> {
+        self.response.answers()
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // DNS message sizes without the leading length for TCP, DoT or DoQ
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn query_size(&self) -> usize {
+        if self.query.length.is_some() {
+            self.netinfo.sent.saturating_sub(2)
+        } else {
+            self.netinfo.sent
+        }
+    }
+
+    pub fn response_size(&self) -> usize {
+        self.netinfo.received
+    }
+
+    // --simulate-bufsize: fate of each RR, and bytes used by each section
+    fn show_simulation(&self, bufsize: u16, qtype: Option<QType>) {
+        let title = match qtype {
+            Some(qt) => format!("BUFSIZE SIMULATION ({}, {} bytes)", qt, bufsize),
+            None => format!("BUFSIZE SIMULATION ({} bytes)", bufsize),
+        };
+        println!("\n{}", header_section(&title, None));
+
+        let Some(stats) = WireStats::new(&self.raw_response) else {
+            println!("response can't be walked through");
+            return;
+        };
+        let sim = stats.simulate(bufsize as usize);
+
+        const SECTIONS: [&str; 4] = ["question", "answer", "authority", "additional"];
+        let resp = self.response();
+        let rrs = [resp.answer.as_ref(), resp.authority(), resp.additional()]
+            .into_iter()
+            .flatten()
+            .flat_map(|list| list.iter());
+
+        let mut kept = [0usize; 4];
+        for ((rr, &(section, _, size)), fate) in rrs.zip(&stats.records).zip(&sim.fates) {
+            if *fate == Fate::Kept {
+                kept[section] += size;
+            }
+            let fate = match fate {
+                Fate::Kept => fate.to_string().normal(),
+                _ => fate.to_string().bright_red(),
+            };
+            println!(
+                "{:<10} {:<40} {:<10} {:>5} {}",
+                SECTIONS[section],
+                rr.name.to_string(),
+                rr.r#type.to_string(),
+                size,
+                fate
+            );
+        }
+
+        println!();
+        for (section, name) in SECTIONS.iter().enumerate().skip(1) {
+            println!("{}: {} of {} bytes kept", name, kept[section], stats.sections[section]);
+        }
+
+        let verdict = if sim.truncated() {
+            String::from(
+                "answer or authority data doesn't fit, the response would be truncated (TC) and retried over TCP",
+            )
+        } else if sim.dropped() > 0 {
+            format!(
+                "{} additional record(s) dropped, the response is not truncated",
+                sim.dropped()
+            )
+        } else {
+            String::from("the whole response fits")
+        };
+        println!("used {} of {} bytes: {}", sim.used, bufsize, verdict);
+    }
+
+    // --wire-stats, titled with the query type when there are several messages
+    fn show_wire_stats(&self, qtype: Option<QType>) {
+        let title = match qtype {
+            Some(qt) => format!("WIRE STATS ({})", qt),
+            None => String::from("WIRE STATS"),
+        };
+        println!("\n{}", header_section(&title, None));
+        match WireStats::new(&self.raw_response) {
+            Some(stats) => println!("{}", stats),
+            None => println!("response can't be walked through"),
+        }
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // amplification factor: response bytes / query bytes
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn amplification(&self) -> Option<f64> {
+        let query_size = self.query_size();
+        (query_size != 0).then(|| self.response_size() as f64 / query_size as f64)
+    }
+
+    // true if the response is larger than the UDP payload size we advertised. The bufsize
+    // doesn't limit streams, nor the TCP retry after TC
+    pub fn exceeds_bufsize(&self) -> bool {
+        self.netinfo.udp && self.response_size() > self.query.bufsize() as usize
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // one line summary of sizes used in stats
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn size_report(&self) -> String {
+        let mut s = format!("query:{}, response:{} bytes", self.query_size(), self.response_size());
+
+        if let Some(factor) = self.amplification() {
+            s += &format!(", amplification:{:.2}", factor);
+        }
+
+        if self.exceeds_bufsize() {
+            s += &format!(
+                ", {} {}",
+                "exceeds advertised bufsize".bright_red(),
+                self.query.bufsize()
+            );
+        }
+
+        s
+    }
 }
 
 impl fmt::Display for Message {
@@ -96,10 +320,110 @@ impl MessageList {
         Self(list)
     }
 
+    pub fn stabilize(&mut self) {
+        self.0.iter_mut().for_each(Message::stabilize);
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Message> {
+        self.0.iter_mut()
+    }
+
+    // all responses, in the order of the queries
+    pub fn responses(&self) -> impl Iterator<Item = &Response> {
+        self.0.iter().map(|msg| msg.response())
+    }
+
+    // answer RRs of all responses
+    pub fn answers(&self) -> impl Iterator<Item = &ResourceRecord> {
+        self.responses()
+            .flat_map(|resp| resp.answer.iter().flat_map(|list| list.iter()))
+    }
+
+    // answer RRs of this type, whatever the query type
+    pub fn answers_of(&self, qtype: QType) -> impl Iterator<Item = &ResourceRecord> {
+        self.answers().filter(move |rr| rr.r#type == qtype)
+    }
+
+    // answer RRs without duplicates (same RRset and RDATA), the first one being kept
+    pub fn dedup_answers(&self) -> Vec<&ResourceRecord> {
+        let mut unique: Vec<&ResourceRecord> = Vec::new();
+        for rr in self.answers() {
+            if !unique.iter().any(|u| u.same_data(rr)) {
+                unique.push(rr);
+            }
+        }
+        unique
+    }
+
+    // answers of all responses merged into RRsets, without duplicates
+    pub fn rrsets(&self) -> Vec<RRSet<'_>> {
+        RRSet::group(self.dedup_answers())
+    }
+
     // Return the max length of all messages (all RRs of all messages)
     pub fn max_length(&self) -> Option<usize> {
         self.0.iter().map(|x| x.max_length()).max()
     }
+
+    // native JSON, stable schema if --json-version is set or RFC8427 representation
+    pub fn to_json(
+        &self,
+        display_options: &DisplayOptions,
+        info: &QueryInfo,
+    ) -> crate::error::Result<serde_json::Value> {
+        if display_options.json_rfc8427 {
+            return Ok(rfc8427(self));
+        }
+
+        match display_options.json_version {
+            Some(_) => {
+                serde_json::to_value(JsonOutputV1::new(self, info)).map_err(|e| crate::error::Error::Buffer(e.into()))
+            }
+            None => {
+                let mut j = serde_json::json!({
+                    "messages": self,
+                    "info": info
+                });
+
+                // answers in special-use address space
+                let found: Vec<_> = self.iter().flat_map(|msg| bogons(msg.response())).collect();
+                if display_options.bogon_check && !found.is_empty() {
+                    j["special_use"] = serde_json::json!(found);
+                }
+
+                // always there, so scripts can check it's empty
+                j["warnings"] = serde_json::json!(info.warnings);
+                Ok(j)
+            }
+        }
+    }
+
+    // answers as CSV, fields being taken from --fmt
+    fn show_csv(&self, display_options: &DisplayOptions) {
+        let fields: Vec<&str> = match &display_options.template {
+            Some(template) => template.fields(),
+            None if !display_options.fmt.is_empty() => display_options.fmt.split(',').map(str::trim).collect(),
+            None => DEFAULT_CSV_FIELDS.to_vec(),
+        };
+
+        println!("{}", fields.join(","));
+        for rr in self.answers() {
+            let row: Vec<_> = fields
+                .iter()
+                .map(|f| csv_field(&rr.field(f, display_options).unwrap_or_default()))
+                .collect();
+            println!("{}", row.join(","));
+        }
+    }
+
+    // column widths to fit all RRs of all messages (--width auto)
+    pub fn widths(&self, raw_ttl: bool) -> ColumnWidths {
+        let mut widths = ColumnWidths::default();
+        for resp in self.responses() {
+            widths.merge(&resp.widths(raw_ttl));
+        }
+        widths
+    }
 }
 
 impl Deref for MessageList {
@@ -120,16 +444,17 @@ impl fmt::Display for MessageList {
 }
 
 impl ShowAll for MessageList {
-    fn show_all(&self, display_options: &mut DisplayOptions, info: QueryInfo) {
+    fn show_all(&self, display_options: &mut DisplayOptions, mut info: QueryInfo) {
+        info.warnings.responses(self, display_options);
+
         //───────────────────────────────────────────────────────────────────────────────────
         // JSON
         //───────────────────────────────────────────────────────────────────────────────────
         if display_options.json_pretty {
-            let j = serde_json::json!({
-                "messages": self,
-                "info": info
-            });
-            println!("{}", serde_json::to_string_pretty(&j).unwrap());
+            match self.to_json(display_options, &info) {
+                Ok(j) => println!("{}", serde_json::to_string_pretty(&j).unwrap()),
+                Err(e) => eprintln!("{}", e.localized()),
+            }
             return;
         }
 
@@ -137,14 +462,34 @@ impl ShowAll for MessageList {
         // JSON pretty
         //───────────────────────────────────────────────────────────────────────────────────
         if display_options.json {
-            let j = serde_json::json!({
-                "messages": self,
-                "info": info
-            });
-            println!("{}", serde_json::to_string(&j).unwrap());
+            match self.to_json(display_options, &info) {
+                Ok(j) => println!("{}", serde_json::to_string(&j).unwrap()),
+                Err(e) => eprintln!("{}", e.localized()),
+            }
             return;
         }
 
+        //───────────────────────────────────────────────────────────────────────────────────
+        // CSV: one line per answer RR, columns being the --fmt fields
+        //───────────────────────────────────────────────────────────────────────────────────
+        if display_options.csv {
+            self.show_csv(display_options);
+            return;
+        }
+
+        // auto-fit: compute widths from the result set before printing. Explicit
+        // widths given together with auto are kept
+        if display_options.widths.auto {
+            let mut widths = self.widths(display_options.raw_ttl);
+            let explicit = &display_options.widths;
+            widths.name = explicit.name.or(widths.name);
+            widths.r#type = explicit.r#type.or(widths.r#type);
+            widths.class = explicit.class.or(widths.class);
+            widths.ttl = explicit.ttl.or(widths.ttl);
+            widths.length = explicit.length.or(widths.length);
+            display_options.widths = widths;
+        }
+
         //───────────────────────────────────────────────────────────────────────────────────
         // fancy print out when only one message
         //───────────────────────────────────────────────────────────────────────────────────
@@ -160,9 +505,36 @@ impl ShowAll for MessageList {
 
             resp.show(display_options, None);
 
+            if let Some(echo) = msg.qname_echo() {
+                println!("\n{}", echo);
+            }
+
+            if resp.is_compact_denial() {
+                println!("\n{}", COMPACT_DENIAL);
+            }
+
+            // print out heuristics
+            if display_options.analyze {
+                println!("\n{}", header_section("ANALYSIS", None));
+                for comment in analyze(resp) {
+                    println!("{}", comment);
+                }
+            }
+
             // print out stats
             println!("{}", header_section("STATS", None));
             println!("{}", info);
+            if let Some(ts) = &msg.timestamp {
+                println!("timestamp: {}", ts);
+            }
+            println!("{}", msg.size_report());
+
+            if display_options.wire_stats {
+                msg.show_wire_stats(None);
+            }
+            if let Some(bufsize) = display_options.simulate_bufsize {
+                msg.show_simulation(bufsize, None);
+            }
         }
         //───────────────────────────────────────────────────────────────────────────────────
         // when several messages, just print out the ANSWER
@@ -174,9 +546,165 @@ impl ShowAll for MessageList {
                 msg.show(display_options, max_length);
             }
 
+            for msg in self.iter() {
+                if let Some(echo) = msg.qname_echo() {
+                    println!("{}: {}", msg.query.question.qtype, echo);
+                }
+                if msg.response().is_compact_denial() {
+                    println!("{}: {}", msg.query.question.qtype, COMPACT_DENIAL);
+                }
+                if let Some(ts) = &msg.timestamp {
+                    println!("{}: timestamp {}", msg.query.question.qtype, ts);
+                }
+            }
+
+            if display_options.analyze {
+                for msg in self.iter() {
+                    for comment in analyze(msg.response()) {
+                        println!("{}: {}", msg.query.question.qtype, comment);
+                    }
+                }
+            }
+
             if display_options.stats {
                 println!("{}", info);
+                for msg in self.iter() {
+                    println!("{}: {}", msg.query.question.qtype, msg.size_report());
+                }
+
+                let mut stats = ExchangeStats::default();
+                stats.extend(self.iter());
+                println!("{}", stats);
             }
+
+            for msg in self.iter() {
+                let qtype = Some(msg.query.question.qtype);
+                if display_options.wire_stats {
+                    msg.show_wire_stats(qtype);
+                }
+                if let Some(bufsize) = display_options.simulate_bufsize {
+                    msg.show_simulation(bufsize, qtype);
+                }
+            }
+        }
+
+        // warnings come after the results
+        if !info.warnings.is_empty() {
+            println!("\n{}", header_section("WARNINGS", None));
+            for warning in info.warnings.iter() {
+                println!("{}", warning);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::rfc::{query::MetaRR, resource_record::OPT};
+
+    #[test]
+    fn amplification() {
+        let mut msg = Message {
+            query: Query::default(),
+            response: Response::default(),
+            netinfo: NetworkInfo {
+                sent: 40,
+                received: 600,
+                udp: true,
+                ..Default::default()
+            },
+            raw_response: Vec::new(),
+            timestamp: None,
+            elapsed: Duration::default(),
+        };
+
+        assert_eq!(msg.amplification(), Some(15.0));
+
+        // no OPT => 512 bytes
+        assert!(msg.exceeds_bufsize());
+        msg.query = Query::default().with_additional(MetaRR::OPT(OPT::new(1232, None)));
+        assert!(!msg.exceeds_bufsize());
+
+        // only UDP is limited
+        msg.query = Query::default();
+        msg.netinfo.udp = false;
+        assert!(!msg.exceeds_bufsize());
+        msg.netinfo.udp = true;
+
+        // TCP length is not part of the DNS message
+        msg.query = msg.query.with_length();
+        assert_eq!(msg.query_size(), 38);
+
+        msg.netinfo.sent = 0;
+        assert!(msg.amplification().is_none());
+    }
+
+    // message from the response of the pcap file, the query being for the same type
+    fn message(pcap: &str) -> Message {
+        use crate::dns::tests::get_packets;
+        use type2network::FromNetworkOrder;
+
+        let pcap = get_packets(pcap, 0, 1);
+        let mut buffer = std::io::Cursor::new(&pcap.1[0x2A..]);
+        let mut response = Response::default();
+        response.deserialize_from(&mut buffer).unwrap();
+
+        let mut query = Query::default();
+        query.question.qtype = response.question.qtype;
+
+        Message {
+            query,
+            response,
+            netinfo: NetworkInfo::default(),
+            raw_response: Vec::new(),
+            timestamp: None,
+            elapsed: Duration::default(),
         }
     }
+
+    #[test]
+    fn list() {
+        let messages = MessageList::new(vec![
+            message("./tests/pcap/mx.pcap"),
+            message("./tests/pcap/a.pcap"),
+            message("./tests/pcap/a.pcap"),
+        ]);
+
+        assert_eq!(messages.responses().count(), 3);
+        assert_eq!(messages.answers().count(), 3);
+        assert_eq!(messages.answers_of(QType::A).count(), 2);
+        assert_eq!(messages.answers_of(QType::AAAA).count(), 0);
+
+        // the same A record is received twice
+        assert_eq!(messages.dedup_answers().len(), 2);
+        let rrsets = messages.rrsets();
+        assert_eq!(rrsets.len(), 2);
+        assert!(rrsets.iter().all(|set| set.len() == 1));
+    }
+
+    #[test]
+    fn qname_echo() {
+        use crate::dns::rfc::domain::DomainName;
+
+        colored::control::set_override(false);
+
+        let mut msg = Message {
+            query: Query::default(),
+            response: Response::default(),
+            netinfo: NetworkInfo::default(),
+            raw_response: Vec::new(),
+            timestamp: None,
+            elapsed: Duration::default(),
+        };
+        msg.query.question.qname = DomainName::try_from("wWw.ExAmple.com").unwrap();
+        msg.response.question.qname = DomainName::try_from("wWw.ExAmple.com").unwrap();
+        assert!(msg.qname_echo().is_none());
+
+        msg.response.question.qname = DomainName::try_from("www.example.com").unwrap();
+        assert_eq!(
+            msg.qname_echo().unwrap(),
+            "QNAME case not preserved: sent wWw.ExAmple.com., received www.example.com."
+        );
+    }
 }
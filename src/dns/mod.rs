@@ -2,6 +2,7 @@ pub mod buffer;
 pub mod date_time;
 pub mod message;
 pub mod rfc;
+pub mod zonefile;
 
 // Macro used to define getters
 #[macro_export]
@@ -16,7 +17,7 @@ macro_rules! getter {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use std::fs::File;
     use type2network::{FromNetworkOrder, ToNetworkOrder};
 
@@ -1,5 +1,7 @@
 use std::{fmt, net::Ipv4Addr};
 
+use super::domain::DomainName;
+
 use type2network::FromNetworkOrder;
 use type2network_derive::FromNetwork;
 
@@ -22,6 +24,20 @@ impl fmt::Display for A {
     }
 }
 
+// in the CHAOS class, A RR is a domain name and a 16-bit octal address
+// https://datatracker.ietf.org/doc/html/rfc1035#section-3.4.1 and RFC973
+#[derive(Debug, Default, FromNetwork, Serialize)]
+pub(super) struct ChaosA {
+    domain: DomainName,
+    address: u16,
+}
+
+impl fmt::Display for ChaosA {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:o}", self.domain, self.address)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::net::Ipv4Addr;
@@ -45,4 +61,14 @@ mod tests {
             assert_eq!(addr, "166.84.7.99");
         })
     );
+
+    #[test]
+    fn chaos() {
+        let data = [0x02, b'c', b'h', 0x00, 0xFE, 0x01];
+        let mut buffer = std::io::Cursor::new(&data[..]);
+
+        let mut a = super::ChaosA::default();
+        a.deserialize_from(&mut buffer).unwrap();
+        assert_eq!(a.to_string(), "ch. 177001");
+    }
 }
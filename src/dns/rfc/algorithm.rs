@@ -5,6 +5,8 @@ use type2network_derive::FromNetwork;
 
 use enum_from::{EnumDisplay, EnumFromStr, EnumTryFrom};
 
+use crate::show::Numeric;
+
 #[derive(Debug, Default, Copy, Clone, PartialEq, EnumFromStr, EnumTryFrom, EnumDisplay, FromNetwork)]
 #[repr(u8)]
 #[from_network(TryFrom)]
@@ -33,6 +35,17 @@ pub enum Algorithm {
     Reserved(u8),
 }
 
+impl Numeric for Algorithm {
+    fn number(&self) -> u16 {
+        match self {
+            Algorithm::Reserved(n) => *n as u16,
+            // enum with a fallback can't be cast, but its u8 representation starts with the
+            // discriminant (see std::mem::discriminant)
+            _ => unsafe { *(self as *const Self).cast::<u8>() as u16 },
+        }
+    }
+}
+
 // https://www.rfc-editor.org/rfc/rfc4034.html#appendix-A.1
 #[derive(Debug, Default, Copy, Clone, PartialEq, EnumFromStr, EnumTryFrom, EnumDisplay, FromNetwork)]
 #[repr(u8)]
@@ -59,6 +72,12 @@ pub enum DNSSECAlgorithmTypes {
     PRIVATEOID = 254,
 }
 
+impl Numeric for DNSSECAlgorithmTypes {
+    fn number(&self) -> u16 {
+        *self as u16
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -72,4 +91,11 @@ mod tests {
         let a = Algorithm::Reserved(255);
         from_network_test(None, &a, &vec![255]);
     }
+
+    #[test]
+    fn number() {
+        assert_eq!(Algorithm::ECDSAP256SHA256.number(), 13);
+        assert_eq!(Algorithm::Reserved(255).number(), 255);
+        assert_eq!(DNSSECAlgorithmTypes::ED25519.number(), 15);
+    }
 }
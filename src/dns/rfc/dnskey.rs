@@ -3,7 +3,7 @@ use std::fmt;
 use type2network::FromNetworkOrder;
 use type2network_derive::FromNetwork;
 
-use crate::{dns::buffer::Buffer, new_rd_length};
+use crate::{dns::buffer::Buffer, new_rd_length, show::Numeric};
 
 use super::algorithm::DNSSECAlgorithmTypes;
 
@@ -83,7 +83,7 @@ impl fmt::Display for DNSKEY {
             "{} {} {} {}",
             flags,
             self.protocol,
-            self.algorithm,
+            self.algorithm.mnemonic(),
             self.key.to_base64()
         )
     }
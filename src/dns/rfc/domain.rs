@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fmt;
 use std::io::{Cursor, Result};
 use std::ops::Deref;
@@ -5,6 +6,7 @@ use std::slice::Iter;
 
 use colored::Colorize;
 use log::trace;
+use rand::Rng;
 use serde::{Serialize, Serializer};
 use type2network::{FromNetworkOrder, ToNetworkOrder};
 use type2network_derive::ToNetwork;
@@ -161,6 +163,59 @@ impl DomainName {
         self.labels.is_empty()
     }
 
+    // randomly mix upper and lower case letters (DNS 0x20: draft-vixie-dnsext-dns0x20)
+    pub fn randomize_case(&self) -> Self {
+        let mut rng = rand::thread_rng();
+
+        let labels = self
+            .labels
+            .iter()
+            .map(|l| {
+                Label(
+                    l.0.iter()
+                        .map(|c| {
+                            if rng.gen() {
+                                c.to_ascii_uppercase()
+                            } else {
+                                c.to_ascii_lowercase()
+                            }
+                        })
+                        .collect(),
+                )
+            })
+            .collect();
+
+        Self { labels }
+    }
+
+    // number of labels, 0 for the root
+    pub fn label_count(&self) -> usize {
+        self.labels.len()
+    }
+
+    // the domain without its first label, None for the root
+    pub fn parent(&self) -> Option<Self> {
+        (!self.labels.is_empty()).then(|| Self {
+            labels: self.labels[1..].to_vec(),
+        })
+    }
+
+    // true if the domain is parent or the domain is below parent
+    pub fn is_subdomain_of(&self, parent: &Self) -> bool {
+        self.labels.len() >= parent.labels.len()
+            && self
+                .labels
+                .iter()
+                .rev()
+                .zip(parent.labels.iter().rev())
+                .all(|(x, y)| x == y)
+    }
+
+    // unlike ==, case is significant
+    pub fn eq_case(&self, other: &Self) -> bool {
+        self.labels.len() == other.labels.len() && self.labels.iter().zip(other.labels.iter()).all(|x| x.0 .0 == x.1 .0)
+    }
+
     // iterator on labels
     fn iter(&self) -> Iter<'_, Label> {
         self.labels.iter()
@@ -368,6 +423,67 @@ impl ToNetworkOrder for DomainName {
     }
 }
 
+//---------------------------------------------------------------------------------------------
+// Name compression when serializing a message: https://datatracker.ietf.org/doc/html/rfc1035#section-4.1.4
+// Offsets of all names (and their suffixes) already written are kept, so a name or its suffix
+// found again is replaced by a pointer to the first occurence.
+//---------------------------------------------------------------------------------------------
+#[derive(Debug, Default)]
+pub struct NameCompressor {
+    // offset of the message start in the buffer (e.g. 2 when the TCP length is prepended)
+    start: usize,
+
+    // lowercase suffix and its offset in the message
+    offsets: HashMap<String, u16>,
+}
+
+// a pointer can only reference the first 16K of the message
+const MAX_POINTER_OFFSET: usize = 0x3FFF;
+
+impl NameCompressor {
+    pub fn new(start: usize) -> Self {
+        Self {
+            start,
+            offsets: HashMap::new(),
+        }
+    }
+}
+
+impl DomainName {
+    // same as serialize_to() but using compression pointers
+    pub fn serialize_compressed(&self, buffer: &mut Vec<u8>, compressor: &mut NameCompressor) -> Result<usize> {
+        let mut length = 0usize;
+
+        for i in 0..self.labels.len() {
+            let suffix = self.labels[i..]
+                .iter()
+                .map(|l| l.to_string().to_ascii_lowercase())
+                .collect::<Vec<_>>()
+                .join(".");
+
+            // suffix already written: point to it and we're done
+            if let Some(offset) = compressor.offsets.get(&suffix) {
+                length += (0xC000 | offset).serialize_to(buffer)?;
+                return Ok(length);
+            }
+
+            // otherwise keep its offset for the next names
+            let offset = buffer.len() - compressor.start;
+            if offset <= MAX_POINTER_OFFSET {
+                compressor.offsets.insert(suffix, offset as u16);
+            }
+
+            let label = &self.labels[i];
+            length += (label.len() as u8).serialize_to(buffer)?;
+            length += label.serialize_to(buffer)?;
+        }
+
+        // trailing 0 means end of domain name
+        length += 0_u8.serialize_to(buffer)?;
+        Ok(length)
+    }
+}
+
 impl<'a> FromNetworkOrder<'a> for DomainName {
     fn deserialize_from(&mut self, buffer: &mut Cursor<&'a [u8]>) -> Result<()> {
         // dbg!("============================");
@@ -397,6 +513,19 @@ impl<'a> FromNetworkOrder<'a> for DomainName {
 mod tests {
     use super::*;
 
+    #[test]
+    fn case() {
+        let dn = DomainName::try_from("www.google.com").unwrap();
+        let upper = DomainName::try_from("WWW.google.COM").unwrap();
+        assert!(dn == upper);
+        assert!(!dn.eq_case(&upper));
+        assert!(dn.eq_case(&dn.clone()));
+
+        let random = dn.randomize_case();
+        assert!(dn == random);
+        assert_eq!(random.to_string().to_lowercase(), "www.google.com.");
+    }
+
     #[test]
     fn size() {
         let dn = DomainName::try_from("www.google.com").unwrap();
@@ -511,6 +640,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn serialize_compressed() {
+        let mut buffer: Vec<u8> = vec![0; 12];
+        let mut compressor = NameCompressor::default();
+
+        let dn = DomainName::try_from("www.google.ie").unwrap();
+        assert_eq!(dn.serialize_compressed(&mut buffer, &mut compressor).unwrap(), 15);
+
+        // same name, case insensitive: only a pointer to offset 12
+        let dn = DomainName::try_from("WWW.google.ie").unwrap();
+        assert_eq!(dn.serialize_compressed(&mut buffer, &mut compressor).unwrap(), 2);
+        assert_eq!(&buffer[27..], &[0xC0, 0x0C]);
+
+        // suffix is compressed: pointer to google.ie at offset 16
+        let dn = DomainName::try_from("mail.google.ie").unwrap();
+        assert_eq!(dn.serialize_compressed(&mut buffer, &mut compressor).unwrap(), 7);
+        assert_eq!(&buffer[29..], &[0x04, b'm', b'a', b'i', b'l', 0xC0, 0x10]);
+
+        // the pointer can be followed when reading back
+        let mut mail = DomainName::default();
+        mail.create_from_position(29, &buffer).unwrap();
+        assert_eq!(mail.to_string(), "mail.google.ie.");
+
+        // root is never compressed
+        let mut buffer: Vec<u8> = Vec::new();
+        assert_eq!(
+            ROOT_DOMAIN.serialize_compressed(&mut buffer, &mut compressor).unwrap(),
+            1
+        );
+    }
+
     #[test]
     fn deserialize_from() {
         use std::io::Cursor;
@@ -534,4 +694,15 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn subdomain() {
+        let dn = DomainName::try_from("www.Example.com").unwrap();
+        assert!(dn.is_subdomain_of(&DomainName::try_from("example.com").unwrap()));
+        assert!(dn.is_subdomain_of(&dn));
+        assert!(dn.is_subdomain_of(&ROOT_DOMAIN));
+        assert!(!dn.is_subdomain_of(&DomainName::try_from("ample.com").unwrap()));
+        assert!(!ROOT_DOMAIN.is_subdomain_of(&dn));
+        assert_eq!(dn.label_count(), 3);
+    }
 }
@@ -3,7 +3,7 @@ use std::fmt;
 use type2network::FromNetworkOrder;
 use type2network_derive::FromNetwork;
 
-use crate::{dns::buffer::Buffer, new_rd_length};
+use crate::{dns::buffer::Buffer, new_rd_length, show::Numeric};
 
 use super::algorithm::Algorithm;
 
@@ -41,7 +41,10 @@ impl fmt::Display for DS {
         write!(
             f,
             "{} {} {} {:?}",
-            self.key_tag, self.algorithm, self.digest_type, self.digest
+            self.key_tag,
+            self.algorithm.mnemonic(),
+            self.digest_type,
+            self.digest
         )
     }
 }
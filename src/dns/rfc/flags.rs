@@ -8,6 +8,7 @@ use type2network::{FromNetworkOrder, ToNetworkOrder};
 use super::packet_type::PacketType;
 use crate::dns::rfc::{opcode::OpCode, response_code::ResponseCode};
 use crate::error::{Dns, Error};
+use crate::show::Numeric;
 
 // Flags: https://datatracker.ietf.org/doc/html/rfc1035#section-4.1.1
 // 1  1  1  1  1  1
@@ -87,6 +88,74 @@ impl Default for BitFlags {
     }
 }
 
+impl Flags {
+    #[inline]
+    pub fn op_code(&self) -> OpCode {
+        self.op_code
+    }
+
+    #[inline]
+    pub fn bitflags(&self) -> &BitFlags {
+        &self.bitflags
+    }
+
+    #[inline]
+    pub fn is_response(&self) -> bool {
+        self.qr == PacketType::Response
+    }
+
+    // names of the flags which are set, in wire order
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.is_response(), "qr"),
+            (self.bitflags.authorative_answer, "aa"),
+            (self.bitflags.truncation, "tc"),
+            (self.bitflags.recursion_desired, "rd"),
+            (self.bitflags.recursion_available, "ra"),
+            (self.bitflags.authentic_data, "ad"),
+            (self.bitflags.checking_disabled, "cd"),
+        ]
+        .into_iter()
+        .filter_map(|(set, name)| set.then_some(name))
+        .collect()
+    }
+
+    // one line per flag set and for the rcode, for people learning DNS (--explain-flags)
+    pub fn explain(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .names()
+            .iter()
+            .map(|name| {
+                let text = match *name {
+                    "qr" => "this message is a response",
+                    "aa" => "server is authoritative for this zone",
+                    "tc" => "response was truncated, it should be retried over TCP",
+                    "rd" => "recursion was desired by the client",
+                    "ra" => "server supports recursion",
+                    "ad" => "all data in the response was validated with DNSSEC by the server",
+                    "cd" => "DNSSEC validation was disabled by the client",
+                    _ => "",
+                };
+                format!("{}: {}", name, text)
+            })
+            .collect();
+
+        if self.op_code != OpCode::Query {
+            lines.push(format!("opcode {}: not a standard query", self.op_code));
+        }
+
+        if self.is_response() {
+            lines.push(format!(
+                "{}: {}",
+                self.response_code.to_string().to_uppercase(),
+                self.response_code.explain()
+            ));
+        }
+
+        lines
+    }
+}
+
 impl TryFrom<u16> for Flags {
     type Error = Error;
 
@@ -113,7 +182,7 @@ impl TryFrom<u16> for Flags {
         flags.bitflags.checking_disabled = (value >> 4 & 1) == 1;
 
         flags.response_code =
-            ResponseCode::try_from((value & 0b1111) as u8).map_err(|_| Error::Dns(Dns::UnknowOpCode))?;
+            ResponseCode::try_from((value & 0b1111) as u8).map_err(|_| Error::Dns(Dns::UnknowResponseCode))?;
 
         Ok(flags)
     }
@@ -180,6 +249,10 @@ impl fmt::Display for Flags {
         // } else {
         // write!(f, "qr:{:?} ", self.qr)?;
         // write!(f, "opcode:{:?} ", self.op_code)?;
+        if self.op_code != OpCode::Query {
+            write!(f, "opcode:{} ", self.op_code.mnemonic())?;
+        }
+
         flag_display!(f, self.bitflags.authentic_data, "ad");
         flag_display!(f, self.bitflags.authorative_answer, "aa");
         flag_display!(f, self.bitflags.checking_disabled, "cd");
@@ -188,7 +261,7 @@ impl fmt::Display for Flags {
         flag_display!(f, self.bitflags.truncation, "tc");
 
         if self.qr == PacketType::Response {
-            write!(f, "{} ", self.response_code)?;
+            write!(f, "{} ", self.response_code.mnemonic())?;
         }
         Ok(())
     }
@@ -221,6 +294,15 @@ mod tests {
         assert_eq!(v.response_code, ResponseCode::FormErr);
     }
 
+    #[test]
+    fn explain() {
+        let flags = Flags::try_from(0b_1000_0101_1000_0011).unwrap();
+        let lines = flags.explain();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[1], "aa: server is authoritative for this zone");
+        assert_eq!(lines[4], "NXDOMAIN: the domain name doesn't exist");
+    }
+
     #[test]
     fn serialize_to() {
         use type2network::ToNetworkOrder;
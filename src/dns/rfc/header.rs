@@ -1,4 +1,5 @@
 use std::fmt;
+use std::str::FromStr;
 
 use colored::Colorize;
 use type2network::{FromNetworkOrder, ToNetworkOrder};
@@ -7,7 +8,7 @@ use type2network_derive::{FromNetwork, ToNetwork};
 use rand::Rng;
 use serde::Serialize;
 
-use super::{flags::Flags, opcode::OpCode, packet_type::PacketType};
+use super::{flags::Flags, opcode::OpCode, packet_type::PacketType, response_code::ResponseCode};
 
 //  1  1  1  1  1  1
 //  0  1  2  3  4  5  6  7  8  9  0  1  2  3  4  5
@@ -87,6 +88,100 @@ impl fmt::Display for Header {
     }
 }
 
+//───────────────────────────────────────────────────────────────────────────────────
+// header fields forced in the query whatever its content (--craft), to build
+// inconsistent messages for lab exercises: QR set in a query, counts not matching
+// the sections, etc. Fields are kept in the order given
+//───────────────────────────────────────────────────────────────────────────────────
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HeaderCraft(Vec<(&'static str, u16)>);
+
+// field name and maximum value
+const CRAFT_FIELDS: &[(&str, u16)] = &[
+    ("id", u16::MAX),
+    ("qr", 1),
+    ("opcode", 15),
+    ("aa", 1),
+    ("tc", 1),
+    ("rd", 1),
+    ("ra", 1),
+    ("z", 1),
+    ("ad", 1),
+    ("cd", 1),
+    ("rcode", 15),
+    ("qdcount", u16::MAX),
+    ("ancount", u16::MAX),
+    ("nscount", u16::MAX),
+    ("arcount", u16::MAX),
+];
+
+impl HeaderCraft {
+    // overwrite the header fields. Opcode and rcode must be known values because
+    // the flags are stored as enums
+    pub fn apply(&self, header: &mut Header) -> crate::error::Result<()> {
+        use crate::error::{Dns, Error};
+
+        for (field, value) in &self.0 {
+            let bit = *value == 1;
+            match *field {
+                "id" => header.id = *value,
+                "qr" => header.flags.qr = if bit { PacketType::Response } else { PacketType::Query },
+                "opcode" => {
+                    header.flags.op_code = OpCode::try_from(*value as u8).map_err(|_| Error::Dns(Dns::UnknowOpCode))?
+                }
+                "aa" => header.flags.bitflags.authorative_answer = bit,
+                "tc" => header.flags.bitflags.truncation = bit,
+                "rd" => header.flags.bitflags.recursion_desired = bit,
+                "ra" => header.flags.bitflags.recursion_available = bit,
+                "z" => header.flags.bitflags.z = bit,
+                "ad" => header.flags.bitflags.authentic_data = bit,
+                "cd" => header.flags.bitflags.checking_disabled = bit,
+                "rcode" => {
+                    header.flags.response_code =
+                        ResponseCode::try_from(*value as u8).map_err(|_| Error::Dns(Dns::UnknowResponseCode))?
+                }
+                "qdcount" => header.qd_count = *value,
+                "ancount" => header.an_count = *value,
+                "nscount" => header.ns_count = *value,
+                "arcount" => header.ar_count = *value,
+                _ => unreachable!("field checked when parsing"),
+            }
+        }
+        Ok(())
+    }
+}
+
+// e.g.: qr=1,ancount=3,id=0x1234
+impl FromStr for HeaderCraft {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut craft = HeaderCraft::default();
+
+        for item in s.split(',').map(|x| x.trim()) {
+            let Some((field, value)) = item.split_once('=') else {
+                return Err(format!("'{}' is not in the form field=value", item));
+            };
+            let Some((field, max)) = CRAFT_FIELDS.iter().find(|(f, _)| *f == field.trim()) else {
+                return Err(format!("unknown header field '{}'", field));
+            };
+
+            let value = value.trim();
+            let value = match value.strip_prefix("0x") {
+                Some(hex) => u16::from_str_radix(hex, 16),
+                None => value.parse::<u16>(),
+            }
+            .ok()
+            .filter(|v| v <= max)
+            .ok_or_else(|| format!("'{}' is not a valid value for {}", value, field))?;
+
+            craft.0.push((field, value));
+        }
+
+        Ok(craft)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -109,4 +204,35 @@ mod tests {
         assert!(h.serialize_to(&mut buffer).is_ok());
         assert_eq!(buffer, sample);
     }
+
+    #[test]
+    fn craft() {
+        use crate::dns::rfc::header::{Header, HeaderCraft};
+        use std::str::FromStr;
+        use type2network::ToNetworkOrder;
+
+        let craft = HeaderCraft::from_str("id=0x1234,qr=1,rd=0,ancount=3").unwrap();
+        let mut h = Header::default();
+        craft.apply(&mut h).unwrap();
+
+        let mut buffer: Vec<u8> = Vec::new();
+        assert!(h.serialize_to(&mut buffer).is_ok());
+        assert_eq!(
+            buffer,
+            &[0x12, 0x34, 0x80, 0x00, 0x00, 0x01, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00]
+        );
+
+        assert!(HeaderCraft::from_str("qr=2").is_err());
+        assert!(HeaderCraft::from_str("foo=1").is_err());
+        assert!(HeaderCraft::from_str("ancount").is_err());
+
+        // unassigned opcode can't be represented
+        let craft = HeaderCraft::from_str("opcode=9").unwrap();
+        assert!(craft.apply(&mut h).is_err());
+
+        // same for unassigned rcode
+        use crate::error::{Dns, Error};
+        let craft = HeaderCraft::from_str("rcode=12").unwrap();
+        assert!(matches!(craft.apply(&mut h), Err(Error::Dns(Dns::UnknowResponseCode))));
+    }
 }
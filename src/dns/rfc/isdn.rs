@@ -0,0 +1,83 @@
+use std::fmt;
+use std::io::Cursor;
+
+use type2network::FromNetworkOrder;
+
+use serde::Serialize;
+
+use super::{char_string::CharacterString, DataLength};
+
+use crate::new_rd_length;
+
+// ISDN RR: https://datatracker.ietf.org/doc/html/rfc1183#section-3.2
+// the subaddress is optional
+// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+// /                  ISDN-address                 /
+// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+// /                       SA                      /
+// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Default, Serialize)]
+pub(super) struct ISDN {
+    // transmistted through RR deserialization
+    #[serde(skip)]
+    rd_length: u16,
+
+    address: CharacterString,
+    subaddress: Option<CharacterString>,
+}
+
+// auto-implement new
+new_rd_length!(ISDN);
+
+impl<'a> FromNetworkOrder<'a> for ISDN {
+    fn deserialize_from(&mut self, buffer: &mut Cursor<&'a [u8]>) -> std::io::Result<()> {
+        self.address.deserialize_from(buffer)?;
+
+        if self.address.size() < self.rd_length {
+            let mut sa = CharacterString::default();
+            sa.deserialize_from(buffer)?;
+            self.subaddress = Some(sa);
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for ISDN {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self.address)?;
+        if let Some(sa) = &self.subaddress {
+            write!(f, " \"{}\"", sa)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns::rfc::{rdata::RData, response::Response},
+        dns::tests::get_packets,
+        test_rdata,
+    };
+
+    use type2network::FromNetworkOrder;
+
+    use super::ISDN;
+
+    test_rdata!(
+        rdata,
+        "./tests/pcap/isdn.pcap",
+        false,
+        1,
+        RData::ISDN,
+        (|x: &ISDN, i: usize| {
+            match i {
+                0 => assert_eq!(&x.to_string(), "\"150862028003217\" \"004\""),
+                1 => assert_eq!(&x.to_string(), "\"150862028003218\""),
+                _ => panic!("data not is the pcap file"),
+            }
+        })
+    );
+}
@@ -25,10 +25,13 @@ impl fmt::Display for KX {
     }
 }
 
+// RT has the same layout (preference + intermediate host): https://datatracker.ietf.org/doc/html/rfc1183#section-3.3
+pub(super) type RT = KX;
+
 #[cfg(test)]
 mod tests {
     use crate::{
-        dns::rfc::{rdata::RData, response::Response},
+        dns::rfc::{kx::RT, rdata::RData, response::Response},
         dns::tests::get_packets,
         test_rdata,
     };
@@ -47,4 +50,15 @@ mod tests {
             assert_eq!(&x.to_string(), "1 panix.netmeister.org.");
         })
     );
+
+    test_rdata!(
+        rdata_rt,
+        "./tests/pcap/rt.pcap",
+        false,
+        1,
+        RData::RT,
+        (|x: &RT, _| {
+            assert_eq!(&x.to_string(), "10 panix.netmeister.org.");
+        })
+    );
 }
@@ -0,0 +1,72 @@
+use std::fmt;
+
+use type2network::FromNetworkOrder;
+use type2network_derive::FromNetwork;
+
+use serde::Serialize;
+
+use super::domain::DomainName;
+
+// MB, MG & MR RRs: https://datatracker.ietf.org/doc/html/rfc1035#section-3.3.3
+// experimental and obsolete, but still found in some legacy zones
+#[derive(Debug, Default, FromNetwork, Serialize)]
+pub struct MB(DomainName);
+
+impl fmt::Display for MB {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+pub type MG = MB;
+pub type MR = MB;
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns::rfc::{
+            mb::{MG, MR},
+            rdata::RData,
+            response::Response,
+        },
+        dns::tests::get_packets,
+        test_rdata,
+    };
+
+    use type2network::FromNetworkOrder;
+
+    use super::MB;
+
+    test_rdata!(
+        rdata_mb,
+        "./tests/pcap/mb.pcap",
+        false,
+        1,
+        RData::MB,
+        (|x: &MB, _| {
+            assert_eq!(&x.to_string(), "panix.netmeister.org.");
+        })
+    );
+
+    test_rdata!(
+        rdata_mg,
+        "./tests/pcap/mg.pcap",
+        false,
+        1,
+        RData::MG,
+        (|x: &MG, _| {
+            assert_eq!(&x.to_string(), "jschauma.netmeister.org.");
+        })
+    );
+
+    test_rdata!(
+        rdata_mr,
+        "./tests/pcap/mr.pcap",
+        false,
+        1,
+        RData::MR,
+        (|x: &MR, _| {
+            assert_eq!(&x.to_string(), "jschauma.netmeister.org.");
+        })
+    );
+}
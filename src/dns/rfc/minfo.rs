@@ -0,0 +1,50 @@
+use std::fmt;
+
+use type2network::FromNetworkOrder;
+use type2network_derive::FromNetwork;
+
+use serde::Serialize;
+
+use super::domain::DomainName;
+
+// MINFO RR: https://datatracker.ietf.org/doc/html/rfc1035#section-3.3.7
+// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+// /                    RMAILBX                    /
+// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+// /                    EMAILBX                    /
+// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+#[derive(Debug, Default, FromNetwork, Serialize)]
+pub(super) struct MINFO {
+    rmailbx: DomainName,
+    emailbx: DomainName,
+}
+
+impl fmt::Display for MINFO {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.rmailbx, self.emailbx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns::rfc::{rdata::RData, response::Response},
+        dns::tests::get_packets,
+        test_rdata,
+    };
+
+    use type2network::FromNetworkOrder;
+
+    use super::MINFO;
+
+    test_rdata!(
+        rdata,
+        "./tests/pcap/minfo.pcap",
+        false,
+        1,
+        RData::MINFO,
+        (|x: &MINFO, _| {
+            assert_eq!(&x.to_string(), "jschauma.netmeister.org. postmaster.netmeister.org.");
+        })
+    );
+}
@@ -34,8 +34,11 @@ pub mod eui64;
 pub mod hinfo;
 pub mod hip;
 pub mod ipseckey;
+pub mod isdn;
 pub mod kx;
 pub mod loc;
+pub mod mb;
+pub mod minfo;
 pub mod mx;
 pub mod naptr;
 pub mod ns;
@@ -44,12 +47,14 @@ pub mod nsec3;
 pub mod nsec3param;
 pub mod openpgpkey;
 pub mod opt;
+pub mod presentation;
 pub mod ptr;
 pub mod query;
 pub mod rdata;
 pub mod response;
 pub mod rp;
 pub mod rrlist;
+pub mod rrset;
 pub mod rrsig;
 pub mod soa;
 pub mod srv;
@@ -57,10 +62,13 @@ pub mod sshfp;
 pub mod svcb;
 // pub mod tkey;
 pub mod tlsa;
+pub mod tsig;
 pub mod txt;
 pub mod type_bitmaps;
 pub mod uri;
 pub mod wallet;
+pub mod wks;
+pub mod x25;
 pub mod zonemd;
 
 //
@@ -8,7 +8,7 @@ use serde::Serialize;
 
 use crate::new_rd_length;
 
-use super::{domain::DomainName, type_bitmaps::TypeBitMaps};
+use super::{domain::DomainName, qtype::QType, type_bitmaps::TypeBitMaps};
 
 // 1 1 1 1 1 1 1 1 1 1 2 2 2 2 2 2 2 2 2 2 3 3
 // 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
@@ -26,13 +26,20 @@ pub(super) struct NSEC {
 
     domain: DomainName,
 
-    #[from_network(with_code( self.types = TypeBitMaps::new(self.rd_length - self.domain.len() as u16); ))]
+    #[from_network(with_code( self.types = TypeBitMaps::new(self.rd_length - self.domain.size() as u16); ))]
     types: TypeBitMaps,
 }
 
 // auto-implement new
 new_rd_length!(NSEC);
 
+impl NSEC {
+    // true if the type is listed in the type bitmap
+    pub(super) fn has_type(&self, qt: &QType) -> bool {
+        self.types.types.contains(qt)
+    }
+}
+
 impl fmt::Display for NSEC {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} {}", self.domain, self.types)
@@ -6,6 +6,8 @@ use type2network_derive::FromNetwork;
 
 use enum_from::{EnumDisplay, EnumTryFrom};
 
+use crate::show::Numeric;
+
 /// op codes: https://www.iana.org/assignments/dns-parameters/dns-parameters.xhtml#dns-parameters-5
 #[derive(Debug, Default, Clone, Copy, PartialEq, FromNetwork, EnumTryFrom, EnumDisplay, Serialize)]
 #[repr(u8)]
@@ -22,6 +24,12 @@ pub enum OpCode {
                 // 7-15 Unassigned
 }
 
+impl Numeric for OpCode {
+    fn number(&self) -> u16 {
+        *self as u16
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,66 @@
+use std::fmt;
+
+use type2network::ToNetworkOrder;
+use type2network_derive::ToNetwork;
+
+use crate::dns::rfc::domain::DomainName;
+use crate::{opt_code, opt_data};
+
+use serde::Serialize;
+
+use super::{
+    opt_rr::{OptionCode, OptionData},
+    OptionDataValue,
+};
+
+// CHAIN: https://www.rfc-editor.org/rfc/rfc7901.html
+// the closest trust point is sent as an uncompressed domain name
+#[derive(Debug, Default, ToNetwork, Serialize)]
+pub struct CHAIN(DomainName);
+
+impl From<DomainName> for CHAIN {
+    fn from(dn: DomainName) -> Self {
+        Self(dn)
+    }
+}
+
+impl fmt::Display for CHAIN {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl OptionDataValue for CHAIN {
+    // return the option code for the option data
+    opt_code!(CHAIN);
+
+    // return option data length
+    fn len(&self) -> u16 {
+        self.0.size() as u16
+    }
+
+    // return the option data enum arm
+    opt_data!(CHAIN);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::tests::to_network_test;
+
+    #[test]
+    fn network() {
+        let chain = CHAIN::from(DomainName::try_from("example.com").unwrap());
+        assert_eq!(chain.len(), 13);
+        to_network_test(
+            &chain,
+            13,
+            &[
+                0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o', b'm', 0x00,
+            ],
+        );
+
+        let chain = CHAIN::from(DomainName::try_from(".").unwrap());
+        assert_eq!(chain.len(), 1);
+    }
+}
@@ -1,9 +1,24 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
 use type2network::{FromNetworkOrder, ToNetworkOrder};
 use type2network_derive::{FromNetwork, ToNetwork};
 
 use serde::Serialize;
 
 use crate::dns::buffer::Buffer;
+use crate::error::{Dns, Error};
+use crate::{opt_code, opt_data};
+
+use super::{
+    opt_rr::{OptionCode, OptionData},
+    OptionDataValue,
+};
+
+// address families: https://www.iana.org/assignments/address-family-numbers/address-family-numbers.xhtml
+const FAMILY_IPV4: u16 = 1;
+const FAMILY_IPV6: u16 = 2;
 
 // https://www.rfc-editor.org/rfc/rfc7871
 #[derive(Debug, Default, ToNetwork, FromNetwork, Serialize)]
@@ -13,3 +28,127 @@ pub struct ClientSubnet {
     pub(super) scope_prefix_length: u8,
     pub(super) address: Buffer,
 }
+
+impl ClientSubnet {
+    // number of significant bits of the address sent
+    pub fn source_prefix(&self) -> u8 {
+        self.source_prefix_length
+    }
+
+    // the prefix length the answer is valid for, as set by the server
+    pub fn scope(&self) -> u8 {
+        self.scope_prefix_length
+    }
+
+    // address padded with 0 to its full length
+    pub fn address(&self) -> Option<IpAddr> {
+        match self.family {
+            FAMILY_IPV4 => {
+                let mut octets = [0u8; 4];
+                let len = self.address.len().min(4);
+                octets[..len].copy_from_slice(&self.address[..len]);
+                Some(IpAddr::V4(Ipv4Addr::from(octets)))
+            }
+            FAMILY_IPV6 => {
+                let mut octets = [0u8; 16];
+                let len = self.address.len().min(16);
+                octets[..len].copy_from_slice(&self.address[..len]);
+                Some(IpAddr::V6(Ipv6Addr::from(octets)))
+            }
+            _ => None,
+        }
+    }
+}
+
+// e.g.: 192.0.2.0/24 or 2001:db8::/48. Only the significant bytes of the address
+// are kept, bits beyond the prefix are zeroed as required by RFC7871
+impl FromStr for ClientSubnet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr = IpAddr::from_str(addr.trim()).map_err(|_| Error::Dns(Dns::InvalidClientSubnet))?;
+
+        let (family, octets, max) = match addr {
+            IpAddr::V4(ip) => (FAMILY_IPV4, ip.octets().to_vec(), 32u8),
+            IpAddr::V6(ip) => (FAMILY_IPV6, ip.octets().to_vec(), 128u8),
+        };
+
+        let prefix = match prefix {
+            Some(p) => p
+                .trim()
+                .parse::<u8>()
+                .map_err(|_| Error::Dns(Dns::InvalidClientSubnet))?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(Error::Dns(Dns::InvalidClientSubnet));
+        }
+
+        let length = (prefix as usize).div_ceil(8);
+        let mut address = octets[..length].to_vec();
+        if prefix % 8 != 0 {
+            address[length - 1] &= 0xFF << (8 - prefix % 8);
+        }
+
+        Ok(Self {
+            family,
+            source_prefix_length: prefix,
+            scope_prefix_length: 0,
+            address: Buffer::from(address.as_slice()),
+        })
+    }
+}
+
+impl fmt::Display for ClientSubnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.address() {
+            Some(addr) => write!(f, "{}/{}/{}", addr, self.source_prefix_length, self.scope_prefix_length),
+            None => write!(f, "{} {}", self.family, self.address),
+        }
+    }
+}
+
+impl OptionDataValue for ClientSubnet {
+    // return the option code for the option data
+    opt_code!(EdnsClientSubnet);
+
+    // return option data length
+    fn len(&self) -> u16 {
+        4 + self.address.len() as u16
+    }
+
+    // return the option data enum arm
+    opt_data!(ClientSubnet);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::tests::to_network_test;
+
+    #[test]
+    fn from_str() {
+        let subnet = ClientSubnet::from_str("192.0.2.130/25").unwrap();
+        assert_eq!(subnet.len(), 8);
+        to_network_test(&subnet, 8, &[0x00, 0x01, 25, 0, 192, 0, 2, 128]);
+        assert_eq!(subnet.to_string(), "192.0.2.128/25/0");
+
+        let subnet = ClientSubnet::from_str("2001:db8:1234::/48").unwrap();
+        assert_eq!(subnet.len(), 10);
+        to_network_test(&subnet, 10, &[0x00, 0x02, 48, 0, 0x20, 0x01, 0x0d, 0xb8, 0x12, 0x34]);
+
+        // no prefix: the whole address
+        let subnet = ClientSubnet::from_str("192.0.2.1").unwrap();
+        assert_eq!(subnet.to_string(), "192.0.2.1/32/0");
+
+        // privacy: 0 prefix, no address
+        assert_eq!(ClientSubnet::from_str("0.0.0.0/0").unwrap().len(), 4);
+
+        assert!(ClientSubnet::from_str("192.0.2.0/33").is_err());
+        assert!(ClientSubnet::from_str("foo/24").is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::{fmt, num::ParseIntError};
+use std::fmt;
 
 use type2network::ToNetworkOrder;
 use type2network_derive::ToNetwork;
@@ -9,13 +9,15 @@ use super::{
     opt_rr::{OptionCode, OptionData},
     OptionDataValue,
 };
-use crate::{opt_code, opt_data, opt_len};
+use crate::{opt_code, opt_data};
 
 // Cookie: https://www.rfc-editor.org/rfc/rfc7873
 // https://www.rfc-editor.org/rfc/rfc9018
-#[derive(Debug, Default, ToNetwork, Serialize)]
+#[derive(Debug, Default, Clone, PartialEq, ToNetwork, Serialize)]
 pub struct COOKIE {
     pub client_cookie: [u8; 8],
+
+    // 8 to 32 bytes, returned by the server (RFC7873 section 4)
     pub server_cookie: Option<Vec<u8>>,
 }
 
@@ -27,43 +29,42 @@ impl COOKIE {
             server_cookie: None,
         }
     }
+
+    pub fn new(client_cookie: [u8; 8], server_cookie: Option<Vec<u8>>) -> Self {
+        Self {
+            client_cookie,
+            server_cookie,
+        }
+    }
 }
 
 impl From<&str> for COOKIE {
+    // the client cookie as 16 hex digits, possibly followed by the server cookie. A random
+    // client cookie is used if it's empty or can't be decoded
     fn from(cookie_string: &str) -> Self {
-        match cookie_string.len() {
-            // cookie is either empty, or less than 16 chars
-            0..=16 => COOKIE::random(),
-
-            // otherwise take only 16 chars
-            _ => {
-                let mut cookie = COOKIE::default();
-
-                // if hex encoding is successful
-                if let Ok(v) = decode_cookie(cookie_string) {
-                    cookie.client_cookie[0] = v[0];
-                    cookie.client_cookie[1] = v[1];
-                    cookie.client_cookie[2] = v[2];
-                    cookie.client_cookie[3] = v[3];
-                    cookie.client_cookie[4] = v[4];
-                    cookie.client_cookie[5] = v[5];
-                    cookie.client_cookie[6] = v[6];
-                    cookie.client_cookie[7] = v[7];
-
-                    cookie
-                }
-                // error, so fall back to a random one
-                else {
-                    COOKIE::random()
-                }
-            }
-        }
+        let Some((client, server)) = cookie_string.split_at_checked(16) else {
+            return COOKIE::random();
+        };
+        let Ok(client) = base16::decode(client) else {
+            return COOKIE::random();
+        };
+        let server = base16::decode(server)
+            .ok()
+            .filter(|server| (8..=32).contains(&server.len()));
+
+        let mut cookie = COOKIE::new([0; 8], server);
+        cookie.client_cookie.copy_from_slice(&client);
+        cookie
     }
 }
 
 impl fmt::Display for COOKIE {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?} {:?}", self.client_cookie, self.server_cookie)
+        write!(f, "{}", base16::encode_lower(&self.client_cookie))?;
+        if let Some(server) = &self.server_cookie {
+            write!(f, "{}", base16::encode_lower(server))?;
+        }
+        Ok(())
     }
 }
 
@@ -72,16 +73,35 @@ impl OptionDataValue for COOKIE {
     opt_code!(COOKIE);
 
     // return option data length
-    opt_len!(8);
+    fn len(&self) -> u16 {
+        8 + self.server_cookie.as_ref().map_or(0, |server| server.len() as u16)
+    }
 
     // return None
     opt_data!(COOKIE);
 }
 
-// encode an hex string into a vector
-fn decode_cookie(s: &str) -> Result<Vec<u8>, ParseIntError> {
-    (0..16)
-        .step_by(2)
-        .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
-        .collect()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str() {
+        let cookie = COOKIE::from("0102030405060708");
+        assert_eq!(cookie.client_cookie, [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(cookie.server_cookie, None);
+        assert_eq!(cookie.len(), 8);
+
+        let cookie = COOKIE::from("01020304050607080a0b0c0d0e0f1011");
+        assert_eq!(cookie.server_cookie, Some(vec![10, 11, 12, 13, 14, 15, 16, 17]));
+        assert_eq!(cookie.len(), 16);
+        assert_eq!(cookie.to_string(), "01020304050607080a0b0c0d0e0f1011");
+
+        // server cookie too short
+        assert_eq!(COOKIE::from("01020304050607080a0b").server_cookie, None);
+
+        // random ones
+        assert_ne!(COOKIE::from("").client_cookie, COOKIE::from("").client_cookie);
+        assert_eq!(COOKIE::from("zz02030405060708").server_cookie, None);
+    }
 }
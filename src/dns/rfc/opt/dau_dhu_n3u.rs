@@ -1,3 +1,5 @@
+use std::fmt;
+
 use type2network::ToNetworkOrder;
 use type2network_derive::ToNetwork;
 
@@ -6,7 +8,7 @@ use serde::Serialize;
 use crate::{opt_code, opt_data};
 
 use super::{
-    opt_rr::{OptionData, OptionCode},
+    opt_rr::{OptionCode, OptionData},
     OptionDataValue,
 };
 
@@ -31,13 +33,20 @@ macro_rules! opt {
             }
         }
 
-        impl OptionData for $opt {
+        impl fmt::Display for $opt {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let list: Vec<_> = self.0.iter().map(|x| x.to_string()).collect();
+                write!(f, "{}", list.join(","))
+            }
+        }
+
+        impl OptionDataValue for $opt {
             // return the option code for the option data
             opt_code!($opt);
 
-            // return option data length
+            // return option data length in bytes
             fn len(&self) -> u16 {
-                self.0.len() as u16
+                (self.0.len() * std::mem::size_of::<$t>()) as u16
             }
 
             // return the option data enum arm
@@ -52,5 +61,19 @@ opt!(DAU, u8);
 opt!(DHU, u8);
 opt!(N3U, u8);
 
-// impl edns-key-tag
+// impl edns-key-tag: https://www.rfc-editor.org/rfc/rfc8145.html
 opt!(EdnsKeyTag, u16);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::tests::to_network_test;
+
+    #[test]
+    fn keytag() {
+        let keytag = EdnsKeyTag::from([20326u16, 38696].as_slice());
+        assert_eq!(keytag.len(), 4);
+        assert_eq!(keytag.to_string(), "20326,38696");
+        to_network_test(&keytag, 4, &[0x4F, 0x66, 0x97, 0x28]);
+    }
+}
@@ -0,0 +1,57 @@
+use std::fmt;
+
+use type2network::ToNetworkOrder;
+use type2network_derive::ToNetwork;
+
+use crate::dns::rfc::resource_record::Ttl;
+use crate::{opt_code, opt_data, opt_len};
+
+use serde::Serialize;
+
+use super::{
+    opt_rr::{OptionCode, OptionData},
+    OptionDataValue,
+};
+
+// EXPIRE: https://www.rfc-editor.org/rfc/rfc7314.html
+// empty in queries, the remaining expire timer in seconds in responses
+#[derive(Debug, Default, ToNetwork, Serialize)]
+pub struct EXPIRE(Option<u32>);
+
+impl From<u32> for EXPIRE {
+    fn from(expire: u32) -> Self {
+        Self(Some(expire))
+    }
+}
+
+impl fmt::Display for EXPIRE {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(expire) = self.0 {
+            write!(f, "{} ({})", expire, Ttl(expire))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl OptionDataValue for EXPIRE {
+    // return the option code for the option data
+    opt_code!(EXPIRE);
+
+    // return option data length
+    opt_len!(0);
+
+    // return None
+    opt_data!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(EXPIRE::default().to_string(), "");
+        assert_eq!(EXPIRE::from(1209600).to_string(), "1209600 (14d0h0m0s)");
+    }
+}
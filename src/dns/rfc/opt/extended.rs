@@ -23,6 +23,13 @@ impl From<(u16, Buffer)> for Extended {
     }
 }
 
+impl Extended {
+    #[inline]
+    pub fn info_code(&self) -> u16 {
+        self.info_code
+    }
+}
+
 impl fmt::Display for Extended {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.info_code {
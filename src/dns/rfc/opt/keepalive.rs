@@ -0,0 +1,64 @@
+use std::fmt;
+
+use type2network::ToNetworkOrder;
+use type2network_derive::ToNetwork;
+
+use crate::{opt_code, opt_data, opt_len};
+
+use serde::Serialize;
+
+use super::{
+    opt_rr::{OptionCode, OptionData},
+    OptionDataValue,
+};
+
+// edns-tcp-keepalive: https://www.rfc-editor.org/rfc/rfc7828.html
+// empty in queries, the idle timeout of the server in units of 100 ms in responses
+#[derive(Debug, Default, ToNetwork, Serialize)]
+pub struct TcpKeepalive(Option<u16>);
+
+impl TcpKeepalive {
+    // idle timeout of the server in ms
+    pub fn timeout(&self) -> Option<u32> {
+        self.0.map(|t| t as u32 * 100)
+    }
+}
+
+impl From<u16> for TcpKeepalive {
+    fn from(timeout: u16) -> Self {
+        Self(Some(timeout))
+    }
+}
+
+impl fmt::Display for TcpKeepalive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(timeout) = self.timeout() {
+            write!(f, "{} ms", timeout)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl OptionDataValue for TcpKeepalive {
+    // return the option code for the option data
+    opt_code!(EdnsTcpKeepalive);
+
+    // return option data length
+    opt_len!(0);
+
+    // return None
+    opt_data!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(TcpKeepalive::default().to_string(), "");
+        assert_eq!(TcpKeepalive::from(1200).timeout(), Some(120000));
+        assert_eq!(TcpKeepalive::from(1200).to_string(), "120000 ms");
+    }
+}
@@ -17,15 +17,19 @@
 
 use self::opt_rr::{OptionCode, OptionData};
 
+pub mod chain;
 pub mod client_subnet;
 pub mod cookie;
-//pub mod dau_dhu_n3u;
+pub mod dau_dhu_n3u;
+pub mod expire;
 pub mod extended;
+pub mod keepalive;
 pub mod llq;
 pub mod nsid;
 pub mod opt_rr;
 pub mod padding;
 pub mod report_chanel;
+pub mod vendor;
 pub mod zoneversion;
 
 pub trait OptionDataValue {
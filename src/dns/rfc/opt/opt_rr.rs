@@ -24,13 +24,18 @@ use crate::dns::{
 };
 
 use super::{
+    chain::CHAIN,
     //client_subnet::ClientSubnet,
     client_subnet::ClientSubnet,
     cookie::COOKIE,
+    dau_dhu_n3u::{EdnsKeyTag, DAU, DHU, N3U},
+    expire::EXPIRE,
     extended::Extended,
+    keepalive::TcpKeepalive,
     llq::LLQ,
     padding::Padding,
     report_chanel::ReportChannel,
+    vendor::{DeviceID, Umbrella, UnknownOption},
     zoneversion::{ZONEVERSION, ZV},
 };
 
@@ -106,8 +111,9 @@ impl<'a> FromNetworkOrder<'a> for OptOption {
             OptionCode::COOKIE => {
                 let mut cookie = COOKIE::default();
                 cookie.client_cookie.deserialize_from(buffer)?;
-                let mut buf: Buffer = Buffer::with_capacity(self.length - 8);
+                let mut buf: Buffer = Buffer::with_capacity(self.length.saturating_sub(8));
                 buf.deserialize_from(buffer)?;
+                cookie.server_cookie = (!buf.is_empty()).then(|| buf.to_vec());
 
                 self.data = Some(OptionData::COOKIE(cookie));
             }
@@ -126,19 +132,77 @@ impl<'a> FromNetworkOrder<'a> for OptOption {
                 self.data = Some(OptionData::Extended(Extended::from((info_code, buf))));
             }
             OptionCode::ReportChannel => {
-                let mut agent_domain = DomainName::default();
-                agent_domain.deserialize_from(buffer)?;
-
-                self.data = Some(OptionData::ReportChanel(ReportChannel::from(agent_domain)));
+                if self.length == 0 {
+                    self.data = Some(OptionData::ReportChanel(ReportChannel::default()));
+                } else {
+                    let mut agent_domain = DomainName::default();
+                    agent_domain.deserialize_from(buffer)?;
+
+                    self.data = Some(OptionData::ReportChanel(ReportChannel::from(agent_domain)));
+                }
             }
             OptionCode::ZONEVERSION => {
-                let mut zv = ZV::default();
-                zv.label_count.deserialize_from(buffer)?;
-                zv.r#type.deserialize_from(buffer)?;
-                let mut buf: Buffer = Buffer::with_capacity(self.length - 2);
+                // an empty option is possible when the server can't give a version
+                if self.length < 2 {
+                    self.data = Some(OptionData::ZONEVERSION(ZONEVERSION::default()));
+                } else {
+                    let mut zv = ZV::default();
+                    zv.label_count.deserialize_from(buffer)?;
+                    zv.r#type.deserialize_from(buffer)?;
+                    let mut buf: Buffer = Buffer::with_capacity(self.length - 2);
+                    buf.deserialize_from(buffer)?;
+                    zv.version = buf;
+
+                    self.data = Some(OptionData::ZONEVERSION(ZONEVERSION::from(zv)));
+                }
+            }
+            OptionCode::CHAIN => {
+                let mut closest = DomainName::default();
+                closest.deserialize_from(buffer)?;
+
+                self.data = Some(OptionData::CHAIN(CHAIN::from(closest)));
+            }
+            OptionCode::DAU | OptionCode::DHU | OptionCode::N3U => {
+                let mut buf: Buffer = Buffer::with_capacity(self.length);
+                buf.deserialize_from(buffer)?;
+
+                self.data = Some(match self.code {
+                    OptionCode::DAU => OptionData::DAU(DAU::from(&buf[..])),
+                    OptionCode::DHU => OptionData::DHU(DHU::from(&buf[..])),
+                    _ => OptionData::N3U(N3U::from(&buf[..])),
+                });
+            }
+            OptionCode::EdnsKeyTag => {
+                // an odd trailing byte is malformed: it's read but ignored
+                let mut buf: Buffer = Buffer::with_capacity(self.length);
                 buf.deserialize_from(buffer)?;
+                let tags: Vec<u16> = buf
+                    .chunks_exact(2)
+                    .map(|tag| u16::from_be_bytes([tag[0], tag[1]]))
+                    .collect();
 
-                self.data = Some(OptionData::ZONEVERSION(ZONEVERSION::from(zv)));
+                self.data = Some(OptionData::EdnsKeyTag(EdnsKeyTag::from(tags.as_slice())));
+            }
+            OptionCode::EXPIRE => {
+                // servers send the expire timer, empty otherwise. The whole option is read so
+                // that a malformed length doesn't throw the next options out of sync
+                let mut buf: Buffer = Buffer::with_capacity(self.length);
+                buf.deserialize_from(buffer)?;
+                let expire = <[u8; 4]>::try_from(&buf[..])
+                    .map_or_else(|_| EXPIRE::default(), |timer| EXPIRE::from(u32::from_be_bytes(timer)));
+
+                self.data = Some(OptionData::EXPIRE(expire));
+            }
+            OptionCode::EdnsTcpKeepalive => {
+                // servers send their idle timeout, empty otherwise. A malformed value is ignored
+                let mut buf: Buffer = Buffer::with_capacity(self.length);
+                buf.deserialize_from(buffer)?;
+                let keepalive = <[u8; 2]>::try_from(&buf[..]).map_or_else(
+                    |_| TcpKeepalive::default(),
+                    |timeout| TcpKeepalive::from(u16::from_be_bytes(timeout)),
+                );
+
+                self.data = Some(OptionData::TcpKeepalive(keepalive));
             }
             OptionCode::EdnsClientSubnet => {
                 let mut subnet = ClientSubnet::default();
@@ -147,7 +211,23 @@ impl<'a> FromNetworkOrder<'a> for OptOption {
 
                 self.data = Some(OptionData::ClientSubnet(subnet));
             }
-            _ => unimplemented!("option code <{}> is not yet implemented", self.code),
+            OptionCode::Umbrella | OptionCode::DeviceID => {
+                let mut buf: Buffer = Buffer::with_capacity(self.length);
+                buf.deserialize_from(buffer)?;
+
+                self.data = Some(if self.code == OptionCode::Umbrella {
+                    OptionData::Umbrella(Umbrella::from(buf))
+                } else {
+                    OptionData::DeviceID(DeviceID::from(buf))
+                });
+            }
+            // keep raw data for options we can't decode
+            _ => {
+                let mut buf: Buffer = Buffer::with_capacity(self.length);
+                buf.deserialize_from(buffer)?;
+
+                self.data = Some(OptionData::Unknown(UnknownOption::from(buf)));
+            }
         }
 
         trace!("OptOption deserialize: {:#?}", self);
@@ -169,7 +249,7 @@ pub enum OptionCode {
     DHU = 6,               // Standard	[RFC6975]
     N3U = 7,               // Standard	[RFC6975]
     EdnsClientSubnet = 8,  //	Optional	[RFC7871]
-    EDNS = 9,              // EXPIRE	Optional	[RFC7314]
+    EXPIRE = 9,            // Optional	[RFC7314]
     COOKIE = 10,           // Standard	[RFC7873]
     EdnsTcpKeepalive = 11, //	Standard	[RFC7828]
     Padding = 12,          // Standard	[RFC7830]
@@ -186,18 +266,24 @@ pub enum OptionCode {
 
 #[derive(Debug, ToNetwork, Serialize)]
 pub enum OptionData {
-    // DAU(DAU),
-    // DHU(DHU),
-    // EdnsKeyTag(EdnsKeyTag),
-    // N3U(N3U),
+    DAU(DAU),
+    DHU(DHU),
+    EdnsKeyTag(EdnsKeyTag),
+    EXPIRE(EXPIRE),
+    N3U(N3U),
+    CHAIN(CHAIN),
     COOKIE(COOKIE),
     ClientSubnet(ClientSubnet),
     Extended(Extended),
     LLQ(LLQ),
     NSID(NSID),
     Padding(Padding),
+    TcpKeepalive(TcpKeepalive),
     ReportChanel(ReportChannel),
     ZONEVERSION(ZONEVERSION),
+    Umbrella(Umbrella),
+    DeviceID(DeviceID),
+    Unknown(UnknownOption),
 }
 
 impl Default for OptionData {
@@ -209,14 +295,24 @@ impl Default for OptionData {
 impl fmt::Display for OptionData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            OptionData::CHAIN(n) => write!(f, "{}", n)?,
+            OptionData::DAU(n) => write!(f, "{}", n)?,
+            OptionData::DHU(n) => write!(f, "{}", n)?,
+            OptionData::EdnsKeyTag(n) => write!(f, "{}", n)?,
+            OptionData::EXPIRE(n) => write!(f, "{}", n)?,
+            OptionData::N3U(n) => write!(f, "{}", n)?,
             OptionData::COOKIE(n) => write!(f, "{}", n)?,
-            OptionData::ClientSubnet(p) => write!(f, "{} {}", p.family, p.address)?,
+            OptionData::ClientSubnet(p) => write!(f, "{}", p)?,
             OptionData::Extended(p) => write!(f, "{}", p)?,
             OptionData::LLQ(p) => write!(f, "{}", p)?,
             OptionData::NSID(n) => write!(f, "{}", n)?,
             OptionData::Padding(p) => write!(f, "{}", p)?,
+            OptionData::TcpKeepalive(p) => write!(f, "{}", p)?,
             OptionData::ReportChanel(p) => write!(f, "{}", p)?,
             OptionData::ZONEVERSION(p) => write!(f, "{}", p)?,
+            OptionData::Umbrella(p) => write!(f, "{}", p)?,
+            OptionData::DeviceID(p) => write!(f, "{}", p)?,
+            OptionData::Unknown(p) => write!(f, "{}", p)?,
             //_ => unimplemented!("EDNS option not yet implemented"),
         }
         Ok(())
@@ -266,3 +362,50 @@ impl fmt::Display for OptionList {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // option parsed from its wire data, with the number of bytes read
+    fn parse(data: &[u8]) -> (OptOption, u64) {
+        let mut cursor = Cursor::new(data);
+        let mut option = OptOption::default();
+        option.deserialize_from(&mut cursor).unwrap();
+        (option, cursor.position())
+    }
+
+    #[test]
+    fn expire() {
+        let (option, read) = parse(&[0x00, 0x09, 0x00, 0x04, 0x00, 0x12, 0x75, 0x00]);
+        assert_eq!(read, 8);
+        assert_eq!(option.data.unwrap().to_string(), "1209600 (14d0h0m0s)");
+
+        // malformed length: the data is skipped
+        let (option, read) = parse(&[0x00, 0x09, 0x00, 0x03, 0x01, 0x02, 0x03, 0xFF]);
+        assert_eq!(read, 7);
+        assert_eq!(option.data.unwrap().to_string(), "");
+    }
+
+    #[test]
+    fn keepalive() {
+        let (option, read) = parse(&[0x00, 0x0B, 0x00, 0x02, 0x04, 0xB0]);
+        assert_eq!(read, 6);
+        assert_eq!(option.data.unwrap().to_string(), "120000 ms");
+
+        let (option, read) = parse(&[0x00, 0x0B, 0x00, 0x03, 0x04, 0xB0, 0x00, 0xFF]);
+        assert_eq!(read, 7);
+        assert_eq!(option.data.unwrap().to_string(), "");
+    }
+
+    #[test]
+    fn keytag() {
+        let (option, read) = parse(&[0x00, 0x0E, 0x00, 0x04, 0x4F, 0x66, 0x97, 0x28]);
+        assert_eq!(read, 8);
+        assert_eq!(option.data.unwrap().to_string(), "20326,38696");
+
+        let (option, read) = parse(&[0x00, 0x0E, 0x00, 0x03, 0x4F, 0x66, 0x97, 0xFF]);
+        assert_eq!(read, 7);
+        assert_eq!(option.data.unwrap().to_string(), "20326");
+    }
+}
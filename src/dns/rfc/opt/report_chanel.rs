@@ -4,7 +4,7 @@ use type2network::ToNetworkOrder;
 use type2network_derive::ToNetwork;
 
 use crate::dns::rfc::domain::DomainName;
-use crate::{opt_code, opt_data, opt_len};
+use crate::{opt_code, opt_data};
 
 use serde::Serialize;
 
@@ -14,18 +14,28 @@ use super::{
 };
 
 // ReportChanel: https://www.rfc-editor.org/rfc/rfc9567.html
+// the agent domain is returned by authoritative servers, a query sends an empty option
 #[derive(Debug, Default, ToNetwork, Serialize)]
-pub struct ReportChannel(DomainName);
+pub struct ReportChannel(Option<DomainName>);
+
+impl ReportChannel {
+    pub fn agent_domain(&self) -> Option<&DomainName> {
+        self.0.as_ref()
+    }
+}
 
 impl From<DomainName> for ReportChannel {
     fn from(dn: DomainName) -> Self {
-        Self(dn)
+        Self(Some(dn))
     }
 }
 
 impl fmt::Display for ReportChannel {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+        if let Some(dn) = &self.0 {
+            write!(f, "{}", dn)?;
+        }
+        Ok(())
     }
 }
 
@@ -34,8 +44,27 @@ impl OptionDataValue for ReportChannel {
     opt_code!(ReportChannel);
 
     // return option data length
-    opt_len!(0);
+    fn len(&self) -> u16 {
+        self.0.as_ref().map(|dn| dn.size() as u16).unwrap_or(0)
+    }
 
-    // return None
-    opt_data!();
+    // return the option data enum arm
+    opt_data!(ReportChanel);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::tests::to_network_test;
+
+    #[test]
+    fn report_channel() {
+        let rc = ReportChannel::default();
+        assert_eq!(rc.len(), 0);
+        to_network_test(&rc, 0, &[]);
+
+        let rc = ReportChannel::from(DomainName::try_from("a01.agentdomain.example").unwrap());
+        assert_eq!(rc.len(), 25);
+        assert_eq!(rc.to_string(), "a01.agentdomain.example.");
+    }
 }
@@ -0,0 +1,137 @@
+//! Vendor options which are not defined by RFCs, and unknown options
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use type2network::ToNetworkOrder;
+use type2network_derive::ToNetwork;
+
+use serde::Serialize;
+
+use crate::dns::buffer::{serialize_buffer, Buffer};
+
+// Cisco Umbrella Ident: https://developer.cisco.com/docs/cloud-security/#!integrating-network-devices/rdata-description
+// data starts with the "ODNS" magic, a version and flags, followed by fields identified by a 1-byte type
+const ODNS_MAGIC: &[u8] = b"ODNS";
+
+#[derive(Debug, Default, ToNetwork, Serialize)]
+pub struct Umbrella(#[serde(serialize_with = "serialize_buffer")] Buffer);
+
+impl From<Buffer> for Umbrella {
+    fn from(buf: Buffer) -> Self {
+        Self(buf)
+    }
+}
+
+impl Umbrella {
+    // interpret fields when possible
+    fn fields(&self) -> Option<Vec<String>> {
+        let data: &[u8] = &self.0;
+        if data.len() < 6 || &data[..4] != ODNS_MAGIC {
+            return None;
+        }
+
+        let mut fields = vec![format!("version:{}", data[4]), format!("flags:{:#04x}", data[5])];
+        let mut rest = &data[6..];
+
+        while let Some((&field, value)) = rest.split_first() {
+            let size = match field {
+                0x08 | 0x40 => 4,
+                0x10 => 4,
+                0x20 => 16,
+                _ => return Some(fields),
+            };
+            if value.len() < size {
+                return Some(fields);
+            }
+            let v = &value[..size];
+
+            fields.push(match field {
+                0x08 => format!("organization:{}", u32::from_be_bytes([v[0], v[1], v[2], v[3]])),
+                0x40 => format!("origin:{}", u32::from_be_bytes([v[0], v[1], v[2], v[3]])),
+                0x10 => format!("remote:{}", Ipv4Addr::new(v[0], v[1], v[2], v[3])),
+                _ => {
+                    let mut octets = [0u8; 16];
+                    octets.copy_from_slice(v);
+                    format!("remote:{}", Ipv6Addr::from(octets))
+                }
+            });
+
+            rest = &value[size..];
+        }
+
+        Some(fields)
+    }
+}
+
+impl fmt::Display for Umbrella {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", self.0.to_base16())?;
+        if let Some(fields) = self.fields() {
+            write!(f, " ({})", fields.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+// Cisco DeviceID: https://developer.cisco.com/docs/cloud-security/#!network-devices-getting-started/response-codes
+// the device identifier is opaque
+#[derive(Debug, Default, ToNetwork, Serialize)]
+pub struct DeviceID(#[serde(serialize_with = "serialize_buffer")] Buffer);
+
+impl From<Buffer> for DeviceID {
+    fn from(buf: Buffer) -> Self {
+        Self(buf)
+    }
+}
+
+impl fmt::Display for DeviceID {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "device:0x{}", self.0.to_base16())
+    }
+}
+
+// options we don't know how to decode are kept as raw data
+#[derive(Debug, Default, ToNetwork, Serialize)]
+pub struct UnknownOption(#[serde(serialize_with = "serialize_buffer")] Buffer);
+
+impl From<Buffer> for UnknownOption {
+    fn from(buf: Buffer) -> Self {
+        Self(buf)
+    }
+}
+
+impl fmt::Display for UnknownOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", self.0.to_base16())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use type2network::FromNetworkOrder;
+
+    fn buffer(data: &[u8]) -> Buffer {
+        let mut buf = Buffer::with_capacity(data.len());
+        buf.deserialize_from(&mut Cursor::new(data)).unwrap();
+        buf
+    }
+
+    #[test]
+    fn umbrella() {
+        let data = [
+            b'O', b'D', b'N', b'S', 0x01, 0x00, 0x08, 0x00, 0x00, 0x30, 0x39, 0x10, 0x0A, 0x00, 0x00, 0x01,
+        ];
+        let umbrella = Umbrella::from(buffer(&data));
+        assert_eq!(
+            umbrella.fields().unwrap(),
+            vec!["version:1", "flags:0x00", "organization:12345", "remote:10.0.0.1"]
+        );
+
+        // not ODNS data
+        let umbrella = Umbrella::from(buffer(&[0x01, 0x02]));
+        assert!(umbrella.fields().is_none());
+        assert_eq!(umbrella.to_string(), "0x0102");
+    }
+}
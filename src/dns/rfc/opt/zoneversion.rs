@@ -6,21 +6,75 @@ use type2network_derive::ToNetwork;
 use crate::dns::buffer::Buffer;
 use crate::{opt_code, opt_data, opt_len};
 
-use serde::Serialize;
+use serde::{ser::SerializeMap, Serialize, Serializer};
 
 use super::{
     opt_rr::{OptionCode, OptionData},
     OptionDataValue,
 };
 
+// only type defined so far: https://www.rfc-editor.org/rfc/rfc9660.html#section-7.2
+const SOA_SERIAL: u8 = 0;
+
 // ZONEVERSION: https://www.rfc-editor.org/rfc/rfc9660.html
-#[derive(Debug, Default, ToNetwork, Serialize)]
+#[derive(Debug, Default, ToNetwork)]
 pub struct ZV {
     pub label_count: u8,
     pub r#type: u8,
     pub version: Buffer,
 }
 
+impl ZV {
+    // for the SOA-SERIAL type, the version is the serial of the zone
+    pub fn serial(&self) -> Option<u32> {
+        if self.r#type == SOA_SERIAL && self.version.len() == 4 {
+            Some(u32::from_be_bytes([
+                self.version[0],
+                self.version[1],
+                self.version[2],
+                self.version[3],
+            ]))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for ZV {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(serial) = self.serial() {
+            write!(f, "SOA-SERIAL:{} labels:{}", serial, self.label_count)
+        } else {
+            write!(
+                f,
+                "type:{} labels:{} version:0x{}",
+                self.r#type,
+                self.label_count,
+                self.version.to_base16()
+            )
+        }
+    }
+}
+
+impl Serialize for ZV {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(3))?;
+        map.serialize_entry("label_count", &self.label_count)?;
+
+        if let Some(serial) = self.serial() {
+            map.serialize_entry("type", "SOA-SERIAL")?;
+            map.serialize_entry("version", &serial)?;
+        } else {
+            map.serialize_entry("type", &self.r#type)?;
+            map.serialize_entry("version", &self.version.to_base16())?;
+        }
+        map.end()
+    }
+}
+
 #[derive(Debug, Default, ToNetwork, Serialize)]
 pub struct ZONEVERSION(pub Option<ZV>);
 
@@ -39,9 +93,7 @@ impl From<ZV> for ZONEVERSION {
 impl fmt::Display for ZONEVERSION {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if let Some(zv) = &self.0 {
-            write!(f, "{}", zv.label_count)?;
-            write!(f, "{}", zv.r#type)?;
-            write!(f, "{}", zv.version.display())?;
+            write!(f, "{}", zv)?;
         }
 
         Ok(())
@@ -58,3 +110,30 @@ impl OptionDataValue for ZONEVERSION {
     // return None
     opt_data!();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use type2network::FromNetworkOrder;
+
+    #[test]
+    fn serial() {
+        let mut version = Buffer::with_capacity(4u16);
+        version
+            .deserialize_from(&mut Cursor::new([0x78, 0xA4, 0x4E, 0x15].as_slice()))
+            .unwrap();
+
+        let zv = ZV {
+            label_count: 2,
+            r#type: SOA_SERIAL,
+            version,
+        };
+        assert_eq!(zv.serial(), Some(2024033813));
+        assert_eq!(zv.to_string(), "SOA-SERIAL:2024033813 labels:2");
+        assert_eq!(
+            serde_json::to_string(&zv).unwrap(),
+            r#"{"label_count":2,"type":"SOA-SERIAL","version":2024033813}"#
+        );
+    }
+}
@@ -0,0 +1,443 @@
+//! RDATA presentation format (as found in zone files) to wire format. The wire RDATA is
+//! then read as any received RDATA, so that all RR types share the same structs.
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use base64::{engine::general_purpose, Engine as _};
+use type2network::ToNetworkOrder;
+
+use super::{domain::DomainName, qtype::QType};
+use crate::error::{Error, Result};
+
+// malformed field or RDATA
+fn invalid(msg: String) -> Error {
+    Error::Presentation(msg)
+}
+
+// RDATA being built from its fields
+struct Wire<'a> {
+    fields: std::slice::Iter<'a, String>,
+    buffer: Vec<u8>,
+}
+
+impl<'a> Wire<'a> {
+    fn new(fields: &'a [String]) -> Self {
+        Self {
+            fields: fields.iter(),
+            buffer: Vec::new(),
+        }
+    }
+
+    fn next(&mut self, what: &str) -> Result<&'a str> {
+        self.fields
+            .next()
+            .map(String::as_str)
+            .ok_or_else(|| invalid(format!("missing {}", what)))
+    }
+
+    fn int<T: FromStr + ToNetworkOrder>(&mut self, what: &str) -> Result<&mut Self> {
+        let field = self.next(what)?;
+        let value = field
+            .parse::<T>()
+            .map_err(|_| invalid(format!("'{}' is not a valid {}", field, what)))?;
+        value
+            .serialize_to(&mut self.buffer)
+            .map_err(|e| invalid(e.to_string()))?;
+        Ok(self)
+    }
+
+    fn name(&mut self, what: &str) -> Result<&mut Self> {
+        let field = self.next(what)?;
+        let name =
+            DomainName::try_from(field).map_err(|e| invalid(format!("'{}' is not a valid {} ({})", field, what, e)))?;
+        name.serialize_to(&mut self.buffer)
+            .map_err(|e| invalid(e.to_string()))?;
+        Ok(self)
+    }
+
+    fn string(&mut self, what: &str) -> Result<&mut Self> {
+        let field = self.next(what)?;
+        let bytes = character_string(field)?;
+        self.buffer.push(bytes.len() as u8);
+        self.buffer.extend(bytes);
+        Ok(self)
+    }
+
+    // all remaining fields as character strings
+    fn strings(&mut self, what: &str) -> Result<&mut Self> {
+        self.string(what)?;
+        while self.fields.len() > 0 {
+            self.string(what)?;
+        }
+        Ok(self)
+    }
+
+    // all remaining fields as one hexadecimal or base64 value, which can contain blanks
+    fn hex(&mut self, what: &str) -> Result<&mut Self> {
+        let hex: String = self.fields.by_ref().map(String::as_str).collect();
+        let bytes =
+            base16::decode(&hex).map_err(|_| invalid(format!("{} '{}' is not valid hexadecimal", what, hex)))?;
+        self.buffer.extend(bytes);
+        Ok(self)
+    }
+
+    fn base64(&mut self, what: &str) -> Result<&mut Self> {
+        let b64: String = self.fields.by_ref().map(String::as_str).collect();
+        let bytes = general_purpose::STANDARD
+            .decode(&b64)
+            .map_err(|_| invalid(format!("{} '{}' is not valid base64", what, b64)))?;
+        self.buffer.extend(bytes);
+        Ok(self)
+    }
+
+    fn end(&mut self) -> Result<Vec<u8>> {
+        match self.fields.next() {
+            Some(field) => Err(invalid(format!("unexpected '{}'", field))),
+            None => Ok(std::mem::take(&mut self.buffer)),
+        }
+    }
+}
+
+// split a line into fields: comments are removed, quoted strings are kept as one field
+// and parentheses are separate fields
+pub fn split_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                current.push(c);
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            '"' => {
+                current.push(c);
+                quoted = !quoted;
+            }
+            ';' if !quoted => break,
+            '(' | ')' if !quoted => {
+                if !current.is_empty() {
+                    fields.push(std::mem::take(&mut current));
+                }
+                fields.push(c.to_string());
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    fields.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        fields.push(current);
+    }
+
+    fields
+}
+
+// quotes are removed and escapes (\X and \DDD) replaced
+fn character_string(field: &str) -> Result<Vec<u8>> {
+    let field = field
+        .strip_prefix('"')
+        .and_then(|f| f.strip_suffix('"'))
+        .unwrap_or(field);
+
+    let mut bytes = Vec::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut utf8 = [0u8; 4];
+            bytes.extend(c.encode_utf8(&mut utf8).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some(d) if d.is_ascii_digit() => {
+                let digits: String = [Some(d), chars.next(), chars.next()].into_iter().flatten().collect();
+                let byte = digits
+                    .parse::<u8>()
+                    .map_err(|_| invalid(format!("invalid escape \\{} in '{}'", digits, field)))?;
+                bytes.push(byte);
+            }
+            Some(c) => bytes.push(c as u8),
+            None => return Err(invalid(format!("dangling escape in '{}'", field))),
+        }
+    }
+
+    if bytes.len() > 255 {
+        return Err(invalid(format!(
+            "character string '{}' is longer than 255 bytes",
+            field
+        )));
+    }
+    Ok(bytes)
+}
+
+// SvcParamKey number from its name: https://www.rfc-editor.org/rfc/rfc9460#section-14.3.2
+fn svc_key(name: &str) -> Result<u16> {
+    match name {
+        "mandatory" => Ok(0),
+        "alpn" => Ok(1),
+        "no-default-alpn" => Ok(2),
+        "port" => Ok(3),
+        "ipv4hint" => Ok(4),
+        "ech" => Ok(5),
+        "ipv6hint" => Ok(6),
+        _ => name
+            .strip_prefix("key")
+            .and_then(|n| n.parse::<u16>().ok())
+            .ok_or_else(|| invalid(format!("unknown SvcParamKey '{}'", name))),
+    }
+}
+
+// SvcParamValue wire format
+fn svc_value(key: u16, value: &str) -> Result<Vec<u8>> {
+    let value = std::str::from_utf8(&character_string(value)?)
+        .map_err(|e| invalid(e.to_string()))?
+        .to_string();
+    let list = || value.split(',').filter(|v| !v.is_empty());
+    let mut wire = Vec::new();
+
+    match key {
+        0 => {
+            for k in list() {
+                wire.extend(svc_key(k)?.to_be_bytes());
+            }
+        }
+        1 => {
+            for id in list() {
+                wire.push(id.len() as u8);
+                wire.extend(id.as_bytes());
+            }
+        }
+        2 => (),
+        3 => wire.extend(
+            value
+                .parse::<u16>()
+                .map_err(|_| invalid(format!("'{}' is not a valid port", value)))?
+                .to_be_bytes(),
+        ),
+        4 => {
+            for ip in list() {
+                wire.extend(
+                    Ipv4Addr::from_str(ip)
+                        .map_err(|_| invalid(format!("'{}' is not an IPv4 address", ip)))?
+                        .octets(),
+                );
+            }
+        }
+        5 => {
+            wire = general_purpose::STANDARD
+                .decode(&value)
+                .map_err(|_| invalid(format!("ech '{}' is not valid base64", value)))?
+        }
+        6 => {
+            for ip in list() {
+                wire.extend(
+                    Ipv6Addr::from_str(ip)
+                        .map_err(|_| invalid(format!("'{}' is not an IPv6 address", ip)))?
+                        .octets(),
+                );
+            }
+        }
+        _ => wire.extend(value.as_bytes()),
+    }
+
+    Ok(wire)
+}
+
+// SvcParams in key order, e.g.: alpn=h2,h3 port=8443
+fn svc_params(fields: &[&str], buffer: &mut Vec<u8>) -> Result<()> {
+    let mut params = Vec::new();
+    for field in fields {
+        let (key, value) = field.split_once('=').unwrap_or((*field, ""));
+        let key = svc_key(key)?;
+        params.push((key, svc_value(key, value)?));
+    }
+    params.sort_by_key(|(key, _)| *key);
+
+    for (key, value) in params {
+        buffer.extend(key.to_be_bytes());
+        buffer.extend((value.len() as u16).to_be_bytes());
+        buffer.extend(value);
+    }
+    Ok(())
+}
+
+// generic RDATA (RFC3597): \# length hex
+fn generic(fields: &[String]) -> Result<Vec<u8>> {
+    let length = fields
+        .get(1)
+        .and_then(|l| l.parse::<usize>().ok())
+        .ok_or_else(|| invalid(String::from("invalid length of generic RDATA")))?;
+    let hex: String = fields[2..].concat();
+    let bytes = base16::decode(&hex).map_err(|_| invalid(format!("'{}' is not valid hexadecimal", hex)))?;
+
+    if bytes.len() != length {
+        return Err(invalid(format!(
+            "generic RDATA length is {} but {} bytes found",
+            length,
+            bytes.len()
+        )));
+    }
+    Ok(bytes)
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// RDATA wire format from its presentation fields. Names must be fully qualified
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn rdata_wire(qtype: &QType, fields: &[String]) -> Result<Vec<u8>> {
+    if fields.first().map(String::as_str) == Some("\\#") {
+        return generic(fields);
+    }
+
+    let mut wire = Wire::new(fields);
+
+    match qtype {
+        QType::A => {
+            let ip = wire.next("address")?;
+            let ip = Ipv4Addr::from_str(ip).map_err(|_| invalid(format!("'{}' is not an IPv4 address", ip)))?;
+            wire.buffer.extend(ip.octets());
+        }
+        QType::AAAA => {
+            let ip = wire.next("address")?;
+            let ip = Ipv6Addr::from_str(ip).map_err(|_| invalid(format!("'{}' is not an IPv6 address", ip)))?;
+            wire.buffer.extend(ip.octets());
+        }
+        QType::NS | QType::CNAME | QType::PTR | QType::DNAME | QType::MB | QType::MG | QType::MR => {
+            wire.name("domain name")?;
+        }
+        QType::MX | QType::KX | QType::AFSDB | QType::RT => {
+            wire.int::<u16>("preference")?.name("domain name")?;
+        }
+        QType::SOA => {
+            wire.name("mname")?.name("rname")?.int::<u32>("serial")?;
+            for what in ["refresh", "retry", "expire", "minimum"] {
+                wire.int::<u32>(what)?;
+            }
+        }
+        QType::RP | QType::MINFO => {
+            wire.name("mailbox")?.name("domain name")?;
+        }
+        QType::SRV => {
+            wire.int::<u16>("priority")?
+                .int::<u16>("weight")?
+                .int::<u16>("port")?
+                .name("target")?;
+        }
+        QType::TXT | QType::SPF => {
+            wire.strings("text")?;
+        }
+        QType::HINFO => {
+            wire.string("cpu")?.string("os")?;
+        }
+        QType::CAA => {
+            wire.int::<u8>("flags")?;
+            let tag = wire.next("tag")?;
+            wire.buffer.push(tag.len() as u8);
+            wire.buffer.extend(tag.as_bytes());
+            let value = character_string(wire.next("value")?)?;
+            wire.buffer.extend(value);
+        }
+        QType::NAPTR => {
+            wire.int::<u16>("order")?
+                .int::<u16>("preference")?
+                .string("flags")?
+                .string("services")?
+                .string("regexp")?
+                .name("replacement")?;
+        }
+        QType::URI => {
+            wire.int::<u16>("priority")?.int::<u16>("weight")?;
+            let target = character_string(wire.next("target")?)?;
+            wire.buffer.extend(target);
+        }
+        QType::DS | QType::CDS | QType::DLV => {
+            wire.int::<u16>("key tag")?
+                .int::<u8>("algorithm")?
+                .int::<u8>("digest type")?
+                .hex("digest")?;
+        }
+        QType::DNSKEY | QType::CDNSKEY => {
+            wire.int::<u16>("flags")?
+                .int::<u8>("protocol")?
+                .int::<u8>("algorithm")?
+                .base64("public key")?;
+        }
+        QType::TLSA | QType::SMIMEA => {
+            wire.int::<u8>("certificate usage")?
+                .int::<u8>("selector")?
+                .int::<u8>("matching type")?
+                .hex("certificate association data")?;
+        }
+        QType::SSHFP => {
+            wire.int::<u8>("algorithm")?
+                .int::<u8>("fingerprint type")?
+                .hex("fingerprint")?;
+        }
+        QType::OPENPGPKEY => {
+            wire.base64("public key")?;
+        }
+        QType::SVCB | QType::HTTPS => {
+            wire.int::<u16>("priority")?.name("target")?;
+            let params: Vec<&str> = wire.fields.by_ref().map(String::as_str).collect();
+            svc_params(&params, &mut wire.buffer)?;
+        }
+        _ => return Err(invalid(format!("no presentation format parser for type {}", qtype))),
+    }
+
+    wire.end()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wire(qtype: QType, text: &str) -> Result<Vec<u8>> {
+        rdata_wire(&qtype, &split_fields(text))
+    }
+
+    #[test]
+    fn rdata() {
+        assert_eq!(wire(QType::A, "192.0.2.1").unwrap(), &[192, 0, 2, 1]);
+        assert_eq!(
+            wire(QType::MX, "10 mx.example.").unwrap(),
+            b"\x00\x0a\x02mx\x07example\x00"
+        );
+        assert_eq!(wire(QType::TXT, r#""a\"b" \065"#).unwrap(), b"\x03a\"b\x01A");
+        assert_eq!(
+            wire(QType::CAA, r#"0 issue "ca.example""#).unwrap(),
+            b"\x00\x05issueca.example"
+        );
+        assert_eq!(wire(QType::TYPE(65400), r"\# 2 ab CD").unwrap(), &[0xAB, 0xCD]);
+
+        assert!(wire(QType::A, "2001:db8::1").is_err());
+        assert!(wire(QType::MX, "10").is_err());
+        assert!(wire(QType::NS, "ns1.example. extra").is_err());
+        assert!(wire(QType::TYPE(65400), r"\# 3 abcd").is_err());
+        assert!(wire(QType::NSEC3, "1 0 0 -").is_err());
+    }
+
+    #[test]
+    fn svcb() {
+        // params are sorted by key
+        assert_eq!(
+            wire(QType::HTTPS, r#"1 . port=8443 alpn="h2,h3" no-default-alpn"#).unwrap(),
+            b"\x00\x01\x00\x00\x01\x00\x06\x02h2\x02h3\x00\x02\x00\x00\x00\x03\x00\x02\x20\xFB"
+        );
+        assert_eq!(
+            wire(QType::SVCB, "0 svc.example.").unwrap(),
+            b"\x00\x00\x03svc\x07example\x00"
+        );
+        assert_eq!(
+            wire(QType::SVCB, "1 . ipv4hint=192.0.2.1,192.0.2.2 key65333=ab").unwrap(),
+            b"\x00\x01\x00\x00\x04\x00\x08\xC0\x00\x02\x01\xC0\x00\x02\x02\xFF\x35\x00\x02ab"
+        );
+        assert!(wire(QType::SVCB, "1 . foo=bar").is_err());
+        assert!(wire(QType::SVCB, "1 . port=http").is_err());
+    }
+}
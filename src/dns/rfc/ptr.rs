@@ -9,7 +9,7 @@ use super::domain::DomainName;
 
 // CNAME resource record
 #[derive(Debug, Default, FromNetwork, Serialize)]
-pub struct PTR(DomainName);
+pub struct PTR(pub(super) DomainName);
 
 impl fmt::Display for PTR {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -1,28 +1,50 @@
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use serde::Serialize;
+use serde::{Serialize, Serializer};
 
 use enum_from::{EnumDisplay, EnumFromStr, EnumTryFrom};
 use type2network::{FromNetworkOrder, ToNetworkOrder};
 use type2network_derive::{FromNetwork, ToNetwork};
 
+use crate::show::Numeric;
+
 // https://datatracker.ietf.org/doc/html/rfc1035#section-3.2.4
-#[derive(
-    Debug, Default, Copy, Clone, PartialEq, EnumFromStr, EnumTryFrom, EnumDisplay, ToNetwork, FromNetwork, Serialize,
-)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, EnumFromStr, EnumTryFrom, EnumDisplay, ToNetwork, FromNetwork)]
 #[repr(u16)]
 #[from_network(TryFrom)]
 pub enum QClass {
     #[default]
     IN = 1, // the Internet
-    CS = 2, // the CSNET class (Obsolete - used only for examples in some obsolete RFCs)
-    CH = 3, // the CHAOS class
-    HS = 4, // Hesiod [Dyer 87]
-    ANY = 255,
+    CS = 2,     // the CSNET class (Obsolete - used only for examples in some obsolete RFCs)
+    CH = 3,     // the CHAOS class
+    HS = 4,     // Hesiod [Dyer 87]
+    NONE = 254, // QCLASS NONE [RFC2136]
+    ANY = 255,  // QCLASS * [RFC1035]
 
     #[fallback]
     CLASS(u16),
 }
 
+impl Numeric for QClass {
+    fn number(&self) -> u16 {
+        match self {
+            QClass::CLASS(n) => *n,
+            // enum with a fallback can't be cast, but its u16 representation starts with the
+            // discriminant (see std::mem::discriminant)
+            _ => unsafe { *(self as *const Self).cast::<u16>() },
+        }
+    }
+}
+
+// mnemonic, or CLASSn for unknown classes (RFC3597)
+impl Serialize for QClass {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -58,6 +80,22 @@ mod tests {
         assert_eq!(&qc.to_string(), "CLASS1000");
         let qc = QClass::from_str("CLASS1234").unwrap();
         assert_eq!(&qc.to_string(), "CLASS1234");
+
+        // number
+        assert_eq!(QClass::ANY.number(), 255);
+        assert_eq!(QClass::CLASS(1234).number(), 1234);
+    }
+
+    #[test]
+    fn serialize() {
+        use std::str::FromStr;
+
+        assert_eq!(serde_json::to_string(&QClass::CH).unwrap(), "\"CH\"");
+        assert_eq!(serde_json::to_string(&QClass::CLASS(1000)).unwrap(), "\"CLASS1000\"");
+
+        let qc = QClass::from_str("NONE").unwrap();
+        assert_eq!(serde_json::to_string(&qc).unwrap(), "\"NONE\"");
+        assert_eq!(QClass::try_from(254u16).unwrap(), QClass::NONE);
     }
 
     #[test]
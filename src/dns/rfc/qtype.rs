@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use colored::Colorize;
 use enum_from::{EnumDisplay, EnumFromStr, EnumTryFrom};
@@ -5,7 +7,7 @@ use serde::Serialize;
 use type2network::{FromNetworkOrder, ToNetworkOrder};
 use type2network_derive::{FromNetwork, ToNetwork};
 
-use crate::show::ToColor;
+use crate::show::{Numeric, ToColor};
 
 #[allow(clippy::unnecessary_cast)]
 // https://datatracker.ietf.org/doc/html/rfc1035#section-3.2.2
@@ -93,7 +95,9 @@ pub enum QType {
     LP = 107,     // [RFC6742]	ILNP/lp-completed-template
     EUI48 = 108,  // an EUI-48 address	[RFC7043]	EUI48/eui48-completed-template	2013-03-27
     EUI64 = 109,  // an EUI-64 address	[RFC7043]	EUI64/eui64-completed-template	2013-03-27
-    // Unassigned	110-248
+    // Unassigned	110-127
+    NXNAME = 128, // NXDOMAIN indicator for Compact Denial of Existence	[RFC9824]
+    // Unassigned	129-248
     TKEY = 249,     // Transaction Key	[RFC2930]
     TSIG = 250,     // Transaction Signature	[RFC8945]
     IXFR = 251,     // incremental transfer	[RFC1995]
@@ -115,9 +119,30 @@ pub enum QType {
     TYPE(u16),
 }
 
+impl QType {
+    // types whose RDATA is decoded, listed in doc/supported_types.txt
+    pub fn supported() -> Vec<QType> {
+        include_str!("../../../doc/supported_types.txt")
+            .split_ascii_whitespace()
+            .filter_map(|qt| QType::from_str(qt).ok())
+            .collect()
+    }
+}
+
+impl Numeric for QType {
+    fn number(&self) -> u16 {
+        match self {
+            QType::TYPE(n) => *n,
+            // enum with a fallback can't be cast, but its u16 representation starts with the
+            // discriminant (see std::mem::discriminant)
+            _ => unsafe { *(self as *const Self).cast::<u16>() },
+        }
+    }
+}
+
 impl ToColor for QType {
     fn to_color(&self) -> colored::ColoredString {
-        self.to_string().bright_blue()
+        self.mnemonic().bright_blue()
     }
 }
 
@@ -128,8 +153,6 @@ mod tests {
 
     #[test]
     fn conversion() {
-        use std::str::FromStr;
-
         // from_str
         let qt = QType::from_str("A").unwrap();
         assert_eq!(qt, QType::A);
@@ -155,6 +178,21 @@ mod tests {
         assert_eq!(&qc.to_string(), "TYPE1000");
         let qt = QType::from_str("TYPE1234").unwrap();
         assert_eq!(&qt.to_string(), "TYPE1234");
+
+        // value
+        assert_eq!(QType::AAAA.number(), 28);
+        assert_eq!(QType::DLV.number(), 32769);
+        assert_eq!(QType::TYPE(1234).number(), 1234);
+
+        // all supported types are known
+        let supported = QType::supported();
+        assert_eq!(
+            supported.len(),
+            include_str!("../../../doc/supported_types.txt")
+                .split_ascii_whitespace()
+                .count()
+        );
+        assert!(supported.contains(&QType::HTTPS));
     }
 
     #[test]
@@ -14,10 +14,19 @@ use type2network_derive::ToNetwork;
 use crate::error::{Dns, Error, Result};
 use crate::show::{header_section, DisplayOptions, Show};
 use crate::transport::network::Messenger;
+use crate::transport::observer::{notify, Direction};
 
 use super::{
-    domain::DomainName, flags::BitFlags, header::Header, qclass::QClass, qtype::QType, question::Question,
+    domain::{DomainName, NameCompressor},
+    flags::BitFlags,
+    header::Header,
+    opt::{cookie::COOKIE, opt_rr::OptionData},
+    qclass::QClass,
+    qtype::QType,
+    question::Question,
+    rdata::RData,
     resource_record::OPT,
+    tsig::TSIG,
 };
 
 const DEFAULT_BUFSIZE: u16 = 4096;
@@ -25,12 +34,14 @@ const DEFAULT_BUFSIZE: u16 = 4096;
 #[derive(Debug, ToNetwork, Serialize)]
 pub enum MetaRR {
     OPT(OPT),
+    TSIG(TSIG),
 }
 
 impl fmt::Display for MetaRR {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             MetaRR::OPT(opt) => write!(f, "{}", opt),
+            MetaRR::TSIG(tsig) => write!(f, "{}", tsig),
             //_ => unimplemented!("Meta RR other than OPT not implemented"),
         }
     }
@@ -100,12 +111,78 @@ impl Query {
         self
     }
 
+    // OPT record of the query, if any
+    pub fn opt_mut(&mut self) -> Option<&mut OPT> {
+        self.additional.iter_mut().flatten().find_map(|meta_rr| match meta_rr {
+            MetaRR::OPT(opt) => Some(opt),
+            MetaRR::TSIG(_) => None,
+        })
+    }
+
+    // COOKIE option sent, if any
+    pub fn cookie(&self) -> Option<&COOKIE> {
+        self.additional.iter().flatten().find_map(|meta_rr| match meta_rr {
+            MetaRR::OPT(opt) => match &opt.r_data {
+                RData::OPT(options) => options.iter().find_map(|option| match &option.data {
+                    Some(OptionData::COOKIE(cookie)) => Some(cookie),
+                    _ => None,
+                }),
+                _ => None,
+            },
+            MetaRR::TSIG(_) => None,
+        })
+    }
+
+    // UDP payload size advertised in the OPT record, or 512 without EDNS
+    pub fn bufsize(&self) -> u16 {
+        self.additional
+            .iter()
+            .flatten()
+            .find_map(|meta_rr| match meta_rr {
+                MetaRR::OPT(opt) => opt.opt_or_class_ttl.opt().map(|x| x.payload),
+                MetaRR::TSIG(_) => None,
+            })
+            .unwrap_or(512)
+    }
+
+    // serialize the whole message, domain names being compressed
+    pub fn serialize_compressed(&self, buffer: &mut Vec<u8>) -> std::io::Result<usize> {
+        let mut length = 0usize;
+
+        if let Some(l) = self.length {
+            length += l.serialize_to(buffer)?;
+        }
+
+        // pointers are relative to the start of the message
+        let start = buffer.len();
+        let mut compressor = NameCompressor::new(start);
+
+        length += self.header.serialize_to(buffer)?;
+        length += self.question.qname.serialize_compressed(buffer, &mut compressor)?;
+        length += self.question.qtype.serialize_to(buffer)?;
+        length += self.question.qclass.serialize_to(buffer)?;
+
+        for meta_rr in self.additional.iter().flatten() {
+            match meta_rr {
+                MetaRR::OPT(opt) => length += opt.serialize_compressed(buffer, &mut compressor)?,
+
+                // TSIG is last and signs all the message before it
+                MetaRR::TSIG(tsig) => {
+                    let message = buffer[start..].to_vec();
+                    length += tsig.sign(&message, buffer)?;
+                }
+            }
+        }
+
+        Ok(length)
+    }
+
     // Send the query through the wire
     pub fn send<T: Messenger>(&mut self, trp: &mut T, save_path: &Option<PathBuf>) -> Result<usize> {
         // convert to network bytes
         let mut buffer: Vec<u8> = Vec::new();
         let message_size = self
-            .serialize_to(&mut buffer)
+            .serialize_compressed(&mut buffer)
             .map_err(|_| Error::Dns(Dns::CantSerialize))? as u16;
         trace!(
             "buffer to send before TCP length addition: {:0X?}, uses_leading_length={}",
@@ -127,6 +204,14 @@ impl Query {
         let sent = trp.send(&buffer)?;
         debug!("sent {} bytes", sent);
 
+        // observers get the message alone, as received ones
+        let message = if trp.uses_leading_length() {
+            &buffer[2..]
+        } else {
+            &buffer[..]
+        };
+        notify(Direction::Sent, trp.mode(), trp.network_info().peer, message);
+
         // save query as raw bytes if requested
         if let Some(path) = save_path {
             let mut f = File::create(path).map_err(|e| Error::OpenFile(e, path.to_path_buf()))?;
@@ -141,7 +226,7 @@ impl Query {
         // convert to network bytes
         let mut buffer: Vec<u8> = Vec::new();
         let message_size = self
-            .serialize_to(&mut buffer)
+            .serialize_compressed(&mut buffer)
             .map_err(|_| Error::Dns(Dns::CantSerialize))? as u16;
         trace!(
             "buffer to send before TCP length addition: {:0X?}, uses_leading_length={}",
@@ -163,6 +248,14 @@ impl Query {
         let sent = trp.asend(&buffer).await?;
         debug!("sent {} bytes", sent);
 
+        // observers get the message alone, as received ones
+        let message = if trp.uses_leading_length() {
+            &buffer[2..]
+        } else {
+            &buffer[..]
+        };
+        notify(Direction::Sent, trp.mode(), trp.network_info().peer, message);
+
         // save query as raw bytes if requested
         if let Some(path) = save_path {
             let mut f = tokio::fs::File::create(path)
@@ -311,4 +404,22 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn compressed() {
+        let domain = DomainName::try_from("www.google.com").unwrap();
+        let query = Query::build()
+            .with_type(&QType::A)
+            .with_domain(&domain)
+            .with_length()
+            .with_additional(MetaRR::default());
+
+        // nothing to compress in a query: same bytes
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut compressed: Vec<u8> = Vec::new();
+        let l1 = query.serialize_to(&mut buffer).unwrap();
+        let l2 = query.serialize_compressed(&mut compressed).unwrap();
+        assert_eq!(l1, l2);
+        assert_eq!(buffer, compressed);
+    }
 }
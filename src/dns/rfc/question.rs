@@ -7,7 +7,7 @@ use serde::Serialize;
 
 use crate::{
     dns::rfc::{domain::DomainName, qclass::QClass, qtype::QType},
-    show::TITLES,
+    show::{Numeric, TITLES},
 };
 
 // Question structure: https://datatracker.ietf.org/doc/html/rfc1035#section-4.1.2
@@ -33,8 +33,13 @@ impl fmt::Display for Question {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{}:{} {}:{:?} {}:{:?}",
-            TITLES["qname"], self.qname, TITLES["qtype"], self.qtype, TITLES["qclass"], self.qclass
+            "{}:{} {}:{} {}:{}",
+            TITLES["qname"],
+            self.qname,
+            TITLES["qtype"],
+            self.qtype.mnemonic(),
+            TITLES["qclass"],
+            self.qclass.mnemonic()
         )
     }
 }
@@ -10,10 +10,14 @@ use type2network::ToNetworkOrder;
 
 use type2network::FromNetworkOrder;
 
-use crate::{dns::buffer::Buffer, show::ToColor};
+use crate::{
+    dns::buffer::Buffer,
+    error::{Error, Result},
+    show::ToColor,
+};
 
 use super::{
-    a::A,
+    a::{ChaosA, A},
     aaaa::AAAA,
     afsdb::AFSDB,
     apl::APL,
@@ -30,8 +34,11 @@ use super::{
     hinfo::HINFO,
     hip::HIP,
     ipseckey::IPSECKEY,
-    kx::KX,
+    isdn::ISDN,
+    kx::{KX, RT},
     loc::LOC,
+    mb::{MB, MG, MR},
+    minfo::MINFO,
     mx::MX,
     naptr::NAPTR,
     ns::NS,
@@ -40,7 +47,9 @@ use super::{
     nsec3param::NSEC3PARAM,
     openpgpkey::OPENPGPKEY,
     opt::opt_rr::{OptOption, OptionList},
+    presentation::{rdata_wire, split_fields},
     ptr::PTR,
+    qclass::QClass,
     qtype::QType,
     rp::RP,
     rrsig::RRSIG,
@@ -49,9 +58,11 @@ use super::{
     sshfp::SSHFP,
     svcb::{HTTPS, SVCB},
     tlsa::{SMIMEA, TLSA},
-    txt::TXT,
+    txt::{SPF, TXT},
     uri::URI,
     wallet::WALLET,
+    wks::WKS,
+    x25::X25,
     zonemd::ZONEMD,
     DataLength,
 };
@@ -69,6 +80,7 @@ pub(super) enum RData {
     CDNSKEY(CDNSKEY),
     CDS(CDS),
     CERT(CERT),
+    ChaosA(ChaosA),
     CNAME(CNAME),
     CSYNC(CSYNC),
     DHCID(DHCID),
@@ -82,8 +94,13 @@ pub(super) enum RData {
     HIP(HIP),
     HTTPS(HTTPS),
     IPSECKEY(IPSECKEY),
+    ISDN(ISDN),
     KX(KX),
     LOC(LOC),
+    MB(MB),
+    MG(MG),
+    MINFO(MINFO),
+    MR(MR),
     MX(MX),
     NAPTR(NAPTR),
     NS(NS),
@@ -95,8 +112,10 @@ pub(super) enum RData {
     PTR(PTR),
     RP(RP),
     RRSIG(RRSIG),
+    RT(RT),
     SMIMEA(SMIMEA),
     SOA(SOA),
+    SPF(SPF),
     SRV(SRV),
     SSHFP(SSHFP),
     SVCB(SVCB),
@@ -107,6 +126,8 @@ pub(super) enum RData {
     URI(URI),
     ZONEMD(ZONEMD),
     WALLET(WALLET),
+    WKS(WKS),
+    X25(X25),
 }
 
 // Macro used to ease the ResourceRecord implementation of the FromNetworkOrder trait
@@ -129,6 +150,34 @@ macro_rules! get_rr {
 }
 
 impl RData {
+    // some RDATA formats depend on the class
+    pub fn from_bytes_with_class(
+        qt: &QType,
+        class: Option<QClass>,
+        rd_length: u16,
+        buffer: &mut Cursor<&[u8]>,
+    ) -> std::io::Result<Self> {
+        match (qt, class) {
+            (QType::A, Some(QClass::CH)) => get_rr!(buffer, ChaosA, RData::ChaosA),
+            _ => Self::from_bytes(qt, rd_length, buffer),
+        }
+    }
+
+    // RDATA from its presentation format, e.g.: "10 mail.example.com." for MX. Names
+    // must be fully qualified
+    pub fn from_presentation(qt: &QType, text: &str) -> Result<Self> {
+        let wire = rdata_wire(qt, &split_fields(text))?;
+        let mut buffer = Cursor::new(wire.as_slice());
+        let rdata =
+            Self::from_bytes(qt, wire.len() as u16, &mut buffer).map_err(|e| Error::Presentation(e.to_string()))?;
+
+        // the whole RDATA must have been read
+        if buffer.position() as usize != wire.len() {
+            return Err(Error::Presentation(format!("invalid {} RDATA '{}'", qt, text)));
+        }
+        Ok(rdata)
+    }
+
     // according to QType, map buffer to RData
     pub fn from_bytes(qt: &QType, rd_length: u16, buffer: &mut Cursor<&[u8]>) -> std::io::Result<Self> {
         match qt {
@@ -154,8 +203,13 @@ impl RData {
             QType::HIP => get_rr!(buffer, HIP, RData::HIP, rd_length),
             QType::HTTPS => get_rr!(buffer, HTTPS, RData::HTTPS, rd_length),
             QType::IPSECKEY => get_rr!(buffer, IPSECKEY, RData::IPSECKEY, rd_length),
+            QType::ISDN => get_rr!(buffer, ISDN, RData::ISDN, rd_length),
             QType::KX => get_rr!(buffer, KX, RData::KX),
             QType::LOC => get_rr!(buffer, LOC, RData::LOC),
+            QType::MB => get_rr!(buffer, MB, RData::MB),
+            QType::MG => get_rr!(buffer, MG, RData::MG),
+            QType::MINFO => get_rr!(buffer, MINFO, RData::MINFO),
+            QType::MR => get_rr!(buffer, MR, RData::MR),
             QType::MX => get_rr!(buffer, MX, RData::MX),
             QType::NAPTR => get_rr!(buffer, NAPTR, RData::NAPTR),
             QType::NS => get_rr!(buffer, NS, RData::NS),
@@ -170,9 +224,14 @@ impl RData {
             QType::PTR => get_rr!(buffer, PTR, RData::PTR),
             QType::RP => get_rr!(buffer, RP, RData::RP),
             QType::RRSIG => get_rr!(buffer, RRSIG, RData::RRSIG, rd_length),
+            QType::RT => get_rr!(buffer, RT, RData::RT),
             QType::SMIMEA => get_rr!(buffer, SMIMEA, RData::SMIMEA, rd_length),
             QType::SRV => get_rr!(buffer, SRV, RData::SRV),
             QType::SOA => get_rr!(buffer, SOA, RData::SOA),
+            QType::SPF => {
+                let v = auto_vec_deser::<CharacterString>(rd_length, buffer)?;
+                Ok(RData::SPF(SPF(v)))
+            }
             QType::SSHFP => get_rr!(buffer, SSHFP, RData::SSHFP, rd_length),
             QType::SVCB => get_rr!(buffer, SVCB, RData::SVCB, rd_length),
             QType::TLSA => get_rr!(buffer, TLSA, RData::TLSA, rd_length),
@@ -184,6 +243,8 @@ impl RData {
             QType::URI => get_rr!(buffer, URI, RData::URI, rd_length),
             QType::ZONEMD => get_rr!(buffer, ZONEMD, RData::ZONEMD, rd_length),
             QType::WALLET => get_rr!(buffer, WALLET, RData::WALLET),
+            QType::WKS => get_rr!(buffer, WKS, RData::WKS, rd_length),
+            QType::X25 => get_rr!(buffer, X25, RData::X25),
             _ => {
                 // allocate the buffer to hold the data
                 let mut buf = Buffer::with_capacity(rd_length);
@@ -254,6 +315,7 @@ impl fmt::Display for RData {
             RData::CDNSKEY(a) => write!(f, "{}", a),
             RData::CDS(a) => write!(f, "{}", a),
             RData::CERT(a) => write!(f, "{}", a),
+            RData::ChaosA(a) => write!(f, "{}", a),
             RData::CNAME(a) => write!(f, "{}", a),
             RData::CSYNC(a) => write!(f, "{}", a),
             RData::DHCID(a) => write!(f, "{}", a),
@@ -267,8 +329,13 @@ impl fmt::Display for RData {
             RData::HTTPS(a) => write!(f, "{}", a),
             RData::HIP(a) => write!(f, "{}", a),
             RData::IPSECKEY(a) => write!(f, "{}", a),
+            RData::ISDN(a) => write!(f, "{}", a),
             RData::KX(a) => write!(f, "{}", a),
             RData::LOC(a) => write!(f, "{}", a),
+            RData::MB(a) => write!(f, "{}", a),
+            RData::MG(a) => write!(f, "{}", a),
+            RData::MINFO(a) => write!(f, "{}", a),
+            RData::MR(a) => write!(f, "{}", a),
             RData::MX(a) => write!(f, "{}", a),
             RData::NAPTR(a) => write!(f, "{}", a),
             RData::NS(a) => write!(f, "{}", a),
@@ -280,7 +347,9 @@ impl fmt::Display for RData {
             RData::PTR(a) => write!(f, "{}", a),
             RData::RP(a) => write!(f, "{}", a),
             RData::RRSIG(a) => write!(f, "{}", a),
+            RData::RT(a) => write!(f, "{}", a),
             RData::SOA(a) => write!(f, "{}", a),
+            RData::SPF(a) => write!(f, "{}", a),
             RData::SMIMEA(a) => write!(f, "{}", a),
             RData::SRV(a) => write!(f, "{}", a),
             RData::SSHFP(a) => write!(f, "{}", a),
@@ -291,6 +360,8 @@ impl fmt::Display for RData {
             RData::UNKNOWN(a) => write!(f, "RR NOT YET IMPLEMENTED: {}", a),
             RData::ZONEMD(a) => write!(f, "{}", a),
             RData::WALLET(a) => write!(f, "{}", a),
+            RData::WKS(a) => write!(f, "{}", a),
+            RData::X25(a) => write!(f, "{}", a),
             _ => unimplemented!("not yet implemented"),
         }
     }
@@ -301,3 +372,30 @@ impl ToColor for RData {
         self.to_string().bright_yellow()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_presentation() {
+        let mx = RData::from_presentation(&QType::MX, "10 mail.example.com.").unwrap();
+        assert!(matches!(mx, RData::MX(_)));
+        assert_eq!(mx.to_string(), "10 mail.example.com.");
+
+        let soa = RData::from_presentation(
+            &QType::SOA,
+            "ns1.example.com. hostmaster.example.com. ( 2026101501 7200 3600 1209600 300 ) ; comment",
+        )
+        .unwrap();
+        assert_eq!(
+            soa.to_string(),
+            "ns1.example.com. hostmaster.example.com. 2026101501 7200 3600 1209600 300"
+        );
+
+        let https = RData::from_presentation(&QType::HTTPS, r#"1 . alpn="h2,h3" port=443"#).unwrap();
+        assert!(matches!(https, RData::HTTPS(_)));
+
+        assert!(RData::from_presentation(&QType::MX, "mail.example.com.").is_err());
+    }
+}
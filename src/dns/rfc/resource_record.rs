@@ -5,12 +5,17 @@ use serde::Serialize;
 use type2network::{FromNetworkOrder, ToNetworkOrder};
 use type2network_derive::{FromNetwork, ToNetwork};
 
-use super::domain::ROOT_DOMAIN;
+use super::domain::{NameCompressor, ROOT_DOMAIN};
 use super::opt::OptionDataValue;
 // use super::opt::opt_rr::OPT;
-use super::{domain::DomainName, qclass::QClass, qtype::QType, rdata::RData};
+use super::{
+    domain::DomainName, qclass::QClass, qtype::QType, rdata::RData, rrset::RRSet, rrsig::RRSIG, srv::SRV, svcb::SVCB,
+    tsig::signed_time,
+};
+use crate::annotate::annotations;
 use crate::dns::rfc::opt::opt_rr::{OptOption, OptionList};
-use crate::show::{DisplayOptions, ToColor, TITLES};
+use crate::error::Result;
+use crate::show::{screen_reader_line, show_offsets, ColumnWidths, DisplayOptions, Numeric, ToColor, TITLES};
 
 use log::{debug, trace};
 
@@ -60,6 +65,18 @@ pub struct RegularClassTtl {
     pub(super) ttl: u32,
 }
 
+impl RegularClassTtl {
+    #[inline]
+    pub fn class(&self) -> QClass {
+        self.class
+    }
+
+    #[inline]
+    pub fn ttl(&self) -> u32 {
+        self.ttl
+    }
+}
+
 // Case of OPT RR
 // https://www.rfc-editor.org/rfc/rfc6891#section-6.1.3
 // +0 (MSB)                            +1 (LSB)
@@ -165,7 +182,7 @@ impl Serialize for OptOrClassTtl {
 }
 
 // a new type definition for printing out TTL as days, hours, minutes and seconds
-struct Ttl(u32);
+pub(crate) struct Ttl(pub(crate) u32);
 
 impl fmt::Display for Ttl {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -198,7 +215,7 @@ impl ToColor for Ttl {
     }
 }
 
-#[derive(Default, ToNetwork, Serialize)]
+#[derive(Default, Serialize)]
 pub struct ResourceRecord {
     pub name: DomainName, // an owner name, i.e., the name of the node to which this resource record pertains.
     pub r#type: QType,    // two octets containing one of the RR TYPE codes.
@@ -222,6 +239,29 @@ pub struct ResourceRecord {
     //  a variable length string of octets that describes the
     //  resource.  The format of this information varies
     //  according to the TYPE and CLASS of the resource record.
+
+    // offset and length in bytes of the RR in the message it was read from. Not part of
+    // the RR itself, only serialized in JSON with --offsets
+    #[serde(skip_serializing_if = "no_offsets")]
+    pub(super) offset: usize,
+    #[serde(skip_serializing_if = "no_offsets")]
+    pub(super) size: usize,
+}
+
+fn no_offsets(_: &usize) -> bool {
+    !show_offsets()
+}
+
+// offset and size are not sent
+impl ToNetworkOrder for ResourceRecord {
+    fn serialize_to(&self, buffer: &mut Vec<u8>) -> std::io::Result<usize> {
+        let mut length = self.name.serialize_to(buffer)?;
+        length += self.r#type.serialize_to(buffer)?;
+        length += self.opt_or_class_ttl.serialize_to(buffer)?;
+        length += self.rd_length.serialize_to(buffer)?;
+        length += self.r_data.serialize_to(buffer)?;
+        Ok(length)
+    }
 }
 
 // standard lengths for displaying and aligning a RR
@@ -249,6 +289,53 @@ impl ResourceRecord {
         None
     }
 
+    // target of a PTR record
+    pub fn ptr_name(&self) -> Option<&DomainName> {
+        match &self.r_data {
+            RData::PTR(ptr) => Some(&ptr.0),
+            _ => None,
+        }
+    }
+
+    // character strings of a TXT record, one by one
+    pub fn txt_strings(&self) -> Option<Vec<String>> {
+        match &self.r_data {
+            RData::TXT(txt) => Some(txt.0.iter().map(|cs| cs.to_string()).collect()),
+            _ => None,
+        }
+    }
+
+    // SRV RDATA
+    pub fn srv(&self) -> Option<&SRV> {
+        match &self.r_data {
+            RData::SRV(srv) => Some(srv),
+            _ => None,
+        }
+    }
+
+    pub fn rrsig(&self) -> Option<&RRSIG> {
+        match &self.r_data {
+            RData::RRSIG(rrsig) => Some(rrsig),
+            _ => None,
+        }
+    }
+
+    // time signed and fudge of a TSIG received, which is kept as raw RDATA
+    pub fn tsig_time(&self) -> Option<(u64, u16)> {
+        match &self.r_data {
+            RData::UNKNOWN(buf) if self.r#type == QType::TSIG => signed_time(buf),
+            _ => None,
+        }
+    }
+
+    // SVCB or HTTPS RDATA
+    pub fn svcb(&self) -> Option<&SVCB> {
+        match &self.r_data {
+            RData::SVCB(svcb) | RData::HTTPS(svcb) => Some(svcb),
+            _ => None,
+        }
+    }
+
     // in case of A or AAAA addresses, returns the ip address (either V4 or V6) from the RData
     pub fn ip_address(&self) -> Option<IpAddr> {
         match self.r#type {
@@ -267,7 +354,136 @@ impl ResourceRecord {
         None
     }
 
-    fn display(&self, fmt: &str, raw_ttl: bool, name_length: usize, puny: bool) {
+    // RR built from its presentation format, e.g. read from a zone file. Names must be
+    // fully qualified
+    pub fn from_presentation(name: DomainName, qtype: QType, class: QClass, ttl: u32, rdata: &str) -> Result<Self> {
+        let r_data = RData::from_presentation(&qtype, rdata)?;
+        let mut rr = Self {
+            name,
+            r#type: qtype,
+            opt_or_class_ttl: OptOrClassTtl::Regular(RegularClassTtl { class, ttl }),
+            r_data,
+            ..Default::default()
+        };
+        rr.rd_length = rr.rdata_wire().len() as u16;
+        Ok(rr)
+    }
+
+    // RDATA as a string, used when comparing answers
+    pub fn rdata_string(&self) -> String {
+        self.r_data.to_string()
+    }
+
+    // same as serialize_to() but the owner name is compressed
+    pub fn serialize_compressed(
+        &self,
+        buffer: &mut Vec<u8>,
+        compressor: &mut NameCompressor,
+    ) -> std::io::Result<usize> {
+        let mut length = self.name.serialize_compressed(buffer, compressor)?;
+        length += self.r#type.serialize_to(buffer)?;
+        length += self.opt_or_class_ttl.serialize_to(buffer)?;
+        length += self.rd_length.serialize_to(buffer)?;
+        length += self.r_data.serialize_to(buffer)?;
+        Ok(length)
+    }
+
+    // offset of the RR from the start of the message it was read from
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    // length in bytes of the whole RR in the message
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    #[inline]
+    pub fn rd_length(&self) -> u16 {
+        self.rd_length
+    }
+
+    // RDATA as sent on the wire (domain names are uncompressed)
+    pub fn rdata_wire(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        // writing into a Vec can't fail
+        let _ = self.r_data.serialize_to(&mut buffer);
+        buffer
+    }
+
+    // TTLs are counted down by caches: --stable sets them to 0. OPT has no TTL
+    pub fn set_ttl(&mut self, ttl: u32) {
+        if let OptOrClassTtl::Regular(r) = &mut self.opt_or_class_ttl {
+            r.ttl = ttl;
+        }
+    }
+
+    // same owner, type and class: both RRs belong to the same RRset
+    pub fn same_rrset(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.r#type == other.r#type
+            && self.opt_or_class_ttl.regular().map(|r| &r.class) == other.opt_or_class_ttl.regular().map(|r| &r.class)
+    }
+
+    // same RRset and same RDATA, only the TTL may differ
+    pub fn same_data(&self, other: &Self) -> bool {
+        self.same_rrset(other) && self.rdata_wire() == other.rdata_wire()
+    }
+
+    // widths of each column when displayed, used for --width auto
+    pub fn widths(&self, raw_ttl: bool) -> ColumnWidths {
+        let regular = self.opt_or_class_ttl.regular();
+
+        ColumnWidths {
+            auto: false,
+            name: Some(self.name.len()),
+            r#type: Some(self.r#type.mnemonic().len()),
+            class: regular.map(|r| r.class.mnemonic().len()),
+            ttl: regular.map(|r| {
+                if raw_ttl {
+                    r.ttl.to_string().len()
+                } else {
+                    Ttl(r.ttl).to_string().len()
+                }
+            }),
+            length: Some(self.rd_length.to_string().len()),
+        }
+    }
+
+    // value of a field as plain text, None if it doesn't apply to this RR. Shared by
+    // --fmt templates and the CSV output
+    pub fn field(&self, field: &str, display_options: &DisplayOptions) -> Option<String> {
+        let regular = self.opt_or_class_ttl.regular();
+        let opt = self.opt_or_class_ttl.opt();
+
+        match field {
+            "name" if !display_options.puny && self.name.is_puny() => self.name.to_unicode().ok(),
+            "name" => Some(self.name.to_string()),
+            "type" => Some(self.r#type.mnemonic()),
+            "length" => Some(self.rd_length.to_string()),
+            "class" => regular.map(|r| r.class.mnemonic()),
+            "ttl" if display_options.raw_ttl => regular.map(|r| r.ttl.to_string()),
+            "ttl" => regular.map(|r| Ttl(r.ttl).to_string()),
+            "rdata" => Some(self.r_data.to_string()),
+            "payload" => opt.map(|r| r.payload.to_string()),
+            "extcode" => opt.map(|r| r.extended_rcode.to_string()),
+            "version" => opt.map(|r| format!("EDNS{}", r.version)),
+            "flags" => opt.map(|r| r.flags.to_string()),
+            _ => None,
+        }
+    }
+
+    fn display(&self, fmt: &str, display_options: &DisplayOptions, name_length: usize) {
+        let puny = display_options.puny;
+
+        // user-defined widths or default ones
+        let widths = &display_options.widths;
+        let type_length = widths.r#type.unwrap_or(TYPE_DISPLAY_LENGTH);
+        let class_length = widths.class.unwrap_or(CLASS_DISPLAY_LENGTH);
+        let length_length = widths.length.unwrap_or(LENGTH_DISPLAY_LENGTH);
+
         for f in fmt.split(",") {
             match f.trim() {
                 // except OPT
@@ -289,20 +505,16 @@ impl ResourceRecord {
                         }
                     }
                 }
-                "type" => print!("{:<TYPE_DISPLAY_LENGTH$} ", self.r#type.to_color()),
-                "length" => print!("{:<LENGTH_DISPLAY_LENGTH$} ", self.rd_length),
+                "type" => print!("{:<type_length$} ", self.r#type.to_color()),
+                "length" => print!("{:<length_length$} ", self.rd_length),
                 "class" => {
                     if let Some(r) = self.opt_or_class_ttl.regular() {
-                        print!("{:<CLASS_DISPLAY_LENGTH$} ", r.class.to_string())
+                        print!("{:<class_length$} ", r.class.mnemonic())
                     }
                 }
                 "ttl" => {
                     if let Some(r) = self.opt_or_class_ttl.regular() {
-                        if raw_ttl {
-                            print!("{:<TTL_INT_DISPLAY_LENGTH$} ", r.ttl)
-                        } else {
-                            print!("{:<TTL_STRING_DISPLAY_LENGTH$} ", Ttl(r.ttl).to_color())
-                        }
+                        Self::display_ttl(r.ttl, display_options);
                     }
                 }
                 "rdata" => print!("{}", self.r_data.to_color()),
@@ -333,17 +545,75 @@ impl ResourceRecord {
         }
     }
 
+    // TTL as an integer or a duration
+    fn display_ttl(ttl: u32, display_options: &DisplayOptions) {
+        let raw_ttl = display_options.raw_ttl;
+        let ttl_length = display_options.widths.ttl.unwrap_or(if raw_ttl {
+            TTL_INT_DISPLAY_LENGTH
+        } else {
+            TTL_STRING_DISPLAY_LENGTH
+        });
+
+        if raw_ttl {
+            print!("{:<ttl_length$} ", ttl)
+        } else {
+            print!("{:<ttl_length$} ", Ttl(ttl).to_color())
+        }
+    }
+
+    // --group: RRset header (owner, type, class & TTL) followed by indented RDATA lines.
+    // The TTL is the lowest one of the RRset (RFC2181 5.2)
+    pub(super) fn show_rrset(rrset: &RRSet, display_options: &DisplayOptions, length: Option<usize>) {
+        let Some(first) = rrset.first() else {
+            return;
+        };
+        let name_length = display_options.widths.name.or(length).unwrap_or(NAME_DISPLAY_LENGTH);
+
+        first.display("name,type,class", display_options, name_length);
+        if let Some(ttl) = rrset.ttl() {
+            Self::display_ttl(ttl, display_options);
+        }
+        println!();
+        for rr in rrset.iter() {
+            print!("    {}", rr.r_data.to_color());
+            rr.show_annotations(display_options);
+            println!();
+        }
+    }
+
+    // --annotate: comments at the end of the line, and the server which sent the RR when
+    // tracing
+    fn show_annotations(&self, display_options: &DisplayOptions) {
+        if self.r#type == QType::OPT {
+            return;
+        }
+
+        let mut comments = if display_options.annotate {
+            annotations(self, display_options)
+        } else {
+            Vec::new()
+        };
+        if let Some(provenance) = &display_options.provenance {
+            comments.push(provenance.to_string());
+        }
+        if !comments.is_empty() {
+            print!(" ; {}", comments.join(", ").dimmed());
+        }
+    }
+
     pub(super) fn show(&self, display_options: &DisplayOptions, length: Option<usize>) {
-        let name_length = length.unwrap_or(NAME_DISPLAY_LENGTH);
+        // an explicit --width name=n takes precedence over aligned names
+        let name_length = display_options.widths.name.or(length).unwrap_or(NAME_DISPLAY_LENGTH);
+
+        // --fmt template
+        if let Some(template) = &display_options.template {
+            println!("{}", template.render(self, display_options));
+            return;
+        }
 
         // formatting display
         if !display_options.fmt.is_empty() {
-            self.display(
-                &display_options.fmt,
-                display_options.raw_ttl,
-                name_length,
-                display_options.puny,
-            );
+            self.display(&display_options.fmt, display_options, name_length);
             println!();
             return;
         }
@@ -351,13 +621,16 @@ impl ResourceRecord {
         // other options
         if display_options.short {
             println!("{}", self.r_data.to_color());
+        } else if display_options.screen_reader {
+            println!("{}", screen_reader_line(self, display_options));
         } else if self.r#type != QType::OPT {
             const ALL_FIELDS: &str = "name,type,class,ttl,length,rdata";
-            self.display(ALL_FIELDS, display_options.raw_ttl, name_length, display_options.puny);
+            self.display(ALL_FIELDS, display_options, name_length);
+            self.show_annotations(display_options);
             println!();
         } else {
             const ALL_FIELDS: &str = "name,type,length,payload,extcode,version,flags,length,rdata";
-            self.display(ALL_FIELDS, display_options.raw_ttl, name_length, display_options.puny);
+            self.display(ALL_FIELDS, display_options, name_length);
             println!();
         }
     }
@@ -384,6 +657,7 @@ impl OPT {
             opt_or_class_ttl: OptOrClassTtl::Opt(opt_payload),
             rd_length: 0,
             r_data: RData::OPT(OptionList::default()), // no options added yet
+            ..Default::default()
         }
     }
 
@@ -404,6 +678,18 @@ impl OPT {
             opt.push(option);
         }
     }
+
+    // replace the option of the same code, or add it
+    pub fn set_option<T: OptionDataValue>(&mut self, data: T) {
+        let code = data.code();
+        if let RData::OPT(opt) = &mut self.r_data {
+            if let Some(i) = opt.iter().position(|option| option.code == code) {
+                let old = opt.remove(i);
+                self.rd_length -= 4 + old.length;
+            }
+        }
+        self.add_option(data);
+    }
 }
 
 impl fmt::Debug for OPT {
@@ -455,6 +741,8 @@ macro_rules! get_rr {
 
 impl<'a> FromNetworkOrder<'a> for ResourceRecord {
     fn deserialize_from(&mut self, buffer: &mut Cursor<&'a [u8]>) -> std::io::Result<()> {
+        self.offset = buffer.position() as usize;
+
         self.name.deserialize_from(buffer)?;
         self.r#type.deserialize_from(buffer)?;
 
@@ -473,7 +761,8 @@ impl<'a> FromNetworkOrder<'a> for ResourceRecord {
         );
 
         if self.rd_length != 0 {
-            self.r_data = RData::from_bytes(&self.r#type, self.rd_length, buffer)?;
+            let class = self.opt_or_class_ttl.regular().map(|r| r.class);
+            self.r_data = RData::from_bytes_with_class(&self.r#type, class, self.rd_length, buffer)?;
         }
         // a specific processing when OPT record has no options (rd_length == 0)
         // because by default RData enum is UNKNOWN
@@ -481,6 +770,8 @@ impl<'a> FromNetworkOrder<'a> for ResourceRecord {
             self.r_data = RData::OPT(OptionList::default());
         }
 
+        self.size = buffer.position() as usize - self.offset;
+
         Ok(())
     }
 }
@@ -544,6 +835,10 @@ mod tests {
         );
         assert_eq!(rr.rd_length, 4);
         assert!(matches!(rr.r_data, RData::A(a) if a == A(Ipv4Addr::from_str("142.250.179.68").unwrap())));
+
+        // the whole buffer is the RR
+        assert_eq!(rr.offset(), 0);
+        assert_eq!(rr.size(), data.len());
     }
 
     #[test]
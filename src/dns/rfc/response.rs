@@ -1,22 +1,36 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
 use std::{fmt, io::Cursor, net::IpAddr};
 
 use log::{debug, trace};
+use rand::seq::IteratorRandom;
 use serde::Serialize;
 use tokio::io::AsyncWriteExt;
 
 use type2network::FromNetworkOrder;
 
 use super::{
-    domain::DomainName, header::Header, qtype::QType, question::Question, resource_record::ResourceRecord,
+    domain::DomainName,
+    header::Header,
+    opt::{
+        client_subnet::ClientSubnet,
+        cookie::COOKIE,
+        opt_rr::{OptOption, OptionData},
+    },
+    qtype::QType,
+    question::Question,
+    rdata::RData,
+    resource_record::ResourceRecord,
     rrlist::RRList,
+    soa::SOA,
 };
 use crate::dns::rfc::response_code::ResponseCode;
 use crate::error::{Dns, Error};
-use crate::show::{header_section, DisplayOptions, Show};
+use crate::show::{header_section, ColumnWidths, DisplayOptions, Show};
 use crate::transport::network::Messenger;
+use crate::transport::observer::{notify, Direction};
 
 pub enum ResponseSection {
     Answer,
@@ -66,6 +80,140 @@ impl Response {
         self.answer.is_none()
     }
 
+    #[inline]
+    pub fn authority(&self) -> Option<&RRList> {
+        self.authority.as_ref()
+    }
+
+    #[inline]
+    pub fn additional(&self) -> Option<&RRList> {
+        self.additional.as_ref()
+    }
+
+    // Compact Denial of Existence (RFC9824): the name doesn't exist although rcode is NOERROR,
+    // which is signaled by the NXNAME type in the NSEC record matching the query name
+    pub fn is_compact_denial(&self) -> bool {
+        self.rcode() == ResponseCode::NoError
+            && self.answer.is_none()
+            && self
+                .authority
+                .iter()
+                .flat_map(|list| list.iter())
+                .any(|rr| match &rr.r_data {
+                    RData::NSEC(nsec) => rr.name == self.question.qname && nsec.has_type(&QType::NXNAME),
+                    _ => false,
+                })
+    }
+
+    // options found in the OPT record of the additional section
+    pub fn opt_options(&self) -> Vec<&OptOption> {
+        self.additional
+            .iter()
+            .flat_map(|list| list.iter())
+            .filter_map(|rr| match &rr.r_data {
+                RData::OPT(options) => Some(options.iter()),
+                _ => None,
+            })
+            .flatten()
+            .collect()
+    }
+
+    // true if the server sent back an OPT record, i.e. supports EDNS
+    pub fn has_opt(&self) -> bool {
+        self.additional
+            .iter()
+            .flat_map(|list| list.iter())
+            .any(|rr| rr.r#type == QType::OPT)
+    }
+
+    // answers as strings "name type rdata" without TTL (which varies with caching)
+    pub fn answers(&self) -> Vec<String> {
+        self.answer
+            .iter()
+            .flat_map(|answer| answer.iter())
+            .map(|rr| {
+                format!(
+                    "{} {} {}",
+                    rr.name.to_string().to_lowercase(),
+                    rr.r#type,
+                    rr.rdata_string()
+                )
+            })
+            .collect()
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // add the answer RRs of the next message of a zone transfer. The 16 bits counter
+    // can't hold large zones and is capped
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn append_answer(&mut self, next: Response) {
+        let Some(rrs) = next.answer else {
+            return;
+        };
+        self.header.an_count = self.header.an_count.saturating_add(rrs.len() as u16);
+
+        match self.answer.as_mut() {
+            Some(answer) => answer.append(rrs),
+            None => self.answer = Some(rrs),
+        }
+    }
+
+    // --stable: ID and TTLs change from one run to the other
+    pub fn stabilize(&mut self) {
+        self.header.id = 0;
+        for list in [&mut self.answer, &mut self.authority, &mut self.additional]
+            .into_iter()
+            .flatten()
+        {
+            list.set_ttl(0);
+        }
+    }
+
+    // agent domain of the Report-Channel option: https://www.rfc-editor.org/rfc/rfc9567.html
+    pub fn report_channel(&self) -> Option<&DomainName> {
+        self.opt_options().into_iter().find_map(|opt| match &opt.data {
+            Some(OptionData::ReportChanel(rc)) => rc.agent_domain(),
+            _ => None,
+        })
+    }
+
+    // EDNS Client Subnet echoed by the server, with its scope: https://www.rfc-editor.org/rfc/rfc7871
+    pub fn client_subnet(&self) -> Option<&ClientSubnet> {
+        self.opt_options().into_iter().find_map(|opt| match &opt.data {
+            Some(OptionData::ClientSubnet(subnet)) => Some(subnet),
+            _ => None,
+        })
+    }
+
+    // DNS cookies sent back by the server: https://www.rfc-editor.org/rfc/rfc7873
+    pub fn cookie(&self) -> Option<&COOKIE> {
+        self.opt_options().into_iter().find_map(|opt| match &opt.data {
+            Some(OptionData::COOKIE(cookie)) => Some(cookie),
+            _ => None,
+        })
+    }
+
+    // BADCOOKIE (23) is an extended rcode: 1 in the OPT record and 7 in the header
+    pub fn is_bad_cookie(&self) -> bool {
+        let extended = self
+            .additional
+            .iter()
+            .flat_map(|list| list.iter())
+            .find_map(|rr| rr.opt_or_class_ttl.opt().map(|opt| opt.extended_rcode));
+        extended == Some(1) && self.rcode() == ResponseCode::YXRRSet
+    }
+
+    // Extended DNS Error codes: https://www.rfc-editor.org/rfc/rfc8914.html
+    pub fn extended_errors(&self) -> Vec<u16> {
+        self.opt_options()
+            .into_iter()
+            .filter_map(|opt| match &opt.data {
+                Some(OptionData::Extended(ext)) => Some(ext.info_code()),
+                _ => None,
+            })
+            .collect()
+    }
+
     // return the max length of all RRs in either answer, additional or authority
     pub fn max_length(&self) -> usize {
         let m1 = if let Some(x) = &self.answer {
@@ -87,6 +235,15 @@ impl Response {
         usize::max(usize::max(m1, m2), m3)
     }
 
+    // widths of all columns to fit all RRs in either answer, additional or authority
+    pub fn widths(&self, raw_ttl: bool) -> ColumnWidths {
+        let mut widths = ColumnWidths::default();
+        for list in [&self.answer, &self.authority, &self.additional].into_iter().flatten() {
+            widths.merge(&list.widths(raw_ttl));
+        }
+        widths
+    }
+
     // Receive message for DNS resolver
     pub fn recv<T: Messenger>(
         &mut self,
@@ -97,6 +254,12 @@ impl Response {
         // receive packet from endpoint
         let received = trp.recv(buffer)?;
         debug!("received {} bytes", received);
+        notify(
+            Direction::Received,
+            trp.mode(),
+            trp.network_info().peer,
+            &buffer[..received],
+        );
         trace!("received buffer {:X?}", &buffer[..received]);
 
         // if using TCP, we get rid of 2 bytes which are the length of the message received
@@ -129,6 +292,12 @@ impl Response {
         // receive packet from endpoint
         let received = trp.arecv(buffer).await?;
         debug!("received {} bytes", received);
+        notify(
+            Direction::Received,
+            trp.mode(),
+            trp.network_info().peer,
+            &buffer[..received],
+        );
         trace!("received buffer {:X?}", &buffer[..received]);
 
         // if using TCP, we get rid of 2 bytes which are the length of the message received
@@ -153,15 +322,79 @@ impl Response {
         Ok(received)
     }
 
-    // return a random ip address in the glue records from the additional section
-    pub fn random_glue_record(&self, qt: &QType) -> Option<&ResourceRecord> {
-        if let Some(add) = &self.additional {
-            // choose a random resource record for an A address
-            let a_record = add.random(qt)?;
-            Some(a_record)
-        } else {
-            None
+    // SOA record of the answer, or of the authority section for negative answers
+    pub fn soa(&self) -> Option<&SOA> {
+        [&self.answer, &self.authority]
+            .into_iter()
+            .flatten()
+            .flat_map(|list| list.iter())
+            .find_map(|rr| match &rr.r_data {
+                RData::SOA(soa) => Some(soa),
+                _ => None,
+            })
+    }
+
+    // return a random ip address in the glue records from the additional section, only
+    // among the ones in the bailiwick of the server
+    pub fn random_glue_record(&self, qt: &QType, bailiwick: &DomainName) -> Option<&ResourceRecord> {
+        let mut rng = rand::thread_rng();
+
+        self.additional
+            .iter()
+            .flat_map(|list| list.iter())
+            .filter(|rr| rr.r#type == *qt && rr.name.is_subdomain_of(bailiwick))
+            .choose(&mut rng)
+    }
+
+    // records of the additional section outside the zone the server is authoritative
+    // for: the server has no authority on them and they could be poisoned (RFC2181 5.4.1)
+    pub fn out_of_bailiwick(&self, bailiwick: &DomainName) -> Vec<&ResourceRecord> {
+        self.additional
+            .iter()
+            .flat_map(|list| list.iter())
+            .filter(|rr| rr.r#type != QType::OPT && !rr.name.is_subdomain_of(bailiwick))
+            .collect()
+    }
+
+    // addresses found in the additional section for each NS name of the authority section
+    pub fn glue(&self) -> BTreeMap<String, BTreeSet<IpAddr>> {
+        let mut glue: BTreeMap<String, BTreeSet<IpAddr>> = BTreeMap::new();
+
+        let ns_names: Vec<DomainName> = self
+            .authority
+            .iter()
+            .flat_map(|list| list.iter())
+            .filter_map(|rr| rr.ns_name())
+            .collect();
+
+        for rr in self.additional.iter().flat_map(|list| list.iter()) {
+            if let Some(ip) = rr.ip_address() {
+                if ns_names.contains(&rr.name) {
+                    glue.entry(rr.name.to_string()).or_default().insert(ip);
+                }
+            }
         }
+
+        glue
+    }
+
+    // zone a referral delegates to: owner of the NS records of the authority section
+    pub fn referral_zone(&self) -> Option<&DomainName> {
+        self.authority
+            .iter()
+            .flat_map(|list| list.iter())
+            .find(|rr| rr.r#type == QType::NS)
+            .map(|rr| &rr.name)
+    }
+
+    // addresses of the answer section for name
+    pub fn ip_addresses(&self, name: &DomainName) -> BTreeSet<IpAddr> {
+        self.answer
+            .iter()
+            .flat_map(|list| list.iter())
+            .filter(|rr| &rr.name == name)
+            .filter_map(|rr| rr.ip_address())
+            .collect()
     }
 
     // return a random NS record in the answer section
@@ -287,7 +520,13 @@ impl Show for Response {
         //───────────────────────────────────────────────────────────────────────────────────
         if display_options.sho_resp_header {
             println!("{}", header_section("Response HEADER", None));
-            println!("{}\n", self.header);
+            println!("{}", self.header);
+            if display_options.explain_flags {
+                for line in self.header.flags.explain() {
+                    println!("  {}", line);
+                }
+            }
+            println!();
         }
 
         //───────────────────────────────────────────────────────────────────────────────────
@@ -388,6 +627,45 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn append() -> crate::error::Result<()> {
+        let pcap = get_packets("./tests/pcap/cap1.pcap", 0, 1);
+        let read = || -> crate::error::Result<Response> {
+            let mut resp = Response::default();
+            resp.deserialize_from(&mut std::io::Cursor::new(&pcap.1[0x2A..]))
+                .map_err(|_| Error::Dns(Dns::CantDeserialize))?;
+            Ok(resp)
+        };
+
+        let mut resp = read()?;
+        resp.append_answer(read()?);
+        assert_eq!(resp.header.an_count, 2);
+        assert_eq!(resp.answer.as_ref().unwrap().len(), 2);
+
+        // nothing to add
+        resp.append_answer(Response::default());
+        assert_eq!(resp.header.an_count, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stabilize() -> crate::error::Result<()> {
+        let pcap = get_packets("./tests/pcap/cap1.pcap", 0, 1);
+        let mut resp = Response::default();
+        resp.deserialize_from(&mut std::io::Cursor::new(&pcap.1[0x2A..]))
+            .map_err(|_| Error::Dns(Dns::CantDeserialize))?;
+
+        resp.stabilize();
+        assert_eq!(resp.id(), 0);
+        let answer = resp.answer.as_ref().unwrap();
+        assert!(answer
+            .iter()
+            .all(|rr| rr.opt_or_class_ttl.regular().unwrap().ttl() == 0));
+
+        Ok(())
+    }
+
     #[test]
     fn cap2() -> crate::error::Result<()> {
         let pcap = get_packets("./tests/pcap/cap2.pcap", 0, 1);
@@ -452,4 +730,43 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn glue() {
+        // referral for example.com with glue for ns1.example.com, and an unrelated address
+        let wire: &[u8] = &[
+            0x00, 0x01, 0x80, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, // header
+            0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o', b'm', 0x00, 0x00, 0x01, 0x00,
+            0x01, // question
+            0xC0, 0x0C, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0x0E, 0x10, 0x00, 0x06, 0x03, b'n', b's', b'1', 0xC0,
+            0x0C, // NS
+            0xC0, 0x29, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x0E, 0x10, 0x00, 0x04, 192, 0, 2, 1, // glue
+            0x05, b'o', b't', b'h', b'e', b'r', 0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x03, b'o', b'r', b'g',
+            0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x0E, 0x10, 0x00, 0x04, 192, 0, 2, 2,
+        ];
+
+        let mut resp = Response::default();
+        resp.deserialize_from(&mut std::io::Cursor::new(wire)).unwrap();
+
+        assert_eq!(resp.referral_zone().unwrap().to_string(), "example.com.");
+
+        let glue = resp.glue();
+        assert_eq!(glue.len(), 1);
+        assert_eq!(
+            glue["ns1.example.com."],
+            BTreeSet::from(["192.0.2.1".parse::<IpAddr>().unwrap()])
+        );
+
+        // the address of other.example.org can't come from a com server
+        let com = DomainName::try_from("com.").unwrap();
+        let outside = resp.out_of_bailiwick(&com);
+        assert_eq!(outside.len(), 1);
+        assert_eq!(outside[0].name.to_string(), "other.example.org.");
+        assert!(resp.out_of_bailiwick(&DomainName::default()).is_empty());
+
+        let glue = resp.random_glue_record(&QType::A, &com).unwrap();
+        assert_eq!(glue.name.to_string(), "ns1.example.com.");
+        assert_eq!(com.parent().unwrap(), DomainName::default());
+        assert!(DomainName::default().parent().is_none());
+    }
 }
@@ -5,6 +5,8 @@ use enum_from::{EnumDisplay, EnumTryFrom};
 use type2network::FromNetworkOrder;
 use type2network_derive::FromNetwork;
 
+use crate::show::Numeric;
+
 // response codes: https://www.iana.org/assignments/dns-parameters/dns-parameters.xhtml#dns-parameters-6
 #[derive(Debug, Default, Clone, Copy, PartialEq, EnumTryFrom, EnumDisplay, FromNetwork, Serialize)]
 #[repr(u8)]
@@ -36,6 +38,40 @@ pub enum ResponseCode {
     BADCOOKIE = 23, //	Bad/missing Server Cookie	[RFC7873]
 }
 
+impl ResponseCode {
+    // meaning of the response code, for --explain-flags
+    pub fn explain(&self) -> &'static str {
+        match self {
+            ResponseCode::NoError => "no error condition",
+            ResponseCode::FormErr => "the server was unable to interpret the query",
+            ResponseCode::ServFail => "the server was unable to process the query (e.g. DNSSEC validation failure or unreachable authoritative servers)",
+            ResponseCode::NXDomain => "the domain name doesn't exist",
+            ResponseCode::NotImp => "the server doesn't support this kind of query",
+            ResponseCode::Refused => "the server refuses to answer for policy reasons",
+            ResponseCode::YXDomain => "a name exists when it should not",
+            ResponseCode::YXRRSet => "a RR set exists when it should not",
+            ResponseCode::NXRRSet => "a RR set that should exist does not",
+            ResponseCode::NotAuth => "the server is not authoritative for the zone or the request is not authorized",
+            ResponseCode::NotZone => "a name is not contained in the zone",
+            ResponseCode::DSOTYPENI => "DSO-TYPE is not implemented",
+            ResponseCode::BADSIG => "TSIG signature failure",
+            ResponseCode::BADKEY => "TSIG key not recognized",
+            ResponseCode::BADTIME => "signature out of time window",
+            ResponseCode::BADMODE => "bad TKEY mode",
+            ResponseCode::BADNAME => "duplicate key name",
+            ResponseCode::BADALG => "algorithm not supported",
+            ResponseCode::BADTRUNC => "bad truncation",
+            ResponseCode::BADCOOKIE => "bad or missing server cookie",
+        }
+    }
+}
+
+impl Numeric for ResponseCode {
+    fn number(&self) -> u16 {
+        *self as u16
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
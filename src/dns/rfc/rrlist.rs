@@ -9,8 +9,8 @@ use serde::Serialize;
 use type2network::FromNetworkOrder;
 use type2network_derive::FromNetwork;
 
-use super::{domain::DomainName, qtype::QType, resource_record::ResourceRecord};
-use crate::show::{DisplayOptions, Show};
+use super::{domain::DomainName, qtype::QType, resource_record::ResourceRecord, rrset::RRSet};
+use crate::show::{ColumnWidths, DisplayOptions, Show};
 
 #[derive(Debug, Default, FromNetwork, Serialize)]
 pub struct RRList(Vec<ResourceRecord>);
@@ -21,6 +21,15 @@ impl RRList {
         Self(Vec::with_capacity(capa))
     }
 
+    // add the RRs of another list at the end
+    pub fn append(&mut self, other: RRList) {
+        self.0.extend(other.0);
+    }
+
+    pub fn set_ttl(&mut self, ttl: u32) {
+        self.0.iter_mut().for_each(|rr| rr.set_ttl(ttl));
+    }
+
     // in case a RR in the set is a A or AAAA type, return the corresponding ip address
     pub fn ip_address<T: TryInto<DomainName>>(&self, qt: &QType, name: T) -> Option<IpAddr> {
         let name = name.try_into().ok()?;
@@ -52,6 +61,20 @@ impl RRList {
         self.0.iter().map(|x| x.name.count()).max()
     }
 
+    // RRsets (same owner, type and class) in order of first appearance
+    pub fn rrsets(&self) -> Vec<RRSet<'_>> {
+        RRSet::group(&self.0)
+    }
+
+    // widths of all columns to fit all RRs of the list
+    pub fn widths(&self, raw_ttl: bool) -> ColumnWidths {
+        let mut widths = ColumnWidths::default();
+        for rr in &self.0 {
+            widths.merge(&rr.widths(raw_ttl));
+        }
+        widths
+    }
+
     // pub fn foo<P>(&self, dimension: P) -> Option<usize>
     // where
     //     P: Fn(&ResourceRecord) -> usize,
@@ -86,6 +109,22 @@ impl Show for RRList {
             None
         };
 
+        // one header per RRset. OPT is not a real RRset and is displayed as usual
+        if display_options.group
+            && !display_options.short
+            && !display_options.screen_reader
+            && display_options.fmt.is_empty()
+        {
+            for rrset in self.rrsets() {
+                if rrset.r#type() == QType::OPT {
+                    rrset[0].show(display_options, max_length);
+                } else {
+                    ResourceRecord::show_rrset(&rrset, display_options, max_length);
+                }
+            }
+            return;
+        }
+
         for rr in &self.0 {
             // don't display OPT if not requested
             // if rr.r#type == QType::OPT && !display_options.show_opt {
@@ -141,6 +180,12 @@ mod tests {
         let ip = add.ip_address(&QType::A, d).unwrap();
         assert_eq!(ip.to_string(), "192.43.172.30");
 
+        // all NS records are in the same RRset
+        let rrsets = auth.rrsets();
+        assert_eq!(rrsets.len(), 1);
+        assert_eq!(rrsets[0].len(), 13);
+        assert!(add.rrsets().iter().all(|set| set.len() == 1));
+
         // let answer = &answer[0];
         // assert_eq!(format!("{}", answer.name), "www.google.com.");
         // assert_eq!(answer.r#type, QType::A);
@@ -0,0 +1,105 @@
+//! A RRset: all the RRs with the same owner name, type and class (RFC2181 section 5).
+//! It's the unit of caching and of DNSSEC signing.
+use std::ops::Deref;
+
+use super::{domain::DomainName, qtype::QType, resource_record::ResourceRecord};
+
+pub struct RRSet<'a>(Vec<&'a ResourceRecord>);
+
+impl<'a> RRSet<'a> {
+    //───────────────────────────────────────────────────────────────────────────────────
+    // group RRs into RRsets, in order of first appearance
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn group(rrs: impl IntoIterator<Item = &'a ResourceRecord>) -> Vec<Self> {
+        let mut rrsets: Vec<Self> = Vec::new();
+
+        for rr in rrs {
+            match rrsets.iter_mut().find(|set| set.0[0].same_rrset(rr)) {
+                Some(set) => set.0.push(rr),
+                None => rrsets.push(Self(vec![rr])),
+            }
+        }
+
+        rrsets
+    }
+
+    pub fn name(&self) -> &DomainName {
+        &self.0[0].name
+    }
+
+    pub fn r#type(&self) -> QType {
+        self.0[0].r#type
+    }
+
+    // all RRs of a RRset should have the same TTL, otherwise the lowest one is the one
+    // to use (RFC2181 section 5.2). None for OPT
+    pub fn ttl(&self) -> Option<u32> {
+        self.0
+            .iter()
+            .filter_map(|rr| rr.opt_or_class_ttl.regular().map(|r| r.ttl()))
+            .min()
+    }
+
+    // true if the RRs don't share the same TTL
+    pub fn ttl_mismatch(&self) -> bool {
+        let mut ttls = self
+            .0
+            .iter()
+            .filter_map(|rr| rr.opt_or_class_ttl.regular().map(|r| r.ttl()));
+        let first = ttls.next();
+        ttls.any(|ttl| Some(ttl) != first)
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // RRs in canonical order, sorted by their RDATA as unsigned octet sequences and
+    // without duplicates, as they're signed (RFC4034 section 6.3). Names in RDATA are
+    // not lowercased
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn canonical(&self) -> Vec<&'a ResourceRecord> {
+        let mut rrs: Vec<_> = self.0.iter().map(|rr| (rr.rdata_wire(), *rr)).collect();
+        rrs.sort_by(|a, b| a.0.cmp(&b.0));
+        rrs.dedup_by(|a, b| a.0 == b.0);
+        rrs.into_iter().map(|(_, rr)| rr).collect()
+    }
+}
+
+impl<'a> Deref for RRSet<'a> {
+    type Target = [&'a ResourceRecord];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::{rfc::response::Response, tests::get_packets};
+    use type2network::FromNetworkOrder;
+
+    #[test]
+    fn rrset() {
+        let pcap = get_packets("./tests/pcap/cap4.pcap", 0, 1);
+        let mut buffer = std::io::Cursor::new(&pcap.1[0x2A..]);
+        let mut resp = Response::default();
+        resp.deserialize_from(&mut buffer).unwrap();
+
+        // referral to the 13 gTLD servers
+        let rrsets = RRSet::group(resp.authority().unwrap().iter());
+        assert_eq!(rrsets.len(), 1);
+        let ns = &rrsets[0];
+        assert_eq!(ns.len(), 13);
+        assert_eq!(ns.r#type(), QType::NS);
+        assert_eq!(ns.ttl(), ns[0].opt_or_class_ttl.regular().map(|r| r.ttl()));
+        assert!(!ns.ttl_mismatch());
+
+        let canonical = ns.canonical();
+        assert_eq!(canonical.len(), 13);
+        assert!(canonical.windows(2).all(|w| w[0].rdata_wire() < w[1].rdata_wire()));
+
+        // the same RRs twice make the same RRset
+        let twice = RRSet::group(resp.authority().unwrap().iter().chain(resp.authority().unwrap().iter()));
+        assert_eq!(twice[0].len(), 26);
+        assert_eq!(twice[0].canonical().len(), 13);
+    }
+}
@@ -6,6 +6,7 @@ use type2network_derive::FromNetwork;
 use super::{algorithm::Algorithm, domain::DomainName, qtype::QType};
 use crate::dns::{buffer::Buffer, date_time::DnsDateTime};
 use crate::new_rd_length;
+use crate::show::Numeric;
 
 // https://datatracker.ietf.org/doc/html/rfc4034
 // The RDATA for an RRSIG RR consists of a 2 octet Type Covered field, a
@@ -58,13 +59,20 @@ pub struct RRSIG {
 // auto-implement new
 new_rd_length!(RRSIG);
 
+impl RRSIG {
+    // seconds from now to the inception and to the expiration, negative if in the past
+    pub fn validity(&self, now: i64) -> (i64, i64) {
+        (self.sign_inception.offset(now), self.sign_expiration.offset(now))
+    }
+}
+
 impl fmt::Display for RRSIG {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "{} {} {} {} {} {} {}",
-            self.type_covered,
-            self.algorithm,
+            self.type_covered.mnemonic(),
+            self.algorithm.mnemonic(),
             self.name,
             self.sign_expiration,
             self.sign_inception,
@@ -18,6 +18,28 @@ pub struct SRV {
     target: DomainName,
 }
 
+impl SRV {
+    #[inline]
+    pub fn priority(&self) -> u16 {
+        self.priority
+    }
+
+    #[inline]
+    pub fn weight(&self) -> u16 {
+        self.weight
+    }
+
+    #[inline]
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    #[inline]
+    pub fn target(&self) -> &DomainName {
+        &self.target
+    }
+}
+
 impl fmt::Display for SRV {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} {} {} {}", self.priority, self.weight, self.port, self.target)?;
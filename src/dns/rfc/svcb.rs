@@ -1,4 +1,4 @@
-use std::{fmt, io::Cursor, ops::Deref};
+use std::{fmt, io::Cursor, net::IpAddr, ops::Deref};
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
@@ -142,6 +142,71 @@ pub struct SVCB {
 // auto-implement new
 new_rd_length!(SVCB);
 
+impl SVCB {
+    #[inline]
+    pub fn priority(&self) -> u16 {
+        self.svc_priority
+    }
+
+    #[inline]
+    pub fn target(&self) -> &DomainName {
+        &self.target_name
+    }
+
+    // priority 0 is AliasMode: the service is found at the target name
+    #[inline]
+    pub fn is_alias(&self) -> bool {
+        self.svc_priority == 0
+    }
+
+    // keys are those of SvcParamKeys
+    fn param(&self, key: u16) -> Option<&SvcParam> {
+        self.svc_params.iter().find(|p| p.key == key)
+    }
+
+    // protocols supported, without the default one
+    pub fn alpn(&self) -> Vec<String> {
+        self.param(1)
+            .map(|p| CSList::from(p.value.deref()).iter().map(|cs| cs.to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn no_default_alpn(&self) -> bool {
+        self.param(2).is_some()
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.param(3)
+            .filter(|p| p.value.len() == 2)
+            .map(|p| u16::from_be_bytes([p.value[0], p.value[1]]))
+    }
+
+    // ECHConfigList as base64
+    pub fn ech(&self) -> Option<String> {
+        self.param(5).map(|p| p.value.to_base64())
+    }
+
+    // all addresses of ipv4hint and ipv6hint
+    pub fn hints(&self) -> Vec<IpAddr> {
+        let mut hints: Vec<IpAddr> = Vec::new();
+        if let Some(p) = self.param(4) {
+            hints.extend(
+                p.value
+                    .chunks_exact(4)
+                    .map(|ip| IpAddr::from(<[u8; 4]>::try_from(ip).unwrap())),
+            );
+        }
+        if let Some(p) = self.param(6) {
+            hints.extend(
+                p.value
+                    .chunks_exact(16)
+                    .map(|ip| IpAddr::from(<[u8; 16]>::try_from(ip).unwrap())),
+            );
+        }
+        hints
+    }
+}
+
 // implement FromNetwork because of the special SVCB format
 impl<'a> FromNetworkOrder<'a> for SVCB {
     fn deserialize_from(&mut self, buffer: &mut Cursor<&'a [u8]>) -> std::io::Result<()> {
@@ -211,6 +276,7 @@ mod tests {
     use type2network::FromNetworkOrder;
 
     use super::SVCB;
+    use crate::dns::rfc::qtype::QType;
 
     test_rdata!(
         rdata,
@@ -225,4 +291,28 @@ mod tests {
             );
         })
     );
+
+    #[test]
+    fn params() {
+        let RData::HTTPS(https) = RData::from_presentation(
+            &QType::HTTPS,
+            r#"1 . alpn="h2,h3" port=8443 ipv4hint=192.0.2.1,192.0.2.2 ech=AEX+DQBB"#,
+        )
+        .unwrap() else {
+            panic!("not an HTTPS RR")
+        };
+        assert!(!https.is_alias());
+        assert_eq!(https.alpn(), vec!["h2", "h3"]);
+        assert!(!https.no_default_alpn());
+        assert_eq!(https.port(), Some(8443));
+        assert_eq!(https.ech().unwrap(), "AEX+DQBB");
+        assert_eq!(https.hints().len(), 2);
+
+        let RData::SVCB(alias) = RData::from_presentation(&QType::SVCB, "0 svc.example.net.").unwrap() else {
+            panic!("not an SVCB RR")
+        };
+        assert!(alias.is_alias());
+        assert_eq!(alias.target().to_string(), "svc.example.net.");
+        assert!(alias.alpn().is_empty() && alias.port().is_none());
+    }
 }
@@ -0,0 +1,294 @@
+use std::fmt;
+use std::str::FromStr;
+
+use base64::{engine::general_purpose, Engine as _};
+use ring::hmac;
+use serde::{ser::SerializeMap, Serialize, Serializer};
+use type2network::ToNetworkOrder;
+
+use crate::error::{Error, Result};
+
+use super::{domain::DomainName, qclass::QClass, qtype::QType};
+
+// TSIG errors and times are checked by the server with this margin (RFC8945 recommends 300s)
+const FUDGE: u16 = 300;
+
+// HMAC algorithms: https://www.iana.org/assignments/tsig-algorithm-names/tsig-algorithm-names.xhtml
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum TsigAlgorithm {
+    HmacSha1,
+    #[default]
+    HmacSha256,
+    HmacSha384,
+    HmacSha512,
+}
+
+impl TsigAlgorithm {
+    // name sent in the TSIG RR
+    pub fn name(&self) -> &'static str {
+        match self {
+            TsigAlgorithm::HmacSha1 => "hmac-sha1",
+            TsigAlgorithm::HmacSha256 => "hmac-sha256",
+            TsigAlgorithm::HmacSha384 => "hmac-sha384",
+            TsigAlgorithm::HmacSha512 => "hmac-sha512",
+        }
+    }
+
+    fn hmac(&self) -> hmac::Algorithm {
+        match self {
+            TsigAlgorithm::HmacSha1 => hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY,
+            TsigAlgorithm::HmacSha256 => hmac::HMAC_SHA256,
+            TsigAlgorithm::HmacSha384 => hmac::HMAC_SHA384,
+            TsigAlgorithm::HmacSha512 => hmac::HMAC_SHA512,
+        }
+    }
+}
+
+impl FromStr for TsigAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().trim_end_matches('.').to_lowercase().as_str() {
+            "hmac-sha1" => Ok(TsigAlgorithm::HmacSha1),
+            "hmac-sha256" => Ok(TsigAlgorithm::HmacSha256),
+            "hmac-sha384" => Ok(TsigAlgorithm::HmacSha384),
+            "hmac-sha512" => Ok(TsigAlgorithm::HmacSha512),
+            _ => Err(format!("unsupported TSIG algorithm '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for TsigAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// shared secret used to sign queries. The secret is never printed out
+//───────────────────────────────────────────────────────────────────────────────────
+#[derive(Clone)]
+pub struct TsigKey {
+    pub name: DomainName,
+    pub algorithm: TsigAlgorithm,
+    secret: Vec<u8>,
+}
+
+impl TsigKey {
+    // secret is base64 encoded, as in key files
+    pub fn new(name: &str, algorithm: TsigAlgorithm, secret: &str) -> Result<Self> {
+        // names are in canonical form for the MAC computation
+        let name = DomainName::try_from(name.to_lowercase().as_str())?;
+        let secret = general_purpose::STANDARD
+            .decode(secret.trim())
+            .map_err(|_| Error::Tsig(format!("secret of key '{}' is not valid base64", name)))?;
+
+        Ok(Self {
+            name,
+            algorithm,
+            secret,
+        })
+    }
+
+    fn mac(&self, data: &[u8]) -> Vec<u8> {
+        let key = hmac::Key::new(self.algorithm.hmac(), &self.secret);
+        hmac::sign(&key, data).as_ref().to_vec()
+    }
+}
+
+impl fmt::Debug for TsigKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TsigKey")
+            .field("name", &self.name)
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+// Transaction signature: https://datatracker.ietf.org/doc/html/rfc8945#section-4.2
+// +---------------------------------------------------------------+
+// /                     Algorithm Name                            /
+// +---------------------------------------------------------------+
+// |                     Time Signed (48 bits)     |     Fudge     |
+// +---------------------------------------------------------------+
+// |          MAC Size             |             MAC               /
+// +---------------------------------------------------------------+
+// |          Original ID          |            Error              |
+// +---------------------------------------------------------------+
+// |          Other Len            |           Other Data          /
+// +---------------------------------------------------------------+
+// time signed and fudge of a received TSIG RDATA, found after the algorithm name which
+// is never compressed
+pub fn signed_time(rdata: &[u8]) -> Option<(u64, u16)> {
+    let mut pos = 0;
+    while *rdata.get(pos)? != 0 {
+        pos += *rdata.get(pos)? as usize + 1;
+    }
+
+    let fields = rdata.get(pos + 1..pos + 9)?;
+    let mut time = [0u8; 8];
+    time[2..].copy_from_slice(&fields[..6]);
+    Some((u64::from_be_bytes(time), u16::from_be_bytes([fields[6], fields[7]])))
+}
+
+#[derive(Debug)]
+pub struct TSIG {
+    key: TsigKey,
+    time_signed: u64,
+}
+
+impl TSIG {
+    pub fn new(key: TsigKey) -> Self {
+        Self {
+            key,
+            time_signed: chrono::Utc::now().timestamp() as u64,
+        }
+    }
+
+    // time signed is a 48-bit integer
+    fn time_fudge(&self, buffer: &mut Vec<u8>) -> std::io::Result<usize> {
+        buffer.extend_from_slice(&self.time_signed.to_be_bytes()[2..]);
+        Ok(6 + FUDGE.serialize_to(buffer)?)
+    }
+
+    // data on which the MAC is computed: RFC8945 section 4.3.3
+    fn digest(&self, message: &[u8]) -> std::io::Result<Vec<u8>> {
+        // message without the TSIG RR, so the additional count is decremented
+        let mut digest = message.to_vec();
+        let ar_count = u16::from_be_bytes([digest[10], digest[11]]).wrapping_sub(1);
+        digest[10..12].copy_from_slice(&ar_count.to_be_bytes());
+
+        self.key.name.serialize_to(&mut digest)?;
+        QClass::ANY.serialize_to(&mut digest)?;
+        0u32.serialize_to(&mut digest)?;
+        DomainName::try_from(self.key.algorithm.name())
+            .map_err(|_| std::io::Error::other("bad TSIG algorithm name"))?
+            .serialize_to(&mut digest)?;
+        self.time_fudge(&mut digest)?;
+
+        // error and other len
+        0u16.serialize_to(&mut digest)?;
+        0u16.serialize_to(&mut digest)?;
+
+        Ok(digest)
+    }
+
+    // append the TSIG RR signing message, which must already count it in ARCOUNT
+    pub fn sign(&self, message: &[u8], buffer: &mut Vec<u8>) -> std::io::Result<usize> {
+        let mac = if message.len() >= 12 {
+            self.key.mac(&self.digest(message)?)
+        } else {
+            Vec::new()
+        };
+        let original_id = message
+            .get(..2)
+            .map(|id| u16::from_be_bytes([id[0], id[1]]))
+            .unwrap_or(0);
+        self.serialize_rr(&mac, original_id, buffer)
+    }
+
+    fn serialize_rr(&self, mac: &[u8], original_id: u16, buffer: &mut Vec<u8>) -> std::io::Result<usize> {
+        let algorithm = DomainName::try_from(self.key.algorithm.name())
+            .map_err(|_| std::io::Error::other("bad TSIG algorithm name"))?;
+
+        let mut rdata: Vec<u8> = Vec::new();
+        algorithm.serialize_to(&mut rdata)?;
+        self.time_fudge(&mut rdata)?;
+        (mac.len() as u16).serialize_to(&mut rdata)?;
+        rdata.extend_from_slice(mac);
+        original_id.serialize_to(&mut rdata)?;
+        0u16.serialize_to(&mut rdata)?;
+        0u16.serialize_to(&mut rdata)?;
+
+        let mut length = self.key.name.serialize_to(buffer)?;
+        length += QType::TSIG.serialize_to(buffer)?;
+        length += QClass::ANY.serialize_to(buffer)?;
+        length += 0u32.serialize_to(buffer)?;
+        length += (rdata.len() as u16).serialize_to(buffer)?;
+        buffer.extend_from_slice(&rdata);
+
+        Ok(length + rdata.len())
+    }
+}
+
+// the MAC needs the whole message: signed queries are built by Query::serialize_compressed,
+// this one is unsigned
+impl ToNetworkOrder for TSIG {
+    fn serialize_to(&self, buffer: &mut Vec<u8>) -> std::io::Result<usize> {
+        self.serialize_rr(&[], 0, buffer)
+    }
+}
+
+impl fmt::Display for TSIG {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} TSIG {} {} {}",
+            self.key.name, self.key.algorithm, self.time_signed, FUDGE
+        )
+    }
+}
+
+impl Serialize for TSIG {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_map(Some(4))?;
+        seq.serialize_entry("name", &self.key.name.to_string())?;
+        seq.serialize_entry("algorithm", self.key.algorithm.name())?;
+        seq.serialize_entry("time_signed", &self.time_signed)?;
+        seq.serialize_entry("fudge", &FUDGE)?;
+        seq.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign() {
+        let key = TsigKey::new("Key.Example.", TsigAlgorithm::HmacSha256, "c2VjcmV0").unwrap();
+        assert_eq!(
+            format!("{:?}", key),
+            "TsigKey { name: key.example., algorithm: HmacSha256, .. }"
+        );
+        assert!(TsigKey::new("key", TsigAlgorithm::HmacSha256, "not base64!").is_err());
+
+        let tsig = TSIG {
+            key,
+            time_signed: 0x0102_0304_0506,
+        };
+
+        // header only, ARCOUNT = 1 for TSIG
+        let message = [0x12, 0x34, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+        let mut buffer = Vec::new();
+        let length = tsig.sign(&message, &mut buffer).unwrap();
+        assert_eq!(length, buffer.len());
+
+        // owner name, type, class
+        assert_eq!(&buffer[..13], b"\x03key\x07example\x00");
+        assert_eq!(&buffer[13..17], &[0x00, 0xFA, 0x00, 0xFF]);
+
+        // RDATA: algorithm, time, fudge, 32-byte MAC, original ID
+        let rdata = &buffer[23..];
+        assert_eq!(&rdata[..13], b"\x0bhmac-sha256\x00");
+        assert_eq!(&rdata[13..21], &[1, 2, 3, 4, 5, 6, 0x01, 0x2C]);
+        assert_eq!(&rdata[21..23], &[0, 32]);
+        assert_eq!(&rdata[55..57], &[0x12, 0x34]);
+        assert_eq!(signed_time(rdata), Some((0x0102_0304_0506, 300)));
+        assert_eq!(signed_time(&rdata[..16]), None);
+
+        // same message, same MAC
+        let mut again = Vec::new();
+        tsig.sign(&message, &mut again).unwrap();
+        assert_eq!(buffer, again);
+
+        assert_eq!(
+            TsigAlgorithm::from_str("HMAC-SHA512.").unwrap(),
+            TsigAlgorithm::HmacSha512
+        );
+        assert!(TsigAlgorithm::from_str("hmac-md5").is_err());
+    }
+}
@@ -21,10 +21,13 @@ impl fmt::Display for TXT {
     }
 }
 
+// SPF is deprecated in favor of TXT, with the same format: https://www.rfc-editor.org/rfc/rfc7208.html#section-14.1
+pub type SPF = TXT;
+
 #[cfg(test)]
 mod tests {
     use crate::{
-        dns::rfc::{rdata::RData, response::Response},
+        dns::rfc::{rdata::RData, response::Response, txt::SPF},
         dns::tests::get_packets,
         test_rdata,
     };
@@ -50,4 +53,14 @@ mod tests {
             }
         })
     );
+    test_rdata!(
+        rdata_spf,
+        "./tests/pcap/spf.pcap",
+        false,
+        1,
+        RData::SPF,
+        (|x: &SPF, _| {
+            assert_eq!(x.to_string(), "v=spf1 -all");
+        })
+    );
 }
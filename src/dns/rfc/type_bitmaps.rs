@@ -9,6 +9,7 @@ use type2network::FromNetworkOrder;
 
 use super::qtype::QType;
 use crate::error::{Dns, Error};
+use crate::show::Numeric;
 
 // https://datatracker.ietf.org/doc/html/rfc5155#section-3.2.1
 // https://datatracker.ietf.org/doc/html/rfc4034#section-4.1.2
@@ -62,7 +63,7 @@ impl<'a> FromNetworkOrder<'a> for TypeBitMaps {
 
 impl fmt::Display for TypeBitMaps {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let s: Vec<String> = self.types.iter().map(|q| q.to_string()).collect();
+        let s: Vec<String> = self.types.iter().map(|q| q.mnemonic()).collect();
         write!(f, "{}", s.join(" "))
     }
 }
@@ -0,0 +1,109 @@
+use std::{fmt, net::Ipv4Addr};
+
+use type2network::FromNetworkOrder;
+use type2network_derive::FromNetwork;
+
+use serde::{ser::SerializeMap, Serialize, Serializer};
+
+use crate::{dns::buffer::Buffer, new_rd_length};
+
+// WKS RR: https://datatracker.ietf.org/doc/html/rfc1035#section-3.4.2
+// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+// |                    ADDRESS                    |
+// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+// |       PROTOCOL        |                       |
+// +--+--+--+--+--+--+--+--+                       |
+// |                                               |
+// /                   <BIT MAP>                   /
+// /                                               /
+// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, FromNetwork)]
+pub(super) struct WKS {
+    // transmistted through RR deserialization
+    #[from_network(ignore)]
+    rd_length: u16,
+
+    address: Ipv4Addr,
+    protocol: u8,
+
+    #[from_network(with_code( self.bitmap = Buffer::with_capacity(self.rd_length.saturating_sub(5)); ))]
+    bitmap: Buffer,
+}
+
+impl Default for WKS {
+    fn default() -> Self {
+        Self {
+            rd_length: 0,
+            address: Ipv4Addr::UNSPECIFIED,
+            protocol: 0,
+            bitmap: Buffer::default(),
+        }
+    }
+}
+
+// auto-implement new
+new_rd_length!(WKS);
+
+impl WKS {
+    // each bit set in the bitmap is a port number
+    fn ports(&self) -> Vec<u16> {
+        self.bitmap
+            .iter()
+            .enumerate()
+            .flat_map(|(i, byte)| {
+                (0..8u16)
+                    .filter(move |bit| byte & (0x80 >> bit) != 0)
+                    .map(move |bit| i as u16 * 8 + bit)
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for WKS {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.address, self.protocol)?;
+        for port in self.ports() {
+            write!(f, " {}", port)?;
+        }
+        Ok(())
+    }
+}
+
+// Custom serialization
+impl Serialize for WKS {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_map(Some(3))?;
+        seq.serialize_entry("address", &self.address)?;
+        seq.serialize_entry("protocol", &self.protocol)?;
+        seq.serialize_entry("ports", &self.ports())?;
+        seq.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns::rfc::{rdata::RData, response::Response},
+        dns::tests::get_packets,
+        test_rdata,
+    };
+
+    use type2network::FromNetworkOrder;
+
+    use super::WKS;
+
+    test_rdata!(
+        rdata,
+        "./tests/pcap/wks.pcap",
+        false,
+        1,
+        RData::WKS,
+        (|x: &WKS, _| {
+            assert_eq!(&x.to_string(), "166.84.7.99 6 25 80");
+        })
+    );
+}
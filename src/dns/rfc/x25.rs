@@ -0,0 +1,45 @@
+use std::fmt;
+
+use type2network::FromNetworkOrder;
+use type2network_derive::FromNetwork;
+
+use serde::Serialize;
+
+use super::char_string::CharacterString;
+
+// X25 RR: https://datatracker.ietf.org/doc/html/rfc1183#section-3.1
+// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+// /                  PSDN-address                 /
+// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+#[derive(Debug, Default, FromNetwork, Serialize)]
+pub(super) struct X25(CharacterString);
+
+impl fmt::Display for X25 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns::rfc::{rdata::RData, response::Response},
+        dns::tests::get_packets,
+        test_rdata,
+    };
+
+    use type2network::FromNetworkOrder;
+
+    use super::X25;
+
+    test_rdata!(
+        rdata,
+        "./tests/pcap/x25.pcap",
+        false,
+        1,
+        RData::X25,
+        (|x: &X25, _| {
+            assert_eq!(&x.to_string(), "\"311061700956\"");
+        })
+    );
+}
@@ -0,0 +1,320 @@
+//! Zone files (RFC1035 section 5): $ORIGIN and $TTL directives, relative names, @,
+//! omitted owners, TTLs with units and records spanning several lines with parentheses.
+//! Records are read as ResourceRecord values, as if received from a server.
+use std::str::FromStr;
+
+use crate::dns::rfc::{
+    domain::DomainName, presentation::split_fields, qclass::QClass, qtype::QType, resource_record::ResourceRecord,
+};
+use crate::error::{Error, Result};
+
+// a record as found in the zone file, names being fully qualified
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneEntry {
+    pub name: String,
+    pub ttl: u32,
+    pub class: QClass,
+    pub qtype: QType,
+
+    // RDATA fields, quoted strings keep their quotes
+    pub rdata: Vec<String>,
+
+    // line of the zone file where the record starts
+    pub line: usize,
+}
+
+impl ZoneEntry {
+    // RDATA as displayed by dqy for received RRs, to compare both
+    pub fn rdata_string(&self) -> String {
+        match self.qtype {
+            // character strings are concatenated
+            QType::TXT | QType::SPF => self.rdata.iter().map(|s| unquote(s)).collect(),
+            QType::CAA if self.rdata.len() == 3 => {
+                format!("{} {} \"{}\"", self.rdata[0], self.rdata[1], unquote(&self.rdata[2]))
+            }
+            _ => self.rdata.join(" "),
+        }
+    }
+
+    pub fn to_resource_record(&self) -> Result<ResourceRecord> {
+        let name = DomainName::try_from(self.name.as_str())?;
+        ResourceRecord::from_presentation(name, self.qtype, self.class, self.ttl, &self.rdata.join(" ")).map_err(|e| {
+            match e {
+                Error::Presentation(msg) => Error::ZoneFile(msg, self.line),
+                e => e,
+            }
+        })
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    s.strip_prefix('"').and_then(|s| s.strip_suffix('"')).unwrap_or(s)
+}
+
+// position of domain names in the RDATA of those types, to qualify relative ones
+fn name_fields(qtype: &QType) -> &'static [usize] {
+    match qtype {
+        QType::NS | QType::CNAME | QType::PTR | QType::DNAME | QType::MB | QType::MG | QType::MR => &[0],
+        QType::MX | QType::KX | QType::AFSDB | QType::RT | QType::SVCB | QType::HTTPS => &[1],
+        QType::SOA | QType::RP | QType::MINFO => &[0, 1],
+        QType::SRV => &[3],
+        QType::NAPTR => &[5],
+        _ => &[],
+    }
+}
+
+// qualify a name relative to the origin
+fn absolute(name: &str, origin: &str) -> String {
+    let name = if name == "@" {
+        origin.to_string()
+    } else if name.ends_with('.') {
+        name.to_string()
+    } else if origin == "." {
+        format!("{}.", name)
+    } else {
+        format!("{}.{}", name, origin)
+    };
+    name.to_lowercase()
+}
+
+// TTL in seconds or with units: 1h30m, 2d, 1w
+fn parse_ttl(s: &str) -> Option<u32> {
+    if let Ok(ttl) = s.parse::<u32>() {
+        return Some(ttl);
+    }
+
+    let mut total = 0u32;
+    let mut value = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            value.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 604800,
+            _ => return None,
+        };
+        total = total.checked_add(value.parse::<u32>().ok()?.checked_mul(unit)?)?;
+        value.clear();
+    }
+
+    value.is_empty().then_some(total)
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// parse the zone file content. Names are relative to origin unless $ORIGIN is found
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn parse_zone(content: &str, origin: &str) -> Result<Vec<ZoneEntry>> {
+    let mut origin = absolute(origin, ".");
+    let mut default_ttl: Option<u32> = None;
+    let mut last_owner: Option<String> = None;
+    let mut last_class = QClass::IN;
+    let mut entries = Vec::new();
+
+    // logical lines: parentheses join physical lines
+    let mut pending: Option<(usize, bool, Vec<String>)> = None;
+    let mut logical = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let (lineno, blank_owner, mut fields_so_far) = pending
+            .take()
+            .unwrap_or_else(|| (i + 1, line.starts_with([' ', '\t']), Vec::new()));
+        fields_so_far.extend(split_fields(line));
+
+        let open = fields_so_far.iter().filter(|f| f.as_str() == "(").count();
+        let close = fields_so_far.iter().filter(|f| f.as_str() == ")").count();
+        if open > close {
+            pending = Some((lineno, blank_owner, fields_so_far));
+            continue;
+        }
+
+        let fields: Vec<String> = fields_so_far.into_iter().filter(|f| f != "(" && f != ")").collect();
+        if !fields.is_empty() {
+            logical.push((lineno, blank_owner, fields));
+        }
+    }
+    if let Some((lineno, _, _)) = pending {
+        return Err(Error::ZoneFile(String::from("unbalanced parenthesis"), lineno));
+    }
+
+    for (lineno, blank_owner, fields) in logical {
+        let err = |msg: String| Error::ZoneFile(msg, lineno);
+
+        // directives
+        match fields[0].to_uppercase().as_str() {
+            "$ORIGIN" => {
+                let name = fields
+                    .get(1)
+                    .ok_or_else(|| err(String::from("$ORIGIN without a name")))?;
+                origin = absolute(name, &origin);
+                continue;
+            }
+            "$TTL" => {
+                let ttl = fields.get(1).and_then(|ttl| parse_ttl(ttl));
+                default_ttl = Some(ttl.ok_or_else(|| err(String::from("invalid $TTL")))?);
+                continue;
+            }
+            d if d.starts_with('$') => return Err(err(format!("directive {} is not supported", d))),
+            _ => (),
+        }
+
+        let mut fields = fields.into_iter().peekable();
+
+        // owner is the previous one when the line starts with a blank
+        let name = if blank_owner {
+            last_owner
+                .clone()
+                .ok_or_else(|| err(String::from("no previous owner name")))?
+        } else {
+            absolute(&fields.next().unwrap_or_default(), &origin)
+        };
+
+        // TTL and class are optional and in any order
+        let mut ttl = None;
+        let mut class = None;
+        while let Some(field) = fields.peek() {
+            if let (None, Some(value)) = (ttl, parse_ttl(field)) {
+                ttl = Some(value);
+            } else if let (None, Ok(value)) = (class, QClass::from_str(&field.to_uppercase())) {
+                class = Some(value);
+            } else {
+                break;
+            }
+            fields.next();
+        }
+
+        let qtype = fields.next().ok_or_else(|| err(String::from("no record type")))?;
+        let qtype = QType::from_str(&qtype.to_uppercase()).map_err(err)?;
+
+        let mut rdata: Vec<String> = fields.collect();
+        for i in name_fields(&qtype) {
+            if let Some(field) = rdata.get_mut(*i) {
+                *field = absolute(field, &origin);
+            }
+        }
+        if matches!(qtype, QType::A | QType::AAAA) {
+            if let Some(ip) = rdata.first().and_then(|ip| ip.parse::<std::net::IpAddr>().ok()) {
+                rdata[0] = ip.to_string();
+            }
+        }
+
+        // without $TTL, the TTL of the previous record is used
+        let ttl = ttl
+            .or(default_ttl)
+            .or(entries.last().map(|e: &ZoneEntry| e.ttl))
+            .ok_or_else(|| err(String::from("no TTL and no $TTL")))?;
+        let class = class.unwrap_or(last_class);
+
+        last_owner = Some(name.clone());
+        last_class = class;
+        entries.push(ZoneEntry {
+            name,
+            ttl,
+            class,
+            qtype,
+            rdata,
+            line: lineno,
+        });
+    }
+
+    Ok(entries)
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// parse the zone file content into RRs
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn parse_zone_records(content: &str, origin: &str) -> Result<Vec<ResourceRecord>> {
+    parse_zone(content, origin)?
+        .iter()
+        .map(ZoneEntry::to_resource_record)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zone() {
+        let content = r#"
+$ORIGIN example.com.
+$TTL 1h
+@   IN  SOA ns1 hostmaster (
+            2026101501 ; serial
+            7200 3600 1209600 300)
+    IN  NS  ns1
+    IN  MX  10 mail.example.net.
+ns1 300 IN A 192.0.2.1
+www IN 2m AAAA 2001:db8:0:0::1
+txt TXT "v=spf1 " "-all"  ; comment
+caa CAA 0 issue "letsencrypt.org"
+"#;
+        let entries = parse_zone(content, "foo.org").unwrap();
+        assert_eq!(entries.len(), 7);
+
+        let soa = &entries[0];
+        assert_eq!(soa.name, "example.com.");
+        assert_eq!(soa.ttl, 3600);
+        assert_eq!(soa.qtype, QType::SOA);
+        assert_eq!(
+            soa.rdata_string(),
+            "ns1.example.com. hostmaster.example.com. 2026101501 7200 3600 1209600 300"
+        );
+
+        assert_eq!(entries[1].name, "example.com.");
+        assert_eq!(entries[1].rdata_string(), "ns1.example.com.");
+        assert_eq!(entries[2].rdata_string(), "10 mail.example.net.");
+        assert_eq!(entries[3].ttl, 300);
+        assert_eq!(entries[4].ttl, 120);
+        assert_eq!(entries[4].rdata_string(), "2001:db8::1");
+        assert_eq!(entries[5].rdata_string(), "v=spf1 -all");
+        assert_eq!(entries[6].rdata_string(), "0 issue \"letsencrypt.org\"");
+
+        // origin from the command line
+        let entries = parse_zone("www 60 A 192.0.2.2", "example.org").unwrap();
+        assert_eq!(entries[0].name, "www.example.org.");
+
+        assert!(parse_zone("www A 192.0.2.2", "example.org").is_err());
+        assert!(parse_zone("$INCLUDE other.zone", "example.org").is_err());
+        assert!(parse_zone("@ 60 SOA ns1 hostmaster ( 1 2 3 4", "example.org").is_err());
+    }
+
+    #[test]
+    fn ttl() {
+        assert_eq!(parse_ttl("3600"), Some(3600));
+        assert_eq!(parse_ttl("1h30m"), Some(5400));
+        assert_eq!(parse_ttl("1W"), Some(604800));
+        assert!(parse_ttl("IN").is_none());
+        assert!(parse_ttl("1h30").is_none());
+    }
+
+    #[test]
+    fn records() {
+        let content = r#"
+$TTL 300
+@       SOA  ns1 hostmaster 1 7200 3600 1209600 300
+        NS   ns1
+ns1     A    192.0.2.1
+_sip._tcp SRV 10 60 5060 sip
+txt     TXT  "hello" "world"
+other   TYPE65400 \# 2 abcd
+"#;
+        let rrs = parse_zone_records(content, "example.com.").unwrap();
+        assert_eq!(rrs.len(), 6);
+        assert_eq!(rrs[0].r#type, QType::SOA);
+        assert_eq!(rrs[1].rdata_string(), "ns1.example.com.");
+        assert_eq!(rrs[2].ip_address(), Some("192.0.2.1".parse().unwrap()));
+        assert_eq!(rrs[3].name.to_string(), "_sip._tcp.example.com.");
+        assert_eq!(rrs[3].rdata_string(), "10 60 5060 sip.example.com.");
+        assert_eq!(rrs[4].rdata_string(), "helloworld");
+
+        // line is reported
+        match parse_zone_records("www 60 A 192.0.2\n", "example.com.") {
+            Err(Error::ZoneFile(_, line)) => assert_eq!(line, 1),
+            _ => panic!("error expected"),
+        }
+    }
+}
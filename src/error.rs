@@ -6,9 +6,12 @@ use std::process::ExitCode;
 use std::time::Duration;
 use std::{fmt, io};
 
+#[cfg(feature = "doq")]
 use quinn::{ClosedStream, ConnectError, ConnectionError, ReadError, ReadExactError, WriteError};
 use thiserror::Error;
 
+use crate::i18n::{expand, fill, tr};
+
 /// A specific custom `Result` for all functions
 pub type Result<T> = std::result::Result<T, self::Error>;
 
@@ -39,6 +42,9 @@ pub enum Dns {
     // can't convert to an OpCode
     UnknowOpCode,
 
+    // can't convert to a ResponseCode
+    UnknowResponseCode,
+
     // can't convert to a packet type
     UnknowPacketType,
 
@@ -65,6 +71,28 @@ pub enum Dns {
 
     // SNI bad name
     InvalidSNI,
+
+    // JSON answer from a DoH JSON API can't be understood
+    InvalidJsonAnswer,
+
+    // query description in JSON is invalid
+    InvalidJsonQuery,
+
+    // client subnet is not a valid address/prefix
+    InvalidClientSubnet,
+
+    // no SOA record in the response
+    NoSoa,
+
+    // transport not compiled in: name of the missing cargo feature
+    NotBuiltWith(&'static str),
+
+    // AXFR messages don't make a zone: the reason why
+    ZoneTransfer(&'static str),
+
+    // --tls-min is above --tls-max
+    #[cfg(any(feature = "dot", feature = "doh"))]
+    InvalidTlsVersions,
     // Unknown domain when resolving gives no address
     //DomainNameNotFound(String),
 }
@@ -72,67 +100,85 @@ pub enum Dns {
 #[derive(Error, Debug)]
 pub enum Error {
     // I/O errors for opening files
-    #[error("cannot open file '{1}' ({0})")]
     OpenFile(#[source] io::Error, PathBuf),
 
-    #[error("error {0} when converting server name {1}")]
     ToSocketAddrs(#[source] io::Error, String),
 
-    #[error("write buffer error {0}")]
     Buffer(#[source] io::Error),
 
-    #[error("network {1:?} error ({0})")]
     Network(#[source] io::Error, Network),
 
-    // #[error("unable to build a socket address '{1}' ({0})")]
+    // ICMP port unreachable received on a connected UDP socket
+    IcmpRefused(String),
+
     // SocketAddr(#[source] io::Error, String),
-    #[error("unable to set network operations timeout to {1:?}ms ({0})")]
     Timeout(#[source] io::Error, Duration),
 
     // TLS errors
-    #[error("TLS error ({0})")]
+    #[cfg(feature = "dot")]
     Tls(#[source] rustls::Error),
 
     // QUIC errors
-    #[error("QUIC error ({0})")]
+    #[cfg(feature = "doq")]
     Quic(QuicError),
 
     // Reqwest errors
-    #[error("https error ({0})")]
+    #[cfg(feature = "doh")]
     Reqwest(#[source] reqwest::Error),
 
+    // DoH server answering 429 (Too Many Requests) or 503 (Service Unavailable), with the
+    // delay advised by Retry-After if any
+    #[cfg(feature = "doh")]
+    Busy(u16, Option<Duration>),
+
+    // HTTP/3 errors of the h3 crate
+    #[cfg(feature = "doh3")]
+    H3(String),
+
+    // Oblivious DoH errors: key configuration, encryption or relay
+    #[cfg(feature = "odoh")]
+    Odoh(String),
+
     // Reqwest errors
-    #[error("DNS error: {0}")]
     Dns(Dns),
 
     // IP address parsing errors
-    #[error("unable to parse IP '{0}'")]
     IPParse(#[source] AddrParseError, String),
 
     // Logger info
-    #[error("logger error '{0}'")]
     Logger(#[source] log::SetLoggerError),
 
     // Resolver errors
-    #[error("resolver error ({0:?})")]
     Resolver(#[source] resolving::Error),
 
     // Conversion from string to int error
-    #[error("error converting {0} to integer")]
     Conversion(#[source] ParseIntError, String),
 
     // runtime tokio error
-    #[error("run time tokio error {0}")]
+    #[cfg(feature = "doq")]
     Tokio(#[source] io::Error),
 
     // IDNA error
-    #[error("IDNA conversion error {0}")]
     IDNA(#[source] idna::Errors),
 
+    // operation cancelled by the user (Ctrl-C) or because the deadline is reached
+    Cancelled,
+
+    // TSIG key can't be found or is invalid
+    Tsig(String),
+
+    // zone file syntax error, with the line number
+    ZoneFile(String, usize),
+
+    // RDATA in presentation format (e.g. from a zone file) can't be read
+    Presentation(String),
+
+    // Lua errors
     #[cfg(feature = "mlua")]
     Lua(#[source] mlua::Error),
 }
 
+#[cfg(feature = "doq")]
 #[derive(Debug)]
 pub enum QuicError {
     Connect(ConnectError, String),
@@ -142,8 +188,105 @@ pub enum QuicError {
     ReadExact(ReadExactError),
     Write(WriteError),
     NoInitialCipherSuite,
+
+    // connection closed by the server with a DoQ error code and a reason
+    Closed(DoqError, String),
+
+    // stream reset or stopped by the server with a DoQ error code
+    Reset(DoqError),
+    Stopped(DoqError),
+
+    // stream finished before the whole message was received (e.g.: no data at all)
+    FinishedEarly(usize),
+
+    // length prefix of 0
+    EmptyMessage,
+
+    // DoQ messages must have an ID of 0 (RFC9250 section 4.2.1)
+    NonZeroId(u16),
+}
+
+// DoQ error codes (RFC9250 section 4.3)
+#[cfg(feature = "doq")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DoqError {
+    NoError,
+    InternalError,
+    ProtocolError,
+    RequestCancelled,
+    ExcessiveLoad,
+    UnspecifiedError,
+    Unknown(u64),
+}
+
+#[cfg(feature = "doq")]
+impl From<u64> for DoqError {
+    fn from(code: u64) -> Self {
+        match code {
+            0x0 => DoqError::NoError,
+            0x1 => DoqError::InternalError,
+            0x2 => DoqError::ProtocolError,
+            0x3 => DoqError::RequestCancelled,
+            0x4 => DoqError::ExcessiveLoad,
+            0x5 => DoqError::UnspecifiedError,
+            _ => DoqError::Unknown(code),
+        }
+    }
+}
+
+#[cfg(feature = "doq")]
+impl fmt::Display for DoqError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DoqError::NoError => f.write_str("DOQ_NO_ERROR"),
+            DoqError::InternalError => f.write_str("DOQ_INTERNAL_ERROR"),
+            DoqError::ProtocolError => f.write_str("DOQ_PROTOCOL_ERROR"),
+            DoqError::RequestCancelled => f.write_str("DOQ_REQUEST_CANCELLED"),
+            DoqError::ExcessiveLoad => f.write_str("DOQ_EXCESSIVE_LOAD"),
+            DoqError::UnspecifiedError => f.write_str("DOQ_UNSPECIFIED_ERROR"),
+            DoqError::Unknown(code) => write!(f, "unknown error code {:#x}", code),
+        }
+    }
+}
+
+// the DoQ error code is extracted from errors of a closed connection or stream
+#[cfg(feature = "doq")]
+impl From<ConnectionError> for QuicError {
+    fn from(e: ConnectionError) -> Self {
+        match e {
+            ConnectionError::ApplicationClosed(close) => QuicError::Closed(
+                DoqError::from(close.error_code.into_inner()),
+                String::from_utf8_lossy(&close.reason).into_owned(),
+            ),
+            e => QuicError::Connection(e),
+        }
+    }
+}
+
+#[cfg(feature = "doq")]
+impl From<ReadExactError> for QuicError {
+    fn from(e: ReadExactError) -> Self {
+        match e {
+            ReadExactError::FinishedEarly(n) => QuicError::FinishedEarly(n),
+            ReadExactError::ReadError(ReadError::Reset(code)) => QuicError::Reset(DoqError::from(code.into_inner())),
+            ReadExactError::ReadError(ReadError::ConnectionLost(e)) => QuicError::from(e),
+            e => QuicError::ReadExact(e),
+        }
+    }
+}
+
+#[cfg(feature = "doq")]
+impl From<WriteError> for QuicError {
+    fn from(e: WriteError) -> Self {
+        match e {
+            WriteError::Stopped(code) => QuicError::Stopped(DoqError::from(code.into_inner())),
+            WriteError::ConnectionLost(e) => QuicError::from(e),
+            e => QuicError::Write(e),
+        }
+    }
 }
 
+#[cfg(feature = "doq")]
 impl fmt::Display for QuicError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -156,6 +299,16 @@ impl fmt::Display for QuicError {
             QuicError::NoInitialCipherSuite => {
                 write!(f, "the initial cipher suite (AES-128-GCM-SHA256) is not available")
             }
+            QuicError::Closed(code, reason) if reason.is_empty() => {
+                write!(f, "connection closed by the server: {}", code)
+            }
+            QuicError::Closed(code, reason) => write!(f, "connection closed by the server: {} ({})", code, reason),
+            QuicError::Reset(code) => write!(f, "stream reset by the server: {}", code),
+            QuicError::Stopped(code) => write!(f, "stream stopped by the server: {}", code),
+            QuicError::FinishedEarly(0) => write!(f, "stream finished by the server without any response"),
+            QuicError::FinishedEarly(n) => write!(f, "stream finished by the server after {} bytes of the response", n),
+            QuicError::EmptyMessage => write!(f, "zero-length DNS message received"),
+            QuicError::NonZeroId(id) => write!(f, "response ID is {} instead of 0 (RFC9250)", id),
         }
     }
 }
@@ -167,42 +320,239 @@ impl From<Error> for ExitCode {
             Error::OpenFile(_, _) => ExitCode::from(1),
             Error::Buffer(_) => ExitCode::from(2),
             Error::Network(_, _) => ExitCode::from(3),
+            Error::IcmpRefused(_) => ExitCode::from(3),
             Error::Timeout(_, _) => ExitCode::from(4),
+            #[cfg(feature = "dot")]
             Error::Tls(_) => ExitCode::from(5),
+            #[cfg(feature = "doh")]
             Error::Reqwest(_) => ExitCode::from(6),
+            #[cfg(feature = "doh")]
+            Error::Busy(_, _) => ExitCode::from(6),
+            #[cfg(feature = "doh3")]
+            Error::H3(_) => ExitCode::from(6),
+            #[cfg(feature = "odoh")]
+            Error::Odoh(_) => ExitCode::from(6),
             Error::Dns(_) => ExitCode::from(7),
             Error::IPParse(_, _) => ExitCode::from(8),
             Error::Logger(_) => ExitCode::from(9),
             Error::Resolver(_) => ExitCode::from(10),
+            #[cfg(feature = "doq")]
             Error::Quic(_) => ExitCode::from(11),
             Error::Conversion(_, _) => ExitCode::from(12),
             Error::ToSocketAddrs(_, _) => ExitCode::from(13),
+            #[cfg(feature = "doq")]
             Error::Tokio(_) => ExitCode::from(14),
             Error::IDNA(_) => ExitCode::from(15),
+            Error::Cancelled => ExitCode::from(16),
+            Error::Tsig(_) => ExitCode::from(17),
+            Error::ZoneFile(_, _) => ExitCode::from(18),
+            Error::Presentation(_) => ExitCode::from(19),
             #[cfg(feature = "mlua")]
             Error::Lua(_) => ExitCode::from(10),
         }
     }
 }
 
-impl fmt::Display for Dns {
+// kind of failure, used to break down failures in the summaries of bulk and watch runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorClass {
+    Timeout,
+    Refused,
+    Network,
+    Tls,
+    Quic,
+    Http,
+    Busy,
+    Parse,
+    Cancelled,
+    Other,
+}
+
+impl fmt::Display for ErrorClass {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            //Dns::DomainNameNotFound(s) => f.write_str("domain name '{}' not found"),
-            Dns::DomainNameTooLong => f.write_str("domain name is longer than 255 bytes"),
-            Dns::DomainLabelTooLong => f.write_str("domain label is longer than 63 bytes"),
-            Dns::EmptyDomainName => f.write_str("trying to create a domain from an empty string"),
-            Dns::UnknowOpCode => f.write_str("opcode found in message was not recognized"),
-            Dns::UnknowPacketType => f.write_str("patcket type found in message was not recognized"),
-            Dns::CantSerialize => f.write_str("can't map DNS record to buffer"),
-            Dns::CantDeserialize => f.write_str("can't recognize DNS message"),
-            Dns::CantCreateDomainName => f.write_str("domain name can't be created from RR"),
-            Dns::CantCreateNSEC3Types => f.write_str("can't extract types from NSEC or NSEC3 RR"),
-            Dns::UnreachableResolvers => f.write_str("can't contact any resolver"),
-            Dns::CantCreateSocketAddress => f.write_str("can't create a socket address from input"),
-            Dns::ImpossibleToTrace => f.write_str("during tracing, an unexpected error occured"),
-            Dns::InvalidSNI => f.write_str("SNI DNS name is invalid"),
-            //Dns::ResponseError(rcode) => write!(f, "{rcode}"),
+            ErrorClass::Timeout => f.write_str("timeout"),
+            ErrorClass::Refused => f.write_str("refused"),
+            ErrorClass::Network => f.write_str("network"),
+            ErrorClass::Tls => f.write_str("tls"),
+            ErrorClass::Quic => f.write_str("quic"),
+            ErrorClass::Http => f.write_str("http"),
+            ErrorClass::Busy => f.write_str("busy"),
+            ErrorClass::Parse => f.write_str("parse"),
+            ErrorClass::Cancelled => f.write_str("cancelled"),
+            ErrorClass::Other => f.write_str("other"),
+        }
+    }
+}
+
+impl Error {
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Error::Timeout(_, _) => ErrorClass::Timeout,
+            Error::IcmpRefused(_) => ErrorClass::Refused,
+            Error::Network(e, _) | Error::ToSocketAddrs(e, _) => match e.kind() {
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => ErrorClass::Timeout,
+                io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionReset => ErrorClass::Refused,
+                _ => ErrorClass::Network,
+            },
+            #[cfg(feature = "dot")]
+            Error::Tls(_) => ErrorClass::Tls,
+            #[cfg(feature = "doq")]
+            Error::Quic(QuicError::Connection(ConnectionError::TimedOut)) => ErrorClass::Timeout,
+            #[cfg(feature = "doq")]
+            Error::Quic(_) => ErrorClass::Quic,
+            #[cfg(feature = "doh")]
+            Error::Reqwest(e) if e.is_timeout() => ErrorClass::Timeout,
+            #[cfg(feature = "doh")]
+            Error::Reqwest(_) => ErrorClass::Http,
+            #[cfg(feature = "doh3")]
+            Error::H3(_) => ErrorClass::Http,
+            #[cfg(feature = "odoh")]
+            Error::Odoh(_) => ErrorClass::Http,
+            #[cfg(feature = "doh")]
+            Error::Busy(_, _) => ErrorClass::Busy,
+            Error::Dns(Dns::UnreachableResolvers | Dns::CantCreateSocketAddress) => ErrorClass::Network,
+            Error::Dns(_) | Error::Buffer(_) | Error::Presentation(_) => ErrorClass::Parse,
+            Error::Cancelled => ErrorClass::Cancelled,
+            _ => ErrorClass::Other,
+        }
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // the English template of the message, with a {} for each argument: it's shown as is
+    // by Display and translated by localized()
+    //───────────────────────────────────────────────────────────────────────────────────
+    fn message(&self, localized: bool) -> (&'static str, Vec<String>) {
+        match self {
+            Error::OpenFile(e, path) => (
+                "cannot open file '{}' ({})",
+                vec![path.display().to_string(), e.to_string()],
+            ),
+            Error::ToSocketAddrs(e, s) => (
+                "error {} when converting server name {}",
+                vec![e.to_string(), s.clone()],
+            ),
+            Error::Buffer(e) => ("write buffer error {}", vec![e.to_string()]),
+            Error::Network(e, n) => ("network {} error ({})", vec![format!("{:?}", n), e.to_string()]),
+            Error::IcmpRefused(s) => ("connection refused (ICMP) by {}", vec![s.clone()]),
+            Error::Timeout(e, d) => (
+                "unable to set network operations timeout to {}ms ({})",
+                vec![format!("{:?}", d), e.to_string()],
+            ),
+            #[cfg(feature = "dot")]
+            Error::Tls(e) => ("TLS error ({})", vec![e.to_string()]),
+            #[cfg(feature = "doq")]
+            Error::Quic(e) => ("QUIC error ({})", vec![e.to_string()]),
+            #[cfg(feature = "doh")]
+            Error::Reqwest(e) => ("https error ({})", vec![e.to_string()]),
+
+            // the advised delay is what the user is after
+            #[cfg(feature = "doh")]
+            Error::Busy(status, Some(delay)) => (
+                "DoH server busy (HTTP status {}), retry after {}s",
+                vec![status.to_string(), delay.as_secs().to_string()],
+            ),
+            #[cfg(feature = "doh")]
+            Error::Busy(status, None) => (
+                "DoH server busy (HTTP status {}), no retry delay advised",
+                vec![status.to_string()],
+            ),
+            #[cfg(feature = "doh3")]
+            Error::H3(e) => ("HTTP/3 error ({})", vec![e.clone()]),
+            #[cfg(feature = "odoh")]
+            Error::Odoh(e) => ("ODoH error ({})", vec![e.clone()]),
+            Error::Dns(e) => (
+                "DNS error: {}",
+                vec![if localized { e.localized() } else { e.to_string() }],
+            ),
+            Error::IPParse(_, s) => ("unable to parse IP '{}'", vec![s.clone()]),
+            Error::Logger(e) => ("logger error '{}'", vec![e.to_string()]),
+            Error::Resolver(e) => ("resolver error ({})", vec![format!("{:?}", e)]),
+            Error::Conversion(_, s) => ("error converting {} to integer", vec![s.clone()]),
+            #[cfg(feature = "doq")]
+            Error::Tokio(e) => ("run time tokio error {}", vec![e.to_string()]),
+            Error::IDNA(e) => ("IDNA conversion error {}", vec![e.to_string()]),
+            Error::Cancelled => ("operation cancelled", vec![]),
+
+            // messages coming from outside dqy (TSIG keys, zone files, Lua) are kept as is
+            Error::Tsig(e) => ("TSIG error: {}", vec![e.clone()]),
+            Error::ZoneFile(e, line) => ("zone file error at line {}: {}", vec![line.to_string(), e.clone()]),
+            Error::Presentation(e) => ("invalid presentation format: {}", vec![e.clone()]),
+            #[cfg(feature = "mlua")]
+            Error::Lua(e) => ("Lua error ({})", vec![e.to_string()]),
+        }
+    }
+
+    // message in the language of --lang
+    pub fn localized(&self) -> String {
+        let (text, args) = self.message(true);
+        fill(text, &display(&args))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (text, args) = self.message(false);
+        f.write_str(&expand(text, &display(&args)))
+    }
+}
+
+fn display(args: &[String]) -> Vec<&dyn fmt::Display> {
+    args.iter().map(|arg| arg as &dyn fmt::Display).collect()
+}
+
+impl Dns {
+    // English message, with a {} placeholder for the argument if any
+    fn text(&self) -> &'static str {
+        match self {
+            //Dns::DomainNameNotFound(s) => "domain name '{}' not found",
+            Dns::DomainNameTooLong => "domain name is longer than 255 bytes",
+            Dns::DomainLabelTooLong => "domain label is longer than 63 bytes",
+            Dns::EmptyDomainName => "trying to create a domain from an empty string",
+            Dns::UnknowOpCode => "opcode found in message was not recognized",
+            Dns::UnknowResponseCode => "rcode found in message was not recognized",
+            Dns::UnknowPacketType => "patcket type found in message was not recognized",
+            Dns::CantSerialize => "can't map DNS record to buffer",
+            Dns::CantDeserialize => "can't recognize DNS message",
+            Dns::CantCreateDomainName => "domain name can't be created from RR",
+            Dns::CantCreateNSEC3Types => "can't extract types from NSEC or NSEC3 RR",
+            Dns::UnreachableResolvers => "can't contact any resolver",
+            Dns::CantCreateSocketAddress => "can't create a socket address from input",
+            Dns::ImpossibleToTrace => "during tracing, an unexpected error occured",
+            Dns::InvalidSNI => "SNI DNS name is invalid",
+            Dns::InvalidJsonAnswer => "JSON answer from DoH resolver is invalid",
+            Dns::InvalidJsonQuery => "JSON query description is invalid",
+            Dns::InvalidClientSubnet => "client subnet is invalid, expected ADDRESS/PREFIX",
+            Dns::NoSoa => "no SOA record found in the response",
+            Dns::ZoneTransfer(_) => "zone transfer failed: {}",
+            #[cfg(any(feature = "dot", feature = "doh"))]
+            Dns::InvalidTlsVersions => "--tls-min is above --tls-max",
+            Dns::NotBuiltWith(_) => "this transport is not available, dqy was built without the '{}' feature",
+            //Dns::ResponseError(rcode) => "{rcode}",
+        }
+    }
+
+    fn arg(&self) -> Option<&'static str> {
+        match self {
+            Dns::ZoneTransfer(arg) | Dns::NotBuiltWith(arg) => Some(arg),
+            _ => None,
+        }
+    }
+
+    // message in the user's language, for the print path only
+    pub fn localized(&self) -> String {
+        match self.arg() {
+            Some(arg) => fill(self.text(), &[&arg]),
+            None => tr(self.text()).to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Dns {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.arg() {
+            Some(arg) => f.write_str(&expand(self.text(), &[&arg])),
+            None => f.write_str(self.text()),
         }
     }
 }
@@ -236,3 +586,37 @@ impl fmt::Display for Dns {
 
 // #[cfg(feature = "mlua")]
 // ErrFrom!(mlua::Error, Error::Lua);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dns_display() {
+        assert_eq!(Dns::NoSoa.to_string(), "no SOA record found in the response");
+        assert_eq!(Dns::ZoneTransfer("no SOA").to_string(), "zone transfer failed: no SOA");
+        assert_eq!(
+            Error::Dns(Dns::NoSoa).to_string(),
+            "DNS error: no SOA record found in the response"
+        );
+        assert_eq!(
+            Error::ZoneFile(String::from("bad TTL"), 3).to_string(),
+            "zone file error at line 3: bad TTL"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "doq")]
+    fn doq_codes() {
+        assert_eq!(DoqError::from(2), DoqError::ProtocolError);
+        assert_eq!(DoqError::from(0xd098ea5e), DoqError::Unknown(0xd098ea5e));
+        assert_eq!(
+            QuicError::Closed(DoqError::from(4), String::from("busy")).to_string(),
+            "connection closed by the server: DOQ_EXCESSIVE_LOAD (busy)"
+        );
+        assert_eq!(
+            QuicError::FinishedEarly(0).to_string(),
+            "stream finished by the server without any response"
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! Server robustness testing (--fuzz-server N): N random but valid queries are sent to the
+//! server, with random names under the zone given, random types, header flags and EDNS
+//! options. Rcodes and failures are counted, and anomalies are listed with the query
+//! which caused them: malformed responses, ID or question not echoed, FORMERR to a valid
+//! query, or the server not answering anymore.
+use std::collections::BTreeMap;
+use std::fmt;
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::args::CliOptions;
+use crate::dns::rfc::{domain::DomainName, qtype::QType, response_code::ResponseCode};
+use crate::error::{Error, ErrorClass, Result};
+use crate::get_messages;
+use crate::i18n::fill;
+use crate::pacing::Pacer;
+use crate::show::header_section;
+use crate::stats::{ExchangeStats, Failures};
+
+// types picked for the queries
+const TYPES: &[QType] = &[
+    QType::A,
+    QType::AAAA,
+    QType::NS,
+    QType::SOA,
+    QType::MX,
+    QType::TXT,
+    QType::CNAME,
+    QType::PTR,
+    QType::SRV,
+    QType::NAPTR,
+    QType::CAA,
+    QType::DS,
+    QType::DNSKEY,
+    QType::TLSA,
+    QType::SVCB,
+    QType::HTTPS,
+    QType::ANY,
+];
+
+// letters, digits and hyphen (RFC952)
+const LDH: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789-";
+
+// advertised UDP payload sizes
+const BUFSIZES: &[u16] = &[512, 1232, 1400, 4096, 65535];
+
+// one random query
+#[derive(Debug, Clone, PartialEq)]
+struct Case {
+    qname: String,
+    qtype: QType,
+    rd: bool,
+    cd: bool,
+    ad: bool,
+
+    // None if sent without OPT
+    bufsize: Option<u16>,
+    dnssec: bool,
+    nsid: bool,
+    padding: Option<u16>,
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.qtype, self.qname)?;
+        for (flag, set) in [("rd", self.rd), ("cd", self.cd), ("ad", self.ad)] {
+            if set {
+                write!(f, " +{}", flag)?;
+            }
+        }
+        match self.bufsize {
+            None => write!(f, " +noedns")?,
+            Some(bufsize) => write!(f, " +bufsize={}", bufsize)?,
+        }
+        if self.dnssec {
+            write!(f, " +dnssec")?;
+        }
+        if self.nsid {
+            write!(f, " +nsid")?;
+        }
+        if let Some(len) = self.padding {
+            write!(f, " +padding={}", len)?;
+        }
+        Ok(())
+    }
+}
+
+// a label of 1 to 20 LDH characters, not starting or ending with an hyphen
+fn random_label<R: Rng>(rng: &mut R) -> String {
+    let len = rng.gen_range(1..=20);
+    (0..len)
+        .map(|i| {
+            let edge = i == 0 || i == len - 1;
+            let letters = if edge { &LDH[..LDH.len() - 1] } else { LDH };
+            *letters.choose(rng).unwrap() as char
+        })
+        .collect()
+}
+
+impl Case {
+    fn random<R: Rng>(zone: &DomainName, rng: &mut R) -> Self {
+        let labels: Vec<_> = (0..rng.gen_range(1..=3)).map(|_| random_label(rng)).collect();
+        let edns = rng.gen_ratio(7, 8);
+
+        Self {
+            qname: format!("{}.{}", labels.join("."), zone).replace("..", "."),
+            qtype: *TYPES.choose(rng).unwrap(),
+            rd: rng.gen(),
+            cd: rng.gen(),
+            ad: rng.gen(),
+            bufsize: edns.then(|| *BUFSIZES.choose(rng).unwrap()),
+            dnssec: edns && rng.gen(),
+            nsid: edns && rng.gen(),
+            padding: (edns && rng.gen()).then(|| rng.gen_range(0..=128)),
+        }
+    }
+
+    // the query options for this case
+    fn options(&self, options: &CliOptions) -> Result<CliOptions> {
+        let mut options = options.clone();
+        options.protocol.domain_name = DomainName::try_from(self.qname.as_str())?;
+        options.protocol.qtype = vec![self.qtype];
+        options.flags.recursion_desired = self.rd;
+        options.flags.checking_disabled = self.cd;
+        options.flags.authentic_data = self.ad;
+        options.edns.no_opt = self.bufsize.is_none();
+        options.edns.no_fallback = true;
+        options.transport.bufsize = self.bufsize.unwrap_or(options.transport.bufsize);
+        options.edns.dnssec = self.dnssec;
+        options.edns.nsid = self.nsid;
+        options.edns.padding = self.padding;
+        Ok(options)
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// send N random queries under the zone and print out what the server did
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn fuzz_server(options: &CliOptions, count: usize) -> Result<()> {
+    let zone = &options.protocol.domain_name;
+    println!(
+        "sending {} random queries under {} to {}",
+        count, zone, options.transport.endpoint.server_name
+    );
+
+    let mut rng = rand::thread_rng();
+    let mut pacer = Pacer::new(&options.dump);
+    let mut stats = ExchangeStats::default();
+    let mut failures = Failures::default();
+    let mut anomalies: BTreeMap<usize, (Case, String)> = BTreeMap::new();
+
+    for i in 1..=count {
+        if options.transport.cancel.is_cancelled() {
+            break;
+        }
+        pacer.wait();
+
+        let case = Case::random(zone, &mut rng);
+        let messages = match get_messages(None, &case.options(options)?) {
+            Ok(messages) => messages,
+            Err(_) if options.transport.cancel.is_cancelled() => break,
+            Err(e) => {
+                failures.error(&e);
+                if e.class() == ErrorClass::Parse {
+                    anomalies.insert(i, (case, fill("malformed response ({})", &[&e.localized()])));
+                    continue;
+                }
+
+                // the server might have crashed: a plain query tells
+                if alive(options) {
+                    continue;
+                }
+                anomalies.insert(i, (case, fill("{}, server not answering anymore", &[&e.localized()])));
+                break;
+            }
+        };
+        let Some(msg) = messages.first() else {
+            continue;
+        };
+        stats.add(msg);
+
+        let resp = msg.response();
+        if !resp.header.flags.is_response() {
+            anomalies.insert(i, (case, String::from("QR flag not set in the response")));
+        } else if resp.id() != msg.query.header.id || resp.question != msg.query.question {
+            anomalies.insert(i, (case, String::from("ID or question not echoed")));
+        } else if resp.rcode() == ResponseCode::FormErr {
+            anomalies.insert(i, (case, String::from("FORMERR to a valid query")));
+        }
+    }
+
+    println!("\n{}", stats);
+    println!("failures: {}", failures);
+
+    println!("\n{}", header_section("ANOMALIES", None));
+    if anomalies.is_empty() {
+        println!("none");
+    }
+    for (i, (case, anomaly)) in anomalies {
+        println!("#{:<6} {:<60} {}", i, case.to_string(), anomaly);
+    }
+
+    Ok(())
+}
+
+// SOA of the zone with the options given
+fn alive(options: &CliOptions) -> bool {
+    let mut options = options.clone();
+    options.protocol.qtype = vec![QType::SOA];
+    matches!(get_messages(None, &options), Ok(_) | Err(Error::Cancelled))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn cases() {
+        let mut rng = StdRng::seed_from_u64(2026);
+        let zone = DomainName::try_from("example.com.").unwrap();
+
+        for _ in 0..100 {
+            let case = Case::random(&zone, &mut rng);
+            assert!(case.qname.ends_with(".example.com."));
+            assert!(!case.qname.contains(".-") && !case.qname.contains("-."));
+            assert!(DomainName::try_from(case.qname.as_str()).is_ok());
+
+            let options = case.options(&CliOptions::default()).unwrap();
+            assert_eq!(options.protocol.qtype, vec![case.qtype]);
+            assert_eq!(options.edns.no_opt, case.bufsize.is_none());
+        }
+
+        // under the root
+        let case = Case::random(&DomainName::try_from(".").unwrap(), &mut rng);
+        assert!(!case.qname.contains(".."));
+    }
+}
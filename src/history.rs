@@ -0,0 +1,212 @@
+//! Per-resolver statistics kept across invocations (--stats-db): each exchange is appended
+//! as a JSON line to the database, and --stats-report summarizes latency and failures of
+//! each resolver, comparing the last 24 hours with the previous ones.
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::args::CliOptions;
+use crate::dns::message::MessageList;
+use crate::dns::rfc::response_code::ResponseCode;
+use crate::error::{Error, Result};
+use crate::i18n::fill;
+use crate::show::header_section;
+
+// a line of the database
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Sample {
+    // RFC3339 timestamp
+    pub timestamp: String,
+    pub server: String,
+    pub transport: String,
+
+    // latency in ms, none if the query failed
+    pub elapsed: Option<u64>,
+}
+
+// name under which the resolver is recorded
+fn server_name(options: &CliOptions, messages: Option<&MessageList>) -> String {
+    let endpoint = &options.transport.endpoint;
+    if !endpoint.server_name.is_empty() {
+        return endpoint.server_name.clone();
+    }
+
+    // host resolvers: the one which answered
+    messages
+        .and_then(|list| list.first())
+        .and_then(|msg| msg.netinfo.peer)
+        .map(|peer| peer.ip().to_string())
+        .unwrap_or_else(|| String::from("host resolvers"))
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// append the outcome of the queries to the database. SERVFAIL and REFUSED are failures
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn record(path: &Path, options: &CliOptions, result: &Result<MessageList>) -> Result<()> {
+    let mut f = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| Error::OpenFile(e, path.to_path_buf()))?;
+
+    let timestamp = Utc::now().to_rfc3339();
+    let transport = options.transport.transport_mode.to_string();
+
+    let samples: Vec<Sample> = match result {
+        Ok(messages) => {
+            let server = server_name(options, Some(messages));
+            messages
+                .iter()
+                .map(|msg| Sample {
+                    timestamp: timestamp.clone(),
+                    server: server.clone(),
+                    transport: transport.clone(),
+                    elapsed: match msg.response().rcode() {
+                        ResponseCode::ServFail | ResponseCode::Refused => None,
+                        _ => Some(msg.elapsed.as_millis() as u64),
+                    },
+                })
+                .collect()
+        }
+        Err(_) => vec![Sample {
+            timestamp,
+            server: server_name(options, None),
+            transport,
+            elapsed: None,
+        }],
+    };
+
+    for sample in samples {
+        writeln!(f, "{}", serde_json::to_string(&sample).unwrap()).map_err(Error::Buffer)?;
+    }
+
+    Ok(())
+}
+
+// what is known of a resolver: latencies and failures, recent or older
+#[derive(Debug, Default)]
+struct Trend {
+    queries: usize,
+    failures: usize,
+    latency: (u64, u64),
+    recent: (u64, u64),
+    older: (u64, u64),
+}
+
+// average of (total, count)
+fn average(sum: (u64, u64)) -> Option<u64> {
+    (sum.1 > 0).then(|| sum.0 / sum.1)
+}
+
+impl Trend {
+    fn add(&mut self, sample: &Sample, recent: bool) {
+        self.queries += 1;
+        match sample.elapsed {
+            Some(ms) => {
+                let bucket = if recent { &mut self.recent } else { &mut self.older };
+                for sum in [&mut self.latency, bucket] {
+                    sum.0 += ms;
+                    sum.1 += 1;
+                }
+            }
+            None => self.failures += 1,
+        }
+    }
+
+    // change in % of the average latency of the last 24 hours
+    fn change(&self) -> Option<f64> {
+        let (recent, older) = (average(self.recent)?, average(self.older)?);
+        (older > 0).then(|| 100.0 * (recent as f64 - older as f64) / older as f64)
+    }
+}
+
+// group samples per resolver
+fn trends(samples: &[Sample], now: DateTime<Utc>) -> BTreeMap<&str, Trend> {
+    let since = now - TimeDelta::hours(24);
+    let mut trends: BTreeMap<&str, Trend> = BTreeMap::new();
+
+    for sample in samples {
+        let recent = DateTime::parse_from_rfc3339(&sample.timestamp).is_ok_and(|ts| ts.with_timezone(&Utc) >= since);
+        trends.entry(&sample.server).or_default().add(sample, recent);
+    }
+
+    trends
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// summary of the database (--stats-report)
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn report(path: &Path) -> Result<()> {
+    let file = File::open(path).map_err(|e| Error::OpenFile(e, path.to_path_buf()))?;
+
+    let mut samples = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(Error::Buffer)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Sample>(&line) {
+            Ok(sample) => samples.push(sample),
+            Err(e) => eprintln!("{}", fill("line {}: invalid sample ({})", &[&(i + 1), &e])),
+        }
+    }
+
+    let ms = |v: Option<u64>| v.map_or(String::from("-"), |v| v.to_string());
+
+    println!("{}", header_section("RESOLVERS HISTORY", None));
+    println!(
+        "{:<40} {:>8} {:>9} {:>8} {:>8} {:>8}",
+        "resolver", "queries", "failures", "avg", "24h", "trend"
+    );
+    for (server, trend) in trends(&samples, Utc::now()) {
+        println!(
+            "{:<40} {:>8} {:>8.1}% {:>8} {:>8} {:>8}",
+            server,
+            trend.queries,
+            100.0 * trend.failures as f64 / trend.queries as f64,
+            ms(average(trend.latency)),
+            ms(average(trend.recent)),
+            trend.change().map_or(String::from("-"), |c| format!("{:+.0}%", c))
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trend() {
+        let now = Utc::now();
+        let sample = |server: &str, hours: i64, elapsed: Option<u64>| Sample {
+            timestamp: (now - TimeDelta::hours(hours)).to_rfc3339(),
+            server: server.to_string(),
+            transport: String::from("udp"),
+            elapsed,
+        };
+        let samples = vec![
+            sample("1.1.1.1", 48, Some(10)),
+            sample("1.1.1.1", 30, Some(30)),
+            sample("1.1.1.1", 1, Some(30)),
+            sample("1.1.1.1", 1, None),
+            sample("9.9.9.9", 2, Some(12)),
+        ];
+
+        let trends = trends(&samples, now);
+        let one = &trends["1.1.1.1"];
+        assert_eq!(one.queries, 4);
+        assert_eq!(one.failures, 1);
+        assert_eq!(average(one.latency), Some(23));
+        assert_eq!(average(one.recent), Some(30));
+        assert_eq!(one.change(), Some(50.0));
+
+        // no older samples, no trend
+        assert_eq!(trends["9.9.9.9"].change(), None);
+    }
+}
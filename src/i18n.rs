@@ -0,0 +1,373 @@
+//! Translation of user-facing messages: section headers, errors and warnings. Messages are
+//! written in English in the code, and looked up in the catalog of the language chosen
+//! with --lang, or taken from the locale (LC_ALL, LC_MESSAGES or LANG). Messages not found
+//! in the catalog are printed in English.
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Lang {
+    En,
+    Fr,
+}
+
+impl FromStr for Lang {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Lang::En),
+            "fr" => Ok(Lang::Fr),
+            _ => Err(format!("unsupported language '{}'", s)),
+        }
+    }
+}
+
+impl Lang {
+    // language of a POSIX locale like fr_FR.UTF-8. C, POSIX or unsupported languages are English
+    pub fn from_locale(locale: &str) -> Self {
+        let lang = locale.split(['_', '-', '.', '@']).next().unwrap_or_default();
+        Lang::from_str(&lang.to_lowercase()).unwrap_or(Lang::En)
+    }
+
+    // the first locale variable set wins, as for gettext
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .map(|value| Lang::from_locale(&value))
+            .unwrap_or(Lang::En)
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// --lang: it's a global setting because messages are printed deep inside Display impls
+//───────────────────────────────────────────────────────────────────────────────────
+static LANG: AtomicU8 = AtomicU8::new(Lang::En as u8);
+
+pub fn set_lang(lang: Lang) {
+    LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+pub fn lang() -> Lang {
+    match LANG.load(Ordering::Relaxed) {
+        1 => Lang::Fr,
+        _ => Lang::En,
+    }
+}
+
+// English message and its French translation. {} are replaced by fill()
+const FR: &[(&str, &str)] = &[
+    // section headers
+    ("ANSWER", "RÉPONSE"),
+    ("AUTHORATIVE", "AUTORITÉ"),
+    ("ADDITIONAL", "ADDITIONNELLE"),
+    ("QUERY", "REQUÊTE"),
+    ("Response HEADER", "EN-TÊTE de la réponse"),
+    ("STATS", "STATISTIQUES"),
+    ("WARNINGS", "AVERTISSEMENTS"),
+    ("ANALYSIS", "ANALYSE"),
+    ("SUMMARY", "RÉSUMÉ"),
+    ("RACE", "COURSE"),
+    ("DELEGATION WARNINGS", "AVERTISSEMENTS DE DÉLÉGATION"),
+    ("GLUE CHECK", "VÉRIFICATION DES GLUES"),
+    ("DELEGATION PATHS", "CHEMINS DE DÉLÉGATION"),
+    ("RESOLVERS HISTORY", "HISTORIQUE DES RÉSOLVEURS"),
+    ("PRIVACY REPORT", "RAPPORT DE CONFIDENTIALITÉ"),
+    ("RESOLVERS", "RÉSOLVEURS"),
+    ("ALIAS CHAIN", "CHAÎNE D'ALIAS"),
+    ("SERVICE", "SERVICE"),
+    ("LOOKUPS", "RECHERCHES"),
+    ("CONNECTION", "CONNEXION"),
+    ("ENDPOINTS", "POINTS D'ACCÈS"),
+    ("ANOMALIES", "ANOMALIES"),
+    ("WIRE STATS", "STATISTIQUES DU MESSAGE"),
+    // errors
+    ("cannot open file '{}' ({})", "impossible d'ouvrir le fichier '{}' ({})"),
+    (
+        "error {} when converting server name {}",
+        "erreur {} lors de la conversion du nom de serveur {}",
+    ),
+    ("write buffer error {}", "erreur d'écriture dans le tampon {}"),
+    ("network {} error ({})", "erreur réseau {} ({})"),
+    ("connection refused (ICMP) by {}", "connexion refusée (ICMP) par {}"),
+    (
+        "unable to set network operations timeout to {}ms ({})",
+        "impossible de fixer le délai des opérations réseau à {}ms ({})",
+    ),
+    ("TLS error ({})", "erreur TLS ({})"),
+    ("QUIC error ({})", "erreur QUIC ({})"),
+    ("https error ({})", "erreur https ({})"),
+    ("HTTP/3 error ({})", "erreur HTTP/3 ({})"),
+    ("ODoH error ({})", "erreur ODoH ({})"),
+    (
+        "DoH server busy (HTTP status {}), retry after {}s",
+        "serveur DoH surchargé (statut HTTP {}), réessayer dans {}s",
+    ),
+    (
+        "DoH server busy (HTTP status {}), no retry delay advised",
+        "serveur DoH surchargé (statut HTTP {}), aucun délai de nouvel essai indiqué",
+    ),
+    ("DNS error: {}", "erreur DNS : {}"),
+    ("unable to parse IP '{}'", "adresse IP '{}' invalide"),
+    ("logger error '{}'", "erreur de journalisation '{}'"),
+    ("resolver error ({})", "erreur de résolveur ({})"),
+    ("error converting {} to integer", "impossible de convertir {} en entier"),
+    ("run time tokio error {}", "erreur d'exécution tokio {}"),
+    ("IDNA conversion error {}", "erreur de conversion IDNA {}"),
+    ("operation cancelled", "opération annulée"),
+    (
+        "domain name is longer than 255 bytes",
+        "le nom de domaine dépasse 255 octets",
+    ),
+    (
+        "domain label is longer than 63 bytes",
+        "un label du nom de domaine dépasse 63 octets",
+    ),
+    ("trying to create a domain from an empty string", "nom de domaine vide"),
+    (
+        "opcode found in message was not recognized",
+        "opcode du message inconnu",
+    ),
+    ("rcode found in message was not recognized", "rcode du message inconnu"),
+    (
+        "patcket type found in message was not recognized",
+        "type de paquet du message inconnu",
+    ),
+    (
+        "can't map DNS record to buffer",
+        "impossible d'écrire l'enregistrement DNS dans le tampon",
+    ),
+    ("can't recognize DNS message", "message DNS incompréhensible"),
+    (
+        "domain name can't be created from RR",
+        "impossible d'extraire le nom de domaine du RR",
+    ),
+    (
+        "can't extract types from NSEC or NSEC3 RR",
+        "impossible d'extraire les types du RR NSEC ou NSEC3",
+    ),
+    ("can't contact any resolver", "aucun résolveur ne répond"),
+    (
+        "can't create a socket address from input",
+        "impossible de créer une adresse de socket",
+    ),
+    (
+        "during tracing, an unexpected error occured",
+        "erreur inattendue pendant le traçage",
+    ),
+    ("SNI DNS name is invalid", "le nom SNI est invalide"),
+    (
+        "JSON answer from DoH resolver is invalid",
+        "la réponse JSON du résolveur DoH est invalide",
+    ),
+    (
+        "JSON query description is invalid",
+        "la description JSON de la requête est invalide",
+    ),
+    (
+        "client subnet is invalid, expected ADDRESS/PREFIX",
+        "sous-réseau client invalide, ADRESSE/PRÉFIXE attendu",
+    ),
+    (
+        "no SOA record found in the response",
+        "aucun enregistrement SOA dans la réponse",
+    ),
+    ("zone transfer failed: {}", "échec du transfert de zone : {}"),
+    ("--tls-min is above --tls-max", "--tls-min est supérieur à --tls-max"),
+    (
+        "this transport is not available, dqy was built without the '{}' feature",
+        "ce transport n'est pas disponible, dqy a été compilé sans la fonctionnalité '{}'",
+    ),
+    // warnings and hints
+    (
+        "EDNS rejected by the server (FORMERR or NOTIMP), the query was resent without OPT",
+        "EDNS refusé par le serveur (FORMERR ou NOTIMP), la requête a été renvoyée sans OPT",
+    ),
+    (
+        "response truncated over UDP, the query was resent over TCP",
+        "réponse tronquée en UDP, la requête a été renvoyée en TCP",
+    ),
+    (
+        "no host resolver found, the fallback resolvers {} were used",
+        "aucun résolveur trouvé sur la machine, les résolveurs de secours {} ont été utilisés",
+    ),
+    (
+        "DoH response has an unexpected content-type (HTTP status {})",
+        "la réponse DoH a un content-type inattendu (statut HTTP {})",
+    ),
+    (
+        "{} RRs of the DoH JSON answer can't be converted and are missing from the response",
+        "{} RRs de la réponse DoH JSON ne peuvent pas être convertis et manquent dans la réponse",
+    ),
+    (
+        "STALE answer served from cache ({}), received {}s ago with a {}s TTL",
+        "réponse PÉRIMÉE servie depuis le cache ({}), reçue il y a {}s avec un TTL de {}s",
+    ),
+    (
+        "connection silently dropped by the resolver, reconnected",
+        "connexion fermée sans préavis par le résolveur, reconnexion effectuée",
+    ),
+    // errors of the modes running several queries
+    ("error", "erreur"),
+    ("resolver {}: {}", "résolveur {} : {}"),
+    ("source {}: {}", "source {} : {}"),
+    ("subnet {}: {}", "sous-réseau {} : {}"),
+    ("line {}: invalid subnet {} ({})", "ligne {} : sous-réseau {} invalide ({})"),
+    ("line {}: invalid session record ({})", "ligne {} : enregistrement de session invalide ({})"),
+    ("line {}: unknown qtype {}", "ligne {} : qtype {} inconnu"),
+    ("line {}: invalid qname {}", "ligne {} : qname {} invalide"),
+    ("line {}: invalid sample ({})", "ligne {} : échantillon invalide ({})"),
+    ("{} error: {}", "{} erreur : {}"),
+    ("{} keepalive failed: {}", "{} échec du keepalive : {}"),
+    ("malformed response ({})", "réponse malformée ({})"),
+    ("{}, server not answering anymore", "{}, le serveur ne répond plus"),
+    ("report failed", "échec du rapport"),
+    (
+        "{} {}: no Report-Channel option in response",
+        "{} {} : pas d'option Report-Channel dans la réponse",
+    ),
+    (
+        "{} {}: no extended DNS error to report",
+        "{} {} : aucune erreur DNS étendue à signaler",
+    ),
+    (
+        "{} {}: report query name is too long",
+        "{} {} : le nom de la requête de rapport est trop long",
+    ),
+    (
+        "interrupted: {} of {} queries completed, {} not sent or failed",
+        "interrompu : {} requêtes sur {} terminées, {} non envoyées ou en échec",
+    ),
+    // privacy report
+    (
+        "transport: {} is encrypted, queries and responses are only visible to the resolver",
+        "transport : {} est chiffré, requêtes et réponses ne sont visibles que du résolveur",
+    ),
+    (
+        "TLS: the resolver name is sent in clear in the SNI, which reveals which resolver is used",
+        "TLS : le nom du résolveur est envoyé en clair dans le SNI, ce qui révèle le résolveur utilisé",
+    ),
+    (
+        "  suggestion: use --ech if the resolver supports Encrypted Client Hello",
+        "  suggestion : utiliser --ech si le résolveur accepte Encrypted Client Hello",
+    ),
+    (
+        "transport: {} is plaintext, query names and answers are visible to anyone on the path",
+        "transport : {} est en clair, noms demandés et réponses sont visibles de tous sur le chemin",
+    ),
+    (
+        "  suggestion: use an encrypted transport (--tls, --https or --doq)",
+        "  suggestion : utiliser un transport chiffré (--tls, --https ou --doq)",
+    ),
+    (
+        "padding: not used, the size of encrypted messages may reveal the query name",
+        "padding : non utilisé, la taille des messages chiffrés peut révéler le nom demandé",
+    ),
+    (
+        "  suggestion: use --padding=128 as recommended by RFC8467",
+        "  suggestion : utiliser --padding=128 comme le recommande la RFC8467",
+    ),
+    (
+        "padding: useless with a plaintext transport, messages are readable anyway",
+        "padding : inutile avec un transport en clair, les messages sont lisibles de toute façon",
+    ),
+    (
+        "ECS: client subnet {} is sent to the resolver and forwarded to authoritative servers",
+        "ECS : le sous-réseau client {} est envoyé au résolveur et transmis aux serveurs faisant autorité",
+    ),
+    (
+        "  suggestion: don't send more than /{} (RFC7871), or a /0 prefix to opt out",
+        "  suggestion : ne pas envoyer plus de /{} (RFC7871), ou un préfixe /0 pour refuser ECS",
+    ),
+    (
+        "cookie: a fixed client cookie allows servers to link queries sent from different networks",
+        "cookie : un cookie client fixe permet aux serveurs de relier des requêtes envoyées depuis des réseaux différents",
+    ),
+    (
+        "  suggestion: use --cookie without value to get a client cookie derived from the client and server addresses",
+        "  suggestion : utiliser --cookie sans valeur pour un cookie client dérivé des adresses du client et du serveur",
+    ),
+    (
+        "QNAME minimization: not done, the full query name is sent to root and TLD servers",
+        "minimisation QNAME : non faite, le nom complet est envoyé aux serveurs racine et TLD",
+    ),
+    (
+        "QNAME: the full query name is sent to the resolver, which may or may not do QNAME minimization (RFC9156)",
+        "QNAME : le nom complet est envoyé au résolveur, qui fait ou non de la minimisation QNAME (RFC9156)",
+    ),
+];
+
+//───────────────────────────────────────────────────────────────────────────────────
+// message in the current language, or the English one if not translated
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn tr(text: &str) -> &str {
+    translate(lang(), text)
+}
+
+fn translate(lang: Lang, text: &str) -> &str {
+    let catalog = match lang {
+        Lang::En => return text,
+        Lang::Fr => FR,
+    };
+    catalog
+        .iter()
+        .find(|(en, _)| *en == text)
+        .map(|(_, translated)| *translated)
+        .unwrap_or(text)
+}
+
+// replace each {} of the translated message by the next argument
+pub fn fill(text: &str, args: &[&dyn fmt::Display]) -> String {
+    expand(tr(text), args)
+}
+
+// replace each {} of the message by the next argument, without translation
+pub fn expand(text: &str, args: &[&dyn fmt::Display]) -> String {
+    let mut parts = text.split("{}");
+    let mut args = args.iter();
+    let mut s = parts.next().unwrap_or_default().to_string();
+
+    for part in parts {
+        if let Some(arg) = args.next() {
+            s.push_str(&arg.to_string());
+        }
+        s.push_str(part);
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locale() {
+        assert_eq!(Lang::from_locale("fr_FR.UTF-8"), Lang::Fr);
+        assert_eq!(Lang::from_locale("fr"), Lang::Fr);
+        assert_eq!(Lang::from_locale("en_US.UTF-8"), Lang::En);
+        assert_eq!(Lang::from_locale("C"), Lang::En);
+        assert_eq!(Lang::from_locale("de_DE"), Lang::En);
+        assert!(Lang::from_str("de").is_err());
+    }
+
+    #[test]
+    fn catalog() {
+        // all placeholders are kept in translations
+        for (en, fr) in FR {
+            assert_eq!(en.matches("{}").count(), fr.matches("{}").count(), "{}", en);
+        }
+
+        assert_eq!(translate(Lang::En, "ANSWER"), "ANSWER");
+        assert_eq!(translate(Lang::Fr, "ANSWER"), "RÉPONSE");
+        assert_eq!(translate(Lang::Fr, "not in the catalog"), "not in the catalog");
+        assert_eq!(
+            fill("zone transfer failed: {}", &[&"no SOA"]),
+            "zone transfer failed: no SOA"
+        );
+        assert_eq!(fill("no placeholder", &[&1]), "no placeholder");
+    }
+}
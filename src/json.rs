@@ -0,0 +1,447 @@
+// Alternate JSON outputs.
+//
+// Stable JSON schema selected with --json-version. The native JSON output (--json without
+// --json-version) is a direct serialization of the internal structures and changes between
+// releases, whereas each version defined here is frozen: new versions are added when the
+// schema needs to evolve, and older ones are kept as is.
+//
+// Version 1:
+// {
+//   "version": 1,
+//   "messages": [
+//     {
+//       "id": 1234,
+//       "opcode": "QUERY",
+//       "rcode": "NOERROR",
+//       "flags": ["qr", "rd", "ra"],
+//       "question": { "name": "www.google.com.", "type": "A", "class": "IN" },
+//       "answer": [ RR ], "authority": [ RR ], "additional": [ RR ]
+//     }
+//   ],
+//   "info": {
+//     "elapsed": 12,
+//     "mode": "Udp",
+//     "netinfo": { "sent": 43, "received": 59, "peer": "1.1.1.1:53" }
+//   }
+// }
+//
+// with RR being the same object whatever the RR type:
+// { "name": "www.google.com.", "type": "A", "class": "IN", "ttl": 300, "rdlength": 4,
+//   "rdata": "142.250.179.100", "rdata_wire": "jvqzZA==" }
+//
+// "rdata" is the presentation format and "rdata_wire" the base64 of the RDATA bytes. For OPT,
+// "class" and "ttl" are null. With --offsets, "offset" and "size" give the position of the RR
+// in the message. A and AAAA records in special-use ranges have a "special_use" classification.
+use std::io::Cursor;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+use base64::{engine::general_purpose, Engine as _};
+use serde::Serialize;
+use serde_json::{json, Value};
+use type2network::FromNetworkOrder;
+
+use crate::annotate::special_use;
+use crate::dns::message::{Message, MessageList};
+use crate::dns::rfc::{
+    domain::DomainName,
+    flags::Flags,
+    qclass::QClass,
+    qtype::QType,
+    query::{MetaRR, Query},
+    resource_record::{ResourceRecord, OPT},
+    rrlist::RRList,
+};
+use crate::error::{Dns, Error};
+use crate::show::{show_offsets, Numeric, QueryInfo};
+
+// current and only schema version
+pub const JSON_VERSION: u8 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct JsonOutputV1 {
+    version: u8,
+    messages: Vec<JsonMessageV1>,
+    info: JsonInfoV1,
+}
+
+// meta-info as it was when the version was defined, whatever is added to QueryInfo
+#[derive(Debug, Serialize)]
+struct JsonInfoV1 {
+    elapsed: u128,
+    mode: String,
+    netinfo: JsonNetInfoV1,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonNetInfoV1 {
+    sent: usize,
+    received: usize,
+    peer: Option<SocketAddr>,
+}
+
+impl From<&QueryInfo> for JsonInfoV1 {
+    fn from(info: &QueryInfo) -> Self {
+        Self {
+            elapsed: info.elapsed,
+            mode: info.mode.clone(),
+            netinfo: JsonNetInfoV1 {
+                sent: info.netinfo.sent,
+                received: info.netinfo.received,
+                peer: info.netinfo.peer,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct JsonMessageV1 {
+    id: u16,
+    opcode: String,
+    rcode: String,
+    flags: Vec<&'static str>,
+    question: JsonQuestionV1,
+    answer: Vec<JsonRRV1>,
+    authority: Vec<JsonRRV1>,
+    additional: Vec<JsonRRV1>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonQuestionV1 {
+    name: String,
+    r#type: String,
+    class: String,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonRRV1 {
+    name: String,
+    r#type: String,
+    class: Option<String>,
+    ttl: Option<u32>,
+    rdlength: u16,
+    rdata: String,
+    rdata_wire: String,
+
+    // only with --offsets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offset: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<usize>,
+
+    // only for A and AAAA in special-use ranges (e.g.: "private (RFC1918)")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    special_use: Option<&'static str>,
+}
+
+impl From<&ResourceRecord> for JsonRRV1 {
+    fn from(rr: &ResourceRecord) -> Self {
+        let regular = rr.opt_or_class_ttl.regular();
+
+        Self {
+            name: rr.name.to_string(),
+            r#type: rr.r#type.to_string(),
+            class: regular.map(|r| r.class().to_string()),
+            ttl: regular.map(|r| r.ttl()),
+            rdlength: rr.rd_length(),
+            rdata: rr.rdata_string(),
+            rdata_wire: general_purpose::STANDARD.encode(rr.rdata_wire()),
+            offset: show_offsets().then_some(rr.offset()),
+            size: show_offsets().then_some(rr.size()),
+            special_use: rr.ip_address().as_ref().and_then(special_use),
+        }
+    }
+}
+
+fn rrs(list: Option<&RRList>) -> Vec<JsonRRV1> {
+    list.map(|l| l.iter().map(JsonRRV1::from).collect()).unwrap_or_default()
+}
+
+impl From<&Message> for JsonMessageV1 {
+    fn from(msg: &Message) -> Self {
+        let resp = msg.response();
+        let question = &resp.question;
+
+        Self {
+            id: resp.id(),
+            opcode: resp.header.flags.op_code().to_string().to_uppercase(),
+            rcode: resp.rcode().to_string().to_uppercase(),
+            flags: resp.header.flags.names(),
+            question: JsonQuestionV1 {
+                name: question.qname.to_string(),
+                r#type: question.qtype.to_string(),
+                class: question.qclass.to_string(),
+            },
+            answer: rrs(resp.answer.as_ref()),
+            authority: rrs(resp.authority()),
+            additional: rrs(resp.additional()),
+        }
+    }
+}
+
+impl JsonOutputV1 {
+    pub fn new(messages: &MessageList, info: &QueryInfo) -> Self {
+        Self {
+            version: JSON_VERSION,
+            messages: messages.iter().map(JsonMessageV1::from).collect(),
+            info: JsonInfoV1::from(info),
+        }
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// RFC8427 representation of DNS messages (--json-rfc8427), for tools expecting
+// this standardized schema
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn rfc8427(messages: &MessageList) -> Value {
+    Value::Array(messages.iter().map(rfc8427_message).collect())
+}
+
+fn rfc8427_message(msg: &Message) -> Value {
+    let resp = msg.response();
+    let header = &resp.header;
+    let flags = header.flags.bitflags();
+    let question = &resp.question;
+
+    // flags are 0 or 1
+    let bit = |b: bool| b as u8;
+
+    let mut j = json!({
+        "ID": header.id,
+        "QR": bit(header.flags.is_response()),
+        "Opcode": header.flags.op_code().number(),
+        "AA": bit(flags.authorative_answer),
+        "TC": bit(flags.truncation),
+        "RD": bit(flags.recursion_desired),
+        "RA": bit(flags.recursion_available),
+        "AD": bit(flags.authentic_data),
+        "CD": bit(flags.checking_disabled),
+        "RCODE": resp.rcode().number(),
+        "QDCOUNT": header.qd_count,
+        "ANCOUNT": header.an_count,
+        "NSCOUNT": header.ns_count,
+        "ARCOUNT": header.ar_count,
+        "QNAME": question.qname.to_string(),
+        "QTYPE": question.qtype.number(),
+        "QTYPEname": question.qtype.to_string(),
+        "QCLASS": question.qclass.number(),
+        "QCLASSname": question.qclass.to_string(),
+    });
+
+    let map = j.as_object_mut().unwrap();
+    for (key, list) in [
+        ("answerRRs", resp.answer.as_ref()),
+        ("authorityRRs", resp.authority()),
+        ("additionalRRs", resp.additional()),
+    ] {
+        if let Some(list) = list {
+            map.insert(key.to_string(), list.iter().map(rfc8427_rr).collect());
+        }
+    }
+
+    if !msg.raw_response.is_empty() {
+        map.insert(
+            "messageOctetsHEX".to_string(),
+            Value::from(base16::encode_upper(&msg.raw_response)),
+        );
+    }
+
+    j
+}
+
+fn rfc8427_rr(rr: &ResourceRecord) -> Value {
+    let mut j = json!({
+        "NAME": rr.name.to_string(),
+        "TYPE": rr.r#type.number(),
+        "TYPEname": rr.r#type.to_string(),
+        "RDLENGTH": rr.rd_length(),
+        "RDATAHEX": base16::encode_upper(&rr.rdata_wire()),
+    });
+
+    let map = j.as_object_mut().unwrap();
+
+    // for OPT, CLASS & TTL are the payload size and the extended rcode and flags which
+    // are kept in RDATAHEX
+    if let Some(r) = rr.opt_or_class_ttl.regular() {
+        map.insert("CLASS".to_string(), Value::from(r.class().number()));
+        map.insert("CLASSname".to_string(), Value::from(r.class().to_string()));
+        map.insert("TTL".to_string(), Value::from(r.ttl()));
+    }
+
+    // RFC8427 defines rdata members for some types
+    if matches!(
+        rr.r#type,
+        QType::A | QType::AAAA | QType::CNAME | QType::DNAME | QType::NS | QType::PTR
+    ) {
+        map.insert(format!("rdata{}", rr.r#type), Value::from(rr.rdata_string()));
+    }
+
+    j
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// build a query from its RFC8427 description (--from-json), e.g.:
+//
+// { "ID": 1234, "RD": 1, "CD": 1, "QNAME": "example.com.", "QTYPEname": "SOA", "QCLASS": 3,
+//   "additionalRRs": [ { "NAME": ".", "TYPE": 41, "CLASS": 1232, "TTL": 32768,
+//                        "RDATAHEX": "000A0008D2F7A1B3C4D5E6F7" } ] }
+//
+// Header members are ID, Opcode, AA, TC, RD (1 if missing), RA, AD, CD and RCODE,
+// the question is given by QNAME, QTYPE or QTYPEname and QCLASS or QCLASSname.
+// Only OPT is allowed in additionalRRs.
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn query_from_json(s: &str) -> crate::error::Result<Query> {
+    let err = || Error::Dns(Dns::InvalidJsonQuery);
+    let j: Value = serde_json::from_str(s).map_err(|_| err())?;
+
+    // get an integer member or its default value
+    let int = |key: &str, default: u64| -> crate::error::Result<u64> {
+        match &j[key] {
+            Value::Null => Ok(default),
+            v => v.as_u64().ok_or(err()),
+        }
+    };
+
+    //───────────────────────────────────────────────────────────────────────────────
+    // question
+    //───────────────────────────────────────────────────────────────────────────────
+    let qname = j["QNAME"].as_str().ok_or(err())?;
+    let qname = DomainName::try_from(qname)?;
+
+    let qtype = match j["QTYPEname"].as_str() {
+        Some(name) => QType::from_str(&name.to_uppercase()).map_err(|_| err())?,
+        None => QType::try_from(int("QTYPE", 1)? as u16).map_err(|_| err())?,
+    };
+    let qclass = match j["QCLASSname"].as_str() {
+        Some(name) => QClass::from_str(&name.to_uppercase()).map_err(|_| err())?,
+        None => QClass::try_from(int("QCLASS", 1)? as u16).map_err(|_| err())?,
+    };
+
+    let mut query = Query::build().with_type(&qtype).with_class(&qclass).with_domain(&qname);
+
+    //───────────────────────────────────────────────────────────────────────────────
+    // header
+    //───────────────────────────────────────────────────────────────────────────────
+    if !j["ID"].is_null() {
+        query.header.id = int("ID", 0)? as u16;
+    }
+
+    let mut flags = ((int("Opcode", 0)? & 0x0F) << 11) as u16;
+    for (key, default, shift) in [
+        ("AA", 0, 10),
+        ("TC", 0, 9),
+        ("RD", 1, 8),
+        ("RA", 0, 7),
+        ("AD", 0, 5),
+        ("CD", 0, 4),
+    ] {
+        if int(key, default)? != 0 {
+            flags |= 1 << shift;
+        }
+    }
+    flags |= (int("RCODE", 0)? & 0x0F) as u16;
+    query.header.flags = Flags::try_from(flags)?;
+
+    //───────────────────────────────────────────────────────────────────────────────
+    // OPT records are rebuilt from their wire format
+    //───────────────────────────────────────────────────────────────────────────────
+    for rr in j["additionalRRs"].as_array().into_iter().flatten() {
+        if rr["TYPE"].as_u64() != Some(41) {
+            return Err(err());
+        }
+
+        let rdata = base16::decode(rr["RDATAHEX"].as_str().unwrap_or_default()).map_err(|_| err())?;
+        let class = rr["CLASS"].as_u64().unwrap_or(4096) as u16;
+        let ttl = rr["TTL"].as_u64().unwrap_or(0) as u32;
+
+        let mut wire = vec![0u8];
+        wire.extend_from_slice(&41u16.to_be_bytes());
+        wire.extend_from_slice(&class.to_be_bytes());
+        wire.extend_from_slice(&ttl.to_be_bytes());
+        wire.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        wire.extend_from_slice(&rdata);
+
+        let mut opt = OPT::default();
+        opt.deserialize_from(&mut Cursor::new(wire.as_slice()))
+            .map_err(|_| err())?;
+        query = query.with_additional(MetaRR::OPT(opt));
+    }
+
+    Ok(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::rfc::response::Response;
+    use crate::dns::tests::get_packets;
+    use type2network::FromNetworkOrder;
+
+    #[test]
+    fn rr() {
+        let pcap = get_packets("./tests/pcap/a.pcap", 0, 1);
+        let mut buffer = std::io::Cursor::new(&pcap.1[0x2A..]);
+        let mut resp = Response::default();
+        resp.deserialize_from(&mut buffer).unwrap();
+
+        let answer = resp.answer.as_ref().unwrap();
+        let rr = JsonRRV1::from(&answer[0]);
+        assert_eq!(rr.r#type, "A");
+        assert_eq!(rr.class.as_deref(), Some("IN"));
+        assert_eq!(rr.rdlength, 4);
+        assert_eq!(general_purpose::STANDARD.decode(&rr.rdata_wire).unwrap().len(), 4);
+
+        let j = serde_json::to_value(&rr).unwrap();
+        for key in ["name", "type", "class", "ttl", "rdlength", "rdata", "rdata_wire"] {
+            assert!(j.get(key).is_some());
+        }
+
+        let j = rfc8427_rr(&answer[0]);
+        assert_eq!(j["TYPE"], 1);
+        assert_eq!(j["TYPEname"], "A");
+        assert_eq!(j["CLASS"], 1);
+        assert_eq!(j["RDLENGTH"], 4);
+        assert_eq!(j["RDATAHEX"].as_str().unwrap().len(), 8);
+        assert_eq!(j["rdataA"], answer[0].rdata_string());
+    }
+
+    #[test]
+    fn info() {
+        let info = QueryInfo {
+            elapsed: 12,
+            mode: String::from("Udp"),
+            netinfo: crate::transport::NetworkInfo {
+                sent: 43,
+                received: 59,
+                peer: Some("1.1.1.1:53".parse().unwrap()),
+                reused: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&JsonInfoV1::from(&info)).unwrap(),
+            r#"{"elapsed":12,"mode":"Udp","netinfo":{"sent":43,"received":59,"peer":"1.1.1.1:53"}}"#
+        );
+    }
+
+    #[test]
+    fn from_json() {
+        let json = r#"{ "ID": 1234, "CD": 1, "QNAME": "example.com.", "QTYPEname": "soa", "QCLASS": 3,
+            "additionalRRs": [ { "NAME": ".", "TYPE": 41, "CLASS": 1232, "TTL": 32768, "RDATAHEX": "00030000" } ] }"#;
+        let query = query_from_json(json).unwrap();
+
+        assert_eq!(query.header.id, 1234);
+        assert_eq!(query.header.flags.names(), vec!["rd", "cd"]);
+        assert_eq!(query.question.qname.to_string(), "example.com.");
+        assert_eq!(query.question.qtype, QType::SOA);
+        assert_eq!(query.question.qclass, QClass::CH);
+        assert_eq!(query.header.ar_count, 1);
+        assert_eq!(query.bufsize(), 1232);
+
+        // only OPT is accepted in additional
+        let json = r#"{ "QNAME": "example.com.", "additionalRRs": [ { "TYPE": 1 } ] }"#;
+        assert!(query_from_json(json).is_err());
+        assert!(query_from_json(r#"{ "QTYPE": 1 }"#).is_err());
+    }
+}
@@ -0,0 +1,267 @@
+//! TSIG secrets fetched from the OS keyring or from an agent (--tsig-name), so that
+//! base64 keys don't leak into the shell history or the process list.
+//!
+//! Secrets are stored base64 encoded under the "dqy" service, the account being the key name:
+//! * Linux (Secret Service): `secret-tool store --label "dqy key" service dqy account key.example.`
+//! * macOS: `security add-generic-password -s dqy -a key.example. -w`
+//!
+//! An agent listens on a Unix socket: it receives the key name followed by a newline and
+//! answers the base64 secret on a single line.
+//!
+//! Keys can also be read from BIND key files or named.conf snippets (--tsig-file).
+use std::fs::read_to_string;
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+
+use crate::dns::rfc::{
+    domain::DomainName,
+    tsig::{TsigAlgorithm, TsigKey},
+};
+use crate::error::{Error, Result};
+
+// keyring service under which secrets are stored
+const SERVICE: &str = "dqy";
+
+// first line of the output, without the trailing newline
+fn first_line(output: &[u8]) -> Option<String> {
+    let line = String::from_utf8_lossy(output).lines().next()?.trim().to_string();
+    (!line.is_empty()).then_some(line)
+}
+
+// the command reading the secret from the platform keyring
+fn keyring_command(name: &str) -> Command {
+    if cfg!(target_os = "macos") {
+        let mut cmd = Command::new("security");
+        cmd.args(["find-generic-password", "-s", SERVICE, "-a", name, "-w"]);
+        cmd
+    } else {
+        let mut cmd = Command::new("secret-tool");
+        cmd.args(["lookup", "service", SERVICE, "account", name]);
+        cmd
+    }
+}
+
+fn from_keyring(name: &str) -> Result<String> {
+    let mut cmd = keyring_command(name);
+    let output = cmd
+        .output()
+        .map_err(|e| Error::Tsig(format!("can't run {:?} to read the keyring ({})", cmd.get_program(), e)))?;
+
+    if !output.status.success() {
+        return Err(Error::Tsig(format!("key '{}' not found in the keyring", name)));
+    }
+    first_line(&output.stdout).ok_or_else(|| Error::Tsig(format!("key '{}' is empty in the keyring", name)))
+}
+
+#[cfg(unix)]
+fn from_agent(name: &str, socket: &Path) -> Result<String> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let agent_error = |e: std::io::Error| Error::Tsig(format!("agent {} ({})", socket.display(), e));
+
+    let mut stream = UnixStream::connect(socket).map_err(agent_error)?;
+    writeln!(stream, "{}", name).map_err(agent_error)?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).map_err(agent_error)?;
+    first_line(line.as_bytes())
+        .ok_or_else(|| Error::Tsig(format!("key '{}' unknown to agent {}", name, socket.display())))
+}
+
+#[cfg(not(unix))]
+fn from_agent(_name: &str, _socket: &Path) -> Result<String> {
+    Err(Error::Tsig(String::from("TSIG agents are only supported on Unix")))
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// return the base64 secret of the key, from the agent if any or from the keyring
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn fetch_secret(name: &str, agent: Option<&Path>) -> Result<String> {
+    match agent {
+        Some(socket) => from_agent(name, socket),
+        None => from_keyring(name),
+    }
+}
+
+// split a named.conf content into words, strings and punctuation, without comments
+fn tokens(content: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => tokens.push(chars.by_ref().take_while(|c| *c != '"').collect()),
+            '{' | '}' | ';' => tokens.push(c.to_string()),
+            '#' => {
+                chars.by_ref().find(|c| *c == '\n');
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                chars.by_ref().find(|c| *c == '\n');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            c if c.is_whitespace() => (),
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !"{};\"".contains(*c)) {
+                    word.push(c);
+                }
+                tokens.push(word);
+            }
+        }
+    }
+
+    tokens
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// keys found in a BIND key file: key "name" { algorithm hmac-sha256; secret "..."; };
+// Other statements are skipped, so a whole named.conf can be given
+//───────────────────────────────────────────────────────────────────────────────────
+fn parse_keys(content: &str) -> Result<Vec<TsigKey>> {
+    let tokens = tokens(content);
+    let mut keys = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        // only top-level key statements are of interest
+        if tokens[i] != "key" || tokens.get(i + 2).map(String::as_str) != Some("{") {
+            if tokens[i] == "{" {
+                let mut depth = 0;
+                while i < tokens.len() {
+                    match tokens[i].as_str() {
+                        "{" => depth += 1,
+                        "}" => depth -= 1,
+                        _ => (),
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    i += 1;
+                }
+            }
+            i += 1;
+            continue;
+        }
+
+        let name = &tokens[i + 1];
+        let (mut algorithm, mut secret) = (None, None);
+        i += 3;
+        while i < tokens.len() && tokens[i] != "}" {
+            match (tokens[i].as_str(), tokens.get(i + 1)) {
+                ("algorithm", Some(alg)) => algorithm = Some(alg.clone()),
+                ("secret", Some(s)) => secret = Some(s.clone()),
+                _ => (),
+            }
+            // next statement
+            while i < tokens.len() && tokens[i] != ";" && tokens[i] != "}" {
+                i += 1;
+            }
+            if tokens.get(i).map(String::as_str) == Some(";") {
+                i += 1;
+            }
+        }
+
+        let algorithm = match algorithm {
+            Some(alg) => TsigAlgorithm::from_str(&alg).map_err(Error::Tsig)?,
+            None => TsigAlgorithm::default(),
+        };
+        let secret = secret.ok_or_else(|| Error::Tsig(format!("no secret for key '{}'", name)))?;
+        keys.push(TsigKey::new(name, algorithm, &secret)?);
+        i += 1;
+    }
+
+    Ok(keys)
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// read the key from a key file: the one named if any, or the only one of the file
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn read_key_file(path: &Path, name: Option<&str>) -> Result<TsigKey> {
+    let content = read_to_string(path).map_err(|e| Error::OpenFile(e, path.to_path_buf()))?;
+    let mut keys = parse_keys(&content)?;
+
+    match name {
+        Some(name) => {
+            let wanted = DomainName::try_from(name.to_lowercase().as_str())?;
+            keys.into_iter()
+                .find(|key| key.name == wanted)
+                .ok_or_else(|| Error::Tsig(format!("key '{}' not found in {}", name, path.display())))
+        }
+        None if keys.len() == 1 => Ok(keys.remove(0)),
+        None => Err(Error::Tsig(format!(
+            "{} keys found in {}, use --tsig-name to choose one",
+            keys.len(),
+            path.display()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines() {
+        assert_eq!(first_line(b"c2VjcmV0\n").unwrap(), "c2VjcmV0");
+        assert!(first_line(b"\n").is_none());
+        assert!(first_line(b"").is_none());
+    }
+
+    #[test]
+    fn key_file() {
+        let conf = r#"
+            // tsig-keygen output
+            key "transfer-key." {
+                algorithm hmac-sha512;
+                secret "c2VjcmV0";
+            };
+            options { directory "/var/cache/bind"; };
+            /* a second key */
+            key update { algorithm "HMAC-SHA1"; secret "dXBkYXRl"; }; # default
+        "#;
+        let keys = parse_keys(conf).unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].name.to_string(), "transfer-key.");
+        assert_eq!(keys[0].algorithm, TsigAlgorithm::HmacSha512);
+        assert_eq!(keys[1].name.to_string(), "update.");
+        assert_eq!(keys[1].algorithm, TsigAlgorithm::HmacSha1);
+
+        assert!(parse_keys(r#"key k { algorithm hmac-sha256; };"#).is_err());
+        assert!(parse_keys(r#"key k { algorithm hmac-md5; secret "c2VjcmV0"; };"#).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn agent() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::UnixListener;
+
+        let socket = std::env::temp_dir().join(format!("dqy-agent-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut name = String::new();
+            reader.read_line(&mut name).unwrap();
+            assert_eq!(name, "key.example.\n");
+            writeln!(reader.get_mut(), "c2VjcmV0").unwrap();
+        });
+
+        assert_eq!(fetch_secret("key.example.", Some(&socket)).unwrap(), "c2VjcmV0");
+        server.join().unwrap();
+        let _ = std::fs::remove_file(&socket);
+    }
+}
@@ -6,13 +6,21 @@
 // fix display options
 
 //! A DNS resource query tool
+#[cfg(feature = "doq")]
+use std::sync::OnceLock;
 use std::{process::ExitCode, time::Instant};
 
-use error::Error;
 // use handlebars::render;
 use log::info;
 
 // internal modules
+mod analyze;
+
+mod annotate;
+use annotate::resolve_ptr;
+
+mod bogon;
+
 mod dns;
 use dns::message::MessageList;
 
@@ -21,28 +29,94 @@ use args::CliOptions;
 
 mod error;
 
+mod i18n;
+use i18n::fill;
+
 mod show;
-use show::{QueryInfo, ShowAll};
+use show::{header_section, QueryInfo, ShowAll};
+
+mod stats;
+
+mod template;
+
+mod warnings;
+
+mod wire;
 
 mod transport;
+#[cfg(feature = "doh3")]
+use transport::http3::H3Protocol;
+#[cfg(feature = "doh")]
+use transport::https::{proxy, redacted, HttpsProtocol};
+#[cfg(feature = "odoh")]
+use transport::odoh::OdohProtocol;
+#[cfg(feature = "doq")]
+use transport::quic::QuicProtocol;
+#[cfg(feature = "dot")]
+use transport::tls::TlsProtocol;
 use transport::{
-    https::HttpsProtocol,
     network::{Messenger, Protocol},
-    quic::QuicProtocol,
+    observer::{self, PacketLog},
+    pool::StatsOnExit,
     root_servers::init_root_map,
     tcp::TcpProtocol,
-    tls::TlsProtocol,
     udp::UdpProtocol,
+    TransportOptions, TransportProtocol,
 };
 
 mod trace;
 use trace::*;
 
+mod privacy;
+use privacy::privacy_report;
+
+mod probe;
+use probe::{check_resolvers, frag_test, probe_bufsize};
+
 mod protocol;
 use protocol::DnsProtocol;
 
+mod history;
+
+mod cache;
+
+mod cookies;
+
+mod json;
+
+mod keyring;
+
+mod race;
+use race::race;
+
+mod all_types;
+use all_types::all_types;
+
+mod report;
+
+mod fuzz;
+
+mod pacing;
+
+mod servers;
+use servers::{all_servers, failover};
+
+mod service;
+use service::{browse, browser_view, follow_svcb, service_discovery};
+
+mod session;
+use session::SessionLog;
+
+mod shootout;
+
+mod watch;
+use watch::{rebind_watch, serial_watch};
+
 mod cli_options;
 
+mod compare;
+use compare::{ecs_sweep, expect_zone, split_horizon};
+
 mod handlebars;
 // mod templating;
 
@@ -68,11 +142,90 @@ fn get_messages_using_sync_transport<T: Messenger>(
     // we want run info
     if let Some(info) = info {
         info.netinfo = *transport.network_info();
+        info.netinfo.retransmissions = messages.iter().map(|msg| msg.netinfo.retransmissions).sum();
+        info.netinfo.reused = messages.iter().next().is_some_and(|msg| msg.netinfo.reused);
+        for msg in messages.iter() {
+            info.warnings.exchange(msg);
+        }
     }
 
     Ok(messages)
 }
 
+//───────────────────────────────────────────────────────────────────────────────────
+// get list of messages using transport: async mode (DoQ)
+//───────────────────────────────────────────────────────────────────────────────────
+#[cfg(feature = "doq")]
+async fn get_messages_using_async_transport(
+    info: Option<&mut QueryInfo>,
+    transport: &mut QuicProtocol,
+    options: &CliOptions,
+) -> error::Result<MessageList> {
+    let messages = if options.transport.parallel {
+        DnsProtocol::async_parallel_request(options, transport, BUFFER_SIZE).await?
+    } else {
+        DnsProtocol::async_process_request(options, transport, BUFFER_SIZE).await?
+    };
+
+    // same queries from another local address: the session survived if they're answered
+    if options.transport.doq_migrate {
+        transport.migrate()?;
+        let again = DnsProtocol::async_process_request(options, transport, BUFFER_SIZE);
+        let survived = matches!(tokio::time::timeout(options.transport.timeout, again).await, Ok(Ok(_)));
+        transport.netinfo.migration = Some(survived);
+    }
+
+    // we want run info
+    if let Some(info) = info {
+        info.netinfo = *transport.network_info();
+        info.netinfo.lost_packets = messages.iter().map(|msg| msg.netinfo.lost_packets).sum();
+        info.netinfo.reused = messages.iter().next().is_some_and(|msg| msg.netinfo.reused);
+    }
+    Ok(messages)
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// the runtime is kept for the whole run: pooled DoQ connections are bound to it, and
+// its worker drives them between queries (QUIC keep-alive, acks, idle timeout)
+//───────────────────────────────────────────────────────────────────────────────────
+#[cfg(feature = "doq")]
+fn runtime() -> error::Result<&'static tokio::runtime::Runtime> {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    if let Some(rt) = RUNTIME.get() {
+        return Ok(rt);
+    }
+
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()
+        .map_err(error::Error::Tokio)?;
+    Ok(RUNTIME.get_or_init(|| rt))
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// exchange on the idle connection of the pool if any, or on a new connection. With
+// a pool, it's called again on a new connection when the idle one has been closed
+//───────────────────────────────────────────────────────────────────────────────────
+fn pooled<H, C, F>(options: &CliOptions, create: C, mut exchange: F) -> error::Result<MessageList>
+where
+    H: Send + 'static,
+    C: Fn(&TransportOptions) -> error::Result<TransportProtocol<H>>,
+    F: FnMut(&mut TransportProtocol<H>) -> error::Result<MessageList>,
+{
+    let Some(pool) = &options.transport.pool else {
+        let mut transport = create(&options.transport)?;
+        return exchange(&mut transport);
+    };
+
+    let mut reconnects = 0;
+    pool.exchange(&options.transport, create, |transport| {
+        transport.netinfo.reconnects = reconnects;
+        reconnects += 1;
+        exchange(transport)
+    })
+}
+
 //───────────────────────────────────────────────────────────────────────────────────
 // send all QTypes to domain and get responses for each query.
 //───────────────────────────────────────────────────────────────────────────────────
@@ -87,33 +240,65 @@ pub fn get_messages(info: Option<&mut QueryInfo>, options: &CliOptions) -> error
             get_messages_using_sync_transport(info, &mut transport, options)
         }
         Protocol::Tcp => {
-            let mut transport = TcpProtocol::new(&options.transport)?;
-            get_messages_using_sync_transport(info, &mut transport, options)
+            let mut info = info;
+            pooled(options, TcpProtocol::new, |transport| {
+                get_messages_using_sync_transport(info.as_deref_mut(), transport, options)
+            })
         }
+        #[cfg(feature = "dot")]
         Protocol::DoT => {
-            let mut transport = TlsProtocol::new(&options.transport)?;
-            get_messages_using_sync_transport(info, &mut transport, options)
+            let mut info = info;
+            pooled(options, TlsProtocol::new, |transport| {
+                get_messages_using_sync_transport(info.as_deref_mut(), transport, options)
+            })
+        }
+        #[cfg(feature = "odoh")]
+        Protocol::DoH if options.transport.odoh => {
+            let mut info = info;
+            if let Some(info) = info.as_deref_mut() {
+                info.proxy = proxy(&options.transport).map(|url| redacted(&url));
+            }
+
+            // the key configuration is fetched again only for a new connection
+            pooled(options, OdohProtocol::new, |transport| {
+                get_messages_using_sync_transport(info.as_deref_mut(), transport, options)
+            })
         }
+        // HTTP/3 is not provided by reqwest's blocking client, and proxies are not used
+        #[cfg(feature = "doh3")]
+        Protocol::DoH if options.transport.https_version == Some(http::Version::HTTP_3) => {
+            let rt = runtime()?;
+
+            let mut info = info;
+            let create = |trp_options: &_| H3Protocol::new(trp_options, rt);
+            pooled(options, create, |transport| {
+                get_messages_using_sync_transport(info.as_deref_mut(), transport, options)
+            })
+        }
+        #[cfg(feature = "doh")]
         Protocol::DoH => {
-            let mut transport = HttpsProtocol::new(&options.transport)?;
-            get_messages_using_sync_transport(info, &mut transport, options)
+            let mut info = info;
+            if let Some(info) = info.as_deref_mut() {
+                info.proxy = proxy(&options.transport).map(|url| redacted(&url));
+            }
+
+            pooled(options, HttpsProtocol::new, |transport| {
+                get_messages_using_sync_transport(info.as_deref_mut(), transport, options)
+            })
         }
+        #[cfg(feature = "doq")]
         Protocol::DoQ => {
             // quinn crate doesn't provide blocking
-            let rt = tokio::runtime::Builder::new_current_thread()
-                .enable_all()
-                .build()
-                .map_err(Error::Tokio)?;
-
-            rt.block_on(async {
-                let mut transport = QuicProtocol::new(&options.transport).await?;
-                let messages = DnsProtocol::async_process_request(options, &mut transport, BUFFER_SIZE).await?;
-
-                // we want run info
-                if let Some(info) = info {
-                    info.netinfo = *transport.network_info();
-                }
-                Ok(messages)
+            let rt = runtime()?;
+
+            let mut info = info;
+            let create = |trp_options: &_| rt.block_on(QuicProtocol::new(trp_options));
+            pooled(options, create, |transport| {
+                rt.block_on(get_messages_using_async_transport(
+                    info.as_deref_mut(),
+                    transport,
+                    options,
+                ))
             })
         }
     }
@@ -126,7 +311,7 @@ fn main() -> ExitCode {
     let res = run();
 
     if let Err(e) = res {
-        eprintln!("{}", e);
+        eprintln!("{}", e.localized());
         e.into()
     } else {
         ExitCode::SUCCESS
@@ -150,6 +335,29 @@ fn run() -> error::Result<()> {
     let mut options = CliOptions::options(&args)?;
     info!("{:#?}", options);
 
+    // Ctrl-C stops sending queries but displays what we've already got
+    options.transport.cancel.cancel_on_ctrl_c();
+
+    // raw messages of all exchanges
+    if let Some(path) = &options.dump.packet_log {
+        observer::register(PacketLog::new(path)?);
+    }
+
+    // exchanges of all modes with the query as sent
+    if let Some(path) = &options.dump.session_log {
+        observer::register(SessionLog::new(path)?);
+    }
+
+    // server cookies learnt are sent back in the next queries
+    let _cookie_jar = options
+        .edns
+        .cookie
+        .is_some()
+        .then(|| cookies::open(options.edns.cookie_jar.as_deref()));
+
+    // connection reuse statistics
+    let _pool_stats = options.transport.pool.clone().map(StatsOnExit);
+
     //───────────────────────────────────────────────────────────────────────────────────
     // this will give user some information on how the protocol ran
     //───────────────────────────────────────────────────────────────────────────────────
@@ -163,10 +371,159 @@ fn run() -> error::Result<()> {
         return Ok(());
     }
 
+    //───────────────────────────────────────────────────────────────────────────────────
+    // service lookups: SVCB/HTTPS alias chains, browser view, SRV and SVCB endpoints,
+    // DNS-SD browsing
+    //───────────────────────────────────────────────────────────────────────────────────
+    if options.dump.follow_svcb {
+        follow_svcb(&mut options)?;
+        return Ok(());
+    }
+    if options.dump.browser_view {
+        browser_view(&mut options)?;
+        return Ok(());
+    }
+    if let Some(service) = options.dump.service.clone() {
+        service_discovery(&mut options, &service)?;
+        return Ok(());
+    }
+    if let Some(domain) = options.dump.browse.clone() {
+        browse(&mut options, &domain)?;
+        return Ok(());
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // zone exploration without AXFR
+    //───────────────────────────────────────────────────────────────────────────────────
+    if options.dump.all_types {
+        all_types(&mut options)?;
+        return Ok(());
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // summary of the statistics database
+    //───────────────────────────────────────────────────────────────────────────────────
+    if options.dump.stats_report {
+        if let Some(path) = &options.dump.stats_db {
+            history::report(path)?;
+        }
+        return Ok(());
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // replay a session log
+    //───────────────────────────────────────────────────────────────────────────────────
+    if let Some(path) = options.dump.replay.clone() {
+        session::replay(&mut options, &path, options.dump.replay_timing)?;
+        return Ok(());
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // compare resolvers performance
+    //───────────────────────────────────────────────────────────────────────────────────
+    if let Some((resolvers, domains)) = options.dump.shootout.clone() {
+        shootout::shootout(&mut options, &resolvers, &domains)?;
+        return Ok(());
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // same query at regular intervals
+    //───────────────────────────────────────────────────────────────────────────────────
+    if options.dump.rebind_watch {
+        rebind_watch(&options)?;
+        return Ok(());
+    }
+    if options.dump.serial_watch {
+        serial_watch(&mut options)?;
+        return Ok(());
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // reachability and features of the resolvers
+    //───────────────────────────────────────────────────────────────────────────────────
+    if options.dump.check_resolvers {
+        check_resolvers(&mut options)?;
+        return Ok(());
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // random queries to test the server robustness
+    //───────────────────────────────────────────────────────────────────────────────────
+    if let Some(count) = options.dump.fuzz_server {
+        fuzz::fuzz_server(&options, count)?;
+        return Ok(());
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // discover the effective EDNS bufsize
+    //───────────────────────────────────────────────────────────────────────────────────
+    if options.edns.probe_bufsize {
+        probe_bufsize(&mut options)?;
+        return Ok(());
+    }
+    if options.edns.frag_test {
+        frag_test(&mut options)?;
+        return Ok(());
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // several source addresses: compare answers from each one
+    //───────────────────────────────────────────────────────────────────────────────────
+    if options.transport.source_ips.len() > 1 {
+        split_horizon(&mut options)?;
+        return Ok(());
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // several client subnets: compare answers for each one
+    //───────────────────────────────────────────────────────────────────────────────────
+    if let Some(path) = options.edns.ecs_sweep.clone() {
+        ecs_sweep(&mut options, &path)?;
+        return Ok(());
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // compare a zone file with live answers
+    //───────────────────────────────────────────────────────────────────────────────────
+    if let Some(path) = options.dump.expect_zone.clone() {
+        expect_zone(&mut options, &path)?;
+        return Ok(());
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // several resolvers: print out the results of each one
+    //───────────────────────────────────────────────────────────────────────────────────
+    if options.transport.all_servers && options.transport.endpoints.len() > 1 {
+        all_servers(&mut options)?;
+        return Ok(());
+    }
+
     //───────────────────────────────────────────────────────────────────────────────────
     // send queries and receive responses
     //───────────────────────────────────────────────────────────────────────────────────
-    let messages = get_messages(Some(&mut info), &options)?;
+    if options.transport.fallback_resolvers {
+        info.warnings
+            .fallback_resolvers(options.protocol.qtype[0], &options.transport.endpoint);
+    }
+    let result = if options.transport.race {
+        race(Some(&mut info), &options)
+    } else if options.transport.endpoints.len() > 1 {
+        failover(Some(&mut info), &mut options)
+    } else {
+        get_messages(Some(&mut info), &options)
+    };
+
+    // failures are recorded too
+    if let Some(path) = &options.dump.stats_db {
+        history::record(path, &options, &result)?;
+    }
+
+    // a failed resolver doesn't prevent from displaying what it previously answered
+    let result = match &options.dump.serve_stale {
+        Some(path) => cache::serve_stale(path, &options, result, &mut info.warnings),
+        None => result,
+    };
+    let mut messages = result?;
 
     //───────────────────────────────────────────────────────────────────────────────────
     // elapsed as millis will be hopefully enough
@@ -177,6 +534,12 @@ fn run() -> error::Result<()> {
     // mode
     info.mode = options.transport.transport_mode.to_string();
 
+    // output diffable against golden files
+    if options.display.stable {
+        messages.stabilize();
+        info.stabilize();
+    }
+
     //───────────────────────────────────────────────────────────────────────────────────
     // final display to the user: either Lua code or Json or else
     //───────────────────────────────────────────────────────────────────────────────────
@@ -189,6 +552,10 @@ fn run() -> error::Result<()> {
     //───────────────────────────────────────────────────────────────────────────────────
     // print out final results
     //───────────────────────────────────────────────────────────────────────────────────
+    if options.display.annotate_ptr {
+        options.display.ptr_names = resolve_ptr(&messages, &options);
+    }
+
     if let Some(tpl) = &options.display.hb_tpl {
         handlebars::render(&messages, &info, tpl);
     } else {
@@ -196,5 +563,36 @@ fn run() -> error::Result<()> {
     }
     //messages.show_all(&options.display, info);
 
+    //───────────────────────────────────────────────────────────────────────────────────
+    // what the query exposes, not mixed with JSON output
+    //───────────────────────────────────────────────────────────────────────────────────
+    if options.display.privacy_report && !options.display.json && !options.display.json_pretty {
+        println!("\n{}", header_section("PRIVACY REPORT", None));
+        for line in privacy_report(&options) {
+            println!("{}", line);
+        }
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // send error reports to the agent domain (RFC9567)
+    //───────────────────────────────────────────────────────────────────────────────────
+    if options.edns.reporting {
+        report::send_reports(&options, &messages)?;
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // if interrupted, give a summary of what was done
+    //───────────────────────────────────────────────────────────────────────────────────
+    let wanted = options.protocol.qtype.len();
+    if options.transport.cancel.is_cancelled() && messages.len() < wanted {
+        eprintln!(
+            "\n{}",
+            fill(
+                "interrupted: {} of {} queries completed, {} not sent or failed",
+                &[&messages.len(), &wanted, &(wanted - messages.len())]
+            )
+        );
+    }
+
     Ok(())
 }
@@ -0,0 +1,107 @@
+//! Pacing of bulk runs (--shootout, --replay, --ecs-sweep, --expect-zone and --fuzz-server):
+//! queries are sent at most --rate per second, each one delayed by a random --jitter, and
+//! lists are optionally queried in a random order (--shuffle). This prevents scripts run at
+//! the same time from sending synchronized bursts of queries to resolvers. UDP retries are delayed
+//! the same way, with an exponential backoff (--max-udp-retries).
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use rand::seq::SliceRandom;
+
+use crate::show::DumpOptions;
+
+#[derive(Debug, Default)]
+pub struct Pacer {
+    // minimum time between 2 queries, from --rate
+    interval: Duration,
+
+    // upper bound of the random delay added to each query
+    jitter: Duration,
+
+    // when the last query was sent
+    last: Option<Instant>,
+}
+
+impl Pacer {
+    pub fn new(options: &DumpOptions) -> Self {
+        Self {
+            interval: options
+                .rate
+                .map(|qps| Duration::from_secs_f64(1.0 / qps as f64))
+                .unwrap_or_default(),
+            jitter: options.jitter.unwrap_or_default(),
+            last: None,
+        }
+    }
+
+    // time to wait after the last query, random being in [0, 1)
+    fn gap(&self, random: f64) -> Duration {
+        self.interval + self.jitter.mul_f64(random)
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // block until the next query can be sent. The first one is only delayed by the jitter
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn wait(&mut self) {
+        let random = rand::random::<f64>();
+        let delay = match self.last {
+            Some(last) => self.gap(random).saturating_sub(last.elapsed()),
+            None => self.jitter.mul_f64(random),
+        };
+
+        if !delay.is_zero() {
+            sleep(delay);
+        }
+        self.last = Some(Instant::now());
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// delay before the nth retry (from 1): the base delay is doubled at each retry, and
+// half of it is random so clients don't retry at the same time. random is in [0, 1)
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn backoff(base: Duration, retry: u32, random: f64) -> Duration {
+    let delay = base.saturating_mul(1 << retry.saturating_sub(1).min(16));
+    delay / 2 + (delay / 2).mul_f64(random)
+}
+
+// randomize the order of the list if --shuffle is set
+pub fn shuffle<T>(items: &mut [T], options: &DumpOptions) {
+    if options.shuffle {
+        items.shuffle(&mut rand::thread_rng());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gap() {
+        let options = DumpOptions {
+            rate: Some(10),
+            jitter: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let pacer = Pacer::new(&options);
+        assert_eq!(pacer.gap(0.0), Duration::from_millis(100));
+        assert_eq!(pacer.gap(0.5), Duration::from_millis(200));
+
+        // no pacing by default
+        let mut pacer = Pacer::new(&DumpOptions::default());
+        assert_eq!(pacer.gap(0.99), Duration::ZERO);
+        let now = Instant::now();
+        pacer.wait();
+        pacer.wait();
+        assert!(now.elapsed() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn retries() {
+        let base = Duration::from_millis(200);
+        assert_eq!(backoff(base, 1, 0.0), Duration::from_millis(100));
+        assert_eq!(backoff(base, 1, 0.5), Duration::from_millis(150));
+        assert_eq!(backoff(base, 3, 0.0), Duration::from_millis(400));
+        assert_eq!(backoff(base, 3, 0.5), Duration::from_millis(600));
+    }
+}
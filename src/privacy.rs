@@ -0,0 +1,131 @@
+//! What metadata a query exposes given the command line options, with suggestions (--privacy-report)
+use std::str::FromStr;
+
+use crate::args::CliOptions;
+use crate::dns::rfc::opt::client_subnet::ClientSubnet;
+use crate::i18n::fill;
+use crate::transport::network::Protocol;
+
+// source prefixes recommended by RFC7871 section 11.1
+const ECS_MAX_PREFIX_V4: u8 = 24;
+const ECS_MAX_PREFIX_V6: u8 = 56;
+
+//───────────────────────────────────────────────────────────────────────────────────
+// return a list of exposures, each one followed by suggestions if any
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn privacy_report(options: &CliOptions) -> Vec<String> {
+    let mut report = Vec::new();
+    let encrypted = !matches!(options.transport.transport_mode, Protocol::Udp | Protocol::Tcp);
+
+    // transport
+    if encrypted {
+        report.push(fill(
+            "transport: {} is encrypted, queries and responses are only visible to the resolver",
+            &[&options.transport.transport_mode],
+        ));
+
+        // SNI is sent in clear during the TLS handshake
+        if !options.transport.ech {
+            report.push(fill(
+                "TLS: the resolver name is sent in clear in the SNI, which reveals which resolver is used",
+                &[],
+            ));
+            report.push(fill(
+                "  suggestion: use --ech if the resolver supports Encrypted Client Hello",
+                &[],
+            ));
+        }
+    } else {
+        report.push(fill(
+            "transport: {} is plaintext, query names and answers are visible to anyone on the path",
+            &[&options.transport.transport_mode],
+        ));
+        report.push(fill(
+            "  suggestion: use an encrypted transport (--tls, --https or --doq)",
+            &[],
+        ));
+    }
+
+    // padding is only useful when encrypted
+    match (encrypted, options.edns.padding) {
+        (true, None) => {
+            report.push(fill(
+                "padding: not used, the size of encrypted messages may reveal the query name",
+                &[],
+            ));
+            report.push(fill("  suggestion: use --padding=128 as recommended by RFC8467", &[]));
+        }
+        (false, Some(_)) => report.push(fill(
+            "padding: useless with a plaintext transport, messages are readable anyway",
+            &[],
+        )),
+        _ => (),
+    }
+
+    // ECS reveals the client network to the resolver and authoritative servers
+    if let Some(subnet) = options
+        .edns
+        .client_subnet
+        .as_ref()
+        .and_then(|s| ClientSubnet::from_str(s).ok())
+    {
+        let max = match subnet.address() {
+            Some(ip) if ip.is_ipv6() => ECS_MAX_PREFIX_V6,
+            _ => ECS_MAX_PREFIX_V4,
+        };
+        report.push(fill(
+            "ECS: client subnet {} is sent to the resolver and forwarded to authoritative servers",
+            &[&subnet],
+        ));
+        if subnet.source_prefix() > max {
+            report.push(fill(
+                "  suggestion: don't send more than /{} (RFC7871), or a /0 prefix to opt out",
+                &[&max],
+            ));
+        }
+    }
+
+    // cookies allow to track a client
+    if options.edns.cookie.as_ref().is_some_and(|c| !c.is_empty()) {
+        report.push(fill(
+            "cookie: a fixed client cookie allows servers to link queries sent from different networks",
+            &[],
+        ));
+        report.push(fill("  suggestion: use --cookie without value to get a client cookie derived from the client and server addresses", &[]));
+    }
+
+    // QNAME minimization is never done: the full name goes to the resolver, and
+    // to each server from the root when tracing
+    if options.display.trace {
+        report.push(fill(
+            "QNAME minimization: not done, the full query name is sent to root and TLD servers",
+            &[],
+        ));
+    } else {
+        report.push(fill(
+            "QNAME: the full query name is sent to the resolver, which may or may not do QNAME minimization (RFC9156)",
+            &[],
+        ));
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        let opts = CliOptions::from_str("@1.1.1.1 A www.example.com --ecs=192.0.2.0/28").unwrap();
+        let report = privacy_report(&opts);
+        assert!(report[0].contains("plaintext"));
+        assert!(report.iter().any(|l| l.contains("192.0.2.0/28/0")));
+        assert!(report.iter().any(|l| l.contains("more than /24")));
+
+        let opts = CliOptions::from_str("@1.1.1.1 A www.example.com --tls --padding=128").unwrap();
+        let report = privacy_report(&opts);
+        assert!(report[0].contains("encrypted"));
+        assert!(!report.iter().any(|l| l.starts_with("padding")));
+    }
+}
@@ -0,0 +1,317 @@
+//! Discover the maximum UDP response size deliverable path-wise to the client (--probe-bufsize)
+//! by sending the same query with different EDNS bufsize values, like DNS flag day tests.
+//! Also checks whether fragmented responses are dropped (--frag-test) and the health of
+//! the resolvers (--check-resolvers).
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::args::CliOptions;
+use crate::dns::rfc::{domain::ROOT_DOMAIN, qtype::QType};
+use crate::error::{Dns, Error, Network, Result};
+use crate::get_messages;
+use crate::protocol::DnsProtocol;
+use crate::show::header_section;
+use crate::transport::endpoint::EndPoint;
+use crate::transport::network::{Messenger, Protocol};
+use crate::transport::udp::UdpProtocol;
+
+// range of bufsize values to probe
+const MIN_BUFSIZE: u16 = 512;
+const MAX_BUFSIZE: u16 = 4096;
+
+// what we got for a bufsize
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    // full response received, with its size
+    Delivered(usize),
+
+    // the response doesn't fit into the bufsize
+    Truncated,
+
+    // nothing received before timeout: the response was probably dropped (fragmentation)
+    Lost,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Delivered(size) => write!(f, "response of {} bytes received", size),
+            Outcome::Truncated => f.write_str("truncated response received"),
+            Outcome::Lost => f.write_str("no response (timeout)"),
+        }
+    }
+}
+
+// send the query using UDP with the bufsize
+fn probe(options: &mut CliOptions, bufsize: u16) -> Result<Outcome> {
+    options.transport.bufsize = bufsize;
+    let qt = options.protocol.qtype[0];
+
+    let mut udp = UdpProtocol::new(&options.transport)?;
+    let mut buffer = vec![0u8; u16::MAX as usize];
+
+    DnsProtocol::send_query(options, &qt, &mut udp)?;
+    match DnsProtocol::receive_response(&mut udp, &mut buffer, &None) {
+        Ok(resp) if resp.is_truncated() => Ok(Outcome::Truncated),
+        Ok(_) => Ok(Outcome::Delivered(udp.network_info().received)),
+        Err(Error::Network(_, Network::Receive)) => Ok(Outcome::Lost),
+        Err(e) => Err(e),
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// binary search for the largest bufsize for which a response is received, returned
+// with the largest response. Only a full response proves the path can carry it
+//───────────────────────────────────────────────────────────────────────────────────
+fn search(mut probe: impl FnMut(u16) -> Result<Outcome>) -> Result<(u16, usize)> {
+    let (mut low, mut high) = (MIN_BUFSIZE, MAX_BUFSIZE);
+    let mut largest = 0usize;
+
+    while low < high {
+        let bufsize = low + (high - low).div_ceil(2);
+
+        match probe(bufsize)? {
+            Outcome::Delivered(size) => {
+                largest = largest.max(size);
+                low = bufsize;
+            }
+            // a truncated response is small and doesn't tell whether a larger one gets through
+            Outcome::Truncated | Outcome::Lost => high = bufsize - 1,
+        }
+    }
+
+    Ok((low, largest))
+}
+
+pub fn probe_bufsize(options: &mut CliOptions) -> Result<()> {
+    // probing only makes sense with UDP and EDNS
+    options.transport.transport_mode = Protocol::Udp;
+    options.edns.no_opt = false;
+
+    let (low, largest) = search(|bufsize| {
+        options.transport.cancel.check()?;
+
+        let outcome = probe(options, bufsize)?;
+        println!("bufsize {:>5}: {}", bufsize, outcome);
+        Ok(outcome)
+    })?;
+
+    println!("\neffective bufsize: {} bytes", low);
+    println!("largest response received: {} bytes", largest);
+
+    // the response is too small to test large sizes
+    if largest < low as usize {
+        println!(
+            "response for {} is smaller than the bufsize probed: use a type giving larger responses (e.g.: DNSKEY, TXT)",
+            options.protocol.qtype[0]
+        );
+    }
+
+    Ok(())
+}
+
+// bufsize values tried with --frag-test: around the flag day value and the ethernet MTU
+const FRAG_STEPS: [u16; 10] = [512, 1232, 1280, 1400, 1452, 1472, 1500, 2048, 3000, 4096];
+
+// bufsize recommended by DNS flag day 2020: https://www.dnsflagday.net/2020/
+const FLAG_DAY_BUFSIZE: u16 = 1232;
+
+// largest UDP payload not fragmented with a 1500-byte MTU
+fn fragmentation_threshold(ipv6: bool) -> usize {
+    if ipv6 {
+        1500 - 40 - 8
+    } else {
+        1500 - 20 - 8
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// ask for larger and larger responses over UDP to find whether fragmented ones are
+// dropped on the path (--frag-test)
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn frag_test(options: &mut CliOptions) -> Result<()> {
+    options.transport.transport_mode = Protocol::Udp;
+    options.edns.no_opt = false;
+
+    let ipv6 = options.transport.endpoint.addrs.first().is_some_and(|a| a.is_ipv6());
+    let threshold = fragmentation_threshold(ipv6);
+
+    let mut largest = 0usize;
+    let mut first_lost: Option<u16> = None;
+
+    for bufsize in FRAG_STEPS {
+        options.transport.cancel.check()?;
+
+        let outcome = probe(options, bufsize)?;
+        let note = match outcome {
+            Outcome::Delivered(size) if size > threshold => " (fragmented)",
+            _ => "",
+        };
+        println!("bufsize {:>5}: {}{}", bufsize, outcome, note);
+
+        match outcome {
+            Outcome::Delivered(size) => largest = largest.max(size),
+            Outcome::Truncated => (),
+            Outcome::Lost => {
+                first_lost.get_or_insert(bufsize);
+            }
+        }
+    }
+
+    println!(
+        "
+largest response received over UDP: {} bytes",
+        largest
+    );
+
+    match first_lost {
+        Some(bufsize) if bufsize <= FLAG_DAY_BUFSIZE => println!(
+            "responses are lost even with a bufsize of {} bytes: UDP is unreliable on this path, use TCP",
+            bufsize
+        ),
+        Some(bufsize) => {
+            println!(
+                "responses are lost from a bufsize of {} bytes: those larger than {} bytes need IP fragmentation, fragments are likely dropped by a firewall or a NAT",
+                bufsize, threshold
+            );
+            println!(
+                "use a bufsize of {} bytes (DNS flag day 2020) to avoid fragmentation",
+                FLAG_DAY_BUFSIZE
+            );
+        }
+        None if largest <= threshold => {
+            println!("no fragmented response was received: use a type giving larger responses (e.g.: DNSKEY, TXT)")
+        }
+        None => println!("fragmented responses are delivered on this path"),
+    }
+
+    Ok(())
+}
+
+// send the query to the address with this transport: RTT and EDNS support if answered
+fn ping(options: &CliOptions, addr: SocketAddr, mode: Protocol) -> Option<(Duration, bool)> {
+    let mut options = options.clone();
+
+    // DoT is checked on its own port
+    let port = match mode {
+        #[cfg(feature = "dot")]
+        Protocol::DoT => mode.default_port(),
+        _ => addr.port(),
+    };
+    options.transport.endpoint = EndPoint {
+        server_name: addr.ip().to_string(),
+        port,
+        addrs: vec![SocketAddr::new(addr.ip(), port)],
+        sni: None,
+    };
+    options.transport.transport_mode = mode;
+
+    let messages = get_messages(None, &options).ok()?;
+    let msg = messages.first()?;
+    Some((msg.elapsed, msg.response().has_opt()))
+}
+
+fn yes_no(b: bool) -> &'static str {
+    if b {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// query . NS to each resolver address and report reachability, RTT and whether EDNS,
+// TCP and DoT are supported (--check-resolvers)
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn check_resolvers(options: &mut CliOptions) -> Result<()> {
+    options.protocol.domain_string = String::from(".");
+    options.protocol.domain_name = ROOT_DOMAIN;
+    options.protocol.qtype = vec![QType::NS];
+    options.edns.no_opt = false;
+
+    // all addresses of all the resolvers given
+    let mut addrs: Vec<SocketAddr> = options.transport.endpoint.addrs.clone();
+    for addr in options.transport.endpoints.iter().flat_map(|ep| ep.addrs.iter()) {
+        if !addrs.contains(addr) {
+            addrs.push(*addr);
+        }
+    }
+
+    println!("{}", header_section("RESOLVERS", None));
+    println!(
+        "{:<45} {:>4} {:>8} {:>5} {:>4} {:>4}",
+        "resolver", "udp", "rtt(ms)", "edns", "tcp", "dot"
+    );
+
+    let mut reachable = 0;
+    for addr in addrs {
+        options.transport.cancel.check()?;
+
+        let udp = ping(options, addr, Protocol::Udp);
+        let tcp = ping(options, addr, Protocol::Tcp);
+        #[cfg(feature = "dot")]
+        let dot = ping(options, addr, Protocol::DoT);
+        #[cfg(not(feature = "dot"))]
+        let dot: Option<(Duration, bool)> = None;
+
+        let rtt = udp.map_or(String::from("-"), |(elapsed, _)| elapsed.as_millis().to_string());
+        let edns = udp.or(tcp).is_some_and(|(_, edns)| edns);
+        println!(
+            "{:<45} {:>4} {:>8} {:>5} {:>4} {:>4}",
+            addr.to_string(),
+            yes_no(udp.is_some()),
+            rtt,
+            yes_no(edns),
+            yes_no(tcp.is_some()),
+            yes_no(dot.is_some())
+        );
+
+        if udp.is_some() || tcp.is_some() || dot.is_some() {
+            reachable += 1;
+        }
+    }
+
+    if reachable == 0 {
+        return Err(Error::Dns(Dns::UnreachableResolvers));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threshold() {
+        assert_eq!(fragmentation_threshold(false), 1472);
+        assert_eq!(fragmentation_threshold(true), 1452);
+        assert!(FRAG_STEPS.contains(&FLAG_DAY_BUFSIZE));
+    }
+
+    #[test]
+    fn bufsize_search() {
+        // responses over 1400 bytes are dropped on the path
+        let path = |bufsize: u16| {
+            Ok(if bufsize <= 1400 {
+                Outcome::Delivered(bufsize as usize)
+            } else {
+                Outcome::Lost
+            })
+        };
+        assert_eq!(search(path).unwrap(), (1400, 1400));
+
+        // truncated responses don't raise the effective bufsize
+        assert_eq!(search(|_| Ok(Outcome::Truncated)).unwrap(), (MIN_BUFSIZE, 0));
+
+        // a 1000-byte response: truncated below, delivered above
+        let small = |bufsize: u16| {
+            Ok(if bufsize < 1000 {
+                Outcome::Truncated
+            } else {
+                Outcome::Delivered(1000)
+            })
+        };
+        assert_eq!(search(small).unwrap(), (MAX_BUFSIZE, 1000));
+    }
+}
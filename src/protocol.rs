@@ -1,16 +1,31 @@
 use std::path::PathBuf;
+#[cfg(feature = "doq")]
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::Instant;
 
 use log::{debug, info};
+#[cfg(feature = "doq")]
+use tokio::task::JoinSet;
 
+use crate::cookies;
 use crate::dns::{
+    date_time::timestamp,
     message::{Message, MessageList},
-    rfc::{qtype::QType, query::Query, response::Response},
+    rfc::{qtype::QType, query::Query, response::Response, response_code::ResponseCode},
 };
-use crate::error::{self};
+use crate::error::{self, ErrorClass};
+use crate::pacing::backoff;
 use crate::transport::network::{Messenger, Protocol};
+#[cfg(feature = "doq")]
+use crate::transport::quic::QuicProtocol;
 use crate::transport::tcp::TcpProtocol;
+use crate::transport::NetworkInfo;
 use crate::{args::CliOptions, cli_options::FromOptions};
 
+// number of unrelated messages read on a stream before giving up on our response
+const MAX_SKIPPED_RESPONSES: usize = 16;
+
 // a unit struct with gathers all high level functions
 pub(crate) struct DnsProtocol;
 
@@ -18,10 +33,13 @@ impl DnsProtocol {
     //───────────────────────────────────────────────────────────────────────────────────
     // send the query to the resolver
     //───────────────────────────────────────────────────────────────────────────────────
-    fn send_query<T: Messenger>(options: &CliOptions, qt: &QType, trp: &mut T) -> error::Result<Query> {
+    pub(crate) fn send_query<T: Messenger>(options: &CliOptions, qt: &QType, trp: &mut T) -> error::Result<Query> {
         // it's safe to unwrap here, see from_options() for Query
         let mut query = Query::from_options(options, qt).unwrap();
 
+        // client and server cookies for this resolver
+        cookies::prepare(&mut query, options, trp.network_info().peer);
+
         // TCP needs to prepend with 2 bytes for message length
         if trp.uses_leading_length() {
             query = query.with_length();
@@ -41,10 +59,17 @@ impl DnsProtocol {
     //───────────────────────────────────────────────────────────────────────────────────
     // send the query to the resolver, async version
     //───────────────────────────────────────────────────────────────────────────────────
+    #[cfg(feature = "doq")]
     async fn asend_query<T: Messenger>(options: &CliOptions, qt: &QType, trp: &mut T) -> error::Result<Query> {
         // it's safe to unwrap here, see from_options() for Query
         let mut query = Query::from_options(options, qt).unwrap();
 
+        // DoQ must set ID to 0 (RFC9250 section 4.2.1)
+        query.header.set_id(0);
+
+        // client and server cookies for this resolver
+        cookies::prepare(&mut query, options, trp.network_info().peer);
+
         // TCP needs to prepend with 2 bytes for message length
         if trp.uses_leading_length() {
             query = query.with_length();
@@ -65,7 +90,7 @@ impl DnsProtocol {
     // receive response from resolver
     //───────────────────────────────────────────────────────────────────────────────────
     #[inline(always)]
-    fn receive_response<T: Messenger>(
+    pub(crate) fn receive_response<T: Messenger>(
         trp: &mut T,
         buffer: &mut [u8],
         save_path: &Option<PathBuf>,
@@ -76,19 +101,388 @@ impl DnsProtocol {
         Ok(response)
     }
 
+    //───────────────────────────────────────────────────────────────────────────────────
+    // over UDP, resend the same query when no response is received in time, waiting
+    // longer before each retry (--max-udp-retries). The number of retries is returned
+    // with the response
+    //───────────────────────────────────────────────────────────────────────────────────
+    fn receive_or_retry<T: Messenger>(
+        options: &CliOptions,
+        query: &mut Query,
+        trp: &mut T,
+        buffer: &mut [u8],
+    ) -> crate::error::Result<(Response, u32)> {
+        let mut retry = 0;
+
+        loop {
+            match Self::receive_response(trp, buffer, &options.dump.write_response) {
+                Err(e)
+                    if trp.mode() == Protocol::Udp
+                        && e.class() == ErrorClass::Timeout
+                        && retry < options.transport.udp_retries =>
+                {
+                    retry += 1;
+                    let delay = backoff(options.transport.udp_backoff, retry as u32, rand::random());
+                    info!(
+                        "no response for {} {}, retry {}/{} in {} ms",
+                        query.question.qname,
+                        query.question.qtype,
+                        retry,
+                        options.transport.udp_retries,
+                        delay.as_millis()
+                    );
+                    sleep(delay);
+                    options.transport.cancel.check()?;
+                    query.send(trp, &options.dump.write_query)?;
+                }
+                result => return result.map(|response| (response, retry as u32)),
+            }
+        }
+    }
+
     //───────────────────────────────────────────────────────────────────────────────────
     // receive response from resolver, async version
     //───────────────────────────────────────────────────────────────────────────────────
+    #[cfg(feature = "doq")]
     #[inline(always)]
     async fn areceive_response<T: Messenger>(
         trp: &mut T,
         buffer: &mut [u8],
         save_path: &Option<PathBuf>,
-    ) -> crate::error::Result<Response> {
+    ) -> crate::error::Result<(Response, usize)> {
         let mut response = Response::default();
-        let _ = response.arecv(trp, buffer, save_path).await?;
+        let received = response.arecv(trp, buffer, save_path).await?;
 
-        Ok(response)
+        Ok((response, received))
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // old servers not implementing EDNS reply FORMERR or NOTIMP without OPT to a query
+    // with an OPT record (RFC6891 section 7): the query is worth resending without it
+    //───────────────────────────────────────────────────────────────────────────────────
+    fn edns_rejected(options: &CliOptions, response: &Response) -> bool {
+        !options.edns.no_opt
+            && !options.edns.no_fallback
+            && matches!(response.rcode(), ResponseCode::FormErr | ResponseCode::NotImp)
+            && !response.has_opt()
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // send a query and get its response using a sync transport
+    //───────────────────────────────────────────────────────────────────────────────────
+    fn sync_exchange<T: Messenger>(
+        options: &CliOptions,
+        qtype: &QType,
+        trp: &mut T,
+        buffer: &mut [u8],
+    ) -> crate::error::Result<Message> {
+        // send query, response is depending on TC flag if UDP
+        let timestamp = options.display.timestamp.then(timestamp);
+        let now = Instant::now();
+        let mut query = Self::send_query(options, qtype, trp)?;
+        let (mut response, mut retransmissions) = Self::receive_or_retry(options, &mut query, trp, buffer)?;
+
+        // on a stream, the server may have sent other messages before ours (e.g. a late
+        // answer to a previous query on the same connection): skip them
+        if trp.mode().uses_leading_length() {
+            let mut skipped = 0;
+            while response.id() != query.header.id && skipped < MAX_SKIPPED_RESPONSES {
+                debug!(
+                    "skipping response with ID {} for query ID {}",
+                    response.id(),
+                    query.header.id
+                );
+                response = Self::receive_response(trp, buffer, &options.dump.write_response)?;
+                skipped += 1;
+            }
+        }
+
+        // the server didn't accept our server cookie, but sent a new one (RFC7873 section
+        // 5.3): resend once with it
+        if options.edns.badcookie && response.is_bad_cookie() {
+            info!(
+                "query for {} got BADCOOKIE, resending with the new server cookie",
+                qtype
+            );
+            cookies::learn(&query, &response, trp.network_info().peer);
+            buffer.fill(0);
+
+            query = Self::send_query(options, qtype, trp)?;
+            let (cookie_response, retries) = Self::receive_or_retry(options, &mut query, trp, buffer)?;
+            response = cookie_response;
+            retransmissions += retries;
+        }
+
+        // resend without EDNS if the server doesn't support it
+        let edns_fallback = Self::edns_rejected(options, &response);
+        if edns_fallback {
+            info!(
+                "query for {} rejected with {}, resending without EDNS",
+                qtype,
+                response.rcode()
+            );
+            buffer.fill(0);
+
+            let mut plain = options.clone();
+            plain.edns.no_opt = true;
+            query = Self::send_query(&plain, qtype, trp)?;
+            let (plain_response, retries) = Self::receive_or_retry(options, &mut query, trp, buffer)?;
+            response = plain_response;
+            retransmissions += retries;
+        }
+        let mut netinfo = *trp.network_info();
+        netinfo.edns_fallback = edns_fallback;
+        netinfo.retransmissions = retransmissions;
+
+        // check for the truncation (TC) header flag. If set and UDP, resend using TCP
+        if response.is_truncated() && trp.mode() == Protocol::Udp {
+            info!("query for {} caused truncation, resending using TCP", qtype);
+
+            // clear buffer using fill(), otherwise buffer will be empty if buffer.clear()
+            buffer.fill(0);
+
+            // resend using TCP, without EDNS if it was rejected
+            let mut tcp_transport = TcpProtocol::new(&options.transport)?;
+            let mut tcp_options = options.clone();
+            tcp_options.edns.no_opt |= edns_fallback;
+            query = Self::send_query(&tcp_options, qtype, &mut tcp_transport)?;
+            response = Self::receive_response(&mut tcp_transport, buffer, &options.dump.write_response)?;
+            netinfo = NetworkInfo {
+                tcp_fallback: true,
+                edns_fallback,
+                retransmissions,
+                ..*tcp_transport.network_info()
+            };
+        }
+
+        cookies::learn(&query, &response, netinfo.peer);
+
+        // struct Message is a convenient way to gather both query and response
+        let msg = Message {
+            query,
+            response,
+            netinfo,
+            raw_response: buffer[..netinfo.received].to_vec(),
+            elapsed: now.elapsed(),
+            timestamp,
+        };
+        msg.check()?;
+        Ok(msg)
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // send a query and get its response using an async transport
+    //───────────────────────────────────────────────────────────────────────────────────
+    #[cfg(feature = "doq")]
+    async fn async_exchange<T: Messenger>(
+        options: &CliOptions,
+        qtype: &QType,
+        trp: &mut T,
+        buffer: &mut [u8],
+    ) -> crate::error::Result<Message> {
+        // for QUIC, we need a specific stream for each query as stated in https://www.rfc-editor.org/rfc/rfc9250.html
+        trp.aconnect().await?;
+
+        let timestamp = options.display.timestamp.then(timestamp);
+        let now = Instant::now();
+        let query = Self::asend_query(options, qtype, trp).await?;
+        let (response, received) = Self::areceive_response(trp, buffer, &options.dump.write_response).await?;
+        if response.id() != 0 {
+            return Err(error::Error::Quic(error::QuicError::NonZeroId(response.id())));
+        }
+
+        // struct Message is a convenient way to gather both query and response
+        let netinfo = *trp.network_info();
+        cookies::learn(&query, &response, netinfo.peer);
+        let msg = Message {
+            query,
+            response,
+            netinfo,
+            raw_response: buffer[..received].to_vec(),
+            elapsed: now.elapsed(),
+            timestamp,
+        };
+        msg.check()?;
+        Ok(msg)
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // AXFR on a stream: the zone comes in as many messages as needed, between 2 copies
+    // of its SOA (RFC5936 section 2.2). The answers of all messages are gathered in the
+    // first one
+    //───────────────────────────────────────────────────────────────────────────────────
+    fn zone_transfer<T: Messenger>(options: &CliOptions, trp: &mut T) -> crate::error::Result<Message> {
+        // a message can be up to 64 KB on a stream
+        let mut buffer = vec![0u8; u16::MAX as usize];
+        let cancel = &options.transport.cancel;
+        let soas = |resp: &Response| {
+            resp.answer
+                .iter()
+                .flat_map(|a| a.iter())
+                .filter(|rr| rr.r#type == QType::SOA)
+                .count()
+        };
+
+        let timestamp = options.display.timestamp.then(timestamp);
+        let now = Instant::now();
+        let query = Self::send_query(options, &QType::AXFR, trp)?;
+        let mut response = Self::receive_response(trp, &mut buffer, &options.dump.write_response)?;
+        let mut netinfo = *trp.network_info();
+        let raw_response = buffer[..netinfo.received].to_vec();
+
+        // a refused transfer is an answer like any other
+        if response.rcode() == ResponseCode::NoError {
+            match response.answer.as_ref().and_then(|a| a.first()) {
+                Some(rr) if rr.r#type == QType::SOA => (),
+                _ => return Err(error::Error::Dns(error::Dns::ZoneTransfer("first RR is not the SOA"))),
+            }
+
+            let mut seen = soas(&response);
+            let mut messages = 1;
+            while seen < 2 {
+                if cancel.is_cancelled() {
+                    info!("zone transfer cancelled after {} message(s)", messages);
+                    break;
+                }
+
+                let next = Self::receive_response(trp, &mut buffer, &options.dump.write_response)?;
+                if next.id() != query.header.id {
+                    return Err(error::Error::Dns(error::Dns::ZoneTransfer(
+                        "message ID differs from the query",
+                    )));
+                }
+                if next.rcode() != ResponseCode::NoError {
+                    return Err(error::Error::Dns(error::Dns::ZoneTransfer(
+                        "error in the middle of the transfer",
+                    )));
+                }
+
+                seen += soas(&next);
+                messages += 1;
+                netinfo.received += trp.network_info().received;
+                response.append_answer(next);
+            }
+            info!(
+                "zone transfer of {} RRs in {} message(s)",
+                response.answer.as_ref().map_or(0, |a| a.len()),
+                messages
+            );
+        }
+
+        let msg = Message {
+            query,
+            response,
+            netinfo,
+            raw_response,
+            elapsed: now.elapsed(),
+            timestamp,
+        };
+        msg.check()?;
+        Ok(msg)
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // --parallel: all queries are sent at once on the same socket or connection, and
+    // responses are matched to queries by ID in whatever order they arrive (RFC7766
+    // section 6.2.1.1 for TCP). Queries not answered before the timeout are None
+    //───────────────────────────────────────────────────────────────────────────────────
+    fn sync_pipeline<T: Messenger>(
+        options: &CliOptions,
+        trp: &mut T,
+        buffer: &mut [u8],
+    ) -> crate::error::Result<Vec<Option<Message>>> {
+        let timestamp = options.display.timestamp.then(timestamp);
+        let now = Instant::now();
+
+        let mut queries = Vec::with_capacity(options.protocol.qtype.len());
+        for qtype in options.protocol.qtype.iter() {
+            queries.push(Some(Self::send_query(options, qtype, trp)?));
+        }
+        let mut messages: Vec<Option<Message>> = queries.iter().map(|_| None).collect();
+
+        let mut pending = queries.len();
+        let mut skipped = 0;
+        while pending > 0 && skipped < MAX_SKIPPED_RESPONSES {
+            let response = match Self::receive_response(trp, buffer, &options.dump.write_response) {
+                Ok(response) => response,
+                Err(e) if e.class() == ErrorClass::Timeout => break,
+                Err(e) => return Err(e),
+            };
+
+            let matching = queries.iter().position(|query| {
+                query
+                    .as_ref()
+                    .is_some_and(|q| q.header.id == response.id() && q.question.qtype == response.question.qtype)
+            });
+            let Some(i) = matching else {
+                debug!("skipping response with ID {} matching no query", response.id());
+                skipped += 1;
+                continue;
+            };
+
+            let netinfo = *trp.network_info();
+            cookies::learn(queries[i].as_ref().unwrap(), &response, netinfo.peer);
+            messages[i] = Some(Message {
+                query: queries[i].take().unwrap(),
+                response,
+                netinfo,
+                raw_response: buffer[..netinfo.received].to_vec(),
+                elapsed: now.elapsed(),
+                timestamp: timestamp.clone(),
+            });
+            pending -= 1;
+        }
+        info!(
+            "{} of {} pipelined queries answered",
+            messages.len() - pending,
+            messages.len()
+        );
+
+        Ok(messages)
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // pipelined queries, then the ones left unanswered, truncated or rejected because of
+    // EDNS are resent one by one to get the usual retries and fallbacks
+    //───────────────────────────────────────────────────────────────────────────────────
+    fn sync_parallel_request<T: Messenger>(
+        options: &CliOptions,
+        trp: &mut T,
+        buffer: &mut [u8],
+    ) -> crate::error::Result<MessageList> {
+        let pipelined = Self::sync_pipeline(options, trp, buffer)?;
+        let mut messages = Vec::with_capacity(pipelined.len());
+        let cancel = &options.transport.cancel;
+
+        for (qtype, msg) in options.protocol.qtype.iter().zip(pipelined) {
+            match msg {
+                Some(msg) if !msg.response.is_truncated() && !Self::edns_rejected(options, &msg.response) => {
+                    msg.check()?;
+                    messages.push(msg);
+                }
+                _ if cancel.is_cancelled() => break,
+                // --all-types resends them one by one itself, to report their errors
+                _ if options.dump.all_types => continue,
+                _ => match Self::sync_exchange(options, qtype, trp, buffer) {
+                    Ok(msg) => messages.push(msg),
+                    Err(_) if cancel.is_cancelled() => break,
+                    Err(e) => return Err(e),
+                },
+            }
+        }
+
+        Self::partial_results(options, messages)
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // when the operation is cancelled, keep messages already received if any
+    //───────────────────────────────────────────────────────────────────────────────────
+    fn partial_results(options: &CliOptions, messages: Vec<Message>) -> crate::error::Result<MessageList> {
+        if messages.is_empty() {
+            options.transport.cancel.check()?;
+        }
+
+        Ok(MessageList::new(messages))
     }
 
     //───────────────────────────────────────────────────────────────────────────────────
@@ -102,37 +496,47 @@ impl DnsProtocol {
         // we'll have the same number of messages than the number of types to query
         let mut messages = Vec::with_capacity(options.protocol.qtype.len());
         let mut buffer = vec![0u8; buffer_size];
+        let cancel = &options.transport.cancel;
 
-        for qtype in options.protocol.qtype.iter() {
-            // send query, response is depending on TC flag if UDP
-            let mut query = Self::send_query(options, qtype, trp)?;
-            let mut response = Self::receive_response(trp, &mut buffer, &options.dump.write_response)?;
+        // DoH sends and receives in one go: it can't be pipelined
+        let qtypes = &options.protocol.qtype;
+        if options.transport.parallel
+            && qtypes.len() > 1
+            && !qtypes.contains(&QType::AXFR)
+            && (trp.mode() == Protocol::Udp || trp.mode().is_tcp())
+        {
+            return Self::sync_parallel_request(options, trp, &mut buffer);
+        }
 
-            // check for the truncation (TC) header flag. If set and UDP, resend using TCP
-            if response.is_truncated() && trp.mode() == Protocol::Udp {
-                info!("query for {} caused truncation, resending using TCP", qtype);
+        for qtype in options.protocol.qtype.iter() {
+            // stop here if cancelled but keep what we've already got
+            if cancel.is_cancelled() {
+                info!("operation cancelled after {} message(s)", messages.len());
+                break;
+            }
 
-                // clear buffer using fill(), otherwise buffer will be empty if buffer.clear()
-                buffer.fill(0);
+            // a zone transfer can span several messages on a stream
+            let exchange = if *qtype == QType::AXFR && trp.mode().is_tcp() {
+                Self::zone_transfer(options, trp)
+            } else {
+                Self::sync_exchange(options, qtype, trp, &mut buffer)
+            };
 
-                // resend using TCP
-                let mut tcp_transport = TcpProtocol::new(&options.transport)?;
-                query = Self::send_query(options, qtype, &mut tcp_transport)?;
-                response = Self::receive_response(&mut tcp_transport, &mut buffer, &options.dump.write_response)?;
+            // a blocking read might fail because of the cancellation
+            match exchange {
+                Ok(msg) => messages.push(msg),
+                Err(_) if cancel.is_cancelled() => break,
+                Err(e) => return Err(e),
             }
-
-            // struct Message is a convenient way to gather both query and response
-            let msg = Message { query, response };
-            msg.check()?;
-            messages.push(msg);
         }
 
-        Ok(MessageList::new(messages))
+        Self::partial_results(options, messages)
     }
 
     //───────────────────────────────────────────────────────────────────────────────────
-    // this sends and receives queries using an async transport
+    // this sends and receives queries using an async transport (only DoQ is)
     //───────────────────────────────────────────────────────────────────────────────────
+    #[cfg(feature = "doq")]
     pub(crate) async fn async_process_request<T: Messenger>(
         options: &CliOptions,
         trp: &mut T,
@@ -141,21 +545,71 @@ impl DnsProtocol {
         // we'll have the same number of messages than the number of types to query
         let mut messages = Vec::with_capacity(options.protocol.qtype.len());
         let mut buffer = vec![0u8; buffer_size];
+        let cancel = &options.transport.cancel;
 
         for qtype in options.protocol.qtype.iter() {
-            // for QUIC, we need a specific stream for each query as stated in https://www.rfc-editor.org/rfc/rfc9250.html
-            trp.aconnect().await?;
+            // stop here if cancelled but keep what we've already got
+            if cancel.is_cancelled() {
+                info!("operation cancelled after {} message(s)", messages.len());
+                break;
+            }
+
+            match Self::async_exchange(options, qtype, trp, &mut buffer).await {
+                Ok(msg) => messages.push(msg),
+                Err(_) if cancel.is_cancelled() => break,
+                Err(e) => return Err(e),
+            }
+        }
 
-            // send query, response is depending on TC flag if UDP
-            let query = Self::asend_query(options, qtype, trp).await?;
-            let response = Self::areceive_response(trp, &mut buffer, &options.dump.write_response).await?;
+        Self::partial_results(options, messages)
+    }
 
-            // struct Message is a convenient way to gather both query and response
-            let msg = Message { query, response };
-            msg.check()?;
-            messages.push(msg);
+    //───────────────────────────────────────────────────────────────────────────────────
+    // --parallel over DoQ: each query on its own stream of the same connection, all
+    // exchanges running concurrently
+    //───────────────────────────────────────────────────────────────────────────────────
+    #[cfg(feature = "doq")]
+    pub(crate) async fn async_parallel_request(
+        options: &CliOptions,
+        trp: &mut QuicProtocol,
+        buffer_size: usize,
+    ) -> crate::error::Result<MessageList> {
+        // tasks must own their data: options are shared and each task gets its own stream
+        let shared = Arc::new(options.clone());
+        let mut exchanges = JoinSet::new();
+        for (index, qtype) in options.protocol.qtype.iter().enumerate() {
+            let (options, qtype, mut stream) = (Arc::clone(&shared), *qtype, trp.fork());
+            exchanges.spawn(async move {
+                let mut buffer = vec![0u8; buffer_size];
+                (
+                    index,
+                    Self::async_exchange(&options, &qtype, &mut stream, &mut buffer).await,
+                )
+            });
         }
 
-        Ok(MessageList::new(messages))
+        // outputs come in completion order, sorted back to the order of the types
+        let mut outputs = Vec::with_capacity(options.protocol.qtype.len());
+        while let Some(output) = exchanges.join_next().await {
+            outputs.push(output.map_err(|e| error::Error::Tokio(e.into()))?);
+        }
+        outputs.sort_by_key(|(index, _)| *index);
+
+        let cancel = &options.transport.cancel;
+        let mut messages = Vec::with_capacity(outputs.len());
+        for (_, exchange) in outputs {
+            match exchange {
+                Ok(msg) => messages.push(msg),
+                Err(_) if cancel.is_cancelled() => break,
+                Err(_) if options.dump.all_types => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        // run info is the one of the last exchange, as when queries are sequential
+        if let Some(msg) = messages.last() {
+            trp.netinfo = msg.netinfo;
+        }
+        Self::partial_results(options, messages)
     }
 }
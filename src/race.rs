@@ -0,0 +1,93 @@
+//! Send the query to all resolver addresses simultaneously and keep the fastest valid response (--race)
+use std::net::SocketAddr;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::args::CliOptions;
+use crate::dns::message::MessageList;
+use crate::dns::rfc::response_code::ResponseCode;
+use crate::error::{Dns, Error, Result};
+use crate::get_messages;
+use crate::show::{header_section, QueryInfo};
+use crate::transport::network::Protocol;
+
+// a response is valid if there's one and the server didn't fail to resolve or refuse the query
+fn is_valid(messages: &MessageList) -> bool {
+    !messages.is_empty()
+        && messages
+            .iter()
+            .all(|msg| !matches!(msg.response().rcode(), ResponseCode::ServFail | ResponseCode::Refused))
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// one UDP exchange per address, each in its own thread. The first valid response
+// wins, and the RTT of each server is listed
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn race(info: Option<&mut QueryInfo>, options: &CliOptions) -> Result<MessageList> {
+    // all addresses of all the resolvers given
+    let mut addrs = options.transport.endpoint.addrs.clone();
+    for addr in options.transport.endpoints.iter().flat_map(|ep| ep.addrs.iter()) {
+        if !addrs.contains(addr) {
+            addrs.push(*addr);
+        }
+    }
+    if addrs.is_empty() {
+        return Err(Error::Dns(Dns::UnreachableResolvers));
+    }
+
+    let (tx, rx) = mpsc::channel::<(SocketAddr, std::result::Result<MessageList, String>, Duration)>();
+    let mut winner: Option<(SocketAddr, MessageList)> = None;
+    let mut rtts: Vec<(SocketAddr, String)> = Vec::with_capacity(addrs.len());
+
+    thread::scope(|s| {
+        for addr in &addrs {
+            let tx = tx.clone();
+            let mut opts = options.clone();
+            opts.transport.transport_mode = Protocol::Udp;
+            opts.transport.endpoint.addrs = vec![*addr];
+
+            s.spawn(move || {
+                let now = Instant::now();
+                let result = get_messages(None, &opts).map_err(|e| e.localized());
+                let _ = tx.send((*addr, result, now.elapsed()));
+            });
+        }
+        drop(tx);
+
+        // results are received from the fastest to the slowest server
+        for (addr, result, elapsed) in rx {
+            match result {
+                Ok(messages) if is_valid(&messages) => {
+                    rtts.push((addr, format!("{} ms", elapsed.as_millis())));
+                    if winner.is_none() {
+                        winner = Some((addr, messages));
+                    }
+                }
+                Ok(messages) => match messages.first() {
+                    Some(msg) => rtts.push((addr, format!("{} ms ({})", elapsed.as_millis(), msg.response().rcode()))),
+                    None => rtts.push((addr, Error::Dns(Dns::UnreachableResolvers).localized())),
+                },
+                Err(e) => rtts.push((addr, e)),
+            }
+        }
+    });
+
+    let (first, messages) = winner.ok_or(Error::Dns(Dns::UnreachableResolvers))?;
+    let netinfo = messages.first().ok_or(Error::Dns(Dns::UnreachableResolvers))?.netinfo;
+
+    if !options.display.json && !options.display.json_pretty {
+        println!("{}", header_section("RACE", None));
+        for (addr, rtt) in &rtts {
+            let mark = if *addr == first { " <- winner" } else { "" };
+            println!("{:<45} {}{}", addr.to_string(), rtt, mark);
+        }
+        println!();
+    }
+
+    if let Some(info) = info {
+        info.netinfo = netinfo;
+    }
+
+    Ok(messages)
+}
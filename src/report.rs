@@ -0,0 +1,109 @@
+//! DNS error reporting (RFC9567): build and send the report query to the agent domain
+use colored::Colorize;
+
+use crate::args::CliOptions;
+use crate::dns::message::MessageList;
+use crate::dns::rfc::{domain::DomainName, qtype::QType};
+use crate::error::Result;
+use crate::get_messages;
+use crate::i18n::{fill, tr};
+use crate::show::{header_section, Numeric, Show};
+
+//───────────────────────────────────────────────────────────────────────────────────
+// report query name: _er.<qtype>.<qname>.<extended error>._er.<agent domain>
+// None if the resulting name is too long
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn report_qname(qtype: &QType, qname: &DomainName, ede: u16, agent: &DomainName) -> Option<DomainName> {
+    let qname = qname.to_string();
+    let qname = qname.trim_end_matches('.');
+
+    let name = if qname.is_empty() {
+        format!("_er.{}.{}._er.{}", qtype.number(), ede, agent)
+    } else {
+        format!("_er.{}.{}.{}._er.{}", qtype.number(), qname, ede, agent)
+    };
+
+    DomainName::try_from(name.as_str()).ok()
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// for each response carrying a Report-Channel option and extended errors, send the
+// TXT report query like a reporting resolver would do
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn send_reports(options: &CliOptions, messages: &MessageList) -> Result<()> {
+    for resp in messages.responses() {
+        let question = &resp.question;
+
+        let Some(agent) = resp.report_channel() else {
+            eprintln!(
+                "{}",
+                fill(
+                    "{} {}: no Report-Channel option in response",
+                    &[&question.qname, &question.qtype]
+                )
+            );
+            continue;
+        };
+
+        let errors = resp.extended_errors();
+        if errors.is_empty() {
+            eprintln!(
+                "{}",
+                fill(
+                    "{} {}: no extended DNS error to report",
+                    &[&question.qname, &question.qtype]
+                )
+            );
+            continue;
+        }
+
+        for ede in errors {
+            let Some(qname) = report_qname(&question.qtype, &question.qname, ede, agent) else {
+                eprintln!(
+                    "{}",
+                    fill(
+                        "{} {}: report query name is too long",
+                        &[&question.qname, &question.qtype]
+                    )
+                );
+                continue;
+            };
+
+            println!("{}", header_section(&format!("error report {}", qname), None));
+
+            let mut report = options.clone();
+            report.protocol.qtype = vec![QType::TXT];
+            report.protocol.domain_name = qname;
+            report.edns.reporting = false;
+
+            match get_messages(None, &report) {
+                Ok(messages) => {
+                    let max_length = messages.max_length();
+                    for msg in messages.iter() {
+                        msg.show(&options.display, max_length);
+                    }
+                }
+                Err(e) => eprintln!("{}: {}", tr("report failed").bright_red(), e.localized()),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qname() {
+        let agent = DomainName::try_from("a01.agent-domain.example.").unwrap();
+        let qname = DomainName::try_from("broken.test").unwrap();
+
+        let report = report_qname(&QType::A, &qname, 7, &agent).unwrap();
+        assert_eq!(report.to_string(), "_er.1.broken.test.7._er.a01.agent-domain.example.");
+
+        let report = report_qname(&QType::NS, &DomainName::default(), 9, &agent).unwrap();
+        assert_eq!(report.to_string(), "_er.2.9._er.a01.agent-domain.example.");
+    }
+}
@@ -0,0 +1,73 @@
+//! Several resolvers given on the command line (@server @server ...): they are tried one
+//! after the other until one answers, or all of them are queried with --all-servers.
+use crate::args::CliOptions;
+use crate::dns::message::MessageList;
+use crate::error::{Dns, Error, Result};
+use crate::get_messages;
+use crate::i18n::fill;
+use crate::show::{header_section, QueryInfo, Show};
+
+//───────────────────────────────────────────────────────────────────────────────────
+// query the resolvers in order and return the first answers received. The endpoint
+// is left to the resolver which answered
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn failover(mut info: Option<&mut QueryInfo>, options: &mut CliOptions) -> Result<MessageList> {
+    let endpoints = options.transport.endpoints.clone();
+    let mut last_error = None;
+
+    for endpoint in endpoints {
+        options.transport.cancel.check()?;
+        options.transport.endpoint = endpoint;
+
+        match get_messages(info.as_deref_mut(), options) {
+            Ok(messages) => return Ok(messages),
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    fill(
+                        "resolver {}: {}",
+                        &[&options.transport.endpoint.server_name, &e.localized()]
+                    )
+                );
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or(Error::Dns(Dns::UnreachableResolvers)))
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// send the queries to each resolver and print out the results of each one (--all-servers)
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn all_servers(options: &mut CliOptions) -> Result<()> {
+    let endpoints = options.transport.endpoints.clone();
+    let mut failures = 0;
+
+    for endpoint in endpoints {
+        options.transport.cancel.check()?;
+        let name = endpoint.server_name.clone();
+        options.transport.endpoint = endpoint;
+
+        println!("{}", header_section(&format!("resolver {}", name), None));
+        match get_messages(None, options) {
+            Ok(messages) => {
+                let max_length = messages.max_length();
+                for msg in messages.iter() {
+                    msg.show(&options.display, max_length);
+                }
+            }
+            Err(e) => {
+                failures += 1;
+                eprintln!("{}", fill("resolver {}: {}", &[&name, &e.localized()]));
+            }
+        }
+        println!();
+    }
+
+    // only an error if none answered
+    if failures == options.transport.endpoints.len() {
+        return Err(Error::Dns(Dns::UnreachableResolvers));
+    }
+    Ok(())
+}
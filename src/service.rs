@@ -0,0 +1,482 @@
+//! Service lookups built on SVCB and HTTPS records: AliasMode chains are followed down to
+//! the ServiceMode records a client would use (--follow-svcb), and the lookups of a
+//! browser are summarized (--browser-view). SRV and SVCB records of a service are turned
+//! into a list of endpoints (--service), and DNS-SD services are browsed (--browse).
+use std::cmp::Reverse;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::args::CliOptions;
+use crate::dns::message::MessageList;
+use crate::dns::rfc::{domain::DomainName, qtype::QType, svcb::SVCB};
+use crate::error::{Dns, Error, Result};
+use crate::get_messages;
+use crate::show::header_section;
+
+// DNS-SD service type enumeration name (RFC6763 section 9)
+const SERVICE_TYPES: &str = "_services._dns-sd._udp";
+
+// aliases followed before giving up (RFC9460 section 2.4.2 asks to limit chains)
+const MAX_ALIAS_CHAIN: usize = 8;
+
+// default protocol of HTTPS records (RFC9460 section 7.1.2)
+const DEFAULT_ALPN: &str = "http/1.1";
+
+// SVCB or HTTPS records of the answer section, with their owner name
+fn svcb_records(messages: &MessageList) -> Vec<(&DomainName, &SVCB)> {
+    messages
+        .answers()
+        .filter_map(|rr| rr.svcb().map(|svcb| (&rr.name, svcb)))
+        .collect()
+}
+
+fn query(options: &mut CliOptions, name: &DomainName, qtypes: &[QType]) -> Result<MessageList> {
+    options.protocol.domain_name = name.clone();
+    options.protocol.qtype = qtypes.to_vec();
+    get_messages(None, options)
+}
+
+// A and AAAA addresses of the name, CNAMEs being followed by the resolver
+fn addresses(options: &mut CliOptions, name: &DomainName) -> Result<Vec<IpAddr>> {
+    let messages = query(options, name, &[QType::A, QType::AAAA])?;
+    Ok(messages.answers().filter_map(|rr| rr.ip_address()).collect())
+}
+
+fn join<T: ToString>(list: &[T]) -> String {
+    list.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(",")
+}
+
+// a ServiceMode record with the addresses of its target
+#[derive(Debug)]
+struct Service {
+    priority: u16,
+    target: DomainName,
+    alpn: Vec<String>,
+    no_default_alpn: bool,
+    port: Option<u16>,
+    ech: bool,
+    addrs: Vec<IpAddr>,
+}
+
+impl Service {
+    // addresses are the hints until the target is resolved
+    fn new(owner: &DomainName, svcb: &SVCB) -> Self {
+        Self {
+            priority: svcb.priority(),
+            // "." as the target of a ServiceMode record is the owner name
+            target: if svcb.target().is_empty() {
+                owner.clone()
+            } else {
+                svcb.target().clone()
+            },
+            alpn: svcb.alpn(),
+            no_default_alpn: svcb.no_default_alpn(),
+            port: svcb.port(),
+            ech: svcb.ech().is_some(),
+            addrs: svcb.hints(),
+        }
+    }
+
+    // protocols a client can use, the default one included
+    fn client_alpn(&self) -> Vec<String> {
+        let mut alpn = self.alpn.clone();
+        if !self.no_default_alpn && !alpn.iter().any(|id| id == DEFAULT_ALPN) {
+            alpn.push(DEFAULT_ALPN.to_string());
+        }
+        alpn
+    }
+}
+
+impl fmt::Display for Service {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "priority {} target {}", self.priority, self.target)?;
+        if !self.alpn.is_empty() {
+            write!(f, " alpn={}", self.alpn.join(","))?;
+        }
+        if self.no_default_alpn {
+            write!(f, " no-default-alpn")?;
+        }
+        if let Some(port) = self.port {
+            write!(f, " port={}", port)?;
+        }
+        write!(
+            f,
+            " ech={} addresses: {}",
+            if self.ech { "yes" } else { "no" },
+            join(&self.addrs)
+        )
+    }
+}
+
+// what an alias chain leads to
+#[derive(Debug, Default)]
+struct Resolution {
+    // (owner, target) of each AliasMode record
+    aliases: Vec<(DomainName, DomainName)>,
+
+    // last name of the chain
+    name: DomainName,
+
+    // ServiceMode records found for this name, best first
+    services: Vec<Service>,
+
+    // why the chain was not followed to its end
+    broken: Option<String>,
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// follow AliasMode records (priority 0) from name and resolve the targets of the
+// ServiceMode records found at the end of the chain
+//───────────────────────────────────────────────────────────────────────────────────
+fn resolve(options: &mut CliOptions, name: &DomainName, qt: QType) -> Result<Resolution> {
+    let mut resolution = Resolution {
+        name: name.clone(),
+        ..Default::default()
+    };
+
+    let messages = loop {
+        options.transport.cancel.check()?;
+        let messages = query(options, &resolution.name, &[qt])?;
+
+        let alias = svcb_records(&messages)
+            .into_iter()
+            .find(|(_, svcb)| svcb.is_alias())
+            .map(|(owner, svcb)| (owner.clone(), svcb.target().clone()));
+
+        let Some((owner, target)) = alias else {
+            break messages;
+        };
+        resolution.aliases.push((owner.clone(), target.clone()));
+
+        // an alias to the root means the service doesn't exist
+        if target.is_empty() {
+            resolution.broken = Some(format!("{}: service not available", owner));
+            return Ok(resolution);
+        }
+        if target == *name
+            || resolution.aliases.iter().any(|(owner, _)| *owner == target)
+            || resolution.aliases.len() > MAX_ALIAS_CHAIN
+        {
+            resolution.broken = Some(format!("{}: alias loop or chain too long, giving up", target));
+            return Ok(resolution);
+        }
+        resolution.name = target;
+    };
+
+    resolution.services = svcb_records(&messages)
+        .into_iter()
+        .filter(|(_, svcb)| !svcb.is_alias())
+        .map(|(owner, svcb)| Service::new(owner, svcb))
+        .collect();
+    resolution.services.sort_by_key(|s| s.priority);
+
+    // hints are only used when the target has no address
+    for service in resolution.services.iter_mut() {
+        options.transport.cancel.check()?;
+        let addrs = addresses(options, &service.target)?;
+        if !addrs.is_empty() {
+            service.addrs = addrs;
+        }
+    }
+
+    Ok(resolution)
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// print out the alias chain and the service parameters and addresses of the
+// ServiceMode records found at its end (--follow-svcb)
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn follow_svcb(options: &mut CliOptions) -> Result<()> {
+    let qt = options
+        .protocol
+        .qtype
+        .iter()
+        .copied()
+        .find(|qt| matches!(qt, QType::HTTPS | QType::SVCB))
+        .unwrap_or(QType::HTTPS);
+    let name = options.protocol.domain_name.clone();
+    let resolution = resolve(options, &name, qt)?;
+
+    println!("{}", header_section("ALIAS CHAIN", None));
+    for (owner, target) in &resolution.aliases {
+        println!("{} {} 0 {}", owner, qt, target);
+    }
+
+    if let Some(reason) = &resolution.broken {
+        println!("\n{}", reason);
+        return Ok(());
+    }
+
+    println!("\n{}", header_section("SERVICE", None));
+
+    // no record: the client connects to the last name of the chain with default parameters
+    if resolution.services.is_empty() {
+        let addrs = addresses(options, &resolution.name)?;
+        println!("{}: no {} record, addresses: {}", resolution.name, qt, join(&addrs));
+    }
+    for service in &resolution.services {
+        println!("{}", service);
+    }
+
+    Ok(())
+}
+
+// protocol a browser tries first among those offered
+fn first_protocol(alpn: &[String]) -> &'static str {
+    if alpn.iter().any(|id| id == "h3") {
+        "HTTP/3 over QUIC"
+    } else if alpn.iter().any(|id| id == "h2") {
+        "HTTP/2"
+    } else {
+        "HTTP/1.1"
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// do the lookups of a browser for the host (A, AAAA and HTTPS) and print out the
+// connection parameters it would choose (--browser-view)
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn browser_view(options: &mut CliOptions) -> Result<()> {
+    let host = options.protocol.domain_name.clone();
+    let host_addrs = addresses(options, &host)?;
+    let resolution = resolve(options, &host, QType::HTTPS)?;
+
+    println!("{}", header_section("LOOKUPS", None));
+    println!("{} A/AAAA: {}", host, join(&host_addrs));
+    for (owner, target) in &resolution.aliases {
+        println!("{} HTTPS alias to {}", owner, target);
+    }
+    if let Some(reason) = &resolution.broken {
+        println!("{}", reason);
+    }
+    for service in &resolution.services {
+        println!("{} HTTPS {}", resolution.name, service);
+    }
+
+    // an alias to the root or a broken chain is like no HTTPS record at all
+    let best = resolution.services.first().filter(|_| resolution.broken.is_none());
+
+    let (mut addrs, port, alpn, ech) = match best {
+        Some(service) if !service.addrs.is_empty() => (
+            service.addrs.clone(),
+            service.port.unwrap_or(443),
+            service.client_alpn(),
+            service.ech,
+        ),
+        Some(service) => (
+            host_addrs.clone(),
+            service.port.unwrap_or(443),
+            service.client_alpn(),
+            service.ech,
+        ),
+        // protocol negotiated during the TLS handshake
+        None => (
+            host_addrs.clone(),
+            443,
+            vec![String::from("h2"), DEFAULT_ALPN.to_string()],
+            false,
+        ),
+    };
+
+    // IPv6 is tried first (Happy Eyeballs, RFC8305)
+    addrs.sort_by_key(|ip| !ip.is_ipv6());
+
+    println!("\n{}", header_section("CONNECTION", None));
+    println!("addresses: {}", join(&addrs));
+    println!("port: {}", port);
+    println!("alpn: {} (first attempt: {})", alpn.join(","), first_protocol(&alpn));
+    println!("ech: {}", if ech { "available" } else { "not available" });
+    println!(
+        "http:// upgraded to https://: {}",
+        if best.is_some() { "yes" } else { "no" }
+    );
+
+    Ok(())
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// query name of a service of the domain: "_sip._tcp" or "sip/tcp" gives _sip._tcp.domain,
+// TCP being the default protocol
+//───────────────────────────────────────────────────────────────────────────────────
+fn service_name(service: &str, domain: &DomainName) -> Result<DomainName> {
+    let (svc, proto) = service
+        .split_once('/')
+        .or_else(|| service.split_once('.'))
+        .unwrap_or((service, "tcp"));
+    let (svc, proto) = (svc.trim_start_matches('_'), proto.trim_start_matches('_'));
+
+    if svc.is_empty() || proto.is_empty() {
+        return Err(Error::Dns(Dns::EmptyDomainName));
+    }
+    DomainName::try_from(format!("_{}._{}.{}", svc, proto, domain).as_str())
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// query the SRV and SVCB records of the service and print out the endpoints found, in
+// the order a client would try them (--service)
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn service_discovery(options: &mut CliOptions, service: &str) -> Result<()> {
+    let name = service_name(service, &options.protocol.domain_name)?;
+    let mut endpoints: Vec<SocketAddr> = Vec::new();
+
+    // lowest priority first, then highest weight (RFC2782)
+    let messages = query(options, &name, &[QType::SRV])?;
+    let mut srvs: Vec<_> = messages
+        .answers()
+        .filter_map(|rr| rr.srv())
+        .map(|srv| (srv.priority(), srv.weight(), srv.port(), srv.target().clone()))
+        .collect();
+    srvs.sort_by_key(|(priority, weight, _, _)| (*priority, Reverse(*weight)));
+
+    println!("{}", header_section(&format!("SRV {}", name), None));
+    if srvs.is_empty() {
+        println!("no SRV record");
+    }
+    for (priority, weight, port, target) in srvs {
+        options.transport.cancel.check()?;
+
+        // "." as the only target means the service is not available
+        if target.is_empty() {
+            println!("priority {} weight {}: service not available", priority, weight);
+            continue;
+        }
+        let addrs = addresses(options, &target)?;
+        println!(
+            "priority {} weight {} target {} port {} addresses: {}",
+            priority,
+            weight,
+            target,
+            port,
+            join(&addrs)
+        );
+        endpoints.extend(addrs.iter().map(|ip| SocketAddr::new(*ip, port)));
+    }
+
+    let resolution = resolve(options, &name, QType::SVCB)?;
+
+    println!("\n{}", header_section(&format!("SVCB {}", name), None));
+    for (owner, target) in &resolution.aliases {
+        println!("{} SVCB alias to {}", owner, target);
+    }
+    if let Some(reason) = &resolution.broken {
+        println!("{}", reason);
+    } else if resolution.services.is_empty() {
+        println!("no SVCB record");
+    }
+    for service in &resolution.services {
+        println!("{}", service);
+
+        // without the port parameter, the default port of the protocol is unknown
+        if let Some(port) = service.port {
+            endpoints.extend(service.addrs.iter().map(|ip| SocketAddr::new(*ip, port)));
+        }
+    }
+
+    // an address can be the target of several records
+    let mut seen = Vec::new();
+    endpoints.retain(|ep| {
+        let new = !seen.contains(ep);
+        seen.push(*ep);
+        new
+    });
+
+    println!("\n{}", header_section("ENDPOINTS", None));
+    for ep in &endpoints {
+        println!("{}", ep);
+    }
+
+    Ok(())
+}
+
+// targets of the PTR records of the name
+fn ptr_targets(options: &mut CliOptions, name: &DomainName) -> Result<Vec<DomainName>> {
+    let messages = query(options, name, &[QType::PTR])?;
+    Ok(messages.answers().filter_map(|rr| rr.ptr_name().cloned()).collect())
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// enumerate the service types advertised in the domain, then their instances with
+// the SRV and TXT records of each one, and print out the service tree (--browse)
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn browse(options: &mut CliOptions, domain: &str) -> Result<()> {
+    let domain = DomainName::try_from(domain)?;
+    let enumeration = DomainName::try_from(format!("{}.{}", SERVICE_TYPES, domain).as_str())?;
+    let service_types = ptr_targets(options, &enumeration)?;
+
+    println!("{}", domain);
+    if service_types.is_empty() {
+        println!("  no service advertised");
+    }
+
+    for service_type in service_types {
+        options.transport.cancel.check()?;
+        println!("  {}", service_type);
+
+        for instance in ptr_targets(options, &service_type)? {
+            options.transport.cancel.check()?;
+            println!("    {}", instance);
+
+            let messages = query(options, &instance, &[QType::SRV, QType::TXT])?;
+            for rr in messages.answers() {
+                if let Some(srv) = rr.srv() {
+                    println!("      {} port {}", srv.target(), srv.port());
+                }
+
+                // key=value pairs, an empty string being no data (RFC6763 section 6.1)
+                if let Some(strings) = rr.txt_strings() {
+                    let pairs: Vec<_> = strings.into_iter().filter(|s| !s.is_empty()).collect();
+                    if !pairs.is_empty() {
+                        println!("      {}", pairs.join(" "));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name() {
+        let domain = DomainName::try_from("example.com.").unwrap();
+        for service in ["_sip._tcp", "sip/tcp", "sip", "_sip"] {
+            assert_eq!(
+                service_name(service, &domain).unwrap().to_string(),
+                "_sip._tcp.example.com."
+            );
+        }
+        assert_eq!(
+            service_name("xmpp-server/udp", &domain).unwrap().to_string(),
+            "_xmpp-server._udp.example.com."
+        );
+        assert!(service_name("/tcp", &domain).is_err());
+    }
+
+    #[test]
+    fn alpn() {
+        let mut service = Service {
+            priority: 1,
+            target: DomainName::try_from("cdn.example.net.").unwrap(),
+            alpn: vec![String::from("h3"), String::from("h2")],
+            no_default_alpn: false,
+            port: None,
+            ech: true,
+            addrs: Vec::new(),
+        };
+        assert_eq!(service.client_alpn(), vec!["h3", "h2", "http/1.1"]);
+        assert_eq!(first_protocol(&service.client_alpn()), "HTTP/3 over QUIC");
+
+        service.alpn = vec![String::from("h2")];
+        service.no_default_alpn = true;
+        assert_eq!(service.client_alpn(), vec!["h2"]);
+        assert_eq!(first_protocol(&service.client_alpn()), "HTTP/2");
+        assert_eq!(
+            service.to_string(),
+            "priority 1 target cdn.example.net. alpn=h2 no-default-alpn ech=yes addresses: "
+        );
+    }
+}
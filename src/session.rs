@@ -0,0 +1,303 @@
+//! Session log: one JSON line per query/response exchange (--session-log)
+use std::collections::{BTreeSet, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Cursor, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, FixedOffset, Utc};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use type2network::FromNetworkOrder;
+
+use crate::args::CliOptions;
+use crate::dns::rfc::{domain::DomainName, qtype::QType, response::Response};
+use crate::error::{Error, Result};
+use crate::get_messages;
+use crate::i18n::{fill, tr};
+use crate::pacing::Pacer;
+use crate::stats::{ExchangeStats, Failures};
+use crate::transport::observer::{Direction, Observer, Packet};
+
+// parsed summary of an exchange
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Summary {
+    pub qname: String,
+    pub qtype: String,
+    pub rcode: String,
+    pub answers: Vec<String>,
+}
+
+// a line of the session log
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SessionRecord {
+    // RFC3339 timestamp
+    pub timestamp: String,
+    pub endpoint: Option<SocketAddr>,
+    pub transport: String,
+
+    // wire data as base64
+    pub query: String,
+    pub response: String,
+
+    pub summary: Summary,
+}
+
+// number of queries waiting for their response, the oldest ones being dropped
+const MAX_PENDING: usize = 256;
+
+impl Summary {
+    pub fn new(response: &Response) -> Self {
+        Self {
+            qname: response.question.qname.to_string(),
+            qtype: response.question.qtype.to_string(),
+            rcode: response.rcode().to_string(),
+            answers: response.answers(),
+        }
+    }
+}
+
+// question section of a message as bytes, to match a response with its query
+fn question(data: &[u8]) -> Option<&[u8]> {
+    let mut end = 12;
+    loop {
+        let length = *data.get(end)? as usize;
+        end += 1;
+        if length == 0 {
+            break;
+        }
+        end += length;
+    }
+    data.get(12..end + 4)
+}
+
+// a query waiting for its response
+struct Sent {
+    timestamp: DateTime<Utc>,
+    data: Vec<u8>,
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// --session-log: queries are kept as sent on the wire and logged with their
+// response once received, whichever mode triggered the exchange
+//───────────────────────────────────────────────────────────────────────────────────
+pub struct SessionLog {
+    file: Mutex<File>,
+    pending: Mutex<VecDeque<Sent>>,
+}
+
+impl SessionLog {
+    pub fn new(path: &Path) -> Result<Self> {
+        let f = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| Error::OpenFile(e, path.to_path_buf()))?;
+        Ok(Self {
+            file: Mutex::new(f),
+            pending: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    // the last query sent with the same ID and question (a UDP retry sends it again)
+    fn query(&self, response: &[u8]) -> Option<Sent> {
+        let mut pending = self.pending.lock().unwrap();
+        let i = pending
+            .iter()
+            .rposition(|sent| sent.data.get(..2) == response.get(..2) && question(&sent.data) == question(response))?;
+        let sent = pending.remove(i)?;
+        pending.retain(|s| s.data != sent.data);
+        Some(sent)
+    }
+}
+
+impl Observer for SessionLog {
+    fn observe(&self, packet: &Packet) {
+        if packet.direction == Direction::Sent {
+            let mut pending = self.pending.lock().unwrap();
+            if pending.len() == MAX_PENDING {
+                pending.pop_front();
+            }
+            pending.push_back(Sent {
+                timestamp: packet.timestamp,
+                data: packet.data.to_vec(),
+            });
+            return;
+        }
+
+        // other messages of a zone transfer don't match any query
+        let Some(sent) = self.query(packet.data) else {
+            return;
+        };
+
+        let mut response = Response::default();
+        let summary = match response.deserialize_from(&mut Cursor::new(packet.data)) {
+            Ok(_) => Summary::new(&response),
+            Err(_) => Summary::default(),
+        };
+        let record = SessionRecord {
+            timestamp: sent.timestamp.to_rfc3339(),
+            endpoint: packet.peer,
+            transport: packet.mode.to_string(),
+            query: general_purpose::STANDARD.encode(&sent.data),
+            response: general_purpose::STANDARD.encode(packet.data),
+            summary,
+        };
+        let _ = writeln!(self.file.lock().unwrap(), "{}", serde_json::to_string(&record).unwrap());
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// re-issue recorded queries to the current endpoint and compare responses with
+// the recorded ones (--replay)
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn replay(options: &mut CliOptions, path: &Path, timing: bool) -> Result<()> {
+    let file = File::open(path).map_err(|e| Error::OpenFile(e, path.to_path_buf()))?;
+
+    let mut replayed = 0usize;
+    let mut drifted = 0usize;
+    let mut previous: Option<DateTime<FixedOffset>> = None;
+    let mut stats = ExchangeStats::default();
+    let mut failures = Failures::default();
+    let mut pacer = Pacer::new(&options.dump);
+
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        options.transport.cancel.check()?;
+
+        let line = line.map_err(Error::Buffer)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let record: SessionRecord = match serde_json::from_str(&line) {
+            Ok(record) => record,
+            Err(e) => {
+                eprintln!("{}", fill("line {}: invalid session record ({})", &[&(i + 1), &e]));
+                continue;
+            }
+        };
+
+        // wait as in the original session
+        let ts = DateTime::parse_from_rfc3339(&record.timestamp).ok();
+        if timing {
+            if let (Some(prev), Some(ts)) = (previous, ts) {
+                if let Ok(delay) = (ts - prev).to_std() {
+                    std::thread::sleep(delay);
+                }
+            }
+        }
+        previous = ts;
+        pacer.wait();
+
+        // rebuild the query from the summary
+        let Ok(qtype) = QType::from_str(&record.summary.qtype) else {
+            eprintln!(
+                "{}",
+                fill("line {}: unknown qtype {}", &[&(i + 1), &record.summary.qtype])
+            );
+            continue;
+        };
+        options.protocol.qtype = vec![qtype];
+        let Ok(qname) = DomainName::try_from(record.summary.qname.as_str()) else {
+            eprintln!(
+                "{}",
+                fill("line {}: invalid qname {}", &[&(i + 1), &record.summary.qname])
+            );
+            continue;
+        };
+        options.protocol.domain_name = qname;
+
+        let header = format!("{} {}", record.summary.qname, record.summary.qtype);
+        let messages = match get_messages(None, options) {
+            Ok(messages) => messages,
+            Err(e) => {
+                failures.error(&e);
+                println!("{}: {} ({})", header, tr("error").bright_red(), e.localized());
+                continue;
+            }
+        };
+        replayed += 1;
+        stats.extend(messages.iter());
+
+        // compare
+        let msg = &messages[0];
+        let rcode = msg.response.rcode().to_string();
+        let recorded: BTreeSet<_> = record.summary.answers.iter().cloned().collect();
+        let current: BTreeSet<_> = msg.answers().into_iter().collect();
+        failures.rcode(msg.response.rcode());
+
+        if rcode == record.summary.rcode && recorded == current {
+            println!("{}: {}", header, "same".bright_green());
+            continue;
+        }
+
+        drifted += 1;
+        println!("{}: {}", header, "drift".bright_red());
+        if rcode != record.summary.rcode {
+            println!("  rcode: {} -> {}", record.summary.rcode, rcode);
+        }
+        for a in recorded.difference(&current) {
+            println!("  {} {}", "-".bright_red(), a);
+        }
+        for a in current.difference(&recorded) {
+            println!("  {} {}", "+".bright_green(), a);
+        }
+    }
+
+    println!(
+        "\nreplayed: {}, same: {}, drift: {}, failures: {}",
+        replayed,
+        replayed - drifted,
+        drifted,
+        failures
+    );
+
+    // rcodes, latencies and slowest queries over the whole session
+    if options.display.stats {
+        println!("\n{}", stats);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::network::Protocol;
+
+    // header with the ID given and a question for "a." A
+    fn message(id: u8, qtype: u8) -> Vec<u8> {
+        vec![0, id, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 1, b'a', 0, 0, qtype, 0, 1]
+    }
+
+    #[test]
+    fn pairing() {
+        assert_eq!(question(&message(1, 1)), Some(&[1, b'a', 0, 0, 1, 0, 1][..]));
+        assert_eq!(question(&[0; 12]), None);
+
+        let path = std::env::temp_dir().join(format!("dqy-session-{}.jsonl", std::process::id()));
+        let log = SessionLog::new(&path).unwrap();
+        let sent = |data: &[u8]| Packet {
+            direction: Direction::Sent,
+            timestamp: DateTime::from_timestamp(0, 0).unwrap(),
+            mode: Protocol::Udp,
+            peer: None,
+            data,
+        };
+
+        // a retry sends the same query again, other queries are kept pending
+        log.observe(&sent(&message(1, 1)));
+        log.observe(&sent(&message(1, 1)));
+        log.observe(&sent(&message(1, 28)));
+        log.observe(&sent(&message(2, 1)));
+        assert!(log.query(&message(1, 2)).is_none());
+        assert_eq!(log.query(&message(1, 1)).unwrap().data, message(1, 1));
+        assert!(log.query(&message(1, 1)).is_none());
+        assert_eq!(log.pending.lock().unwrap().len(), 2);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
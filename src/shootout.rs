@@ -0,0 +1,235 @@
+//! Resolver performance comparison (--shootout): each domain of a list is queried
+//! against each resolver of another list, and latencies and failures are compared.
+use std::fs::read_to_string;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::args::CliOptions;
+use crate::dns::rfc::domain::DomainName;
+use crate::error::{Error, Result};
+use crate::get_messages;
+use crate::i18n::fill;
+use crate::pacing::{shuffle, Pacer};
+use crate::show::header_section;
+use crate::stats::Failures;
+use crate::template::csv_field;
+use crate::transport::endpoint::EndPoint;
+
+// what was measured for a resolver
+#[derive(Debug, Default)]
+struct Measure {
+    resolver: String,
+
+    // latency of each successful query
+    latencies: Vec<Duration>,
+
+    // timeouts, network errors, SERVFAIL or REFUSED, by kind
+    failures: Failures,
+}
+
+impl Measure {
+    fn queries(&self) -> usize {
+        self.latencies.len() + self.failures.total()
+    }
+
+    fn failure_rate(&self) -> f64 {
+        if self.queries() == 0 {
+            0.0
+        } else {
+            100.0 * self.failures.total() as f64 / self.queries() as f64
+        }
+    }
+}
+
+// nearest-rank percentile of sorted values
+fn percentile(sorted: &[Duration], p: usize) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    Some(sorted[rank - 1])
+}
+
+// non-empty lines of a file, except comments
+fn read_list(path: &Path) -> Result<Vec<String>> {
+    let content = read_to_string(path).map_err(|e| Error::OpenFile(e, path.to_path_buf()))?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+// send one query, returning its latency or None if failed, the failure being counted
+fn measure(options: &CliOptions, failures: &mut Failures) -> Option<Duration> {
+    let messages = match get_messages(None, options) {
+        Ok(messages) => messages,
+        Err(e) => {
+            failures.error(&e);
+            return None;
+        }
+    };
+    let msg = messages.first()?;
+
+    (!failures.rcode(msg.response().rcode())).then_some(msg.elapsed)
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// run the resolvers x domains matrix and print out the comparison
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn shootout(options: &mut CliOptions, resolvers: &Path, domains: &Path) -> Result<()> {
+    let mut resolvers = read_list(resolvers)?;
+    let mut domains: Vec<DomainName> = read_list(domains)?
+        .iter()
+        .map(|d| DomainName::try_from(d.as_str()))
+        .collect::<Result<_>>()?;
+
+    // only the first type is used
+    options.protocol.qtype.truncate(1);
+
+    shuffle(&mut resolvers, &options.dump);
+    let mut pacer = Pacer::new(&options.dump);
+
+    let mut measures = Vec::with_capacity(resolvers.len());
+
+    for resolver in resolvers {
+        let mut endpoint = match EndPoint::new(&resolver, options.transport.port) {
+            Ok(ep) => ep,
+            Err(e) => {
+                eprintln!("{}", fill("resolver {}: {}", &[&resolver, &e.localized()]));
+                continue;
+            }
+        };
+        endpoint.retain(&options.transport.ip_version);
+        endpoint.prefer(&options.transport.prefer);
+        options.transport.endpoint = endpoint;
+
+        // each resolver gets its own order
+        shuffle(&mut domains, &options.dump);
+
+        // warm-up: fill the resolver cache, results are discarded
+        for domain in domains.iter().take(options.dump.warmup) {
+            options.transport.cancel.check()?;
+            pacer.wait();
+            options.protocol.domain_name = domain.clone();
+            let _ = measure(options, &mut Failures::default());
+        }
+
+        let mut m = Measure {
+            resolver,
+            ..Default::default()
+        };
+
+        for domain in &domains {
+            options.transport.cancel.check()?;
+            pacer.wait();
+            options.protocol.domain_name = domain.clone();
+
+            if let Some(elapsed) = measure(options, &mut m.failures) {
+                m.latencies.push(elapsed);
+            }
+        }
+
+        m.latencies.sort();
+        measures.push(m);
+    }
+
+    // fastest first
+    measures.sort_by_key(|m| percentile(&m.latencies, 50).unwrap_or(Duration::MAX));
+
+    if options.dump.csv {
+        println!("resolver,queries,failures,failure_rate,min,p50,p90,p99,max,failure_kinds");
+    } else {
+        println!(
+            "{}",
+            header_section(
+                &format!(
+                    "{:<40} {:>7} {:>8} {:>7} {:>7} {:>7} {:>7} {:>7}",
+                    "resolver", "queries", "failures", "min", "p50", "p90", "p99", "max"
+                ),
+                None
+            )
+        );
+    }
+
+    for m in &measures {
+        let ms = |d: Option<Duration>| {
+            d.map(|d| d.as_millis().to_string())
+                .unwrap_or_else(|| String::from("-"))
+        };
+        let (min, p50, p90, p99, max) = (
+            ms(m.latencies.first().copied()),
+            ms(percentile(&m.latencies, 50)),
+            ms(percentile(&m.latencies, 90)),
+            ms(percentile(&m.latencies, 99)),
+            ms(m.latencies.last().copied()),
+        );
+
+        if options.dump.csv {
+            println!(
+                "{},{},{},{:.1},{},{},{},{},{},{}",
+                csv_field(&m.resolver),
+                m.queries(),
+                m.failures.total(),
+                m.failure_rate(),
+                min,
+                p50,
+                p90,
+                p99,
+                max,
+                m.failures
+            );
+        } else {
+            println!(
+                "{:<40} {:>7} {:>7.1}% {:>7} {:>7} {:>7} {:>7} {:>7}",
+                m.resolver,
+                m.queries(),
+                m.failure_rate(),
+                min,
+                p50,
+                p90,
+                p99,
+                max
+            );
+        }
+    }
+
+    // what the failures were
+    if !options.dump.csv {
+        let failed: Vec<_> = measures.iter().filter(|m| m.failures.total() > 0).collect();
+        if !failed.is_empty() {
+            println!("\nfailures:");
+            for m in failed {
+                println!("  {:<40} {}", m.resolver, m.failures);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles() {
+        let values: Vec<_> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&values, 50), Some(Duration::from_millis(50)));
+        assert_eq!(percentile(&values, 90), Some(Duration::from_millis(90)));
+        assert_eq!(percentile(&values, 99), Some(Duration::from_millis(99)));
+        assert_eq!(percentile(&values, 0), Some(Duration::from_millis(1)));
+        assert_eq!(percentile(&values[..1], 99), Some(Duration::from_millis(1)));
+        assert!(percentile(&[], 50).is_none());
+
+        let mut m = Measure {
+            resolver: String::from("1.1.1.1"),
+            latencies: values[..3].to_vec(),
+            ..Default::default()
+        };
+        m.failures.error(&Error::Cancelled);
+        assert_eq!(m.queries(), 4);
+        assert_eq!(m.failure_rate(), 25.0);
+    }
+}
@@ -1,10 +1,21 @@
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::Display;
+use std::net::IpAddr;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 use serde::Serialize;
 
+use crate::annotate::annotations;
+use crate::dns::rfc::{qtype::QType, resource_record::ResourceRecord};
+use crate::i18n::tr;
+use crate::template::Template;
+use crate::trace::Provenance;
 use crate::transport::NetworkInfo;
+use crate::warnings::Warnings;
 
 //───────────────────────────────────────────────────────────────────────────────────
 // Gather some information which might be useful for the user
@@ -19,6 +30,22 @@ pub struct QueryInfo {
 
     // network info gathered during network operations
     pub netinfo: NetworkInfo,
+
+    // proxy used for DoH, without credentials
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+
+    // warnings raised during the exchanges and on the responses, printed after the results
+    #[serde(skip)]
+    pub warnings: Warnings,
+}
+
+impl QueryInfo {
+    // --stable: elapsed time and network counters change from one run to the other
+    pub fn stabilize(&mut self) {
+        self.elapsed = 0;
+        self.netinfo.stabilize();
+    }
 }
 
 impl fmt::Display for QueryInfo {
@@ -26,12 +53,41 @@ impl fmt::Display for QueryInfo {
         if let Some(peer) = self.netinfo.peer {
             writeln!(f, "endpoint: {} ({})", peer, self.mode)?;
         }
+        if self.netinfo.reused {
+            writeln!(f, "reused connection")?;
+        }
+        if let Some(version) = self.netinfo.tls_version {
+            writeln!(f, "TLS version: {}", version)?;
+        }
+        if let Some(survived) = self.netinfo.migration {
+            writeln!(
+                f,
+                "connection migration: {}",
+                if survived { "survived" } else { "failed" }
+            )?;
+        }
         writeln!(f, "elapsed: {} ms", self.elapsed)?;
         writeln!(
             f,
             "sent:{}, received:{} bytes",
             self.netinfo.sent, self.netinfo.received
-        )
+        )?;
+        write!(
+            f,
+            "retransmissions:{}, reconnects:{}",
+            self.netinfo.retransmissions, self.netinfo.reconnects
+        )?;
+        if let Some(lost) = self.netinfo.lost_packets {
+            write!(f, ", lost packets:{}", lost)?;
+        }
+        writeln!(f)?;
+        if let Some(http) = self.netinfo.http {
+            writeln!(f, "{}", http)?;
+        }
+        if let Some(proxy) = &self.proxy {
+            writeln!(f, "proxy: {}", proxy)?;
+        }
+        Ok(())
     }
 }
 
@@ -43,6 +99,15 @@ pub struct DisplayOptions {
     // print out stats like elasped time etc
     pub stats: bool,
 
+    // RFC3339 timestamp of each query (--time)
+    pub timestamp: bool,
+
+    // sections sizes and name compression of responses (--wire-stats)
+    pub wire_stats: bool,
+
+    // RRs which would not fit in a response of that size (--simulate-bufsize)
+    pub simulate_bufsize: Option<u16>,
+
     // iterative lookup
     pub trace: bool,
 
@@ -50,6 +115,15 @@ pub struct DisplayOptions {
     pub json: bool,
     pub json_pretty: bool,
 
+    // stable JSON schema version, native serialization if None
+    pub json_version: Option<u8>,
+
+    // RFC8427 JSON representation
+    pub json_rfc8427: bool,
+
+    // report what metadata the query exposes
+    pub privacy_report: bool,
+
     // true if we want the question in non-JSON print
     pub show_question: bool,
 
@@ -68,15 +142,34 @@ pub struct DisplayOptions {
     // show OPT record if any
     pub show_opt: bool,
 
+    // nothing changing from one run to the other is printed: IDs, TTLs, elapsed times,
+    // timestamps (--stable)
+    pub stable: bool,
+
     // formtting RRs
     pub fmt: String,
 
+    // --fmt given as a template
+    pub template: Option<Template>,
+
+    // RRs of the answer as CSV
+    pub csv: bool,
+
     // display TTL as seconds
     pub raw_ttl: bool,
 
     // align domain names
     pub align_names: bool,
 
+    // one header line per RRset followed by RDATA lines
+    pub group: bool,
+
+    // one line of labeled fields per RR, without alignment nor colors (--screen-reader)
+    pub screen_reader: bool,
+
+    // column widths for text output (--width)
+    pub widths: ColumnWidths,
+
     // content of the handlebars template file
     pub hb_tpl: Option<String>,
 
@@ -89,11 +182,94 @@ pub struct DisplayOptions {
     // show response header
     pub sho_resp_header: bool,
 
+    // explain flags and rcode after the header
+    pub explain_flags: bool,
+
+    // add some heuristics on responses (e.g. minimal responses)
+    pub analyze: bool,
+
+    // warn about answers in special-use address space
+    pub bogon_check: bool,
+
+    // comments appended to each RR: expiry time, reverse name, special-use range
+    pub annotate: bool,
+
+    // PTR names of the addresses found in answers (--annotate-ptr)
+    pub annotate_ptr: bool,
+    pub ptr_names: BTreeMap<IpAddr, String>,
+
+    // server which sent the response being printed, with --trace
+    pub provenance: Option<Provenance>,
+
     // Lua code if specified
     #[cfg(feature = "mlua")]
     pub lua_code: Option<String>,
 }
 
+//───────────────────────────────────────────────────────────────────────────────────
+// Column widths when printing out RRs: --width name=40,type=8 or --width auto.
+// A None value means the default width is used
+//───────────────────────────────────────────────────────────────────────────────────
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ColumnWidths {
+    // widths are computed from the result set before printing
+    pub auto: bool,
+
+    pub name: Option<usize>,
+    pub r#type: Option<usize>,
+    pub class: Option<usize>,
+    pub ttl: Option<usize>,
+    pub length: Option<usize>,
+}
+
+impl ColumnWidths {
+    // keep the largest width for each column
+    pub fn merge(&mut self, other: &ColumnWidths) {
+        fn max(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+            a.max(b)
+        }
+
+        self.name = max(self.name, other.name);
+        self.r#type = max(self.r#type, other.r#type);
+        self.class = max(self.class, other.class);
+        self.ttl = max(self.ttl, other.ttl);
+        self.length = max(self.length, other.length);
+    }
+}
+
+impl FromStr for ColumnWidths {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut widths = ColumnWidths::default();
+
+        for item in s.split(',').map(|x| x.trim()) {
+            if item == "auto" {
+                widths.auto = true;
+                continue;
+            }
+
+            let Some((column, width)) = item.split_once('=') else {
+                return Err(format!("'{}' is not in the form column=width or auto", item));
+            };
+            let width = width
+                .parse::<usize>()
+                .map_err(|_| format!("'{}' is not a valid width", width))?;
+
+            match column {
+                "name" => widths.name = Some(width),
+                "type" => widths.r#type = Some(width),
+                "class" => widths.class = Some(width),
+                "ttl" => widths.ttl = Some(width),
+                "length" => widths.length = Some(width),
+                _ => return Err(format!("unknown column '{}'", column)),
+            }
+        }
+
+        Ok(widths)
+    }
+}
+
 //───────────────────────────────────────────────────────────────────────────────────
 // Dump options
 //───────────────────────────────────────────────────────────────────────────────────
@@ -104,6 +280,72 @@ pub struct DumpOptions {
 
     // optional file containing Query raw data to read
     pub write_response: Option<PathBuf>,
+
+    // optional JSONL file where each exchange is appended
+    pub session_log: Option<PathBuf>,
+
+    // optional JSONL file where each message sent or received is appended
+    pub packet_log: Option<PathBuf>,
+
+    // session log to replay, optionally respecting the original timing
+    pub replay: Option<PathBuf>,
+    pub replay_timing: bool,
+
+    // resolvers and domains lists for a resolver comparison, number of warm-up
+    // queries and CSV output
+    pub shootout: Option<(PathBuf, PathBuf)>,
+    pub warmup: usize,
+    pub csv: bool,
+
+    // query repeatedly and alert when answers move between public and private space
+    pub rebind_watch: bool,
+
+    // follow the SOA serial of the zone, until the target serial if any
+    pub serial_watch: bool,
+    pub target_serial: Option<u32>,
+
+    // time between 2 queries of watch modes, if not the default one
+    pub interval: Option<Duration>,
+
+    // intervals of watch modes follow the SOA refresh and retry values
+    pub auto_interval: bool,
+
+    // zone file whose records are compared to live answers
+    pub expect_zone: Option<PathBuf>,
+
+    // probe each resolver instead of querying
+    pub check_resolvers: bool,
+
+    // number of random queries sent to test the server robustness
+    pub fuzz_server: Option<usize>,
+
+    // follow SVCB/HTTPS aliases down to the service parameters
+    pub follow_svcb: bool,
+
+    // connection parameters a browser would choose
+    pub browser_view: bool,
+
+    // service whose SRV and SVCB records give a list of endpoints
+    pub service: Option<String>,
+
+    // domain whose DNS-SD services are browsed
+    pub browse: Option<String>,
+
+    // query every supported type
+    pub all_types: bool,
+
+    // database where the outcome of each query is appended, and its summary
+    pub stats_db: Option<PathBuf>,
+    pub stats_report: bool,
+
+    // cache of the responses served when the resolver fails
+    pub serve_stale: Option<PathBuf>,
+
+    // pacing of bulk runs: maximum queries per second, random delay added to each
+    // query and random order of the lists
+    pub rate: Option<u32>,
+    pub jitter: Option<Duration>,
+    pub shuffle: bool,
 }
 
 pub trait Show: Display {
@@ -117,6 +359,43 @@ pub trait ToColor: Display {
     fn to_color(&self) -> colored::ColoredString;
 }
 
+//───────────────────────────────────────────────────────────────────────────────────
+// --numeric: types, classes, opcodes, rcodes and algorithms are printed as raw numbers.
+// It's a global setting because those values are printed deep inside Display impls
+//───────────────────────────────────────────────────────────────────────────────────
+static NUMERIC: AtomicBool = AtomicBool::new(false);
+
+pub fn set_numeric(numeric: bool) {
+    NUMERIC.store(numeric, Ordering::Relaxed);
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// --offsets: offset and length of each RR in the message are added to the JSON output
+//───────────────────────────────────────────────────────────────────────────────────
+static OFFSETS: AtomicBool = AtomicBool::new(false);
+
+pub fn set_offsets(offsets: bool) {
+    OFFSETS.store(offsets, Ordering::Relaxed);
+}
+
+pub fn show_offsets() -> bool {
+    OFFSETS.load(Ordering::Relaxed)
+}
+
+pub trait Numeric: Display {
+    // value as found in the DNS message
+    fn number(&self) -> u16;
+
+    // mnemonic or raw number depending on --numeric
+    fn mnemonic(&self) -> String {
+        if NUMERIC.load(Ordering::Relaxed) {
+            self.number().to_string()
+        } else {
+            self.to_string()
+        }
+    }
+}
+
 // pub const NAME_COLOR: (u8, u8, u8) = (100, 100, 100);
 
 // titles when displaying headers: build a map giving for each title its colored version
@@ -152,6 +431,7 @@ pub static TITLES: LazyLock<ColoredTitles> = LazyLock::new(|| {
 });
 
 pub fn header_section(text: &str, length: Option<usize>) -> ColoredString {
+    let text = tr(text);
     let s = if let Some(l) = length {
         format!("{:<l$}", text)
     } else {
@@ -159,3 +439,101 @@ pub fn header_section(text: &str, length: Option<usize>) -> ColoredString {
     };
     s.black().on_bright_cyan()
 }
+
+//───────────────────────────────────────────────────────────────────────────────────
+// --screen-reader: each field is named and fields are separated by commas instead of
+// being aligned by whitespace, so the line makes sense when read aloud
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn screen_reader_line(rr: &ResourceRecord, display_options: &DisplayOptions) -> String {
+    const REGULAR: &[(&str, &str)] = &[
+        ("name", "name"),
+        ("type", "type"),
+        ("class", "class"),
+        ("ttl", "ttl"),
+        ("rdata", "data"),
+    ];
+    const OPT: &[(&str, &str)] = &[
+        ("name", "name"),
+        ("type", "type"),
+        ("payload", "payload"),
+        ("extcode", "extended rcode"),
+        ("version", "version"),
+        ("flags", "flags"),
+        ("rdata", "data"),
+    ];
+
+    let fields = if rr.r#type == QType::OPT { OPT } else { REGULAR };
+    let mut labeled: Vec<_> = fields
+        .iter()
+        .filter_map(|(field, label)| rr.field(field, display_options).map(|value| (label, value)))
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(label, value)| format!("{}: {}", label, value))
+        .collect();
+
+    if display_options.annotate && rr.r#type != QType::OPT {
+        let comments = annotations(rr, display_options);
+        if !comments.is_empty() {
+            labeled.push(format!("notes: {}", comments.join("; ")));
+        }
+    }
+    if let Some(provenance) = display_options.provenance.as_ref().filter(|_| rr.r#type != QType::OPT) {
+        labeled.push(format!("source: {}", provenance));
+    }
+
+    labeled.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn widths() {
+        let w = ColumnWidths::from_str("name=40,type=8").unwrap();
+        assert!(!w.auto);
+        assert_eq!(w.name, Some(40));
+        assert_eq!(w.r#type, Some(8));
+        assert!(w.ttl.is_none());
+
+        let w = ColumnWidths::from_str("auto").unwrap();
+        assert!(w.auto);
+
+        assert!(ColumnWidths::from_str("foo=3").is_err());
+        assert!(ColumnWidths::from_str("name=x").is_err());
+        assert!(ColumnWidths::from_str("name").is_err());
+
+        let mut w = ColumnWidths {
+            name: Some(10),
+            ..Default::default()
+        };
+        w.merge(&ColumnWidths {
+            name: Some(5),
+            ttl: Some(3),
+            ..Default::default()
+        });
+        assert_eq!(w.name, Some(10));
+        assert_eq!(w.ttl, Some(3));
+    }
+
+    #[test]
+    fn screen_reader() {
+        use crate::dns::rfc::{domain::DomainName, qclass::QClass};
+
+        let rr = ResourceRecord::from_presentation(
+            DomainName::try_from("example.com.").unwrap(),
+            QType::A,
+            QClass::IN,
+            60,
+            "1.2.3.4",
+        )
+        .unwrap();
+        let display_options = DisplayOptions {
+            raw_ttl: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            screen_reader_line(&rr, &display_options),
+            "name: example.com., type: A, class: IN, ttl: 60, data: 1.2.3.4"
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Aggregated statistics over several query/response exchanges: rcode counts,
+//! average latency per resolver and slowest queries, and failures broken down by kind.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+use crate::dns::message::Message;
+use crate::dns::rfc::response_code::ResponseCode;
+use crate::error::Error;
+use crate::show::header_section;
+
+// number of slowest queries to report
+const SLOWEST: usize = 5;
+
+// one exchange kept for the slowest queries
+#[derive(Debug)]
+struct Exchange {
+    query: String,
+    resolver: String,
+    elapsed: Duration,
+}
+
+#[derive(Debug, Default)]
+pub struct ExchangeStats {
+    // number of responses per rcode
+    rcodes: BTreeMap<String, usize>,
+
+    // number of responses per opcode
+    opcodes: BTreeMap<String, usize>,
+
+    // total latency and number of exchanges per resolver
+    resolvers: BTreeMap<String, (Duration, u32)>,
+
+    // slowest exchanges, sorted from the slowest
+    slowest: Vec<Exchange>,
+}
+
+impl ExchangeStats {
+    //───────────────────────────────────────────────────────────────────────────────────
+    // account for a query/response exchange
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn add(&mut self, msg: &Message) {
+        *self
+            .rcodes
+            .entry(msg.response.rcode().to_string().to_uppercase())
+            .or_default() += 1;
+        *self
+            .opcodes
+            .entry(msg.response.header.flags.op_code().to_string().to_uppercase())
+            .or_default() += 1;
+
+        let resolver = msg
+            .netinfo
+            .peer
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| String::from("unknown"));
+
+        let entry = self.resolvers.entry(resolver.clone()).or_default();
+        entry.0 += msg.elapsed;
+        entry.1 += 1;
+
+        // keep only the slowest ones
+        let pos = self.slowest.partition_point(|e| e.elapsed >= msg.elapsed);
+        if pos < SLOWEST {
+            self.slowest.insert(
+                pos,
+                Exchange {
+                    query: format!("{} {}", msg.query.question.qname, msg.query.question.qtype),
+                    resolver,
+                    elapsed: msg.elapsed,
+                },
+            );
+            self.slowest.truncate(SLOWEST);
+        }
+    }
+
+    // number of exchanges accounted for
+    pub fn count(&self) -> usize {
+        self.rcodes.values().sum()
+    }
+
+    // average latency per resolver
+    pub fn average_latency(&self) -> BTreeMap<&str, Duration> {
+        self.resolvers
+            .iter()
+            .map(|(r, (total, count))| (r.as_str(), *total / *count))
+            .collect()
+    }
+}
+
+// failures of a bulk or watch run, counted by error class or rcode
+#[derive(Debug, Default)]
+pub struct Failures(BTreeMap<String, usize>);
+
+impl Failures {
+    pub fn error(&mut self, e: &Error) {
+        *self.0.entry(e.class().to_string()).or_default() += 1;
+    }
+
+    // SERVFAIL and REFUSED are failures, other rcodes are answers. Returns true if counted
+    pub fn rcode(&mut self, rcode: ResponseCode) -> bool {
+        let failed = matches!(rcode, ResponseCode::ServFail | ResponseCode::Refused);
+        if failed {
+            *self.0.entry(rcode.to_string().to_uppercase()).or_default() += 1;
+        }
+        failed
+    }
+
+    pub fn total(&self) -> usize {
+        self.0.values().sum()
+    }
+}
+
+impl fmt::Display for Failures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str("none");
+        }
+        let list: Vec<_> = self.0.iter().map(|(k, v)| format!("{}:{}", k, v)).collect();
+        f.write_str(&list.join(" "))
+    }
+}
+
+impl<'a> Extend<&'a Message> for ExchangeStats {
+    fn extend<T: IntoIterator<Item = &'a Message>>(&mut self, iter: T) {
+        for msg in iter {
+            self.add(msg);
+        }
+    }
+}
+
+impl fmt::Display for ExchangeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", header_section("SUMMARY", None))?;
+        writeln!(f, "exchanges: {}", self.count())?;
+
+        let join = |map: &BTreeMap<String, usize>| {
+            map.iter()
+                .map(|(k, v)| format!("{}: {}", k, v))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        writeln!(f, "opcodes: {}", join(&self.opcodes))?;
+        writeln!(f, "rcodes: {}", join(&self.rcodes))?;
+
+        writeln!(f, "\naverage latency per resolver:")?;
+        for (resolver, avg) in self.average_latency() {
+            writeln!(f, "  {:<40} {:>8} ms", resolver, avg.as_millis())?;
+        }
+
+        writeln!(f, "\nslowest queries:")?;
+        for e in &self.slowest {
+            writeln!(
+                f,
+                "  {:<40} {:<40} {:>8} ms",
+                e.query,
+                e.resolver,
+                e.elapsed.as_millis()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::rfc::{flags::Flags, query::Query, response::Response};
+    use crate::error::Network;
+    use crate::transport::NetworkInfo;
+
+    fn message(peer: &str, ms: u64, rcode: u16) -> Message {
+        let mut response = Response::default();
+        response.header.flags = Flags::try_from(0x8180 | rcode).unwrap();
+
+        Message {
+            query: Query::default(),
+            response,
+            netinfo: NetworkInfo {
+                peer: Some(peer.parse().unwrap()),
+                ..Default::default()
+            },
+            raw_response: Vec::new(),
+            timestamp: None,
+            elapsed: Duration::from_millis(ms),
+        }
+    }
+
+    #[test]
+    fn aggregate() {
+        let mut stats = ExchangeStats::default();
+        for ms in 1..=10 {
+            stats.add(&message("1.1.1.1:53", ms * 10, 0));
+        }
+        stats.add(&message("8.8.8.8:53", 20, 3));
+        stats.add(&message("8.8.8.8:53", 40, 2));
+
+        assert_eq!(stats.count(), 12);
+        assert_eq!(stats.rcodes["NOERROR"], 10);
+        assert_eq!(stats.rcodes["NXDOMAIN"], 1);
+        assert_eq!(stats.rcodes["SERVFAIL"], 1);
+
+        let avg = stats.average_latency();
+        assert_eq!(avg["1.1.1.1:53"], Duration::from_millis(55));
+        assert_eq!(avg["8.8.8.8:53"], Duration::from_millis(30));
+
+        let slowest: Vec<_> = stats.slowest.iter().map(|e| e.elapsed.as_millis()).collect();
+        assert_eq!(slowest, vec![100, 90, 80, 70, 60]);
+    }
+
+    #[test]
+    fn failures() {
+        let mut failures = Failures::default();
+        assert_eq!(failures.to_string(), "none");
+
+        let timeout = std::io::Error::from(std::io::ErrorKind::WouldBlock);
+        failures.error(&Error::Network(timeout, Network::Receive));
+        failures.error(&Error::IcmpRefused(String::from("1.1.1.1:53")));
+        failures.error(&Error::Buffer(std::io::Error::from(std::io::ErrorKind::UnexpectedEof)));
+        assert!(failures.rcode(ResponseCode::ServFail));
+        assert!(!failures.rcode(ResponseCode::NXDomain));
+
+        assert_eq!(failures.total(), 4);
+        assert_eq!(failures.to_string(), "SERVFAIL:1 parse:1 refused:1 timeout:1");
+    }
+}
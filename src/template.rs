@@ -0,0 +1,233 @@
+//! --fmt templates: literal text with RR fields between braces, each with an optional
+//! list of RR types it applies to and an optional alignment and width. E.g.:
+//! `{name:<40}{ttl:>8} {rdata@MX|SRV}`. Fields are also the columns of the CSV output.
+use std::str::FromStr;
+
+use crate::dns::rfc::{qtype::QType, resource_record::ResourceRecord};
+use crate::show::DisplayOptions;
+
+// fields which can be used in --fmt
+pub const FIELDS: &[&str] = &[
+    "name", "type", "class", "ttl", "length", "rdata", "payload", "extcode", "version", "flags",
+];
+
+// CSV columns if no --fmt is given
+pub const DEFAULT_CSV_FIELDS: &[&str] = &["name", "type", "class", "ttl", "rdata"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Field {
+        name: String,
+
+        // only printed for those types if not empty
+        types: Vec<QType>,
+        align: Align,
+        width: usize,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Template(Vec<Segment>);
+
+// true if --fmt is a template and not a list of comma-separated fields
+pub fn is_template(fmt: &str) -> bool {
+    fmt.contains('{')
+}
+
+// check the --fmt value while parsing arguments
+pub fn parse_fmt(fmt: &str) -> Result<String, String> {
+    if is_template(fmt) {
+        Template::from_str(fmt)?;
+    } else if let Some(name) = fmt.split(',').map(str::trim).find(|name| !FIELDS.contains(name)) {
+        return Err(format!("unknown field '{}'", name));
+    }
+    Ok(fmt.to_string())
+}
+
+// quote a CSV value if needed (RFC4180)
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// field between braces: name[@TYPE|TYPE...][:[<>^]width]
+fn parse_field(spec: &str) -> Result<Segment, String> {
+    let (field, format) = spec.split_once(':').unwrap_or((spec, ""));
+    let (name, types) = field.split_once('@').unwrap_or((field, ""));
+
+    let name = name.trim();
+    if !FIELDS.contains(&name) {
+        return Err(format!("unknown field '{}'", name));
+    }
+
+    let types = types
+        .split('|')
+        .filter(|t| !t.is_empty())
+        .map(|t| QType::from_str(&t.trim().to_uppercase()).map_err(|_| format!("unknown RR type '{}'", t)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (align, width) = match format.chars().next() {
+        Some('<') => (Align::Left, &format[1..]),
+        Some('>') => (Align::Right, &format[1..]),
+        Some('^') => (Align::Center, &format[1..]),
+        _ => (Align::Left, format),
+    };
+    let width = if width.is_empty() {
+        0
+    } else {
+        width
+            .parse::<usize>()
+            .map_err(|_| format!("'{}' is not a valid width", width))?
+    };
+
+    Ok(Segment::Field {
+        name: name.to_string(),
+        types,
+        align,
+        width,
+    })
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                // {{ and }} are literal braces
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut spec = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => spec.push(c),
+                            None => return Err(String::from("unterminated '{', use '{{' for a literal brace")),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(parse_field(&spec)?);
+                }
+                '}' => return Err(String::from("unmatched '}', use '}}' for a literal brace")),
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Self(segments))
+    }
+}
+
+impl Template {
+    // field names in order, used as CSV columns
+    pub fn fields(&self) -> Vec<&str> {
+        self.0
+            .iter()
+            .filter_map(|s| match s {
+                Segment::Field { name, .. } => Some(name.as_str()),
+                Segment::Literal(_) => None,
+            })
+            .collect()
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // the RR as a line of text
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn render(&self, rr: &ResourceRecord, display_options: &DisplayOptions) -> String {
+        let mut line = String::new();
+
+        for segment in &self.0 {
+            match segment {
+                Segment::Literal(s) => line.push_str(s),
+                Segment::Field {
+                    name,
+                    types,
+                    align,
+                    width,
+                } => {
+                    let value = if types.is_empty() || types.contains(&rr.r#type) {
+                        rr.field(name, display_options).unwrap_or_default()
+                    } else {
+                        String::new()
+                    };
+                    let width = *width;
+                    let value = match align {
+                        Align::Left => format!("{:<width$}", value),
+                        Align::Right => format!("{:>width$}", value),
+                        Align::Center => format!("{:^width$}", value),
+                    };
+                    line.push_str(&value);
+                }
+            }
+        }
+
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let tpl = Template::from_str("{name:<20}|{ttl:>6} {{{rdata@MX|srv}}}").unwrap();
+        assert_eq!(tpl.fields(), vec!["name", "ttl", "rdata"]);
+        assert_eq!(tpl.0.len(), 6);
+        assert_eq!(
+            tpl.0[4],
+            Segment::Field {
+                name: String::from("rdata"),
+                types: vec![QType::MX, QType::SRV],
+                align: Align::Left,
+                width: 0
+            }
+        );
+        assert_eq!(tpl.0[5], Segment::Literal(String::from("}")));
+
+        assert!(Template::from_str("{foo}").is_err());
+        assert!(Template::from_str("{name@FOO}").is_err());
+        assert!(Template::from_str("{name:<x}").is_err());
+        assert!(Template::from_str("name}").is_err());
+        assert_eq!(
+            Template::from_str("{name:<20"),
+            Err(String::from("unterminated '{', use '{{' for a literal brace"))
+        );
+
+        assert!(is_template("{name}"));
+        assert_eq!(parse_fmt("name, ttl"), Ok(String::from("name, ttl")));
+        assert_eq!(parse_fmt("name,tll"), Err(String::from("unknown field 'tll'")));
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(csv_field("1.1.1.1"), "1.1.1.1");
+        assert_eq!(csv_field("\"v=spf1\" \"a,b\""), "\"\"\"v=spf1\"\" \"\"a,b\"\"\"");
+    }
+}
@@ -1,66 +1,336 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
+use std::net::IpAddr;
+use std::thread;
+
 use log::trace;
+use serde::Serialize;
 
 use crate::args::CliOptions;
+use crate::dns::message::MessageList;
 use crate::dns::rfc::domain::ROOT;
-use crate::dns::rfc::{domain::ROOT_DOMAIN, qtype::QType};
+use crate::dns::rfc::{domain::DomainName, response_code::ResponseCode};
+use crate::dns::rfc::{domain::ROOT_DOMAIN, qtype::QType, resource_record::ResourceRecord};
 use crate::error::{Dns, Error};
 use crate::get_messages;
-use crate::show::Show;
-use crate::transport::{endpoint::EndPoint, root_servers::get_root_server};
+use crate::show::{header_section, Show};
+use crate::transport::{
+    endpoint::EndPoint,
+    root_servers::{get_root_server, root_server_name},
+};
 
-pub fn trace_resolution(options: &mut CliOptions) -> crate::error::Result<()> {
-    trace!("tracing started");
+// maximum number of referrals followed before giving up
+const MAX_REFERRALS: usize = 16;
 
-    // save original options
-    let orig_qt = options.protocol.qtype[0];
+//───────────────────────────────────────────────────────────────────────────────────
+// anomalies found while following referrals
+//───────────────────────────────────────────────────────────────────────────────────
+#[derive(Debug)]
+pub enum TraceWarning {
+    // referral to a zone already asked to the same server
+    Loop {
+        zone: DomainName,
+        server: IpAddr,
+    },
+
+    // server not authoritative for the zone it was delegated
+    Lame {
+        zone: DomainName,
+        server: IpAddr,
+        reason: String,
+    },
+
+    // additional records the server has no authority on, not used as glue
+    OutOfBailiwick {
+        zone: DomainName,
+        server: IpAddr,
+        records: Vec<String>,
+    },
+
+    // referral chain is longer than the limit
+    TooDeep(usize),
+}
+
+impl fmt::Display for TraceWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceWarning::Loop { zone, server } => {
+                write!(f, "referral loop: zone {} already asked to {}", zone, server)
+            }
+            TraceWarning::Lame { zone, server, reason } => {
+                write!(f, "lame delegation: {} for zone {}: {}", server, zone, reason)
+            }
+            TraceWarning::OutOfBailiwick { zone, server, records } => {
+                write!(
+                    f,
+                    "out of bailiwick: {} for zone {} sent {}, ignored",
+                    server,
+                    zone,
+                    records.join(", ")
+                )
+            }
+            TraceWarning::TooDeep(n) => write!(f, "referral chain exceeds {} referrals", n),
+        }
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// server which sent a response while tracing, and at which delegation depth (0 for the
+// root servers). There's no depth for the responses of the resolver
+//───────────────────────────────────────────────────────────────────────────────────
+#[derive(Debug, Clone, Serialize)]
+pub struct Provenance {
+    pub server: String,
+    pub ip: Option<IpAddr>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<usize>,
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "from {}", self.server)?;
+        if let Some(ip) = self.ip.filter(|ip| ip.to_string() != self.server) {
+            write!(f, " ({})", ip)?;
+        }
+        if let Some(depth) = self.depth {
+            write!(f, " at depth {}", depth)?;
+        }
+        Ok(())
+    }
+}
+
+// an RR of the trace with the server which sent it, in JSON
+#[derive(Serialize)]
+struct TracedRecord<'a> {
+    qtype: QType,
+    section: &'static str,
+
+    #[serde(flatten)]
+    rr: &'a ResourceRecord,
+
+    provenance: &'a Provenance,
+}
+
+// a response of the trace with the server which sent it
+struct Step {
+    messages: MessageList,
+    provenance: Provenance,
+}
+
+// response of the resolver, which has no delegation depth
+fn resolver_step(ep: &EndPoint, messages: MessageList) -> Step {
+    Step {
+        provenance: Provenance {
+            server: ep.server_name.clone(),
+            ip: messages[0].netinfo.peer.map(|peer| peer.ip()),
+            depth: None,
+        },
+        messages,
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// server asked while following the referrals, with the zone it was delegated. The
+// delegation path of a type is the list of these hops from the root
+//───────────────────────────────────────────────────────────────────────────────────
+#[derive(Debug, Clone, PartialEq)]
+struct Hop {
+    zone: String,
+    server: String,
+    ip: IpAddr,
+}
+
+impl fmt::Display for Hop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<20} {} ({})", self.zone, self.server, self.ip)
+    }
+}
+
+// number of hops from the root all the paths have in common
+fn shared_hops(paths: &[&[Hop]]) -> usize {
+    let Some(first) = paths.first() else {
+        return 0;
+    };
+    first
+        .iter()
+        .enumerate()
+        .take_while(|(i, hop)| paths.iter().all(|path| path.get(*i) == Some(hop)))
+        .count()
+}
+
+// the iterative resolution of a type: responses, delegation path and anomalies found
+struct Walk {
+    qtype: QType,
+    steps: Vec<Step>,
+    path: Vec<Hop>,
+    warnings: Vec<TraceWarning>,
+    glue_warnings: Vec<String>,
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// print out a response of the trace, each RR annotated with the server which sent it.
+// In JSON, RRs are gathered to be printed at the end
+//───────────────────────────────────────────────────────────────────────────────────
+fn show_step(options: &mut CliOptions, qtype: QType, step: &Step, records: &mut Vec<serde_json::Value>) {
+    let resp = step.messages[0].response();
+    if !options.display.json && !options.display.json_pretty {
+        options.display.provenance = Some(step.provenance.clone());
+        resp.show(&options.display, None);
+        println!();
+        options.display.provenance = None;
+        return;
+    }
+
+    let sections = [
+        ("answer", resp.answer.as_ref()),
+        ("authority", resp.authority()),
+        ("additional", resp.additional()),
+    ];
+    for (section, list) in sections {
+        for rr in list
+            .iter()
+            .flat_map(|list| list.iter())
+            .filter(|rr| rr.r#type != QType::OPT)
+        {
+            let record = TracedRecord {
+                qtype,
+                section,
+                rr,
+                provenance: &step.provenance,
+            };
+            records.push(serde_json::to_value(record).unwrap());
+        }
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// follow the referrals for a type, from the root server given. Servers are reached with
+// their A addresses, or AAAA ones when tracing AAAA, so any type can be traced
+//───────────────────────────────────────────────────────────────────────────────────
+fn walk(mut options: CliOptions, qtype: QType, root: IpAddr) -> crate::error::Result<Walk> {
     let orig_domain = options.protocol.domain_name.clone();
     let orig_ep = options.transport.endpoint.clone();
+    let addr_qt = if qtype == QType::AAAA { QType::AAAA } else { QType::A };
+    options.protocol.qtype = vec![qtype];
 
-    // no recursion wanted
-    options.flags.recursion_desired = true;
+    let mut walk = Walk {
+        qtype,
+        steps: Vec::new(),
+        path: Vec::new(),
+        warnings: Vec::new(),
+        glue_warnings: Vec::new(),
+    };
 
-    // send NS . to my DNS to get list of root servers
-    trace!("query:{} domain:{} server:{}", QType::NS, ROOT, orig_ep);
-    options.protocol.qtype = vec![QType::NS];
-    options.protocol.domain_name = ROOT_DOMAIN;
-    let messages = get_messages(None, options)?;
-    let resp = messages[0].response();
-    resp.show(&options.display, None);
-    println!();
+    let mut ip = root;
+    let mut server = root_server_name(&ip).map_or_else(|| ip.to_string(), String::from);
 
-    // chose a random root server
-    let mut ip = get_root_server(&options.transport.ip_version, None);
-    options.protocol.qtype = vec![orig_qt];
+    // zone the current server is delegated for, and (zone, server) already asked
+    let mut zone = ROOT_DOMAIN;
+    let mut visited: HashSet<(String, IpAddr)> = HashSet::new();
 
-    // reset the original domain to query
-    options.protocol.domain_name = orig_domain.clone();
+    for depth in 0.. {
+        // stop tracing if Ctrl-C or deadline reached
+        options.transport.cancel.check()?;
+
+        if depth == MAX_REFERRALS {
+            walk.warnings.push(TraceWarning::TooDeep(MAX_REFERRALS));
+            break;
+        }
+        if !visited.insert((zone.to_string(), ip)) {
+            walk.warnings.push(TraceWarning::Loop { zone, server: ip });
+            break;
+        }
 
-    loop {
         // iterative query => RD = false
         options.flags.recursion_desired = false;
 
         options.transport.endpoint = EndPoint::try_from((&ip, options.transport.port))?;
         trace!(
             "query:{} domain:{} server:{}",
-            orig_qt,
+            qtype,
             orig_domain,
             options.transport.endpoint
         );
 
-        let messages = get_messages(None, options)?;
-        let resp = messages[0].response();
-        resp.show(&options.display, None);
-        println!();
+        let messages = get_messages(None, &options)?;
+        walk.path.push(Hop {
+            zone: zone.to_string(),
+            server: server.clone(),
+            ip,
+        });
+        walk.steps.push(Step {
+            messages,
+            provenance: Provenance {
+                server: server.clone(),
+                ip: Some(ip),
+                depth: Some(depth),
+            },
+        });
+        let resp = walk.steps[walk.steps.len() - 1].messages[0].response();
 
         // did we find the ip address for the domain we asked for ?
-        if let Some(ip) = resp.ip_address(&orig_qt, &options.protocol.domain_name) {
-            // println!("!!! found ip={}", ip);
-            return Ok(());
+        if resp.ip_address(&qtype, &options.protocol.domain_name).is_some() {
+            break;
+        }
+
+        // the server is expected to answer or refer to a zone closer to the domain
+        let lame = |reason: String| TraceWarning::Lame {
+            zone: zone.clone(),
+            server: ip,
+            reason,
+        };
+        if matches!(
+            resp.rcode(),
+            ResponseCode::ServFail | ResponseCode::Refused | ResponseCode::NotAuth
+        ) {
+            walk.warnings.push(lame(format!("{} response", resp.rcode())));
+            break;
+        }
+
+        // final answer without address (e.g. NXDOMAIN or NODATA)
+        if resp.is_authorative() {
+            break;
+        }
+
+        // the server can only be trusted for names of its zone
+        let bailiwick = zone.clone();
+        let outside = resp.out_of_bailiwick(&bailiwick);
+        if !outside.is_empty() {
+            walk.warnings.push(TraceWarning::OutOfBailiwick {
+                zone: bailiwick.clone(),
+                server: ip,
+                records: outside.iter().map(|rr| format!("{} {}", rr.name, rr.r#type)).collect(),
+            });
+        }
+
+        match resp.referral_zone() {
+            None => {
+                walk.warnings
+                    .push(lame(String::from("neither an authoritative answer nor a referral")));
+                break;
+            }
+            Some(child) if zone.is_subdomain_of(child) => {
+                walk.warnings.push(lame(format!("upward referral to {}", child)));
+                break;
+            }
+            Some(child) if !child.is_subdomain_of(&zone) || !orig_domain.is_subdomain_of(child) => {
+                walk.warnings
+                    .push(lame(format!("referral to unrelated zone {}", child)));
+                break;
+            }
+            Some(child) => zone = child.clone(),
         }
 
         // no, so continue. If glue records, this means we have addresses
-        if let Some(rr) = resp.random_glue_record(&orig_qt) {
+        if let Some(rr) = resp.random_glue_record(&addr_qt, &bailiwick) {
             ip = rr.ip_address().ok_or(Error::Dns(Dns::ImpossibleToTrace))?;
+            server = rr.name.to_string();
+
+            // compare glue offered by the parent with the child zone data
+            let mut glue = resp.glue();
+            glue.retain(|ns, _| DomainName::try_from(ns.as_str()).is_ok_and(|ns| ns.is_subdomain_of(&bailiwick)));
+            walk.glue_warnings.extend(check_glue(&mut options, &ip, &glue));
         } else {
             // query regular resolver for resolving random ns server in the auth section
             let rr = resp.random_ns_record().ok_or(Error::Dns(Dns::ImpossibleToTrace))?;
@@ -68,25 +338,341 @@ pub fn trace_resolution(options: &mut CliOptions) -> crate::error::Result<()> {
             options.flags.recursion_desired = true;
 
             options.transport.endpoint = orig_ep.clone();
+            options.protocol.qtype = vec![addr_qt];
             options.protocol.domain_name = rr.ns_name().ok_or(Error::Dns(Dns::ImpossibleToTrace))?;
 
             trace!(
                 "query:{} domain:{} server:{}",
-                orig_qt,
-                orig_domain,
+                addr_qt,
+                options.protocol.domain_name,
                 options.transport.endpoint
             );
-            let messages = get_messages(None, options)?;
-            let resp = messages[0].response();
-            resp.show(&options.display, None);
+            let messages = get_messages(None, &options)?;
 
             // find the ip address
-            ip = resp
-                .ip_address(&orig_qt, &options.protocol.domain_name)
+            ip = messages[0]
+                .response()
+                .ip_address(&addr_qt, &options.protocol.domain_name)
                 .ok_or(Error::Dns(Dns::ImpossibleToTrace))?;
+            server = options.protocol.domain_name.to_string();
+            walk.steps.push(resolver_step(&orig_ep, messages));
 
-            // reset to the original domain we're looking for
+            // reset to the original domain and type we're looking for
+            options.protocol.qtype = vec![qtype];
             options.protocol.domain_name = orig_domain.clone();
         }
     }
+
+    Ok(walk)
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// trace each type in its own thread from the same root server, so the delegation
+// paths can be compared: the hops all the types have in common are shown once
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn trace_resolution(options: &mut CliOptions) -> crate::error::Result<()> {
+    trace!("tracing started");
+
+    // save original options
+    let qtypes = options.protocol.qtype.clone();
+    let orig_domain = options.protocol.domain_name.clone();
+    let orig_ep = options.transport.endpoint.clone();
+
+    // no recursion wanted
+    options.flags.recursion_desired = true;
+
+    // send NS . to my DNS to get list of root servers
+    trace!("query:{} domain:{} server:{}", QType::NS, ROOT, orig_ep);
+    options.protocol.qtype = vec![QType::NS];
+    options.protocol.domain_name = ROOT_DOMAIN;
+    let messages = get_messages(None, options)?;
+    let roots = resolver_step(&orig_ep, messages);
+
+    // reset the original domain to query
+    options.protocol.qtype = qtypes.clone();
+    options.protocol.domain_name = orig_domain;
+
+    // chose a random root server
+    let root = get_root_server(&options.transport.ip_version, None);
+
+    // a type which can't be traced doesn't stop the other ones
+    let mut failures: Vec<String> = Vec::new();
+    let mut walks: Vec<Walk> = Vec::new();
+    if let [qtype] = qtypes[..] {
+        walks.push(walk(options.clone(), qtype, root)?);
+    } else {
+        let results = thread::scope(|s| {
+            let handles: Vec<_> = qtypes
+                .iter()
+                .map(|qtype| {
+                    let opts = options.clone();
+                    let qtype = *qtype;
+                    s.spawn(move || {
+                        walk(opts, qtype, root).map_err(|e| format!("{}: tracing failed ({})", qtype, e.localized()))
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>()
+        });
+        options.transport.cancel.check()?;
+
+        for result in results {
+            match result {
+                Ok(walk) => walks.push(walk),
+                Err(e) => failures.push(e),
+            }
+        }
+        if walks.is_empty() {
+            for e in &failures {
+                eprintln!("{}", e);
+            }
+            return Err(Error::Dns(Dns::ImpossibleToTrace));
+        }
+    }
+
+    let paths: Vec<&[Hop]> = walks.iter().map(|walk| walk.path.as_slice()).collect();
+    let shared = shared_hops(&paths);
+    let several = qtypes.len() > 1;
+
+    // anomalies of all the types, each one once
+    let mut warnings = failures;
+    let mut glue_warnings: Vec<String> = Vec::new();
+    for walk in &walks {
+        for w in &walk.warnings {
+            let w = if several {
+                format!("{}: {}", walk.qtype, w)
+            } else {
+                w.to_string()
+            };
+            if !warnings.contains(&w) {
+                warnings.push(w);
+            }
+        }
+        for w in &walk.glue_warnings {
+            if !glue_warnings.contains(w) {
+                glue_warnings.push(w.clone());
+            }
+        }
+    }
+
+    // RRs printed with the server which sent them
+    let mut records: Vec<serde_json::Value> = Vec::new();
+    show_step(options, QType::NS, &roots, &mut records);
+
+    if options.display.json || options.display.json_pretty {
+        for walk in &walks {
+            for step in &walk.steps {
+                show_step(options, walk.qtype, step, &mut records);
+            }
+        }
+
+        let paths: Vec<_> = walks
+            .iter()
+            .map(|walk| {
+                let hops: Vec<_> = walk
+                    .path
+                    .iter()
+                    .enumerate()
+                    .map(|(depth, hop)| {
+                        serde_json::json!({
+                            "depth": depth,
+                            "zone": hop.zone,
+                            "server": hop.server,
+                            "ip": hop.ip,
+                            "shared": depth < shared,
+                        })
+                    })
+                    .collect();
+                serde_json::json!({"qtype": walk.qtype, "hops": hops})
+            })
+            .collect();
+        let j = serde_json::json!({
+            "records": records,
+            "paths": paths,
+            "warnings": warnings,
+            "glue_check": glue_warnings,
+        });
+        if options.display.json_pretty {
+            println!("{}", serde_json::to_string_pretty(&j).unwrap());
+        } else {
+            println!("{}", serde_json::to_string(&j).unwrap());
+        }
+        return Ok(());
+    }
+
+    // steps before the first divergent hop are the same for all the types, they're
+    // shown once
+    let divergent = |walk: &Walk| {
+        walk.steps
+            .iter()
+            .position(|step| step.provenance.depth.is_some_and(|depth| depth >= shared))
+            .unwrap_or(walk.steps.len())
+    };
+    if let Some(first) = walks.first() {
+        for step in &first.steps[..divergent(first)] {
+            show_step(options, first.qtype, step, &mut records);
+        }
+    }
+    for walk in &walks {
+        let steps = &walk.steps[divergent(walk)..];
+        if several && !steps.is_empty() {
+            println!("{}", header_section(&walk.qtype.to_string(), None));
+        }
+        for step in steps {
+            show_step(options, walk.qtype, step, &mut records);
+        }
+    }
+
+    if several {
+        show_paths(&walks, shared);
+    }
+    if !warnings.is_empty() {
+        println!("{}", header_section("DELEGATION WARNINGS", None));
+        for w in &warnings {
+            println!("{}", w);
+        }
+        println!();
+    }
+    show_glue_check(&glue_warnings);
+
+    Ok(())
+}
+
+// hops common to all the types, then the ones of each type
+fn show_paths(walks: &[Walk], shared: usize) {
+    println!("{}", header_section("DELEGATION PATHS", None));
+    if let Some(first) = walks.first() {
+        for (depth, hop) in first.path[..shared].iter().enumerate() {
+            println!("{:<10} {:>2} {}", "shared", depth, hop);
+        }
+    }
+    for walk in walks {
+        for (depth, hop) in walk.path.iter().enumerate().skip(shared) {
+            println!("{:<10} {:>2} {}", walk.qtype.to_string(), depth, hop);
+        }
+    }
+    println!();
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// ask the child server for the addresses of the glued NS names and compare with
+// the glue offered by the parent. Only authoritative answers are compared, and only
+// for address families found in the glue
+//───────────────────────────────────────────────────────────────────────────────────
+fn check_glue(options: &mut CliOptions, server: &IpAddr, glue: &BTreeMap<String, BTreeSet<IpAddr>>) -> Vec<String> {
+    let orig_qt = options.protocol.qtype.clone();
+    let orig_domain = options.protocol.domain_name.clone();
+    let mut warnings = Vec::new();
+
+    for (ns, glue_addrs) in glue {
+        let Ok(ns_name) = DomainName::try_from(ns.as_str()) else {
+            continue;
+        };
+
+        for qt in [QType::A, QType::AAAA] {
+            let expected: BTreeSet<IpAddr> = glue_addrs
+                .iter()
+                .filter(|ip| ip.is_ipv4() == (qt == QType::A))
+                .copied()
+                .collect();
+            if expected.is_empty() {
+                continue;
+            }
+
+            options.protocol.qtype = vec![qt];
+            options.protocol.domain_name = ns_name.clone();
+            let Ok(endpoint) = EndPoint::try_from((server, options.transport.port)) else {
+                continue;
+            };
+            options.transport.endpoint = endpoint;
+
+            let Ok(messages) = get_messages(None, options) else {
+                continue;
+            };
+            let resp = messages[0].response();
+            if !resp.is_authorative() || resp.rcode() != ResponseCode::NoError {
+                continue;
+            }
+
+            let child = resp.ip_addresses(&ns_name);
+            if child != expected {
+                let list = |set: &BTreeSet<IpAddr>| set.iter().map(|ip| ip.to_string()).collect::<Vec<_>>().join(" ");
+                warnings.push(format!(
+                    "stale {} glue for {}: parent has [{}], child zone ({}) has [{}]",
+                    qt,
+                    ns,
+                    list(&expected),
+                    server,
+                    list(&child)
+                ));
+            }
+        }
+    }
+
+    options.protocol.qtype = orig_qt;
+    options.protocol.domain_name = orig_domain;
+    warnings
+}
+
+fn show_glue_check(warnings: &[String]) {
+    println!("{}", header_section("GLUE CHECK", None));
+    if warnings.is_empty() {
+        println!("glue records offered by parents are consistent with child zones");
+    }
+    for w in warnings {
+        println!("{}", w);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn provenance() {
+        let ip: IpAddr = "198.41.0.4".parse().unwrap();
+        let root = Provenance {
+            server: root_server_name(&ip).unwrap().to_string(),
+            ip: Some(ip),
+            depth: Some(0),
+        };
+        assert_eq!(root.to_string(), "from a.root-servers.net. (198.41.0.4) at depth 0");
+        assert_eq!(
+            serde_json::to_string(&root).unwrap(),
+            r#"{"server":"a.root-servers.net.","ip":"198.41.0.4","depth":0}"#
+        );
+
+        let resolver = Provenance {
+            server: String::from("1.1.1.1"),
+            ip: Some("1.1.1.1".parse().unwrap()),
+            depth: None,
+        };
+        assert_eq!(resolver.to_string(), "from 1.1.1.1");
+    }
+
+    #[test]
+    fn shared() {
+        let hop = |zone: &str, ip: &str| Hop {
+            zone: String::from(zone),
+            server: String::from(ip),
+            ip: ip.parse().unwrap(),
+        };
+        let a = [
+            hop(".", "198.41.0.4"),
+            hop("com.", "192.5.6.30"),
+            hop("example.com.", "199.43.135.53"),
+        ];
+        let aaaa = [
+            hop(".", "198.41.0.4"),
+            hop("com.", "192.5.6.30"),
+            hop("example.com.", "199.43.133.53"),
+        ];
+        let mx = [hop(".", "198.41.0.4"), hop("com.", "192.33.4.12")];
+
+        assert_eq!(shared_hops(&[&a]), 3);
+        assert_eq!(shared_hops(&[&a, &aaaa]), 2);
+        assert_eq!(shared_hops(&[&a, &aaaa, &mx]), 1);
+        assert_eq!(shared_hops(&[&a[..1], &aaaa]), 1);
+        assert_eq!(shared_hops(&[]), 0);
+    }
 }
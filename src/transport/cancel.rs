@@ -0,0 +1,105 @@
+// A cancellation token shared between the Ctrl-C handler and the protocol loops (AXFR,
+// trace, multiple types) to abort long operations cleanly, partial results being kept.
+// With --deadline, the token is also cancelled when the deadline is reached.
+use std::io::{stdout, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Default, Clone)]
+pub struct CancellationToken {
+    // shared flag: all clones of the token see the cancellation
+    cancelled: Arc<AtomicBool>,
+
+    // optional point in time after which the token is considered cancelled
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    // cancel the operation once duration has elapsed from now
+    pub fn with_deadline(mut self, duration: Duration) -> Self {
+        self.deadline = Some(Instant::now() + duration);
+        self
+    }
+
+    // cancel all operations using this token or one of its clones
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    // true if cancel() was called or the deadline is reached
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst) || self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+
+    // time left before the deadline, if any
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(|d| d.saturating_duration_since(Instant::now()))
+    }
+
+    // spawn a thread waiting for Ctrl-C which cancels the token. A second Ctrl-C exits immediately
+    pub fn cancel_on_ctrl_c(&self) {
+        let token = self.clone();
+
+        std::thread::spawn(move || {
+            let Ok(rt) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+                return;
+            };
+
+            rt.block_on(async {
+                if tokio::signal::ctrl_c().await.is_err() {
+                    return;
+                }
+
+                // we might have been interrupted in the middle of a colored output
+                if stdout().is_terminal() {
+                    print!("\x1b[0m");
+                }
+                token.cancel();
+
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    std::process::exit(130);
+                }
+            });
+        });
+    }
+
+    // convenient way to stop with ? when the token is cancelled
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel() {
+        let token = CancellationToken::default();
+        assert!(!token.is_cancelled());
+        assert!(token.check().is_ok());
+
+        // clones share the same state
+        let cloned = token.clone();
+        cloned.cancel();
+        assert!(token.is_cancelled());
+        assert!(matches!(token.check(), Err(Error::Cancelled)));
+    }
+
+    #[test]
+    fn deadline() {
+        let token = CancellationToken::default().with_deadline(Duration::from_millis(0));
+        assert!(token.is_cancelled());
+        assert_eq!(token.remaining(), Some(Duration::ZERO));
+
+        let token = CancellationToken::default().with_deadline(Duration::from_secs(3600));
+        assert!(!token.is_cancelled());
+    }
+}
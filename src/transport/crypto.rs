@@ -1,19 +1,42 @@
 // Common functions for TLS related processes (TLS, QUIC)
 
-use rustls::{ClientConfig, RootCertStore};
+use log::{debug, warn};
+use rustls::{ClientConfig, ProtocolVersion, RootCertStore, SupportedProtocolVersion};
 use rustls_pki_types::CertificateDer;
 
+use super::{Roots, TlsVersion};
 use crate::error::{Error, Result};
 
-// build a new client config for TLS connexions
-pub fn tls_config(root_store: RootCertStore) -> ClientConfig {
-    ClientConfig::builder()
+// build a new client config for TLS connexions, limited to these TLS versions
+pub fn tls_config(root_store: RootCertStore, versions: &[&'static SupportedProtocolVersion]) -> ClientConfig {
+    ClientConfig::builder_with_protocol_versions(versions)
         .with_root_certificates(root_store)
         .with_no_client_auth()
 }
 
+// TLS versions between --tls-min and --tls-max
+pub fn tls_versions(min: TlsVersion, max: TlsVersion) -> Vec<&'static SupportedProtocolVersion> {
+    [
+        (TlsVersion::Tls12, &rustls::version::TLS12),
+        (TlsVersion::Tls13, &rustls::version::TLS13),
+    ]
+    .into_iter()
+    .filter(|(v, _)| *v >= min && *v <= max)
+    .map(|(_, p)| p)
+    .collect()
+}
+
+// name of the negotiated version, reported in stats
+pub fn version_name(version: ProtocolVersion) -> &'static str {
+    match version {
+        ProtocolVersion::TLSv1_2 => "TLSv1.2",
+        ProtocolVersion::TLSv1_3 => "TLSv1.3",
+        _ => "unknown",
+    }
+}
+
 // manage CAs
-pub fn root_store(cert: &Option<Vec<u8>>) -> Result<RootCertStore> {
+pub fn root_store(cert: &Option<Vec<u8>>, roots: Roots) -> Result<RootCertStore> {
     let mut root_store = rustls::RootCertStore::empty();
 
     // we've got a certificate here
@@ -23,8 +46,49 @@ pub fn root_store(cert: &Option<Vec<u8>>) -> Result<RootCertStore> {
     }
     // use root CAs
     else {
-        root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        if roots == Roots::System {
+            let native = rustls_native_certs::load_native_certs();
+            for e in native.errors.iter() {
+                debug!("error loading system root certificates: {}", e);
+            }
+            let (added, ignored) = root_store.add_parsable_certificates(native.certs);
+            debug!("{} system root certificates loaded, {} ignored", added, ignored);
+        }
+
+        // minimal systems (e.g. containers) might have no store at all
+        if root_store.is_empty() {
+            if roots == Roots::System {
+                warn!("no root certificate found in the system store, using the webpki ones");
+            }
+            root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        }
     }
 
     Ok(root_store)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions() {
+        assert_eq!(tls_versions(TlsVersion::Tls12, TlsVersion::Tls13).len(), 2);
+
+        let v = tls_versions(TlsVersion::Tls12, TlsVersion::Tls12);
+        assert_eq!(v.len(), 1);
+        assert_eq!(version_name(v[0].version), "TLSv1.2");
+
+        let v = tls_versions(TlsVersion::Tls13, TlsVersion::Tls13);
+        assert_eq!(version_name(v[0].version), "TLSv1.3");
+    }
+
+    #[test]
+    fn roots() {
+        let webpki = root_store(&None, Roots::WebPki).unwrap();
+        assert_eq!(webpki.len(), webpki_roots::TLS_SERVER_ROOTS.len());
+
+        // the system store or the webpki roots if there's none
+        assert!(!root_store(&None, Roots::System).unwrap().is_empty());
+    }
+}
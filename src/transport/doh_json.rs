@@ -0,0 +1,291 @@
+// JSON API of some DoH resolvers (e.g. https://dns.google/resolve or https://cloudflare-dns.com/dns-query):
+// the query is sent as a GET request with name and type parameters and the answer is a JSON document:
+//
+// {"Status":0,"TC":false,"RD":true,"RA":true,"AD":false,"CD":false,
+//  "Question":[{"name":"www.google.com.","type":1}],
+//  "Answer":[{"name":"www.google.com.","type":1,"TTL":300,"data":"142.250.179.100"}]}
+//
+// The JSON answer is converted back to a DNS message in wire format, so that it's
+// processed and displayed as any other response.
+use std::io::Cursor;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use log::debug;
+use serde_json::Value;
+use type2network::{FromNetworkOrder, ToNetworkOrder};
+
+use crate::dns::rfc::{domain::DomainName, question::Question};
+use crate::error::{Dns, Error, Result};
+use crate::show::Numeric;
+
+// header flags
+const QR: u16 = 0x8000;
+const TC: u16 = 0x0200;
+const RD: u16 = 0x0100;
+const RA: u16 = 0x0080;
+const AD: u16 = 0x0020;
+const CD: u16 = 0x0010;
+
+// length of the DNS header
+const HEADER_LENGTH: usize = 12;
+
+// split the query into its header and question parts
+fn question(query: &[u8]) -> Result<(Question, usize)> {
+    let mut cursor = Cursor::new(query);
+    cursor.set_position(HEADER_LENGTH as u64);
+
+    let mut question = Question::default();
+    question
+        .deserialize_from(&mut cursor)
+        .map_err(|_| Error::Dns(Dns::CantDeserialize))?;
+
+    Ok((question, cursor.position() as usize))
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// build the GET URL from the query in wire format
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn json_url(server: &str, query: &[u8]) -> Result<String> {
+    let (question, _) = question(query)?;
+
+    let sep = if server.contains('?') { '&' } else { '?' };
+    let mut url = format!(
+        "{}{}name={}&type={}",
+        server,
+        sep,
+        question.qname,
+        question.qtype.number()
+    );
+
+    // checking disabled flag is a query parameter too
+    if query.len() > 3 && u16::from_be_bytes([query[2], query[3]]) & CD != 0 {
+        url.push_str("&cd=1");
+    }
+
+    Ok(url)
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// convert the JSON answer to a DNS response in wire format. The ID and question
+// are taken from the query. The number of RRs which couldn't be converted is returned
+// too, for the user to be warned the response is incomplete
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn json_to_wire(query: &[u8], answer: &str) -> Result<(Vec<u8>, usize)> {
+    let json: Value = serde_json::from_str(answer).map_err(|_| Error::Dns(Dns::InvalidJsonAnswer))?;
+    let (_, question_end) = question(query)?;
+
+    // flags & rcode
+    let mut flags = QR;
+    for (key, bit) in [("TC", TC), ("RD", RD), ("RA", RA), ("AD", AD), ("CD", CD)] {
+        if json[key].as_bool().unwrap_or(false) {
+            flags |= bit;
+        }
+    }
+    let status = json["Status"].as_u64().ok_or(Error::Dns(Dns::InvalidJsonAnswer))?;
+    flags |= (status & 0x0F) as u16;
+
+    // RRs of each section
+    let mut skipped = 0;
+    let sections: Vec<Vec<Vec<u8>>> = ["Answer", "Authority", "Additional"]
+        .iter()
+        .map(|s| {
+            let rrs = json[s].as_array().map(Vec::as_slice).unwrap_or_default();
+            let converted: Vec<_> = rrs.iter().filter_map(rr_to_wire).collect();
+            skipped += rrs.len() - converted.len();
+            converted
+        })
+        .collect();
+
+    // header: ID is copied from the query
+    let mut buffer = Vec::with_capacity(512);
+    buffer.extend_from_slice(&query[..2]);
+    buffer.extend_from_slice(&flags.to_be_bytes());
+    buffer.extend_from_slice(&1u16.to_be_bytes());
+    for section in &sections {
+        buffer.extend_from_slice(&(section.len() as u16).to_be_bytes());
+    }
+
+    // question as sent
+    buffer.extend_from_slice(&query[HEADER_LENGTH..question_end]);
+
+    for rr in sections.iter().flatten() {
+        buffer.extend_from_slice(rr);
+    }
+
+    Ok((buffer, skipped))
+}
+
+// one RR of the JSON answer. RRs whose data can't be converted are skipped and counted
+fn rr_to_wire(rr: &Value) -> Option<Vec<u8>> {
+    let name = rr["name"].as_str()?;
+    let qtype = rr["type"].as_u64()? as u16;
+    let ttl = rr["TTL"].as_u64().unwrap_or(0) as u32;
+    let data = rr["data"].as_str()?;
+
+    let Some(rdata) = rdata_to_wire(qtype, data) else {
+        debug!("unable to convert data '{}' of type {} from JSON answer", data, qtype);
+        return None;
+    };
+
+    let mut buffer = domain(name)?;
+    buffer.extend_from_slice(&qtype.to_be_bytes());
+    buffer.extend_from_slice(&1u16.to_be_bytes());
+    buffer.extend_from_slice(&ttl.to_be_bytes());
+    buffer.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+    buffer.extend_from_slice(&rdata);
+
+    Some(buffer)
+}
+
+// RDATA from its presentation format, for the most common types
+fn rdata_to_wire(qtype: u16, data: &str) -> Option<Vec<u8>> {
+    let data = data.trim();
+
+    // RFC3597 generic format: \# length hex
+    if let Some(generic) = data.strip_prefix("\\#") {
+        let mut it = generic.split_whitespace();
+        let length = it.next()?.parse::<usize>().ok()?;
+        let bytes = base16::decode(&it.collect::<String>()).ok()?;
+        return (bytes.len() == length).then_some(bytes);
+    }
+
+    let fields: Vec<&str> = data.split_whitespace().collect();
+
+    match qtype {
+        // A
+        1 => Some(data.parse::<Ipv4Addr>().ok()?.octets().to_vec()),
+        // AAAA
+        28 => Some(data.parse::<Ipv6Addr>().ok()?.octets().to_vec()),
+        // NS, CNAME, PTR, DNAME
+        2 | 5 | 12 | 39 => domain(data),
+        // SOA
+        6 if fields.len() == 7 => {
+            let mut buffer = domain(fields[0])?;
+            buffer.extend(domain(fields[1])?);
+            for f in &fields[2..] {
+                buffer.extend_from_slice(&f.parse::<u32>().ok()?.to_be_bytes());
+            }
+            Some(buffer)
+        }
+        // MX
+        15 if fields.len() == 2 => {
+            let mut buffer = fields[0].parse::<u16>().ok()?.to_be_bytes().to_vec();
+            buffer.extend(domain(fields[1])?);
+            Some(buffer)
+        }
+        // TXT, SPF
+        16 | 99 => Some(character_strings(data)),
+        // SRV
+        33 if fields.len() == 4 => {
+            let mut buffer = Vec::new();
+            for f in &fields[..3] {
+                buffer.extend_from_slice(&f.parse::<u16>().ok()?.to_be_bytes());
+            }
+            buffer.extend(domain(fields[3])?);
+            Some(buffer)
+        }
+        // CAA
+        257 if fields.len() >= 3 => {
+            let mut buffer = vec![fields[0].parse::<u8>().ok()?, fields[1].len() as u8];
+            buffer.extend_from_slice(fields[1].as_bytes());
+            buffer.extend_from_slice(fields[2..].join(" ").trim_matches('"').as_bytes());
+            Some(buffer)
+        }
+        _ => None,
+    }
+}
+
+// domain name in wire format
+fn domain(name: &str) -> Option<Vec<u8>> {
+    let domain = DomainName::try_from(name).ok()?;
+
+    let mut buffer = Vec::new();
+    domain.serialize_to(&mut buffer).ok()?;
+    Some(buffer)
+}
+
+// TXT data is either a list of quoted strings, or a raw string (Google)
+fn character_strings(data: &str) -> Vec<u8> {
+    let mut strings: Vec<Vec<u8>> = Vec::new();
+
+    if data.starts_with('"') {
+        let mut current: Option<Vec<u8>> = None;
+        let mut chars = data.chars();
+
+        while let Some(c) = chars.next() {
+            match (c, current.as_mut()) {
+                ('"', None) => current = Some(Vec::new()),
+                ('"', Some(_)) => strings.push(current.take().unwrap()),
+                ('\\', Some(s)) => {
+                    if let Some(escaped) = chars.next() {
+                        s.extend_from_slice(escaped.to_string().as_bytes());
+                    }
+                }
+                (_, Some(s)) => s.extend_from_slice(c.to_string().as_bytes()),
+                (_, None) => (),
+            }
+        }
+    } else {
+        strings.extend(data.as_bytes().chunks(255).map(|c| c.to_vec()));
+    }
+
+    let mut buffer = Vec::new();
+    for s in strings {
+        for chunk in s.chunks(255) {
+            buffer.push(chunk.len() as u8);
+            buffer.extend_from_slice(chunk);
+        }
+    }
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::rfc::{qtype::QType, response::Response};
+
+    // query for A www.google.com with ID 0x1234
+    const QUERY: &[u8] = &[
+        0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, b'w', b'w', b'w', 0x06, b'g',
+        b'o', b'o', b'g', b'l', b'e', 0x03, b'c', b'o', b'm', 0x00, 0x00, 0x01, 0x00, 0x01,
+    ];
+
+    #[test]
+    fn url() {
+        let url = json_url("https://dns.google/resolve", QUERY).unwrap();
+        assert_eq!(url, "https://dns.google/resolve?name=www.google.com.&type=1");
+    }
+
+    #[test]
+    fn to_wire() {
+        let answer = r#"{"Status":0,"TC":false,"RD":true,"RA":true,"AD":false,"CD":false,
+            "Question":[{"name":"www.google.com.","type":1}],
+            "Answer":[{"name":"www.google.com.","type":5,"TTL":300,"data":"google.com."},
+                      {"name":"google.com.","type":1,"TTL":300,"data":"142.250.179.100"},
+                      {"name":"google.com.","type":16,"TTL":300,"data":"\"v=spf1 \" \"-all\""},
+                      {"name":"google.com.","type":48,"TTL":300,"data":"257 3 8 AwEAAc=="}]}"#;
+        let (wire, skipped) = json_to_wire(QUERY, answer).unwrap();
+        assert_eq!(skipped, 1);
+
+        let mut resp = Response::default();
+        resp.deserialize_from(&mut Cursor::new(wire.as_slice())).unwrap();
+        assert_eq!(resp.id(), 0x1234);
+        assert_eq!(resp.header.flags.names(), vec!["qr", "rd", "ra"]);
+
+        // DNSKEY is skipped
+        let answer = resp.answer.as_ref().unwrap();
+        assert_eq!(answer.len(), 3);
+        assert_eq!(answer[0].r#type, QType::CNAME);
+        assert_eq!(answer[1].rdata_string(), "142.250.179.100");
+        assert_eq!(answer[2].r#type, QType::TXT);
+    }
+
+    #[test]
+    fn txt() {
+        assert_eq!(
+            character_strings("\"a\\\"b\" \"c\""),
+            vec![3, b'a', b'"', b'b', 1, b'c']
+        );
+        assert_eq!(character_strings("v=spf1"), vec![6, b'v', b'=', b's', b'p', b'f', b'1']);
+    }
+}
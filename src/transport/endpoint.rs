@@ -5,7 +5,7 @@
 // - a couple of ip:port
 use std::{
     fmt,
-    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs},
     path::PathBuf,
     str::FromStr,
 };
@@ -16,6 +16,15 @@ use resolving::ResolverList;
 use super::network::IPVersion;
 use crate::error::{Error, Result};
 
+// well-known public resolvers used when no host resolver is found (e.g.: empty
+// /etc/resolv.conf in a container), unless --fallback-resolvers or --no-fallback-resolvers
+pub const FALLBACK_RESOLVERS: [IpAddr; 4] = [
+    IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+    IpAddr::V4(Ipv4Addr::new(9, 9, 9, 9)),
+    IpAddr::V6(Ipv6Addr::new(0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1111)),
+    IpAddr::V6(Ipv6Addr::new(0x2620, 0xfe, 0, 0, 0, 0, 0, 0xfe)),
+];
+
 #[derive(Debug, Default, Clone)]
 pub struct EndPoint {
     // value of the endpoint (e.g.: 1.1.1.1 or one.one.one.one)
@@ -83,8 +92,13 @@ impl EndPoint {
         Ok(t)
     }
 
-    // only keep IPV4 or IPV6 or both addresses' version
+    // only keep IPV4 or IPV6 or both addresses' version. IPv4-mapped IPv6 addresses
+    // (e.g.: ::ffff:192.0.2.1) are IPV4 addresses
     pub fn retain(&mut self, ver: &IPVersion) {
+        for addr in self.addrs.iter_mut() {
+            addr.set_ip(addr.ip().to_canonical());
+        }
+
         match ver {
             IPVersion::Any => (),
             IPVersion::V4 => self.addrs.retain(|ip| ip.is_ipv4()),
@@ -92,9 +106,19 @@ impl EndPoint {
         }
     }
 
-    // true if all addresses in addrs field are ipv6
+    // when both versions are there, put addresses of the preferred version first.
+    // Transports try addresses in order
+    pub fn prefer(&mut self, ver: &IPVersion) {
+        match ver {
+            IPVersion::Any => (),
+            IPVersion::V4 => self.addrs.sort_by_key(|ip| !ip.is_ipv4()),
+            IPVersion::V6 => self.addrs.sort_by_key(|ip| !ip.is_ipv6()),
+        }
+    }
+
+    // true if all addresses in addrs field are ipv6, not counting IPv4-mapped ones
     pub fn is_ipv6(&self) -> bool {
-        !self.addrs.is_empty() && self.addrs.iter().all(|a| a.is_ipv6())
+        !self.addrs.is_empty() && self.addrs.iter().all(|a| a.ip().to_canonical().is_ipv6())
     }
 
     // test if a string ip is IPV6. Used to disambiguate from cases where port is added
@@ -171,6 +195,19 @@ impl TryFrom<u16> for EndPoint {
     }
 }
 
+// build an endpoint from a list of resolver addresses
+// e.g.: EndPoint::from((FALLBACK_RESOLVERS.as_slice(), 53))
+impl From<(&[IpAddr], u16)> for EndPoint {
+    fn from(value: (&[IpAddr], u16)) -> Self {
+        Self {
+            server_name: String::new(),
+            port: value.1,
+            addrs: value.0.iter().map(|ip| SocketAddr::from((*ip, value.1))).collect(),
+            sni: None,
+        }
+    }
+}
+
 impl TryFrom<(&IpAddr, u16)> for EndPoint {
     type Error = crate::error::Error;
 
@@ -194,7 +231,8 @@ mod tests {
         str::FromStr,
     };
 
-    use super::EndPoint;
+    use super::{EndPoint, FALLBACK_RESOLVERS};
+    use crate::transport::network::IPVersion;
 
     #[test]
     fn new() {
@@ -331,6 +369,38 @@ mod tests {
     //     }
     // }
 
+    #[test]
+    fn versions() {
+        let addrs: Vec<SocketAddr> = ["[2001:db8::1]:53", "192.0.2.1:53", "[::ffff:192.0.2.2]:53"]
+            .iter()
+            .map(|a| SocketAddr::from_str(a).unwrap())
+            .collect();
+        let mut ep = EndPoint {
+            addrs: addrs.clone(),
+            ..Default::default()
+        };
+
+        // mapped address is not IPV6
+        ep.addrs = addrs[2..].to_vec();
+        assert!(!ep.is_ipv6());
+        ep.retain(&IPVersion::V4);
+        assert_eq!(ep.addrs, vec![SocketAddr::from_str("192.0.2.2:53").unwrap()]);
+
+        ep.addrs = addrs.clone();
+        ep.retain(&IPVersion::V6);
+        assert_eq!(ep.addrs, vec![SocketAddr::from_str("[2001:db8::1]:53").unwrap()]);
+
+        // order is kept inside each version
+        ep.addrs = addrs.clone();
+        ep.retain(&IPVersion::Any);
+        ep.prefer(&IPVersion::V4);
+        let order: Vec<_> = ep.addrs.iter().map(|a| a.to_string()).collect();
+        assert_eq!(order, vec!["192.0.2.1:53", "192.0.2.2:53", "[2001:db8::1]:53"]);
+
+        ep.prefer(&IPVersion::V6);
+        assert_eq!(ep.addrs[0], addrs[0]);
+    }
+
     #[test]
     fn from_ip_only_port() {
         let ip = IpAddr::from_str("1.1.1.1").unwrap();
@@ -339,4 +409,13 @@ mod tests {
         let ep = ep.unwrap();
         assert_eq!(ep.addrs, vec![SocketAddr::from_str("1.1.1.1:53").unwrap()]);
     }
+
+    #[test]
+    fn fallback() {
+        let ep = EndPoint::from((FALLBACK_RESOLVERS.as_slice(), 53));
+        assert_eq!(ep.addrs.len(), 4);
+        assert_eq!(ep.addrs[0], SocketAddr::from_str("1.1.1.1:53").unwrap());
+        assert_eq!(ep.addrs[2], SocketAddr::from_str("[2606:4700:4700::1111]:53").unwrap());
+        assert!(!ep.is_ipv6());
+    }
 }
@@ -0,0 +1,122 @@
+//! Reader of length-prefixed DNS messages on a TCP or TLS stream (RFC1035 4.2.2).
+//! A server can send several messages on the same connection (e.g. AXFR or pipelined
+//! answers): each read yields the next one.
+use std::fmt::Debug;
+use std::io::{ErrorKind, Read};
+
+use log::trace;
+
+use crate::error::{Error, Network, Result};
+
+pub(crate) struct FramedReader<'a, R> {
+    stream: &'a mut R,
+}
+
+impl<'a, R> FramedReader<'a, R>
+where
+    R: Read + Debug,
+{
+    pub fn new(stream: &'a mut R) -> Self {
+        Self { stream }
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // read the next message into buffer and return its length, or None if the server
+    // closed the connection between 2 messages
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn read_frame(&mut self, buffer: &mut [u8]) -> Result<Option<usize>> {
+        // the first 2 bytes are the length of the message coming
+        let mut buf = [0u8; 2];
+
+        loop {
+            match self.stream.read(&mut buf[..1]) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Error::Network(e, Network::Read)),
+            }
+        }
+        self.stream
+            .read_exact(&mut buf[1..])
+            .map_err(|e| Error::Network(e, Network::Read))?;
+        let length = u16::from_be_bytes(buf) as usize;
+
+        trace!("about to read {} bytes in the TCP stream {:?}", length, self.stream);
+
+        if length > buffer.len() {
+            let e = std::io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "message of {} bytes doesn't fit in a {} bytes buffer",
+                    length,
+                    buffer.len()
+                ),
+            );
+            return Err(Error::Network(e, Network::Read));
+        }
+
+        // now read exact length
+        self.stream
+            .read_exact(&mut buffer[..length])
+            .map_err(|e| Error::Network(e, Network::Read))?;
+
+        trace!("inside read_frame, buffer={:X?}", &buffer[..length]);
+
+        Ok(Some(length))
+    }
+
+    // read the next message which must be there
+    pub fn read_expected(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        self.read_frame(buffer)?
+            .ok_or_else(|| Error::Network(std::io::Error::from(ErrorKind::UnexpectedEof), Network::Read))
+    }
+}
+
+// all messages until the connection is closed
+impl<R> Iterator for FramedReader<'_, R>
+where
+    R: Read + Debug,
+{
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buffer = vec![0u8; u16::MAX as usize];
+
+        match self.read_frame(&mut buffer) {
+            Ok(Some(length)) => {
+                buffer.truncate(length);
+                Some(Ok(buffer))
+            }
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn frames() {
+        let mut stream = Cursor::new(vec![0, 2, 0xAA, 0xBB, 0, 0, 0, 3, 1, 2, 3]);
+        let messages: Vec<_> = FramedReader::new(&mut stream).map(Result::unwrap).collect();
+        assert_eq!(messages, vec![vec![0xAA, 0xBB], vec![], vec![1, 2, 3]]);
+
+        // connection closed in the middle of a message
+        let mut stream = Cursor::new(vec![0, 4, 1, 2]);
+        let mut buffer = [0u8; 16];
+        assert!(FramedReader::new(&mut stream).read_frame(&mut buffer).is_err());
+
+        // buffer too small
+        let mut stream = Cursor::new(vec![0, 4, 1, 2, 3, 4]);
+        let mut buffer = [0u8; 2];
+        assert!(FramedReader::new(&mut stream).read_frame(&mut buffer).is_err());
+
+        // no more message
+        let mut stream = Cursor::new(vec![]);
+        let mut buffer = [0u8; 2];
+        assert!(FramedReader::new(&mut stream).read_expected(&mut buffer).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+// DNS over HTTP/3 (RFC8484 over RFC9114): the DNS message is sent on a request stream of a
+// QUIC connection negotiated with the h3 ALPN. As reqwest doesn't provide HTTP/3 in its
+// blocking client, the h3 crate is driven by the tokio runtime used for DoQ
+use std::future::poll_fn;
+use std::io;
+use std::net::ToSocketAddrs;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::{Buf, Bytes, BytesMut};
+use chrono::Utc;
+use h3::client::SendRequest;
+use http::{
+    header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, RETRY_AFTER, USER_AGENT},
+    Request, Response, StatusCode, Uri,
+};
+use log::{debug, info};
+use quinn::{crypto::rustls::QuicClientConfig, Endpoint};
+use tokio::runtime::Runtime;
+
+use super::cancel::CancellationToken;
+use super::crypto::{root_store, tls_config};
+use super::doh_json::{json_to_wire, json_url};
+use super::https::{content_types, copy_body, http_info, retry_after, wait, MAX_RETRIES};
+use super::network::{Messenger, Protocol};
+use super::{HttpInfo, NetworkInfo, TransportOptions, TransportProtocol};
+use crate::error::{self, Dns, Error, Network, QuicError, Result};
+
+// ALPN of HTTP/3 (RFC9114 section 3.1)
+const ALPN_H3: &[u8] = b"h3";
+
+const DNS_MESSAGE: &str = "application/dns-message";
+
+pub struct H3Conn {
+    // URL endpoint
+    url: Uri,
+
+    // kept for the connection to live as long as the transport
+    _endpoint: Endpoint,
+    send_request: SendRequest<h3_quinn::OpenStreams, Bytes>,
+    rt: &'static Runtime,
+
+    // use the JSON API instead of wire format
+    json: bool,
+    timeout: Duration,
+
+    // longest Retry-After delay waited for before resending (--retry-after)
+    retry_after: Option<Duration>,
+    cancel: CancellationToken,
+
+    // data received from the response
+    bytes_recv: Bytes,
+}
+
+pub type H3Protocol = TransportProtocol<H3Conn>;
+
+// host and port to connect to, IPv6 brackets removed
+fn authority(url: &Uri) -> Option<(String, u16)> {
+    let host = url.host()?.trim_start_matches('[').trim_end_matches(']');
+    Some((host.to_string(), url.port_u16().unwrap_or(443)))
+}
+
+fn h3_error(e: h3::Error) -> Error {
+    Error::H3(e.to_string())
+}
+
+impl H3Protocol {
+    pub fn new(trp_options: &TransportOptions, rt: &'static Runtime) -> Result<Self> {
+        debug_assert!(!trp_options.endpoint.server_name.is_empty());
+        let url: Uri = trp_options
+            .endpoint
+            .server_name
+            .parse()
+            .map_err(|e: http::uri::InvalidUri| Error::H3(e.to_string()))?;
+        let (host, port) = authority(&url).ok_or(Error::Dns(Dns::CantCreateSocketAddress))?;
+
+        // reqwest resolves the URL host itself, it's done here with the same IP version as
+        // the local address
+        let local = trp_options.local_addr();
+        let addr = (host.as_str(), port)
+            .to_socket_addrs()
+            .map_err(|e| Error::ToSocketAddrs(e, host.clone()))?
+            .find(|addr| addr.is_ipv4() == local.is_ipv4())
+            .ok_or(Error::Dns(Dns::CantCreateSocketAddress))?;
+        debug!("url={} addr={} local ip={}", url, addr, local);
+
+        // QUIC only uses TLS 1.3 (RFC9001)
+        let root_store = root_store(&trp_options.cert, trp_options.roots)?;
+        let mut client_crypto = tls_config(root_store, &[&rustls::version::TLS13]);
+        client_crypto.alpn_protocols = vec![ALPN_H3.to_vec()];
+
+        let qcc =
+            QuicClientConfig::try_from(client_crypto).map_err(|_| Error::Quic(QuicError::NoInitialCipherSuite))?;
+        let mut client_config = quinn::ClientConfig::new(Arc::new(qcc));
+
+        // PINGs keep the pooled connection open between the queries of watch modes
+        if let Some(period) = trp_options.keepalive {
+            let mut transport_config = quinn::TransportConfig::default();
+            transport_config.keep_alive_interval(Some(period));
+            client_config.transport_config(Arc::new(transport_config));
+        }
+
+        let (endpoint, send_request) = rt.block_on(async {
+            let mut endpoint = Endpoint::client(local).map_err(|e| Error::Network(e, Network::Bind))?;
+            endpoint.set_default_client_config(client_config);
+
+            let conn = endpoint
+                .connect(addr, &host)
+                .map_err(|e| Error::Quic(QuicError::Connect(e, host.clone())))?
+                .await
+                .map_err(|e| Error::Quic(e.into()))?;
+            debug!("conn: {:?}", conn);
+
+            let (mut driver, send_request) = h3::client::new(h3_quinn::Connection::new(conn))
+                .await
+                .map_err(h3_error)?;
+
+            // the HTTP/3 connection is driven in the background until it's closed
+            tokio::spawn(async move {
+                let res = poll_fn(|cx| driver.poll_close(cx)).await;
+                debug!("HTTP/3 connection closed: {:?}", res);
+            });
+
+            Ok::<_, Error>((endpoint, send_request))
+        })?;
+
+        Ok(Self {
+            handle: H3Conn {
+                url,
+                _endpoint: endpoint,
+                send_request,
+                rt,
+                json: trp_options.doh_json,
+                timeout: trp_options.timeout,
+                retry_after: trp_options.retry_after,
+                cancel: trp_options.cancel.clone(),
+                bytes_recv: Bytes::default(),
+            },
+            netinfo: NetworkInfo {
+                peer: Some(addr),
+                tls_version: Some("TLSv1.3"),
+                ..Default::default()
+            },
+        })
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // send one request on a new stream and read the whole response
+    //───────────────────────────────────────────────────────────────────────────────────
+    fn request(&mut self, buffer: &[u8]) -> Result<(Response<()>, Bytes)> {
+        // JSON API: GET request, otherwise the message is POSTed
+        let (request, body) = if self.handle.json {
+            let url = json_url(&self.handle.url.to_string(), buffer)?;
+            debug!("url: {}", url);
+            (Request::get(url).header(ACCEPT, "application/dns-json"), None)
+        } else {
+            let request = Request::post(self.handle.url.clone())
+                .header(ACCEPT, DNS_MESSAGE)
+                .header(CONTENT_TYPE, DNS_MESSAGE)
+                .header(CONTENT_LENGTH, buffer.len());
+            (request, Some(Bytes::copy_from_slice(buffer)))
+        };
+        let request = request
+            .header(USER_AGENT, "dqy")
+            .body(())
+            .map_err(|e| Error::H3(e.to_string()))?;
+
+        let send_request = &mut self.handle.send_request;
+        let exchange = async {
+            let mut stream = send_request.send_request(request).await.map_err(h3_error)?;
+            if let Some(body) = body {
+                stream.send_data(body).await.map_err(h3_error)?;
+            }
+            stream.finish().await.map_err(h3_error)?;
+
+            let resp = stream.recv_response().await.map_err(h3_error)?;
+            let mut data = BytesMut::new();
+            while let Some(mut chunk) = stream.recv_data().await.map_err(h3_error)? {
+                data.extend_from_slice(&chunk.copy_to_bytes(chunk.remaining()));
+            }
+            Ok::<_, Error>((resp, data.freeze()))
+        };
+
+        let timeout = self.handle.timeout;
+        self.handle.rt.block_on(async {
+            tokio::time::timeout(timeout, exchange)
+                .await
+                .map_err(|_| Error::Network(io::Error::from(io::ErrorKind::TimedOut), Network::Receive))?
+        })
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // same retries as the reqwest transport when the server is busy (429 or 503)
+    //───────────────────────────────────────────────────────────────────────────────────
+    fn execute(&mut self, buffer: &[u8]) -> Result<Bytes> {
+        let mut retries = 0;
+
+        loop {
+            let (resp, body) = self.request(buffer)?;
+            let status = resp.status();
+
+            if status != StatusCode::TOO_MANY_REQUESTS && status != StatusCode::SERVICE_UNAVAILABLE {
+                self.netinfo.http = Some(HttpInfo {
+                    retries,
+                    ..http_info(status, resp.version(), resp.headers(), content_types(self.handle.json))
+                });
+                return Ok(body);
+            }
+
+            let delay = resp
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| retry_after(v, Utc::now()));
+
+            match (delay, self.handle.retry_after) {
+                (Some(delay), Some(max)) if delay <= max && retries < MAX_RETRIES => {
+                    retries += 1;
+                    info!(
+                        "HTTP status {}, resending the query in {}s ({}/{})",
+                        status.as_u16(),
+                        delay.as_secs(),
+                        retries,
+                        MAX_RETRIES
+                    );
+                    wait(delay, &self.handle.cancel)?;
+                }
+                _ => return Err(Error::Busy(status.as_u16(), delay)),
+            }
+        }
+    }
+}
+
+impl Messenger for H3Protocol {
+    async fn asend(&mut self, _: &[u8]) -> error::Result<usize> {
+        Ok(0)
+    }
+    async fn arecv(&mut self, _: &mut [u8]) -> error::Result<usize> {
+        Ok(0)
+    }
+
+    async fn aconnect(&mut self) -> error::Result<()> {
+        Ok(())
+    }
+
+    fn send(&mut self, buffer: &[u8]) -> Result<usize> {
+        // each query is sent on a new stream of the same connection
+        self.netinfo.reused = self.netinfo.sent > 0;
+        self.netinfo.sent = buffer.len();
+
+        let body = self.execute(buffer)?;
+        self.handle.bytes_recv = if self.handle.json {
+            let answer = String::from_utf8_lossy(&body);
+            debug!("JSON answer: {}", answer);
+            let (wire, skipped) = json_to_wire(buffer, &answer)?;
+            if let Some(http) = self.netinfo.http.as_mut() {
+                http.skipped_rrs = skipped;
+            }
+            Bytes::from(wire)
+        } else {
+            body
+        };
+
+        Ok(buffer.len())
+    }
+
+    fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        self.netinfo.received = copy_body(&self.handle.bytes_recv, buffer)?;
+        Ok(self.netinfo.received)
+    }
+
+    // don't add the message length
+    fn uses_leading_length(&self) -> bool {
+        false
+    }
+
+    fn mode(&self) -> Protocol {
+        Protocol::DoH
+    }
+
+    fn network_info(&self) -> &NetworkInfo {
+        self.netinfo()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_authority() {
+        let url: Uri = "https://dns.google/dns-query".parse().unwrap();
+        assert_eq!(authority(&url), Some((String::from("dns.google"), 443)));
+
+        let url: Uri = "https://[2606:4700:4700::1111]:8443/dns-query".parse().unwrap();
+        assert_eq!(authority(&url), Some((String::from("2606:4700:4700::1111"), 8443)));
+
+        let url: Uri = "/dns-query".parse().unwrap();
+        assert_eq!(authority(&url), None);
+    }
+}
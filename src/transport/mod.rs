@@ -1,24 +1,40 @@
-use std::fmt::Debug;
-use std::io::{ErrorKind, Read};
-use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::fmt;
+use std::io::ErrorKind;
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
 use std::time::Duration;
 
+use cancel::CancellationToken;
 use endpoint::EndPoint;
+#[cfg(feature = "doh")]
 use http::version::Version;
-use log::trace;
 use serde::Serialize;
+use socket2::{Domain, Socket, Type};
 
 use crate::error::{Error, Network, Result};
 use network::{IPVersion, Protocol};
 
+pub mod cancel;
+#[cfg(feature = "dot")]
 pub mod crypto;
+#[cfg(feature = "doh")]
+pub mod doh_json;
 pub mod endpoint;
+pub mod framed;
+#[cfg(feature = "doh3")]
+pub mod http3;
+#[cfg(feature = "doh")]
 pub mod https;
 pub mod network;
+pub mod observer;
+#[cfg(feature = "odoh")]
+pub mod odoh;
+pub mod pool;
+#[cfg(feature = "doq")]
 pub mod quic;
 pub mod root_servers;
 // pub mod target;
 pub mod tcp;
+#[cfg(feature = "dot")]
 pub mod tls;
 pub mod udp;
 
@@ -30,11 +46,145 @@ pub struct NetworkInfo {
     pub sent: usize,
     pub received: usize,
     pub peer: Option<SocketAddr>,
+
+    // the exchange was over UDP, the only transport limited by the EDNS bufsize
+    #[serde(skip)]
+    pub udp: bool,
+
+    // the UDP response was truncated and the query resent over TCP
+    pub tcp_fallback: bool,
+
+    // EDNS was rejected and the query resent without OPT
+    pub edns_fallback: bool,
+
+    // TLS version negotiated with the resolver (DoT, DoQ)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_version: Option<&'static str>,
+
+    // DoQ session still answering after the local address changed (--doq-migrate)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub migration: Option<bool>,
+
+    // UDP queries resent after a timeout (--max-udp-retries)
+    pub retransmissions: u32,
+
+    // idle pooled connection found closed and replaced by a new one
+    pub reconnects: u32,
+
+    // the query was sent on a connection already used by previous queries
+    pub reused: bool,
+
+    // QUIC packets lost during the exchange, from the quinn path statistics (DoQ)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lost_packets: Option<u64>,
+
+    // HTTP metadata of the DoH response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http: Option<HttpInfo>,
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// what the HTTP layer of DoH costs and tells: status, version negotiated, size of the
+// headers and whether a cache between the client and the resolver answered
+//───────────────────────────────────────────────────────────────────────────────────
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize)]
+pub struct HttpInfo {
+    pub status: u16,
+    pub version: &'static str,
+
+    // response headers size, as if sent uncompressed (HTTP/1.1 framing)
+    pub header_bytes: usize,
+
+    // Age of the response if served by an intermediary cache, and max-age of Cache-Control
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<u32>,
+
+    // content-type is the expected one: application/dns-message (RFC8484) or
+    // application/dns-json for the JSON API
+    pub content_type_valid: bool,
+
+    // requests resent after a 429 or 503 status, as advised by Retry-After (--retry-after)
+    pub retries: u32,
+
+    // RRs of a JSON answer whose data couldn't be converted to wire format, and are missing
+    pub skipped_rrs: usize,
+}
+
+impl HttpInfo {
+    pub fn cached(&self) -> bool {
+        self.age.is_some_and(|age| age > 0)
+    }
+}
+
+impl fmt::Display for HttpInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "HTTP status:{}, version:{}, header bytes:{}",
+            self.status, self.version, self.header_bytes
+        )?;
+        if let Some(max_age) = self.max_age {
+            write!(f, ", max-age:{}", max_age)?;
+        }
+        if let Some(age) = self.age.filter(|_| self.cached()) {
+            write!(f, ", served from cache (age:{})", age)?;
+        }
+        if !self.content_type_valid {
+            write!(f, ", unexpected content-type")?;
+        }
+        if self.retries > 0 {
+            write!(f, ", retried:{}", self.retries)?;
+        }
+        if self.skipped_rrs > 0 {
+            write!(f, ", JSON RRs skipped:{}", self.skipped_rrs)?;
+        }
+        Ok(())
+    }
+}
+
+impl NetworkInfo {
+    // --stable: counters depending on the network conditions are reset
+    pub fn stabilize(&mut self) {
+        self.retransmissions = 0;
+        self.reconnects = 0;
+        self.reused = false;
+        self.lost_packets = self.lost_packets.map(|_| 0);
+
+        // cache ages and retries depend on when the query is sent
+        if let Some(http) = self.http.as_mut() {
+            http.age = None;
+            http.max_age = None;
+            http.retries = 0;
+        }
+    }
+}
+
+// TLS protocol versions, bounds given with --tls-min and --tls-max
+#[cfg(any(feature = "dot", feature = "doh"))]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum TlsVersion {
+    Tls12,
+    Tls13,
+}
+
+// trust store used to authenticate resolvers, chosen with --system-roots or --webpki-roots
+#[cfg(any(feature = "dot", feature = "doh"))]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Roots {
+    // the OS store, which has the roots added by the administrator (e.g. of a TLS inspecting proxy)
+    #[default]
+    System,
+
+    // the Mozilla roots built in dqy
+    WebPki,
 }
 
 // default UDP buffer size
 const BUFFER_SIZE: u16 = 1232;
 const DEFAULT_TIMEOUT: u64 = 3000;
+const DEFAULT_BACKOFF: u64 = 250;
 
 pub struct TransportProtocol<T> {
     // handle is either a socket or a stream
@@ -61,12 +211,25 @@ pub struct TransportOptions {
     // V4 or V6 or Any
     pub ip_version: IPVersion,
 
+    // addresses of this version are tried first (Any for no preference)
+    pub prefer: IPVersion,
+
     // timeout for network operations
     pub timeout: Duration,
 
+    // number of UDP retransmissions on timeout, and delay before the first one
+    pub udp_retries: u8,
+    pub udp_backoff: Duration,
+
     // resolver
     pub endpoint: EndPoint,
 
+    // all resolvers when several @server are given, the first one being endpoint
+    pub endpoints: Vec<EndPoint>,
+
+    // query all of them instead of failing over to the next one
+    pub all_servers: bool,
+
     // if true, elasped time and some stats are printed out
     pub stats: bool,
 
@@ -85,8 +248,27 @@ pub struct TransportOptions {
     // pub doh: bool,
 
     // http version
+    #[cfg(feature = "doh")]
     pub https_version: Option<Version>,
 
+    // use the JSON API of the DoH resolver instead of wire format
+    #[cfg(feature = "doh")]
+    pub doh_json: bool,
+
+    // DoH queries answered 429 or 503 are resent if Retry-After is at most this delay
+    #[cfg(feature = "doh")]
+    pub retry_after: Option<Duration>,
+
+    // ignore the proxy given by the environment for DoH
+    #[cfg(feature = "doh")]
+    pub no_proxy: bool,
+
+    // Oblivious DoH: the endpoint is the target, and queries are relayed by this proxy if any
+    #[cfg(feature = "odoh")]
+    pub odoh: bool,
+    #[cfg(feature = "odoh")]
+    pub odoh_proxy: Option<String>,
+
     // true if DNS over Quic
     //pub doq: bool,
 
@@ -98,13 +280,55 @@ pub struct TransportOptions {
     pub bytes_received: usize,
 
     // set DoT ALPN
+    #[cfg(feature = "dot")]
     pub alpn: bool,
 
     // optional certificate file as PEM
+    #[cfg(any(feature = "dot", feature = "doh"))]
     pub cert: Option<Vec<u8>>,
 
+    // TLS versions allowed for DoT and DoH
+    #[cfg(any(feature = "dot", feature = "doh"))]
+    pub tls_min: TlsVersion,
+    #[cfg(any(feature = "dot", feature = "doh"))]
+    pub tls_max: TlsVersion,
+
+    // trust store used when no certificate is given with --cert
+    #[cfg(any(feature = "dot", feature = "doh"))]
+    pub roots: Roots,
+
     // encrypted client hello
     pub ech: bool,
+
+    // used to abort long operations (Ctrl-C or deadline)
+    pub cancel: CancellationToken,
+
+    // source addresses given with --source-ip, and the one used to bind UDP sockets
+    pub source_ips: Vec<IpAddr>,
+    pub source: Option<IpAddr>,
+
+    // no host resolver was found: the fallback resolvers are used
+    pub fallback_resolvers: bool,
+
+    // send the query to all endpoint addresses at once, keep the fastest answer
+    pub race: bool,
+
+    // resend the DoQ queries after rebinding the local socket
+    #[cfg(feature = "doq")]
+    pub doq_migrate: bool,
+
+    // don't connect the UDP socket: answers from any address are accepted, but ICMP
+    // errors are not reported
+    pub udp_unconnected: bool,
+
+    // idle TCP, DoT, DoH and DoQ connections reused between queries
+    pub pool: Option<pool::ConnectionPool>,
+
+    // pooled connections of watch modes kept open by pings sent at this period
+    pub keepalive: Option<Duration>,
+
+    // queries of all types sent at once instead of one after the other
+    pub parallel: bool,
 }
 
 impl Default for TransportOptions {
@@ -112,8 +336,13 @@ impl Default for TransportOptions {
         Self {
             transport_mode: Protocol::default(),
             ip_version: IPVersion::default(),
+            prefer: IPVersion::default(),
             timeout: Duration::from_millis(DEFAULT_TIMEOUT),
+            udp_retries: 0,
+            udp_backoff: Duration::from_millis(DEFAULT_BACKOFF),
             endpoint: EndPoint::default(),
+            endpoints: Vec::new(),
+            all_servers: false,
             stats: false,
             bufsize: BUFFER_SIZE,
             // tls: false,
@@ -121,52 +350,82 @@ impl Default for TransportOptions {
             // tcp: false,
             // https: false,
             // doh: false,
+            #[cfg(feature = "doh")]
             https_version: None,
+            #[cfg(feature = "doh")]
+            doh_json: false,
+            #[cfg(feature = "doh")]
+            retry_after: None,
+            #[cfg(feature = "doh")]
+            no_proxy: false,
+            #[cfg(feature = "odoh")]
+            odoh: false,
+            #[cfg(feature = "odoh")]
+            odoh_proxy: None,
             // doq: false,
             port: 53,
             bytes_sent: 0,
             bytes_received: 0,
+            #[cfg(feature = "dot")]
             alpn: false,
+            #[cfg(any(feature = "dot", feature = "doh"))]
             cert: None,
+            #[cfg(any(feature = "dot", feature = "doh"))]
+            tls_min: TlsVersion::Tls12,
+            #[cfg(any(feature = "dot", feature = "doh"))]
+            tls_max: TlsVersion::Tls13,
+            #[cfg(any(feature = "dot", feature = "doh"))]
+            roots: Roots::default(),
             ech: false,
+            cancel: CancellationToken::default(),
+            source_ips: Vec::new(),
+            source: None,
+            fallback_resolvers: false,
+            race: false,
+            #[cfg(feature = "doq")]
+            doq_migrate: false,
+            udp_unconnected: false,
+            pool: None,
+            keepalive: None,
+            parallel: false,
         }
     }
 }
 
-// Helper function to read TCP data
-pub(crate) fn tcp_read<R>(stream: &mut R, buffer: &mut [u8]) -> Result<usize>
-where
-    R: Read + Debug,
-{
-    // in case of TCP, the first 2 bytes is lthe length of data coming
-    // so read 2 first bytes
-
-    let mut buf = [0u8; 2];
-    stream
-        .read_exact(&mut buf)
-        .map_err(|e| Error::Network(e, Network::Read))?;
-    let length = u16::from_be_bytes(buf) as usize;
-
-    trace!("about to read {} bytes in the TCP stream {:?}", length, stream);
-
-    // now read exact length
-    stream
-        .read_exact(&mut buffer[..length])
-        .map_err(|e| Error::Network(e, Network::Read))?;
+impl TransportOptions {
+    // address to bind UDP and QUIC sockets to: the source address if any (--source-ip)
+    pub fn local_addr(&self) -> SocketAddr {
+        match self.source {
+            Some(ip) => SocketAddr::new(ip, 0),
+            None => self.ip_version.unspecified_ip(),
+        }
+    }
+}
 
-    trace!("inside tcp_read, buffer={:X?}", buffer);
+// connect from the source address if any, the socket must then be bound before
+fn tcp_connect(addr: &SocketAddr, source: Option<IpAddr>, timeout: Duration) -> std::io::Result<TcpStream> {
+    let Some(ip) = source else {
+        return TcpStream::connect_timeout(addr, timeout);
+    };
 
-    Ok(length)
+    let socket = Socket::new(Domain::for_address(*addr), Type::STREAM, Some(socket2::Protocol::TCP))?;
+    socket.bind(&SocketAddr::new(ip, 0).into())?;
+    socket.connect_timeout(&(*addr).into(), timeout)?;
+    Ok(socket.into())
 }
 
 // Connect to the first address for which connection succeeds
-pub(crate) fn get_tcpstream_ok<A: ToSocketAddrs>(addrs: A, timeout: Duration) -> Result<(TcpStream, SocketAddr)> {
+pub(crate) fn get_tcpstream_ok<A: ToSocketAddrs>(
+    addrs: A,
+    source: Option<IpAddr>,
+    timeout: Duration,
+) -> Result<(TcpStream, SocketAddr)> {
     // find the first address for which the connexion succeeds
     for addr in addrs
         .to_socket_addrs()
         .map_err(|e| Error::Network(e, Network::SocketAddr))?
     {
-        if let Ok(s) = TcpStream::connect_timeout(&addr, timeout) {
+        if let Ok(s) = tcp_connect(&addr, source, timeout) {
             return Ok((s, addr));
         }
     }
@@ -57,8 +57,11 @@ pub enum Protocol {
     #[default]
     Udp,
     Tcp,
+    #[cfg(feature = "doh")]
     DoH,
+    #[cfg(feature = "dot")]
     DoT,
+    #[cfg(feature = "doq")]
     DoQ,
 }
 
@@ -68,15 +71,33 @@ impl Protocol {
         match self {
             Protocol::Udp => 53,
             Protocol::Tcp => 53,
+            #[cfg(feature = "dot")]
             Protocol::DoT => 853,
+            #[cfg(feature = "doh")]
             Protocol::DoH => 443,
+            #[cfg(feature = "doq")]
             Protocol::DoQ => 853,
         }
     }
 
     // true if message needs to be sent with prepended length
     pub fn uses_leading_length(&self) -> bool {
-        *self == Protocol::Tcp || *self == Protocol::DoT || *self == Protocol::DoQ
+        match self {
+            Protocol::Udp => false,
+            #[cfg(feature = "doh")]
+            Protocol::DoH => false,
+            _ => true,
+        }
+    }
+
+    // DNS messages are exchanged on a TCP connection: TCP and DoT
+    pub fn is_tcp(&self) -> bool {
+        match self {
+            Protocol::Tcp => true,
+            #[cfg(feature = "dot")]
+            Protocol::DoT => true,
+            _ => false,
+        }
     }
 }
 
@@ -85,8 +106,11 @@ impl fmt::Display for Protocol {
         match self {
             Protocol::Udp => write!(f, "Udp"),
             Protocol::Tcp => write!(f, "Tcp"),
+            #[cfg(feature = "dot")]
             Protocol::DoT => write!(f, "DoT"),
+            #[cfg(feature = "doh")]
             Protocol::DoH => write!(f, "DoH"),
+            #[cfg(feature = "doq")]
             Protocol::DoQ => write!(f, "DoQ"),
         }
     }
@@ -0,0 +1,171 @@
+//! Observers of the raw messages exchanged with the resolvers: each datagram or stream
+//! message is handed over with its direction, time, transport and peer, so logging, pcap
+//! writing or live dissection can be plugged in without touching the transports.
+//! --packet-log is the built-in one.
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Mutex, RwLock};
+
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+use super::network::Protocol;
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+// a DNS message as sent or received, without the length prefix of streams
+#[derive(Debug)]
+pub struct Packet<'a> {
+    pub direction: Direction,
+    pub timestamp: DateTime<Utc>,
+    pub mode: Protocol,
+    pub peer: Option<SocketAddr>,
+    pub data: &'a [u8],
+}
+
+pub trait Observer: Send + Sync {
+    fn observe(&self, packet: &Packet);
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// observers are global because messages are sent and received deep inside the
+// transports, whichever mode triggered the exchange
+//───────────────────────────────────────────────────────────────────────────────────
+static OBSERVERS: Observers = Observers::new();
+
+pub fn register(observer: impl Observer + 'static) {
+    OBSERVERS.register(observer);
+}
+
+// hand over the message to all observers
+pub fn notify(direction: Direction, mode: Protocol, peer: Option<SocketAddr>, data: &[u8]) {
+    OBSERVERS.notify(direction, mode, peer, data);
+}
+
+// a registry of observers, the global one being used by the transports
+struct Observers(RwLock<Vec<Box<dyn Observer>>>);
+
+impl Observers {
+    const fn new() -> Self {
+        Self(RwLock::new(Vec::new()))
+    }
+
+    fn register(&self, observer: impl Observer + 'static) {
+        self.0.write().unwrap().push(Box::new(observer));
+    }
+
+    fn notify(&self, direction: Direction, mode: Protocol, peer: Option<SocketAddr>, data: &[u8]) {
+        let observers = self.0.read().unwrap();
+        if observers.is_empty() {
+            return;
+        }
+
+        let packet = Packet {
+            direction,
+            timestamp: Utc::now(),
+            mode,
+            peer,
+            data,
+        };
+        for observer in observers.iter() {
+            observer.observe(&packet);
+        }
+    }
+}
+
+// a line of the packet log
+#[derive(Debug, Serialize)]
+struct PacketRecord {
+    // RFC3339 timestamp
+    timestamp: String,
+    direction: Direction,
+    transport: String,
+    peer: Option<SocketAddr>,
+
+    // wire data as base64
+    data: String,
+}
+
+impl From<&Packet<'_>> for PacketRecord {
+    fn from(packet: &Packet<'_>) -> Self {
+        Self {
+            timestamp: packet.timestamp.to_rfc3339_opts(SecondsFormat::Micros, true),
+            direction: packet.direction,
+            transport: packet.mode.to_string(),
+            peer: packet.peer,
+            data: general_purpose::STANDARD.encode(packet.data),
+        }
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// --packet-log: one JSON line per message appended to a file
+//───────────────────────────────────────────────────────────────────────────────────
+pub struct PacketLog(Mutex<File>);
+
+impl PacketLog {
+    pub fn new(path: &Path) -> Result<Self> {
+        let f = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| Error::OpenFile(e, path.to_path_buf()))?;
+        Ok(Self(Mutex::new(f)))
+    }
+}
+
+impl Observer for PacketLog {
+    fn observe(&self, packet: &Packet) {
+        let record = PacketRecord::from(packet);
+        let _ = writeln!(self.0.lock().unwrap(), "{}", serde_json::to_string(&record).unwrap());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct Counter(Arc<AtomicUsize>);
+
+    impl Observer for Counter {
+        fn observe(&self, packet: &Packet) {
+            if packet.direction == Direction::Received {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    #[test]
+    fn observers() {
+        // a local registry, to not leave the counter in the global one
+        let observers = Observers::new();
+        let seen = Arc::new(AtomicUsize::new(0));
+        observers.register(Counter(seen.clone()));
+        observers.notify(Direction::Received, Protocol::Tcp, None, &[0xDE, 0xAD]);
+        observers.notify(Direction::Sent, Protocol::Tcp, None, &[0xDE, 0xAD]);
+        assert_eq!(seen.load(Ordering::SeqCst), 1);
+
+        let packet = Packet {
+            direction: Direction::Sent,
+            timestamp: DateTime::from_timestamp(0, 0).unwrap(),
+            mode: Protocol::Udp,
+            peer: Some("1.1.1.1:53".parse().unwrap()),
+            data: &[1, 2, 3],
+        };
+        assert_eq!(
+            serde_json::to_string(&PacketRecord::from(&packet)).unwrap(),
+            r#"{"timestamp":"1970-01-01T00:00:00.000000Z","direction":"sent","transport":"Udp","peer":"1.1.1.1:53","data":"AQID"}"#
+        );
+    }
+}
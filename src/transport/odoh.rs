@@ -0,0 +1,207 @@
+// Oblivious DNS over HTTPS (RFC9230): the query is encrypted with the HPKE public key of the
+// target resolver and relayed by a proxy, so the proxy sees the client address but not the
+// query, and the target the query but not the client address
+use bytes::Bytes;
+use log::debug;
+use odoh_rs::{
+    compose, decrypt_response, encrypt_query, parse, ObliviousDoHConfigContents, ObliviousDoHConfigs,
+    ObliviousDoHMessage, ObliviousDoHMessagePlaintext,
+};
+use reqwest::{
+    blocking::Client,
+    header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE},
+    Url,
+};
+
+use super::https::{copy_body, http_info, HttpsProtocol};
+use super::network::{Messenger, Protocol};
+use super::{NetworkInfo, TransportOptions, TransportProtocol};
+use crate::error::{self, Error, Result};
+
+// media type of encrypted messages (RFC9230 section 8.1)
+const ODOH_MESSAGE: &str = "application/oblivious-dns-message";
+
+// queries are padded to a multiple of this length (RFC8467 section 4.1)
+const PADDING_BLOCK: usize = 128;
+
+pub struct OdohConn {
+    // reqwest client, with the same settings as for DoH
+    client: Client,
+
+    // URL the encrypted queries are POSTed to: the proxy with the target as parameters, or
+    // the target itself without proxy
+    url: Url,
+
+    // public key of the target
+    config: ObliviousDoHConfigContents,
+
+    // data received from Response
+    bytes_recv: Bytes,
+}
+
+pub type OdohProtocol = TransportProtocol<OdohConn>;
+
+fn odoh_error(e: odoh_rs::Error) -> Error {
+    Error::Odoh(e.to_string())
+}
+
+// proxy URL with the targethost and targetpath variables (RFC9230 section 4.1)
+fn relay_url(target: &Url, proxy: Option<&str>) -> Result<Url> {
+    let Some(proxy) = proxy else {
+        return Ok(target.clone());
+    };
+
+    let mut url = Url::parse(proxy).map_err(|e| Error::Odoh(e.to_string()))?;
+    let host = match target.port() {
+        Some(port) => format!("{}:{}", target.host_str().unwrap_or_default(), port),
+        None => target.host_str().unwrap_or_default().to_string(),
+    };
+    url.query_pairs_mut()
+        .append_pair("targethost", &host)
+        .append_pair("targetpath", target.path());
+    Ok(url)
+}
+
+// padding of the plaintext query
+fn padding(len: usize) -> usize {
+    (PADDING_BLOCK - len % PADDING_BLOCK) % PADDING_BLOCK
+}
+
+impl OdohProtocol {
+    pub fn new(trp_options: &TransportOptions) -> Result<Self> {
+        let client = HttpsProtocol::client_builder(trp_options)?
+            .build()
+            .map_err(Error::Reqwest)?;
+
+        debug_assert!(!trp_options.endpoint.server_name.is_empty());
+        let target = Url::parse(&trp_options.endpoint.server_name).map_err(|e| Error::Odoh(e.to_string()))?;
+        let url = relay_url(&target, trp_options.odoh_proxy.as_deref())?;
+        debug!("target: {} relay: {}", target, url);
+
+        let config = Self::config(&client, &target)?;
+
+        Ok(Self {
+            handle: OdohConn {
+                client,
+                url,
+                config,
+                bytes_recv: Bytes::default(),
+            },
+            netinfo: NetworkInfo::default(),
+        })
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // key configurations are published by the target at a well-known URL (RFC9230
+    // section 6), the first one supported is used
+    //───────────────────────────────────────────────────────────────────────────────────
+    fn config(client: &Client, target: &Url) -> Result<ObliviousDoHConfigContents> {
+        let mut url = target.clone();
+        url.set_path("/.well-known/odohconfigs");
+        url.set_query(None);
+        debug!("fetching ODoH configs from {}", url);
+
+        let resp = client
+            .get(url)
+            .send()
+            .and_then(|resp| resp.error_for_status())
+            .map_err(Error::Reqwest)?;
+        let mut body = resp.bytes().map_err(Error::Reqwest)?;
+
+        let configs: ObliviousDoHConfigs = parse(&mut body).map_err(odoh_error)?;
+        let config = configs
+            .supported()
+            .into_iter()
+            .next()
+            .ok_or(Error::Odoh(String::from("no supported key configuration")))?;
+        Ok(config.into())
+    }
+}
+
+impl Messenger for OdohProtocol {
+    async fn asend(&mut self, _: &[u8]) -> error::Result<usize> {
+        Ok(0)
+    }
+    async fn arecv(&mut self, _: &mut [u8]) -> error::Result<usize> {
+        Ok(0)
+    }
+
+    async fn aconnect(&mut self) -> error::Result<()> {
+        Ok(())
+    }
+
+    fn send(&mut self, buffer: &[u8]) -> Result<usize> {
+        self.netinfo.reused = self.netinfo.sent > 0;
+        self.netinfo.sent = buffer.len();
+
+        // a new secret for each query
+        let query = ObliviousDoHMessagePlaintext::new(buffer, padding(buffer.len()));
+        let (encrypted, secret) =
+            encrypt_query(&query, &self.handle.config, &mut rand::thread_rng()).map_err(odoh_error)?;
+        let body = compose(&encrypted).map_err(odoh_error)?.freeze();
+
+        let resp = self
+            .handle
+            .client
+            .post(self.handle.url.clone())
+            .header(ACCEPT, ODOH_MESSAGE)
+            .header(CONTENT_TYPE, ODOH_MESSAGE)
+            .header(CONTENT_LENGTH, body.len())
+            .body(body)
+            .send()
+            .map_err(Error::Reqwest)?;
+
+        // the proxy reports its own errors and the ones of the target as HTTP statuses
+        let status = resp.status();
+        self.netinfo.peer = resp.remote_addr();
+        self.netinfo.http = Some(http_info(status, resp.version(), resp.headers(), &[ODOH_MESSAGE]));
+        if !status.is_success() {
+            return Err(Error::Odoh(format!("HTTP status {}", status)));
+        }
+
+        let mut body = resp.bytes().map_err(Error::Reqwest)?;
+        let response: ObliviousDoHMessage = parse(&mut body).map_err(odoh_error)?;
+        let response = decrypt_response(&query, &response, secret).map_err(odoh_error)?;
+        self.handle.bytes_recv = response.into_msg();
+
+        Ok(buffer.len())
+    }
+
+    fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        self.netinfo.received = copy_body(&self.handle.bytes_recv, buffer)?;
+        Ok(self.netinfo.received)
+    }
+
+    // don't add the message length
+    fn uses_leading_length(&self) -> bool {
+        false
+    }
+
+    fn mode(&self) -> Protocol {
+        Protocol::DoH
+    }
+
+    fn network_info(&self) -> &NetworkInfo {
+        self.netinfo()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relay() {
+        let target = Url::parse("https://odoh.cloudflare-dns.com/dns-query").unwrap();
+        assert_eq!(relay_url(&target, None).unwrap(), target);
+
+        let url = relay_url(&target, Some("https://odoh1.surfdomeinen.nl/proxy")).unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://odoh1.surfdomeinen.nl/proxy?targethost=odoh.cloudflare-dns.com&targetpath=%2Fdns-query"
+        );
+
+        assert_eq!(padding(43), 85);
+        assert_eq!(padding(128), 0);
+    }
+}
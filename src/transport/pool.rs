@@ -0,0 +1,243 @@
+//! Connection pool for stream transports (TCP, DoT, DoH and DoQ): modes sending many queries to
+//! the same resolvers reuse idle connections instead of connecting, and handshaking,
+//! for each query.
+use std::any::{type_name, Any};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::debug;
+
+use super::TransportOptions;
+use crate::error::Result;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PoolStats {
+    // connections created
+    pub created: usize,
+
+    // exchanges made on an idle connection
+    pub reused: usize,
+
+    // idle connections dropped because of the idle timeout
+    pub expired: usize,
+
+    // idle connections dropped because the pool is full
+    pub evicted: usize,
+}
+
+impl fmt::Display for PoolStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "connection pool: {} created, {} reused, {} expired, {} evicted",
+            self.created, self.reused, self.expired, self.evicted
+        )
+    }
+}
+
+// an idle connection: the transport type is known from the key
+struct Idle {
+    conn: Box<dyn Any + Send>,
+    last_used: Instant,
+}
+
+struct Inner {
+    max_connections: usize,
+    idle_timeout: Duration,
+    idle: HashMap<String, Idle>,
+    stats: PoolStats,
+}
+
+// clones share the same connections, like the cancellation token
+#[derive(Clone)]
+pub struct ConnectionPool(Arc<Mutex<Inner>>);
+
+impl fmt::Debug for ConnectionPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.0.lock().unwrap();
+        f.debug_struct("ConnectionPool")
+            .field("max_connections", &inner.max_connections)
+            .field("idle_timeout", &inner.idle_timeout)
+            .field("idle", &inner.idle.len())
+            .field("stats", &inner.stats)
+            .finish()
+    }
+}
+
+impl ConnectionPool {
+    pub fn new(max_connections: usize, idle_timeout: Duration) -> Self {
+        Self(Arc::new(Mutex::new(Inner {
+            max_connections,
+            idle_timeout,
+            idle: HashMap::new(),
+            stats: PoolStats::default(),
+        })))
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        self.0.lock().unwrap().stats
+    }
+
+    // connections are shared for the same kind of transport, resolver and source address.
+    // The type of the connection tells apart transports of the same mode (e.g.: DoH,
+    // DoH over HTTP/3 and ODoH)
+    fn key<T>(trp_options: &TransportOptions) -> String {
+        format!(
+            "{}/{}/{:?}/{}/{:?}/{:?}",
+            trp_options.transport_mode,
+            type_name::<T>(),
+            trp_options.endpoint.addrs,
+            trp_options.endpoint.server_name,
+            trp_options.endpoint.sni,
+            trp_options.source
+        )
+    }
+
+    // take the idle connection for the key, if not expired
+    fn take<T: Any + Send>(&self, key: &str) -> Option<T> {
+        let mut inner = self.0.lock().unwrap();
+        let idle = inner.idle.remove(key)?;
+
+        if idle.last_used.elapsed() > inner.idle_timeout {
+            inner.stats.expired += 1;
+            return None;
+        }
+        idle.conn.downcast::<T>().ok().map(|conn| *conn)
+    }
+
+    // keep the connection, evicting the least recently used one if the pool is full
+    fn put_back<T: Any + Send>(&self, key: String, conn: T) {
+        let mut inner = self.0.lock().unwrap();
+
+        if inner.idle.len() >= inner.max_connections && !inner.idle.contains_key(&key) {
+            let oldest = inner
+                .idle
+                .iter()
+                .min_by_key(|(_, idle)| idle.last_used)
+                .map(|(k, _)| k.clone());
+            match oldest {
+                Some(oldest) => {
+                    inner.idle.remove(&oldest);
+                    inner.stats.evicted += 1;
+                }
+                None => return,
+            }
+        }
+
+        inner.idle.insert(
+            key,
+            Idle {
+                conn: Box::new(conn),
+                last_used: Instant::now(),
+            },
+        );
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // run the exchange on an idle connection if any, or on a new one. If the idle
+    // connection fails (e.g. closed by the server), a new one is tried. The connection
+    // is kept for the next exchange when successful
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn exchange<T, C, F, R>(&self, trp_options: &TransportOptions, create: C, mut f: F) -> Result<R>
+    where
+        T: Any + Send,
+        C: Fn(&TransportOptions) -> Result<T>,
+        F: FnMut(&mut T) -> Result<R>,
+    {
+        let key = Self::key::<T>(trp_options);
+
+        if let Some(mut conn) = self.take::<T>(&key) {
+            match f(&mut conn) {
+                Ok(r) => {
+                    self.0.lock().unwrap().stats.reused += 1;
+                    self.put_back(key, conn);
+                    return Ok(r);
+                }
+                Err(e) => debug!("idle connection {} failed ({}), reconnecting", key, e),
+            }
+        }
+
+        let mut conn = create(trp_options)?;
+        self.0.lock().unwrap().stats.created += 1;
+
+        let r = f(&mut conn)?;
+        self.put_back(key, conn);
+        Ok(r)
+    }
+}
+
+// prints out the pool statistics when dropped, whichever way the program exits
+pub struct StatsOnExit(pub ConnectionPool);
+
+impl Drop for StatsOnExit {
+    fn drop(&mut self) {
+        eprintln!("{}", self.0.stats());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{Error, Network};
+    use crate::transport::network::Protocol;
+
+    // a connection is just a counter of exchanges made on it
+    fn create(_: &TransportOptions) -> Result<usize> {
+        Ok(0)
+    }
+
+    fn exchange(conn: &mut usize) -> Result<usize> {
+        *conn += 1;
+        Ok(*conn)
+    }
+
+    #[test]
+    fn reuse() {
+        let pool = ConnectionPool::new(1, Duration::from_secs(60));
+        let mut trp = TransportOptions::default();
+
+        assert_eq!(pool.exchange(&trp, create, exchange).unwrap(), 1);
+        assert_eq!(pool.exchange(&trp, create, exchange).unwrap(), 2);
+        assert_eq!(pool.exchange(&trp, create, exchange).unwrap(), 3);
+
+        // failing idle connection is replaced
+        let failed = pool.exchange(&trp, create, |conn: &mut usize| {
+            if *conn == 3 {
+                Err(Error::Network(std::io::Error::other("closed"), Network::Read))
+            } else {
+                exchange(conn)
+            }
+        });
+        assert_eq!(failed.unwrap(), 1);
+
+        // another transport evicts the idle connection
+        trp.transport_mode = Protocol::Tcp;
+        assert_eq!(pool.exchange(&trp, create, exchange).unwrap(), 1);
+
+        // so does another kind of connection for the same mode
+        assert_ne!(ConnectionPool::key::<usize>(&trp), ConnectionPool::key::<u8>(&trp));
+
+        assert_eq!(
+            pool.stats(),
+            PoolStats {
+                created: 3,
+                reused: 2,
+                expired: 0,
+                evicted: 1
+            }
+        );
+    }
+
+    #[test]
+    fn expired() {
+        let pool = ConnectionPool::new(1, Duration::ZERO);
+        let trp = TransportOptions::default();
+
+        assert_eq!(pool.exchange(&trp, create, exchange).unwrap(), 1);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(pool.exchange(&trp, create, exchange).unwrap(), 1);
+        assert_eq!(pool.stats().expired, 1);
+    }
+}
@@ -1,8 +1,9 @@
 // Specific TLS handling
+use std::net::{SocketAddr, UdpSocket};
 use std::sync::Arc;
 
-use log::debug;
-use quinn::{crypto::rustls::QuicClientConfig, Connection, RecvStream, SendStream};
+use log::{debug, info};
+use quinn::{crypto::rustls::QuicClientConfig, Connection, Endpoint, RecvStream, SendStream};
 
 use super::{
     crypto::{root_store, tls_config},
@@ -15,9 +16,14 @@ use crate::{
 };
 
 pub struct QuicConn {
+    // kept to rebind the local socket (--doq-migrate)
+    endpoint: Endpoint,
     conn: Connection,
     send: Option<SendStream>,
     recv: Option<RecvStream>,
+
+    // packets lost on the connection before the current exchange
+    lost: u64,
 }
 pub type QuicProtocol = TransportProtocol<QuicConn>;
 
@@ -28,24 +34,32 @@ impl QuicProtocol {
     pub async fn new(trp_options: &TransportOptions) -> Result<Self> {
         // First we load some root certificates. These are used to authenticate the server.
         // The recommended way is to depend on the webpki_roots crate which contains the Mozilla set of root certificates.
-        let root_store = root_store(&trp_options.cert)?;
+        let root_store = root_store(&trp_options.cert, trp_options.roots)?;
         debug!("built root store with {} CAs", root_store.len());
 
         // Next, we make a TLS config. You’re likely to make one of these per process, and use it for all connections made by that process.
-        let mut client_crypto = tls_config(root_store);
+        // QUIC only uses TLS 1.3 (RFC9001)
+        let mut client_crypto = tls_config(root_store, &[&rustls::version::TLS13]);
 
         // setting ALPN for DoQ is mandatory
         client_crypto.alpn_protocols = vec![ALPN_DOQ.to_vec()];
 
         // address to bind to
-        let unspec = trp_options.ip_version.unspecified_ip();
-        debug!("unspec ip={}", unspec);
+        let local = trp_options.local_addr();
+        debug!("local ip={}", local);
 
         // create a Quinn config
         let qcc =
             QuicClientConfig::try_from(client_crypto).map_err(|_| Error::Quic(QuicError::NoInitialCipherSuite))?;
-        let client_config = quinn::ClientConfig::new(Arc::new(qcc));
-        let mut quic_endpoint = quinn::Endpoint::client(unspec).map_err(|e| Error::Network(e, Network::Bind))?;
+        let mut client_config = quinn::ClientConfig::new(Arc::new(qcc));
+
+        // PINGs keep the pooled connection open between the queries of watch modes
+        if let Some(period) = trp_options.keepalive {
+            let mut transport_config = quinn::TransportConfig::default();
+            transport_config.keep_alive_interval(Some(period));
+            client_config.transport_config(Arc::new(transport_config));
+        }
+        let mut quic_endpoint = quinn::Endpoint::client(local).map_err(|e| Error::Network(e, Network::Bind))?;
         quic_endpoint.set_default_client_config(client_config);
 
         let addr = trp_options.endpoint.random(&trp_options.ip_version);
@@ -56,24 +70,62 @@ impl QuicProtocol {
             .connect(addr.unwrap(), host)
             .map_err(|e| Error::Quic(QuicError::Connect(e, host.clone())))?
             .await
-            .map_err(|e| Error::Quic(QuicError::Connection(e)))?;
+            .map_err(|e| Error::Quic(e.into()))?;
         debug!("conn: {:?}", conn);
 
         let addr = conn.remote_address();
 
         Ok(Self {
             handle: QuicConn {
+                endpoint: quic_endpoint,
                 conn,
                 send: None,
                 recv: None,
+                lost: 0,
             },
             netinfo: NetworkInfo {
-                sent: 0,
-                received: 0,
                 peer: Some(addr),
+                tls_version: Some("TLSv1.3"),
+                lost_packets: Some(0),
+                ..Default::default()
             },
         })
     }
+
+    // another handle on the same connection, whose queries are sent on their own streams
+    // concurrently with the other handles (--parallel)
+    pub fn fork(&self) -> Self {
+        Self {
+            handle: QuicConn {
+                endpoint: self.handle.endpoint.clone(),
+                conn: self.handle.conn.clone(),
+                send: None,
+                recv: None,
+                lost: self.handle.lost,
+            },
+            netinfo: self.netinfo,
+        }
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // rebind the local UDP socket to another port (--doq-migrate): the resolver sees the
+    // connection coming from a new address and should follow it (RFC9000 section 9)
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn migrate(&mut self) -> Result<()> {
+        let endpoint = &self.handle.endpoint;
+        let old = endpoint
+            .local_addr()
+            .map_err(|e| Error::Network(e, Network::LocalAddr))?;
+
+        let socket = UdpSocket::bind(SocketAddr::new(old.ip(), 0)).map_err(|e| Error::Network(e, Network::Bind))?;
+        endpoint.rebind(socket).map_err(|e| Error::Network(e, Network::Bind))?;
+
+        let new = endpoint
+            .local_addr()
+            .map_err(|e| Error::Network(e, Network::LocalAddr))?;
+        info!("local address migrated from {} to {}", old, new);
+        Ok(())
+    }
 }
 
 impl Messenger for QuicProtocol {
@@ -87,8 +139,11 @@ impl Messenger for QuicProtocol {
     async fn asend(&mut self, buffer: &[u8]) -> Result<usize> {
         let send = self.handle.send.as_mut().unwrap();
 
-        let sent = send.write(buffer).await.map_err(|e| Error::Quic(QuicError::Write(e)))?;
+        // the whole message is written, length prefix included
+        send.write_all(buffer).await.map_err(|e| Error::Quic(e.into()))?;
         send.finish().map_err(|e| Error::Quic(QuicError::CloseStream(e)))?;
+        let sent = buffer.len();
+        self.netinfo.reused = self.netinfo.sent > 0;
         self.netinfo.sent = sent;
         debug!("{} bytes sent", sent);
 
@@ -102,36 +157,38 @@ impl Messenger for QuicProtocol {
     async fn arecv(&mut self, buffer: &mut [u8]) -> Result<usize> {
         let recv = self.handle.recv.as_mut().unwrap();
 
+        // a stream finished without any data is reported as such, not as a read error
         let mut buf = [0u8; 2];
-        recv.read_exact(&mut buf)
-            .await
-            .map_err(|e| Error::Quic(QuicError::ReadExact(e)))?;
+        recv.read_exact(&mut buf).await.map_err(|e| Error::Quic(e.into()))?;
         let length = u16::from_be_bytes(buf) as usize;
         debug!("about to read {} bytes in the TCP stream", length);
 
+        if length == 0 {
+            return Err(Error::Quic(QuicError::EmptyMessage));
+        }
+
         // now read exact length
         recv.read_exact(&mut buffer[..length])
             .await
-            .map_err(|e| Error::Quic(QuicError::ReadExact(e)))?;
+            .map_err(|e| Error::Quic(e.into()))?;
 
         //println!("read {} bytes in the TCP stream", length);
 
         //println!("inside async recv, buffer={:X?}", buffer);
 
-        self.netinfo.received = length;
+        // bytes read on the stream count the length prefix, the message doesn't
+        self.netinfo.received = length + 2;
+        let lost = self.handle.conn.stats().path.lost_packets;
+        self.netinfo.lost_packets = Some(lost.saturating_sub(self.handle.lost));
         Ok(length)
     }
 
     async fn aconnect(&mut self) -> Result<()> {
-        let (send, recv) = self
-            .handle
-            .conn
-            .open_bi()
-            .await
-            .map_err(|e| Error::Quic(QuicError::Connection(e)))?;
+        let (send, recv) = self.handle.conn.open_bi().await.map_err(|e| Error::Quic(e.into()))?;
 
         self.handle.send = Some(send);
         self.handle.recv = Some(recv);
+        self.handle.lost = self.handle.conn.stats().path.lost_packets;
 
         Ok(())
     }
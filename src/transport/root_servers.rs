@@ -230,3 +230,11 @@ pub fn get_root_server(version: &IPVersion, server: Option<&str>) -> IpAddr {
         IpAddr::from(ROOT_SERVERS[root].1)
     }
 }
+
+// name of the root server having this address, if any
+pub fn root_server_name(ip: &IpAddr) -> Option<&'static str> {
+    ROOT_SERVERS
+        .iter()
+        .find(|(_, (v4, v6))| *ip == IpAddr::from(*v4) || *ip == IpAddr::from(*v6))
+        .map(|(name, _)| *name)
+}
@@ -2,6 +2,7 @@ use std::{io::Write, net::TcpStream};
 
 use log::debug;
 
+use super::framed::FramedReader;
 use super::network::{Messenger, Protocol};
 use super::{get_tcpstream_ok, TransportOptions, TransportProtocol};
 use crate::{
@@ -13,7 +14,7 @@ pub type TcpProtocol = TransportProtocol<TcpStream>;
 
 impl TcpProtocol {
     pub fn new(trp_options: &TransportOptions) -> Result<Self> {
-        let (handle, _) = get_tcpstream_ok(&trp_options.endpoint.addrs[..], trp_options.timeout)?;
+        let (handle, _) = get_tcpstream_ok(&trp_options.endpoint.addrs[..], trp_options.source, trp_options.timeout)?;
 
         handle
             .set_read_timeout(Some(trp_options.timeout))
@@ -28,9 +29,8 @@ impl TcpProtocol {
         Ok(Self {
             handle,
             netinfo: NetworkInfo {
-                sent: 0,
-                received: 0,
                 peer,
+                ..Default::default()
             },
         })
     }
@@ -49,13 +49,14 @@ impl Messenger for TcpProtocol {
     }
 
     fn send(&mut self, buffer: &[u8]) -> Result<usize> {
+        self.netinfo.reused = self.netinfo.sent > 0;
         self.netinfo.sent = self.handle.write(buffer).map_err(crate::error::Error::Buffer)?;
         self.handle.flush().map_err(crate::error::Error::Buffer)?;
         Ok(self.netinfo.sent)
     }
 
     fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
-        self.netinfo.received = super::tcp_read(&mut self.handle, buffer)?;
+        self.netinfo.received = FramedReader::new(&mut self.handle).read_expected(buffer)?;
         Ok(self.netinfo.received)
     }
 
@@ -9,8 +9,9 @@ use log::{debug, info};
 use rustls::{ClientConnection, StreamOwned};
 use rustls_pki_types::ServerName;
 
+use super::framed::FramedReader;
 use super::{
-    crypto::{root_store, tls_config},
+    crypto::{root_store, tls_config, tls_versions, version_name},
     endpoint::EndPoint,
     network::{Messenger, Protocol},
 };
@@ -29,11 +30,11 @@ impl TlsProtocol {
     pub fn new(trp_options: &TransportOptions) -> Result<Self> {
         // First we load some root certificates. These are used to authenticate the server.
         // The recommended way is to depend on the webpki_roots crate which contains the Mozilla set of root certificates.
-        let root_store = root_store(&trp_options.cert)?;
+        let root_store = root_store(&trp_options.cert, trp_options.roots)?;
         debug!("built root store with {} CAs", root_store.len());
 
         // Next, we make a ClientConfig. You’re likely to make one of these per process, and use it for all connections made by that process.
-        let mut config = tls_config(root_store);
+        let mut config = tls_config(root_store, &tls_versions(trp_options.tls_min, trp_options.tls_max));
 
         if trp_options.alpn {
             config.alpn_protocols = vec![ALPN_DOT.to_vec()];
@@ -41,7 +42,8 @@ impl TlsProtocol {
 
         // as EndPoint addrs can contain several addresses, we get the first address for which
         // we can create a TcpStream. This is the case when we pass e.g.: one.one.one.one:853
-        let (stream, addr) = get_tcpstream_ok(&trp_options.endpoint.addrs[..], trp_options.timeout)?;
+        let (stream, addr) =
+            get_tcpstream_ok(&trp_options.endpoint.addrs[..], trp_options.source, trp_options.timeout)?;
         debug!("created TLS-TCP socket to {}", addr);
 
         let server_name = Self::build_server_name(&trp_options.endpoint, &addr)?;
@@ -55,9 +57,8 @@ impl TlsProtocol {
         Ok(Self {
             handle: tls_stream,
             netinfo: NetworkInfo {
-                sent: 0,
-                received: 0,
                 peer,
+                ..Default::default()
             },
         })
     }
@@ -88,6 +89,7 @@ impl Messenger for TlsProtocol {
     }
 
     fn send(&mut self, buffer: &[u8]) -> Result<usize> {
+        self.netinfo.reused = self.netinfo.sent > 0;
         self.netinfo.sent = self
             .handle
             .write(buffer)
@@ -96,12 +98,13 @@ impl Messenger for TlsProtocol {
         if let Some(cs) = self.handle.conn.negotiated_cipher_suite() {
             info!("negociated ciphersuite: {:?}", cs);
         }
+        self.netinfo.tls_version = self.handle.conn.protocol_version().map(version_name);
 
         Ok(self.netinfo.sent)
     }
 
     fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
-        self.netinfo.received = super::tcp_read(&mut self.handle, buffer)?;
+        self.netinfo.received = FramedReader::new(&mut self.handle).read_expected(buffer)?;
         Ok(self.netinfo.received)
     }
 
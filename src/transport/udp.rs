@@ -1,18 +1,30 @@
-use std::net::UdpSocket;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, UdpSocket};
 
 use log::debug;
 
 use super::network::{Messenger, Protocol};
 use super::{TransportOptions, TransportProtocol};
-use crate::error::{self, Error, Network, Result};
+use crate::error::{self, Dns, Error, Network, Result};
 use crate::transport::NetworkInfo;
 
 pub type UdpProtocol = TransportProtocol<UdpSocket>;
 
 impl UdpProtocol {
     pub fn new(trp_options: &TransportOptions) -> Result<Self> {
-        let unspec = trp_options.ip_version.unspecified_ip_vec();
-        let sock = UdpSocket::bind(&unspec[..]).map_err(|e| Error::Network(e, Network::Bind))?;
+        // bind to the source address if any
+        let local = match trp_options.source {
+            Some(ip) => vec![SocketAddr::new(ip, 0)],
+            None => {
+                // bind first to the version of the first address of the resolver
+                let mut local = trp_options.ip_version.unspecified_ip_vec();
+                if let Some(first) = trp_options.endpoint.addrs.first() {
+                    local.sort_by_key(|l| l.is_ipv4() != first.is_ipv4());
+                }
+                local
+            }
+        };
+        let sock = UdpSocket::bind(&local[..]).map_err(|e| Error::Network(e, Network::Bind))?;
 
         debug!(
             "bound UDP socket to {}",
@@ -24,24 +36,50 @@ impl UdpProtocol {
         sock.set_write_timeout(Some(trp_options.timeout))
             .map_err(|e| Error::Timeout(e, trp_options.timeout))?;
 
-        // connect() will chose any socket address which is succesful
-        // as TransportOptions impl ToSocketAddrs
-        sock.connect(&trp_options.endpoint.addrs[..])
-            .map_err(|e| Error::Network(e, Network::Connect))?;
-
-        let peer = sock.peer_addr().ok();
+        let peer = if trp_options.udp_unconnected {
+            // answers are accepted from any address, but ICMP errors are not reported
+            let local = sock.local_addr().map_err(|e| Error::Network(e, Network::LocalAddr))?;
+            let peer = trp_options
+                .endpoint
+                .addrs
+                .iter()
+                .find(|addr| addr.is_ipv4() == local.is_ipv4())
+                .copied()
+                .ok_or(Error::Dns(Dns::CantCreateSocketAddress))?;
+            Some(peer)
+        } else {
+            // connect() will chose any socket address which is succesful
+            // as TransportOptions impl ToSocketAddrs
+            sock.connect(&trp_options.endpoint.addrs[..])
+                .map_err(|e| Error::Network(e, Network::Connect))?;
+            sock.peer_addr().ok()
+        };
         debug!("created UDP socket to {:?}", peer);
 
         Ok(Self {
             handle: sock,
             netinfo: NetworkInfo {
-                sent: 0,
-                received: 0,
                 peer,
+                udp: true,
+                ..Default::default()
             },
         })
     }
 
+    fn is_connected(&self) -> bool {
+        self.handle.peer_addr().is_ok()
+    }
+
+    // on a connected socket, an ICMP port unreachable is reported as a refused connection
+    fn error(&self, e: std::io::Error, op: Network) -> Error {
+        if e.kind() == ErrorKind::ConnectionRefused {
+            let peer = self.netinfo.peer.map(|p| p.to_string()).unwrap_or_default();
+            Error::IcmpRefused(peer)
+        } else {
+            Error::Network(e, op)
+        }
+    }
+
     // // display list of found host resolvers and try to bind
     // pub fn list_resolvers(trp_options: &TransportOptions) -> Result<()> {
     //     // create udp socket on either V4 or V6
@@ -71,17 +109,28 @@ impl Messenger for UdpProtocol {
     }
 
     fn send(&mut self, buffer: &[u8]) -> Result<usize> {
-        self.netinfo.sent = self.handle.send(buffer).map_err(|e| Error::Network(e, Network::Send))?;
+        let sent = match self.netinfo.peer {
+            Some(peer) if !self.is_connected() => self.handle.send_to(buffer, peer),
+            _ => self.handle.send(buffer),
+        };
+        self.netinfo.sent = sent.map_err(|e| self.error(e, Network::Send))?;
         debug!("sent {} bytes", self.netinfo.sent);
 
         Ok(self.netinfo.sent)
     }
 
     fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
-        self.netinfo.received = self
-            .handle
-            .recv(buffer)
-            .map_err(|e| Error::Network(e, Network::Receive))?;
+        if self.is_connected() {
+            self.netinfo.received = self.handle.recv(buffer).map_err(|e| self.error(e, Network::Receive))?;
+        } else {
+            // keep the address the answer comes from
+            let (received, from) = self
+                .handle
+                .recv_from(buffer)
+                .map_err(|e| self.error(e, Network::Receive))?;
+            self.netinfo.received = received;
+            self.netinfo.peer = Some(from);
+        }
         debug!("received {} bytes", self.netinfo.received);
 
         Ok(self.netinfo.received)
@@ -0,0 +1,222 @@
+//! Warnings gathered while querying and displaying, kept apart from the results: transport
+//! downgrades, fallback resolvers, lint findings on responses, answers in special-use address space,
+//! signatures out of their validity period or skewed clocks, and stale answers served from the
+//! cache. They're printed after the results, or as a `warnings` array in JSON, so scripts can check there
+//! are none.
+use std::fmt;
+
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::analyze::lint;
+use crate::bogon::bogons;
+use crate::dns::date_time::humanize;
+use crate::dns::message::{Message, MessageList};
+use crate::dns::rfc::{qtype::QType, resource_record::ResourceRecord};
+use crate::i18n::{fill, tr};
+use crate::show::DisplayOptions;
+use crate::transport::endpoint::EndPoint;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    // the exchange fell back to a less capable transport
+    Downgrade,
+
+    // no host resolver was found, the fallback ones were used
+    Resolver,
+
+    // the response doesn't follow the RFCs
+    Lint,
+
+    // an answer points to special-use address space
+    Bogon,
+
+    // a signature is not valid now: stale data or wrong local clock
+    Clock,
+
+    // the resolver failed, a cached response was served (--serve-stale)
+    Stale,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Downgrade => f.write_str("downgrade"),
+            Kind::Resolver => f.write_str("resolver"),
+            Kind::Lint => f.write_str("lint"),
+            Kind::Bogon => f.write_str("bogon"),
+            Kind::Clock => f.write_str("clock"),
+            Kind::Stale => f.write_str("stale"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Warning {
+    pub kind: Kind,
+    pub qtype: QType,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.qtype, self.kind, self.message)
+    }
+}
+
+// RRSIG outside its validity period, or TSIG time of the server outside the fudge: either
+// the data is stale or one of the clocks is wrong (RFC4034 3.1.5, RFC8945 5.2.3)
+fn clock(rr: &ResourceRecord, now: i64) -> Option<String> {
+    if let Some(rrsig) = rr.rrsig() {
+        let (inception, expiration) = rrsig.validity(now);
+        if expiration < 0 {
+            return Some(format!(
+                "RRSIG {} of {} expired {} ({}): stale signatures or local clock ahead",
+                rrsig.type_covered,
+                rr.name,
+                humanize(expiration),
+                rrsig.sign_expiration
+            ));
+        }
+        if inception > 0 {
+            return Some(format!(
+                "RRSIG {} of {} not valid until {} ({}): local clock behind",
+                rrsig.type_covered,
+                rr.name,
+                humanize(inception),
+                rrsig.sign_inception
+            ));
+        }
+    }
+
+    let (signed, fudge) = rr.tsig_time()?;
+    let skew = signed as i64 - now;
+    (skew.unsigned_abs() > fudge as u64).then(|| {
+        format!(
+            "TSIG time of the server is {}s {} the local clock, over the {}s fudge: clocks are skewed",
+            skew.unsigned_abs(),
+            if skew > 0 { "ahead of" } else { "behind" },
+            fudge
+        )
+    })
+}
+
+// the sink where warnings are pushed while the queries are processed
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(transparent)]
+pub struct Warnings(Vec<Warning>);
+
+impl Warnings {
+    pub fn push(&mut self, kind: Kind, qtype: QType, message: String) {
+        self.0.push(Warning { kind, qtype, message });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Warning> {
+        self.0.iter()
+    }
+
+    // downgrades which happened during the exchange
+    pub fn exchange(&mut self, msg: &Message) {
+        if msg.netinfo.edns_fallback {
+            self.push(
+                Kind::Downgrade,
+                msg.query.question.qtype,
+                tr("EDNS rejected by the server (FORMERR or NOTIMP), the query was resent without OPT").to_string(),
+            );
+        }
+        if msg.netinfo.tcp_fallback {
+            self.push(
+                Kind::Downgrade,
+                msg.query.question.qtype,
+                tr("response truncated over UDP, the query was resent over TCP").to_string(),
+            );
+        }
+        if let Some(http) = msg.netinfo.http.filter(|http| !http.content_type_valid) {
+            self.push(
+                Kind::Lint,
+                msg.query.question.qtype,
+                fill(
+                    "DoH response has an unexpected content-type (HTTP status {})",
+                    &[&http.status],
+                ),
+            );
+        }
+        if let Some(http) = msg.netinfo.http.filter(|http| http.skipped_rrs > 0) {
+            self.push(
+                Kind::Downgrade,
+                msg.query.question.qtype,
+                fill(
+                    "{} RRs of the DoH JSON answer can't be converted and are missing from the response",
+                    &[&http.skipped_rrs],
+                ),
+            );
+        }
+    }
+
+    // resolvers used instead of the host ones
+    pub fn fallback_resolvers(&mut self, qtype: QType, endpoint: &EndPoint) {
+        let addrs: Vec<_> = endpoint.addrs.iter().map(|a| a.ip().to_string()).collect();
+        self.push(
+            Kind::Resolver,
+            qtype,
+            fill(
+                "no host resolver found, the fallback resolvers {} were used",
+                &[&addrs.join(", ")],
+            ),
+        );
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // findings on the responses, once all are received
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn responses(&mut self, messages: &MessageList, display_options: &DisplayOptions) {
+        for msg in messages.iter() {
+            let qtype = msg.query.question.qtype;
+
+            for finding in lint(msg.response()) {
+                self.push(Kind::Lint, qtype, finding);
+            }
+
+            if display_options.bogon_check {
+                for bogon in bogons(msg.response()) {
+                    self.push(Kind::Bogon, qtype, bogon.to_string());
+                }
+            }
+
+            let resp = msg.response();
+            let now = Utc::now().timestamp();
+            let rrs = [resp.answer.as_ref(), resp.authority(), resp.additional()];
+            for rr in rrs.into_iter().flatten().flat_map(|list| list.iter()) {
+                if let Some(finding) = clock(rr, now) {
+                    self.push(Kind::Clock, qtype, finding);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json() {
+        let mut warnings = Warnings::default();
+        assert_eq!(serde_json::to_string(&warnings).unwrap(), "[]");
+
+        warnings.push(Kind::Lint, QType::A, String::from("NXDOMAIN without SOA"));
+        assert_eq!(
+            warnings.iter().next().unwrap().to_string(),
+            "A: lint: NXDOMAIN without SOA"
+        );
+        assert_eq!(
+            serde_json::to_string(&warnings).unwrap(),
+            r#"[{"kind":"lint","qtype":"A","message":"NXDOMAIN without SOA"}]"#
+        );
+    }
+}
@@ -0,0 +1,360 @@
+//! Modes sending the same query again and again at regular intervals, until Ctrl-C or
+//! the deadline: --rebind-watch alerts when answers flip between public and private
+//! address space, --serial-watch logs the SOA serial changes of a zone. With
+//! --auto-interval, intervals follow the SOA refresh and retry values. With --keepalive,
+//! the pooled connection is pinged between queries to keep it open.
+use std::collections::BTreeSet;
+use std::net::IpAddr;
+use std::thread::sleep;
+use std::time::Duration;
+
+use chrono::{SecondsFormat, Utc};
+
+use crate::annotate::special_use;
+use crate::args::CliOptions;
+use crate::dns::message::MessageList;
+use crate::dns::rfc::{qtype::QType, soa::SOA};
+use crate::error::{Dns, Error, Result};
+use crate::get_messages;
+use crate::i18n::{fill, tr};
+use crate::stats::Failures;
+use crate::transport::cancel::CancellationToken;
+
+// default interval of --rebind-watch
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(1000);
+
+// sleeping is done by slices to react quickly to Ctrl-C
+const SLEEP_SLICE: Duration = Duration::from_millis(100);
+
+// wait for duration unless cancelled before
+fn wait(cancel: &CancellationToken, duration: Duration) {
+    let mut left = duration;
+    while !left.is_zero() && !cancel.is_cancelled() {
+        let slice = left.min(SLEEP_SLICE);
+        sleep(slice);
+        left -= slice;
+    }
+}
+
+// current time for logging
+pub fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+// a pooled connection found closed by the resolver has been replaced
+fn reconnected(messages: &MessageList) {
+    if messages.first().is_some_and(|msg| msg.netinfo.reconnects > 0) {
+        println!(
+            "{} {}",
+            now(),
+            tr("connection silently dropped by the resolver, reconnected")
+        );
+    }
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// wait for duration, pinging the resolver every --keepalive period over TCP and DoT
+// so the pooled connection and the NAT bindings are kept. Over DoH and DoQ, HTTP/2 and
+// QUIC PINGs are sent by reqwest and quinn themselves
+//───────────────────────────────────────────────────────────────────────────────────
+fn pause(options: &CliOptions, duration: Duration) {
+    let cancel = &options.transport.cancel;
+    let period = match options.transport.keepalive {
+        Some(period) if options.transport.transport_mode.is_tcp() => period,
+        _ => return wait(cancel, duration),
+    };
+
+    let mut left = duration;
+    while left > period && !cancel.is_cancelled() {
+        wait(cancel, period);
+        left -= period;
+
+        match get_messages(None, options) {
+            Ok(messages) => reconnected(&messages),
+            Err(_) if cancel.is_cancelled() => return,
+            Err(e) => println!("{}", fill("{} keepalive failed: {}", &[&now(), &e.localized()])),
+        }
+    }
+    wait(cancel, left);
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// call step until it returns None, pausing for the interval it returns between each
+// call. Cancellation ends the watch normally
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn watch<F, P>(cancel: &CancellationToken, mut step: F, mut pause: P) -> Result<()>
+where
+    F: FnMut() -> Result<Option<Duration>>,
+    P: FnMut(Duration),
+{
+    while !cancel.is_cancelled() {
+        match step() {
+            Ok(Some(interval)) => pause(interval),
+            Ok(None) => break,
+            Err(_) if cancel.is_cancelled() => break,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// --auto-interval: polling intervals taken from the SOA of the zone as secondaries
+// do, refresh after a successful query and retry after a failed one
+//───────────────────────────────────────────────────────────────────────────────────
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SoaSchedule {
+    refresh: Duration,
+    retry: Duration,
+}
+
+impl SoaSchedule {
+    fn new(soa: &SOA) -> Self {
+        // a 0 value would mean polling without any pause
+        Self {
+            refresh: Duration::from_secs(soa.refresh.max(1) as u64),
+            retry: Duration::from_secs(soa.retry.max(1) as u64),
+        }
+    }
+
+    fn next(&self, success: bool) -> Duration {
+        if success {
+            self.refresh
+        } else {
+            self.retry
+        }
+    }
+}
+
+// SOA of the zone the queried name belongs to
+fn zone_schedule(options: &CliOptions) -> Result<SoaSchedule> {
+    let mut options = options.clone();
+    options.protocol.qtype = vec![QType::SOA];
+
+    let messages = get_messages(None, &options)?;
+    let schedule = messages
+        .first()
+        .and_then(|msg| msg.response().soa())
+        .map(SoaSchedule::new)
+        .ok_or(Error::Dns(Dns::NoSoa))?;
+
+    println!(
+        "{} polling every {} s, {} s after a failure",
+        now(),
+        schedule.refresh.as_secs(),
+        schedule.retry.as_secs()
+    );
+    Ok(schedule)
+}
+
+// A and AAAA addresses of all answers
+fn addresses(messages: &MessageList) -> BTreeSet<IpAddr> {
+    messages.answers().filter_map(|rr| rr.ip_address()).collect()
+}
+
+// "private" for special-use ranges, "public" otherwise
+fn address_spaces(addresses: &BTreeSet<IpAddr>) -> BTreeSet<&'static str> {
+    addresses
+        .iter()
+        .map(|ip| if special_use(ip).is_some() { "private" } else { "public" })
+        .collect()
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// query the name at each interval and alert when the answers move between public
+// and private address space, which is what a DNS rebinding attack does
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn rebind_watch(options: &CliOptions) -> Result<()> {
+    let schedule = if options.dump.auto_interval {
+        Some(zone_schedule(options)?)
+    } else {
+        None
+    };
+    let interval = |success: bool| match schedule {
+        Some(schedule) => schedule.next(success),
+        None => options.dump.interval.unwrap_or(DEFAULT_INTERVAL),
+    };
+    let mut previous: Option<BTreeSet<&'static str>> = None;
+    let mut flips = 0;
+    let mut failures = Failures::default();
+
+    let step = || {
+        let messages = match get_messages(None, options) {
+            Ok(messages) => messages,
+            Err(e) => {
+                // a failed query is not a flip
+                failures.error(&e);
+                println!("{}", fill("{} error: {}", &[&now(), &e.localized()]));
+                return Ok(Some(interval(false)));
+            }
+        };
+        reconnected(&messages);
+        if let Some(msg) = messages.first() {
+            failures.rcode(msg.response().rcode());
+        }
+
+        let addresses = addresses(&messages);
+        let spaces = address_spaces(&addresses);
+        let list: Vec<_> = addresses
+            .iter()
+            .map(|ip| match special_use(ip) {
+                Some(range) => format!("{} ({})", ip, range),
+                None => ip.to_string(),
+            })
+            .collect();
+        println!("{} {}", now(), list.join(" "));
+
+        // an empty answer doesn't tell anything
+        if !spaces.is_empty() {
+            if let Some(prev) = previous.as_ref().filter(|prev| *prev != &spaces) {
+                flips += 1;
+                println!(
+                    "{} ALERT: answers flipped from {:?} to {:?} address space, possible DNS rebinding",
+                    now(),
+                    prev,
+                    spaces
+                );
+            }
+            previous = Some(spaces);
+        }
+
+        Ok(Some(interval(true)))
+    };
+    watch(&options.transport.cancel, step, |interval| pause(options, interval))?;
+
+    println!("{} flip(s) detected, failures: {}", flips, failures);
+    Ok(())
+}
+
+// true if serial is target or after it, using serial number arithmetic (RFC1982)
+fn serial_reached(serial: u32, target: u32) -> bool {
+    (serial.wrapping_sub(target) as i32) >= 0
+}
+
+//───────────────────────────────────────────────────────────────────────────────────
+// query the SOA of the zone at each interval and log serial changes
+//───────────────────────────────────────────────────────────────────────────────────
+pub fn serial_watch(options: &mut CliOptions) -> Result<()> {
+    options.protocol.qtype = vec![QType::SOA];
+    let options = &*options;
+    let mut previous: Option<u32> = None;
+    let mut last_schedule: Option<SoaSchedule> = None;
+    let mut failures = Failures::default();
+
+    let step = || {
+        let soa = get_messages(None, options).map(|messages| {
+            reconnected(&messages);
+            messages
+                .first()
+                .and_then(|msg| msg.response().soa())
+                .map(|soa| (soa.serial, SoaSchedule::new(soa)))
+        });
+
+        // with --auto-interval, failures are retried as secondaries do
+        let (serial, schedule) = match (soa, last_schedule) {
+            (Ok(Some(soa)), _) => soa,
+            (Err(e), Some(schedule)) if options.dump.auto_interval => {
+                failures.error(&e);
+                println!("{}", fill("{} error: {}", &[&now(), &e.localized()]));
+                return Ok(Some(schedule.next(false)));
+            }
+            (Ok(None), Some(schedule)) if options.dump.auto_interval => {
+                println!("{} no SOA found for {}", now(), options.protocol.domain_name);
+                return Ok(Some(schedule.next(false)));
+            }
+            (Err(e), _) => return Err(e),
+            (Ok(None), _) => {
+                println!("{} no SOA found for {}", now(), options.protocol.domain_name);
+                return Ok(None);
+            }
+        };
+        last_schedule = Some(schedule);
+
+        match previous {
+            None => println!("{} serial {}", now(), serial),
+            Some(prev) if prev != serial => println!("{} serial changed from {} to {}", now(), prev, serial),
+            _ => (),
+        }
+        previous = Some(serial);
+
+        if let Some(target) = options.dump.target_serial {
+            if serial_reached(serial, target) {
+                println!("{} target serial {} reached", now(), target);
+                return Ok(None);
+            }
+        }
+
+        // poll at the refresh interval by default
+        Ok(Some(options.dump.interval.unwrap_or(schedule.next(true))))
+    };
+    watch(&options.transport.cancel, step, |interval| pause(options, interval))?;
+
+    if failures.total() > 0 {
+        println!("failures: {}", failures);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn steps() {
+        let cancel = CancellationToken::default();
+
+        // stops when the step says so
+        let mut count = 0;
+        let step = || {
+            count += 1;
+            Ok((count < 3).then_some(Duration::ZERO))
+        };
+        watch(&cancel, step, |interval| wait(&cancel, interval)).unwrap();
+        assert_eq!(count, 3);
+
+        // or when cancelled while waiting
+        let cancel = cancel.with_deadline(Duration::from_millis(50));
+        let now = Instant::now();
+        watch(
+            &cancel,
+            || Ok(Some(Duration::from_secs(60))),
+            |interval| wait(&cancel, interval),
+        )
+        .unwrap();
+        assert!(now.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn schedule() {
+        let soa = SOA {
+            refresh: 7200,
+            retry: 0,
+            ..Default::default()
+        };
+        let schedule = SoaSchedule::new(&soa);
+        assert_eq!(schedule.next(true), Duration::from_secs(7200));
+        assert_eq!(schedule.next(false), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn serials() {
+        assert!(serial_reached(2026101501, 2026101501));
+        assert!(serial_reached(2026101502, 2026101501));
+        assert!(!serial_reached(2026101500, 2026101501));
+
+        // wrapping
+        assert!(serial_reached(5, u32::MAX - 5));
+        assert!(!serial_reached(u32::MAX - 5, 5));
+    }
+
+    #[test]
+    fn spaces() {
+        let addrs: BTreeSet<IpAddr> = ["93.184.215.14", "192.168.1.1"]
+            .iter()
+            .map(|a| a.parse().unwrap())
+            .collect();
+        assert_eq!(address_spaces(&addrs), BTreeSet::from(["private", "public"]));
+    }
+}
@@ -0,0 +1,323 @@
+//! Statistics on the wire format of a response (--wire-stats): size of each section, and
+//! how domain names are compressed: labels written, pointers used and bytes saved. The
+//! size of each RR is also used to simulate a smaller bufsize (--simulate-bufsize).
+use std::fmt;
+
+// a name can't have more than 127 labels, more jumps means a pointer loop
+const MAX_JUMPS: usize = 127;
+
+const ADDITIONAL: usize = 3;
+const OPT: u16 = 41;
+
+// RR types whose RDATA holds compressed names (RFC3597 section 4): bytes before the
+// first name, and number of names following each other. NS, CNAME, PTR, MX and SOA
+fn rdata_names(rtype: u16) -> (usize, usize) {
+    match rtype {
+        2 | 5 | 12 => (0, 1),
+        15 => (2, 1),
+        6 => (0, 2),
+        _ => (0, 0),
+    }
+}
+
+// a domain name found in the message
+#[derive(Debug, Default)]
+struct Name {
+    // bytes taken in the message
+    wire: usize,
+
+    // bytes it would take without compression
+    expanded: usize,
+
+    // labels written, not reached through a pointer
+    labels: usize,
+
+    pointer: bool,
+}
+
+// read the name at pos, following pointers
+fn name(raw: &[u8], mut pos: usize) -> Option<Name> {
+    let mut name = Name::default();
+    let mut jumps = 0;
+
+    loop {
+        let length = *raw.get(pos)? as usize;
+
+        // pointer: only the first one is in the name itself
+        if length & 0xC0 == 0xC0 {
+            if !name.pointer {
+                name.wire += 2;
+                name.pointer = true;
+            }
+            jumps += 1;
+            if jumps > MAX_JUMPS {
+                return None;
+            }
+            pos = ((length & 0x3F) << 8) | *raw.get(pos + 1)? as usize;
+            continue;
+        }
+
+        name.expanded += length + 1;
+        if !name.pointer {
+            name.wire += length + 1;
+            if length > 0 {
+                name.labels += 1;
+            }
+        }
+        if length == 0 {
+            return Some(name);
+        }
+        pos += length + 1;
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct WireStats {
+    // whole message
+    pub size: usize,
+
+    // question, answer, authority and additional sections
+    pub sections: [usize; 4],
+
+    pub names: usize,
+    pub labels: usize,
+    pub pointers: usize,
+
+    // size of all names, without compression and as sent
+    pub expanded: usize,
+    pub compressed: usize,
+
+    // section, type and size of each RR, in the message order
+    pub records: Vec<(usize, u16, usize)>,
+}
+
+// what would become of a RR in a smaller response
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fate {
+    Kept,
+
+    // additional data which doesn't fit is left out
+    Dropped,
+
+    // answer or authority data which doesn't fit: the TC flag is set
+    Truncated,
+}
+
+impl fmt::Display for Fate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fate::Kept => f.write_str("kept"),
+            Fate::Dropped => f.write_str("dropped"),
+            Fate::Truncated => f.write_str("truncated"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Simulation {
+    pub bufsize: usize,
+
+    // fate of each RR, in the message order
+    pub fates: Vec<Fate>,
+
+    // bytes used by the header, the question and the RRs kept
+    pub used: usize,
+}
+
+impl Simulation {
+    pub fn truncated(&self) -> bool {
+        self.fates.contains(&Fate::Truncated)
+    }
+
+    pub fn dropped(&self) -> usize {
+        self.fates.iter().filter(|f| **f == Fate::Dropped).count()
+    }
+}
+
+impl WireStats {
+    //───────────────────────────────────────────────────────────────────────────────────
+    // walk the raw message. None if it can't be walked through
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn new(raw: &[u8]) -> Option<Self> {
+        let header = raw.get(..12)?;
+        let count = |i: usize| u16::from_be_bytes([header[i], header[i + 1]]) as usize;
+
+        let mut stats = Self {
+            size: raw.len(),
+            ..Default::default()
+        };
+        let mut pos = 12;
+
+        for (section, rrs) in [count(4), count(6), count(8), count(10)].into_iter().enumerate() {
+            let start = pos;
+            for _ in 0..rrs {
+                let rr_start = pos;
+                pos += stats.add(raw, pos)?;
+
+                // question: type and class only
+                if section == 0 {
+                    pos += 4;
+                    continue;
+                }
+
+                let fixed = raw.get(pos..pos + 10)?;
+                let rtype = u16::from_be_bytes([fixed[0], fixed[1]]);
+                let rd_length = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+                pos += 10;
+
+                let (skip, names) = rdata_names(rtype);
+                let mut offset = pos + skip;
+                for _ in 0..names {
+                    offset += stats.add(raw, offset)?;
+                }
+                pos += rd_length;
+                stats.records.push((section, rtype, pos - rr_start));
+            }
+            stats.sections[section] = pos - start;
+        }
+
+        (pos <= raw.len()).then_some(stats)
+    }
+
+    // account for the name at pos and return its size on the wire
+    fn add(&mut self, raw: &[u8], pos: usize) -> Option<usize> {
+        let name = name(raw, pos)?;
+        self.names += 1;
+        self.labels += name.labels;
+        self.pointers += name.pointer as usize;
+        self.expanded += name.expanded;
+        self.compressed += name.wire;
+        Some(name.wire)
+    }
+
+    // bytes saved by compression: a pointer to a name shorter than 2 bytes (the root)
+    // takes more room than the name itself
+    pub fn saved(&self) -> usize {
+        self.expanded.saturating_sub(self.compressed)
+    }
+
+    //───────────────────────────────────────────────────────────────────────────────────
+    // which RRs would fit in bufsize bytes: sections are filled in order, additional
+    // data which doesn't fit is dropped, other data makes the response truncated
+    // (RFC2181 9). OPT is kept first as it carries the bufsize. RR sizes are the ones
+    // received, so compression pointers to dropped RRs are not accounted for
+    //───────────────────────────────────────────────────────────────────────────────────
+    pub fn simulate(&self, bufsize: usize) -> Simulation {
+        let mut used = 12
+            + self.sections[0]
+            + self
+                .records
+                .iter()
+                .filter(|(_, rtype, _)| *rtype == OPT)
+                .map(|(_, _, size)| size)
+                .sum::<usize>();
+        let mut truncated = false;
+        let mut dropping = false;
+
+        let fates = self
+            .records
+            .iter()
+            .map(|&(section, rtype, size)| {
+                if rtype == OPT {
+                    Fate::Kept
+                } else if section == ADDITIONAL && (truncated || dropping || used + size > bufsize) {
+                    dropping = true;
+                    Fate::Dropped
+                } else if truncated || used + size > bufsize {
+                    truncated = true;
+                    Fate::Truncated
+                } else {
+                    used += size;
+                    Fate::Kept
+                }
+            })
+            .collect();
+
+        Simulation { bufsize, fates, used }
+    }
+}
+
+impl fmt::Display for WireStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [question, answer, authority, additional] = self.sections;
+        writeln!(
+            f,
+            "size: {} bytes (header:12, question:{}, answer:{}, authority:{}, additional:{})",
+            self.size, question, answer, authority, additional
+        )?;
+        writeln!(
+            f,
+            "names: {}, labels written: {}, pointers: {}",
+            self.names, self.labels, self.pointers
+        )?;
+        write!(
+            f,
+            "compression: {} bytes saved, names take {} bytes instead of {}, message {:.1}% smaller",
+            self.saved(),
+            self.compressed,
+            self.expanded,
+            100.0 * self.saved() as f64 / (self.size + self.saved()) as f64
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compression() {
+        // www.example.com CNAME example.com, example.com A 93.184.215.14
+        let wire: &[u8] = &[
+            0x00, 0x01, 0x81, 0x80, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, // header
+            0x03, b'w', b'w', b'w', 0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o', b'm', 0x00, 0x00,
+            0x01, 0x00, 0x01, // question
+            0xC0, 0x0C, 0x00, 0x05, 0x00, 0x01, 0x00, 0x00, 0x0E, 0x10, 0x00, 0x02, 0xC0, 0x10, // CNAME
+            0xC0, 0x10, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x0E, 0x10, 0x00, 0x04, 93, 184, 215, 14, // A
+        ];
+
+        let stats = WireStats::new(wire).unwrap();
+        assert_eq!(stats.size, 63);
+        assert_eq!(stats.sections, [21, 30, 0, 0]);
+        assert_eq!(stats.names, 4);
+        assert_eq!(stats.labels, 3);
+        assert_eq!(stats.pointers, 3);
+        assert_eq!(stats.expanded, 60);
+        assert_eq!(stats.compressed, 23);
+        assert_eq!(stats.saved(), 37);
+        assert_eq!(stats.records, vec![(1, 5, 14), (1, 1, 16)]);
+
+        // 33 bytes for the header and question
+        let sim = stats.simulate(512);
+        assert_eq!(sim.fates, vec![Fate::Kept, Fate::Kept]);
+        assert_eq!(sim.used, 63);
+        let sim = stats.simulate(50);
+        assert_eq!(sim.fates, vec![Fate::Kept, Fate::Truncated]);
+        assert!(sim.truncated());
+        assert_eq!(sim.used, 47);
+
+        // pointer loop
+        let mut looping = wire.to_vec();
+        looping[45] = 0xC0;
+        looping[46] = 45;
+        assert!(WireStats::new(&looping).is_none());
+
+        // truncated
+        assert!(WireStats::new(&wire[..50]).is_none());
+    }
+
+    #[test]
+    fn root_pointer() {
+        // . NS, answered with pointers to the root name of the question
+        let wire: &[u8] = &[
+            0x00, 0x01, 0x81, 0x80, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, // header
+            0x00, 0x00, 0x02, 0x00, 0x01, // question
+            0xC0, 0x0C, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0x0E, 0x10, 0x00, 0x02, 0xC0, 0x0C, // NS
+        ];
+
+        let stats = WireStats::new(wire).unwrap();
+        assert_eq!(stats.expanded, 3);
+        assert_eq!(stats.compressed, 5);
+        assert_eq!(stats.saved(), 0);
+    }
+}